@@ -1,13 +1,12 @@
+mod relay;
+
 use {
     anyhow::Result,
-    clap::{value_parser, Arg, Command},
-    rtmp::session::client_session::ClientSession,
-    rtmp::session::client_session::ClientType,
-    rtmp::utils::RtmpUrlParser,
+    clap::{value_parser, Arg, ArgAction, Command},
+    relay::{PullInput, PushOutput, Relay},
+    rtmp::remuxer::{audio_transcoder::DropAudioTranscoder, RtmpRemuxer},
     std::env,
-    std::process::exit,
     streamhub::StreamsHub,
-    tokio::net::TcpStream,
     tokio::signal,
     tokio::time::Duration,
 };
@@ -23,11 +22,12 @@ async fn main() -> Result<()> {
         .author("HarlanC <harlanc@foxmail.com>")
         .about("pull and push rtmp!!!")
         .arg(
-            Arg::new("pullrtmp")
-                .long("pull_rtmp_url")
+            Arg::new("pullurl")
+                .long("pull_url")
+                .alias("pull_rtmp_url")
                 .short('i')
                 .value_name("path")
-                .help("Specify the pull rtmp url.")
+                .help("Specify the pull url, rtmp://, rtsp:// and http(s)://...flv are supported.")
                 .value_parser(value_parser!(String))
                 .required(true),
         )
@@ -36,9 +36,19 @@ async fn main() -> Result<()> {
                 .long("push_rtmp_url")
                 .short('o')
                 .value_name("path")
-                .help("Specify the push rtmp url.")
+                .help("Specify the push rtmp url, it can be specified multiple times.")
                 .value_parser(value_parser!(String))
+                .action(ArgAction::Append)
                 .required(true),
+        )
+        .arg(
+            Arg::new("statsinterval")
+                .long("stats_interval")
+                .short('s')
+                .value_name("seconds")
+                .help("Specify the interval of printing the statistics, 0 means disabled.")
+                .value_parser(value_parser!(u64))
+                .default_value("10"),
         );
 
     let args: Vec<String> = env::args().collect();
@@ -47,60 +57,34 @@ async fn main() -> Result<()> {
         return Ok(());
     }
     let matches = cmd.clone().get_matches();
-    let pull_rtmp_url = matches.get_one::<String>("pullrtmp").unwrap().clone();
-    let push_rtmp_url = matches.get_one::<String>("pushrtmp").unwrap().clone();
-
-    let mut stream_hub = StreamsHub::new(None);
-    let producer = stream_hub.get_hub_event_sender();
-    tokio::spawn(async move { stream_hub.run().await });
+    let pull_url = matches.get_one::<String>("pullurl").unwrap();
+    let push_rtmp_urls = matches.get_many::<String>("pushrtmp").unwrap();
+    let stats_interval = *matches.get_one::<u64>("statsinterval").unwrap();
 
-    let mut pull_parser = RtmpUrlParser::new(pull_rtmp_url);
-    if let Err(err) = pull_parser.parse_url() {
-        log::error!("err: {}", err);
+    let input = PullInput::new(pull_url)?;
+    let mut outputs = Vec::new();
+    for push_rtmp_url in push_rtmp_urls {
+        outputs.push(PushOutput::new(push_rtmp_url)?);
     }
-    pull_parser.append_port(String::from("1935"));
-    let stream1 = TcpStream::connect(pull_parser.raw_domain_name.clone()).await?;
-    let mut pull_client_session = ClientSession::new(
-        stream1,
-        ClientType::Play,
-        pull_parser.raw_domain_name,
-        pull_parser.app_name.clone(),
-        pull_parser.raw_stream_name,
-        producer.clone(),
-        0,
-    );
-    tokio::spawn(async move {
-        if let Err(err) = pull_client_session.run().await {
-            log::error!("pull_client_session as pull client run error: {}", err);
-        }
-    });
-
-    tokio::time::sleep(Duration::from_secs(2)).await;
 
-    let mut push_parser = RtmpUrlParser::new(push_rtmp_url);
-    if let Err(err) = push_parser.parse_url() {
-        log::error!("err: {}", err);
+    let mut stream_hub = StreamsHub::new(None);
+    let producer = stream_hub.get_hub_event_sender();
+    if input.is_remux_needed() {
+        let mut remuxer = RtmpRemuxer::new(
+            stream_hub.get_client_event_consumer(),
+            producer.clone(),
+            DropAudioTranscoder::creator(),
+        );
+        stream_hub.set_rtmp_remuxer_enabled(true);
+        tokio::spawn(async move {
+            if let Err(err) = remuxer.run().await {
+                log::error!("rtmp remuxer error: {}", err);
+            }
+        });
     }
-    push_parser.append_port(String::from("1935"));
-    // push the rtmp stream from local to remote rtmp server
-    let stream2 = TcpStream::connect(push_parser.raw_domain_name.clone()).await?;
-    let mut push_client_session = ClientSession::new(
-        stream2,
-        ClientType::Publish,
-        push_parser.raw_domain_name,
-        push_parser.app_name,
-        push_parser.raw_stream_name,
-        producer.clone(),
-        0,
-    );
+    tokio::spawn(async move { stream_hub.run().await });
 
-    push_client_session.subscribe(pull_parser.app_name, pull_parser.stream_name);
-    tokio::spawn(async move {
-        if let Err(err) = push_client_session.run().await {
-            log::error!("push_client_session as push client run error: {}", err);
-            exit(0);
-        }
-    });
+    Relay::new(input, outputs, producer).start(Duration::from_secs(stats_interval));
 
     signal::ctrl_c().await?;
     Ok(())
@@ -0,0 +1,312 @@
+use {
+    anyhow::{anyhow, bail, Result},
+    httpflv::client_session::HttpFlvClientSession,
+    rtmp::{
+        session::client_session::{ClientSession, ClientType},
+        utils::RtmpUrlParser,
+    },
+    std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+    streamhub::{
+        define::{self, StreamHubEventSender},
+        statistics::StreamDetail,
+        stream::StreamIdentifier,
+    },
+    tokio::{net::TcpStream, time::sleep},
+    xrtsp::{
+        global_trait::Unmarshal,
+        rtsp_transport::ProtocolType,
+        rtsp_url::RtspUrl,
+        session::client_session::{ClientType as RtspClientType, RtspClientSession},
+    },
+};
+
+const INITIAL_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+//the retry interval is reset if a session has lasted so long
+const STABLE_SESSION_DURATION: Duration = Duration::from_secs(30);
+//the push sessions check whether the local stream is published in this interval
+const WAIT_STREAM_INTERVAL: Duration = Duration::from_secs(1);
+//the local rtmp stream which the rtsp/http-flv input is published(remuxed) to
+const LOCAL_APP_NAME: &str = "pprtmp";
+const LOCAL_STREAM_NAME: &str = "relay";
+
+fn parse_rtmp_url(url: &str) -> Result<RtmpUrlParser> {
+    let mut parser = RtmpUrlParser::new(url.to_string());
+    if let Err(err) = parser.parse_url() {
+        bail!("invalid rtmp url: {}, err: {}", url, err);
+    }
+    parser.append_port(String::from("1935"));
+    Ok(parser)
+}
+
+pub enum PullInput {
+    Rtmp(RtmpUrlParser),
+    Rtsp(RtspUrl),
+    HttpFlv(String),
+}
+
+impl PullInput {
+    pub fn new(url: &str) -> Result<Self> {
+        if url.starts_with("rtmp://") {
+            return Ok(Self::Rtmp(parse_rtmp_url(url)?));
+        }
+        if url.starts_with("rtsp://") {
+            return RtspUrl::unmarshal(url)
+                .map(Self::Rtsp)
+                .ok_or_else(|| anyhow!("invalid rtsp url: {}", url));
+        }
+        let path = url.split('?').next().unwrap_or_default();
+        if (url.starts_with("http://") || url.starts_with("https://")) && path.ends_with(".flv") {
+            return Ok(Self::HttpFlv(url.to_string()));
+        }
+
+        bail!("unsupported pull url: {}", url)
+    }
+
+    //the rtsp stream is published as the rtsp stream and remuxed to rtmp by the rtmp remuxer
+    pub fn is_remux_needed(&self) -> bool {
+        matches!(self, Self::Rtsp(_))
+    }
+
+    //the local rtmp stream which the push sessions subscribe from
+    fn local_stream(&self) -> (String, String) {
+        match self {
+            Self::Rtmp(parser) => (parser.app_name.clone(), parser.stream_name.clone()),
+            Self::Rtsp(_) | Self::HttpFlv(_) => {
+                (LOCAL_APP_NAME.to_string(), LOCAL_STREAM_NAME.to_string())
+            }
+        }
+    }
+
+    async fn pull(&self, producer: StreamHubEventSender) -> Result<()> {
+        match self {
+            Self::Rtmp(parser) => {
+                let stream = TcpStream::connect(parser.raw_domain_name.clone()).await?;
+                let mut session = ClientSession::new(
+                    stream,
+                    ClientType::Play,
+                    parser.raw_domain_name.clone(),
+                    parser.app_name.clone(),
+                    parser.raw_stream_name.clone(),
+                    producer,
+                    0,
+                );
+                session.run().await.map_err(|err| anyhow!("{}", err))
+            }
+            Self::Rtsp(url) => {
+                let stream = TcpStream::connect(url.address()).await?;
+                let mut session = RtspClientSession::new(
+                    stream,
+                    RtspClientType::Pull,
+                    url.clone(),
+                    format!("{LOCAL_APP_NAME}/{LOCAL_STREAM_NAME}"),
+                    ProtocolType::TCP,
+                    producer,
+                );
+                session.run().await.map_err(|err| anyhow!("{}", err))
+            }
+            Self::HttpFlv(url) => {
+                let mut session = HttpFlvClientSession::new(
+                    url.clone(),
+                    LOCAL_APP_NAME.to_string(),
+                    LOCAL_STREAM_NAME.to_string(),
+                    producer,
+                    0,
+                );
+                session.run().await.map_err(|err| anyhow!("{}", err))
+            }
+        }
+    }
+}
+
+pub struct PushOutput {
+    url: String,
+    parser: RtmpUrlParser,
+}
+
+impl PushOutput {
+    pub fn new(url: &str) -> Result<Self> {
+        if !url.starts_with("rtmp://") {
+            bail!("unsupported push url: {}", url);
+        }
+
+        Ok(Self {
+            url: url.to_string(),
+            parser: parse_rtmp_url(url)?,
+        })
+    }
+
+    //do not print the parameters, they may contain the secret keys
+    fn url_without_parameters(&self) -> &str {
+        self.url.split('?').next().unwrap_or_default()
+    }
+
+    async fn push(
+        &self,
+        app_name: String,
+        stream_name: String,
+        producer: StreamHubEventSender,
+    ) -> Result<()> {
+        let stream = TcpStream::connect(self.parser.raw_domain_name.clone()).await?;
+        let mut session = ClientSession::new(
+            stream,
+            ClientType::Publish,
+            self.parser.raw_domain_name.clone(),
+            self.parser.app_name.clone(),
+            self.parser.raw_stream_name.clone(),
+            producer,
+            0,
+        );
+        session.subscribe(app_name, stream_name);
+        session.run().await.map_err(|err| anyhow!("{}", err))
+    }
+}
+
+//The exponential backoff between the reconnections of a pull/push session.
+struct Backoff {
+    retry_interval: Duration,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Self {
+            retry_interval: INITIAL_RETRY_INTERVAL,
+        }
+    }
+
+    async fn wait(&mut self, name: &str, session_duration: Duration) {
+        if session_duration > STABLE_SESSION_DURATION {
+            self.retry_interval = INITIAL_RETRY_INTERVAL;
+        }
+        log::info!(
+            "{} reconnects in {} seconds",
+            name,
+            self.retry_interval.as_secs()
+        );
+        sleep(self.retry_interval).await;
+        self.retry_interval = std::cmp::min(self.retry_interval * 2, MAX_RETRY_INTERVAL);
+    }
+}
+
+// Pull one stream and push it to several rtmp servers, the pull session and
+// each push session reconnect independently.
+pub struct Relay {
+    input: Arc<PullInput>,
+    outputs: Vec<Arc<PushOutput>>,
+    producer: StreamHubEventSender,
+}
+
+impl Relay {
+    pub fn new(input: PullInput, outputs: Vec<PushOutput>, producer: StreamHubEventSender) -> Self {
+        Self {
+            input: Arc::new(input),
+            outputs: outputs.into_iter().map(Arc::new).collect(),
+            producer,
+        }
+    }
+
+    pub fn start(&self, stats_interval: Duration) {
+        let input = self.input.clone();
+        let producer = self.producer.clone();
+        tokio::spawn(async move {
+            let mut backoff = Backoff::new();
+            loop {
+                let begin_time = Instant::now();
+                if let Err(err) = input.pull(producer.clone()).await {
+                    log::error!("pull session error: {}", err);
+                }
+                backoff.wait("pull session", begin_time.elapsed()).await;
+            }
+        });
+
+        let (app_name, stream_name) = self.input.local_stream();
+        for output in &self.outputs {
+            let output = output.clone();
+            let producer = self.producer.clone();
+            let (app_name, stream_name) = (app_name.clone(), stream_name.clone());
+            tokio::spawn(async move {
+                let name = format!("push session({})", output.url_without_parameters());
+                let mut backoff = Backoff::new();
+                loop {
+                    /*do not push until the input stream is available*/
+                    while query_stream(&producer, &app_name, &stream_name)
+                        .await
+                        .is_none()
+                    {
+                        sleep(WAIT_STREAM_INTERVAL).await;
+                    }
+
+                    let begin_time = Instant::now();
+                    if let Err(err) = output
+                        .push(app_name.clone(), stream_name.clone(), producer.clone())
+                        .await
+                    {
+                        log::error!("{} error: {}", name, err);
+                    }
+                    backoff.wait(&name, begin_time.elapsed()).await;
+                }
+            });
+        }
+
+        if stats_interval.is_zero() {
+            return;
+        }
+        let output_count = self.outputs.len();
+        let producer = self.producer.clone();
+        tokio::spawn(async move {
+            loop {
+                sleep(stats_interval).await;
+                match query_stream(&producer, &app_name, &stream_name).await {
+                    Some(detail) => print_statistics(&detail, output_count),
+                    None => log::info!("[stats] the input stream is not available now"),
+                }
+            }
+        });
+    }
+}
+
+async fn query_stream(
+    producer: &StreamHubEventSender,
+    app_name: &str,
+    stream_name: &str,
+) -> Option<StreamDetail> {
+    let identifier = StreamIdentifier::Rtmp {
+        app_name: app_name.to_string(),
+        stream_name: stream_name.to_string(),
+    };
+    define::query_stream_details(producer, Some(identifier))
+        .await
+        .ok()?
+        .pop()
+}
+
+fn print_statistics(detail: &StreamDetail, output_count: usize) {
+    if let Some(statistics) = &detail.statistics {
+        log::info!(
+            "[stats] video: {:.0} kbits/s, {} fps, {}x{}, audio: {:.0} kbits/s",
+            statistics.video.bitrate,
+            statistics.video.frame_rate,
+            statistics.video.width,
+            statistics.video.height,
+            statistics.audio.bitrate
+        );
+    }
+    log::info!(
+        "[stats] input: {} bytes received in {} seconds, outputs: {}/{} pushing",
+        detail.publisher.recv_bytes,
+        detail.publisher.uptime,
+        detail.subscribers.len(),
+        output_count
+    );
+    for subscriber in &detail.subscribers {
+        let request_url = &subscriber.info.notify_info.request_url;
+        log::info!(
+            "[stats] output {}: {} bytes sent",
+            request_url.split('?').next().unwrap_or_default(),
+            subscriber.send_bytes
+        );
+    }
+}
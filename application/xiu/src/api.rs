@@ -1,12 +1,28 @@
 use {
     anyhow::Result,
     axum::{
+        http::StatusCode,
+        response::{IntoResponse, Response},
         routing::{get, post},
         Json, Router,
     },
-    serde::Deserialize,
-    std::sync::Arc,
-    streamhub::{define, define::StreamHubEventSender, utils::Uuid},
+    bytesio::tls::{ReloadableTlsAcceptor, TlsListener},
+    hyper::server::accept,
+    rtmp::relay::{
+        errors::{ClientError, PushClientErrorValue},
+        push_rule::PushRule,
+        rule_push_client::{PushRuleManager, PushSessionStatus},
+    },
+    serde::{Deserialize, Serialize},
+    std::{net::SocketAddr, sync::Arc},
+    streamhub::{
+        define,
+        define::StreamHubEventSender,
+        errors::{ChannelError, ChannelErrorValue},
+        statistics::{PublisherStatistics, StreamDetail, SubscriberStatistics},
+        stream::StreamIdentifier,
+        utils::Uuid,
+    },
     {
         tokio,
         tokio::sync::{mpsc, oneshot},
@@ -19,9 +35,86 @@ struct KickOffClient {
     id: String,
 }
 
+// the input to our `QueryStream` and `KickOffStream` handlers, e.g.
+// {"identifier": {"Rtmp": {"app_name": "live", "stream_name": "test"}}}
+#[derive(Deserialize)]
+struct StreamRequest {
+    identifier: StreamIdentifier,
+}
+
+// the input to our `RemovePushRule` handler
+#[derive(Deserialize)]
+struct RemovePushRule {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct PublisherItem {
+    identifier: StreamIdentifier,
+    #[serde(flatten)]
+    publisher: PublisherStatistics,
+}
+
+#[derive(Serialize)]
+struct SubscriberItem {
+    identifier: StreamIdentifier,
+    #[serde(flatten)]
+    subscriber: SubscriberStatistics,
+}
+
+// the error is responded as {"error": "..."}
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: &str) -> Self {
+        Self {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.message }));
+        (self.status, body).into_response()
+    }
+}
+
+impl From<ClientError> for ApiError {
+    fn from(error: ClientError) -> Self {
+        let status = match error.value {
+            PushClientErrorValue::InvalidPushRule(_) => StatusCode::BAD_REQUEST,
+            PushClientErrorValue::PushRuleExists(_) => StatusCode::CONFLICT,
+            PushClientErrorValue::PushRuleNotFound(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self::new(status, error.to_string().trim_end())
+    }
+}
+
+//the stream details are queried from the stream hub
+impl From<ChannelError> for ApiError {
+    fn from(error: ChannelError) -> Self {
+        log::error!("query stream details error: {}", error);
+        let message = match error.value {
+            ChannelErrorValue::SendError => "stream hub is unavailable",
+            _ => "failed to query streams",
+        };
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
 #[derive(Clone)]
 struct ApiService {
     channel_event_producer: StreamHubEventSender,
+    //none if rtmp is not enabled
+    push_rule_manager: Option<PushRuleManager>,
 }
 
 impl ApiService {
@@ -29,7 +122,16 @@ impl ApiService {
         String::from(
             "Usage of xiu http api:
                 ./get_stream_status(get)  get audio and video stream statistic information.
-                ./kick_off_client(post) kick off client by publish/subscribe id.\n",
+                ./kick_off_client(post) kick off client by publish/subscribe id.
+                ./list_streams(get) list all the streams with their publisher and subscribers.
+                ./list_publishers(get) list all the publishers.
+                ./list_subscribers(get) list all the subscribers.
+                ./query_stream(post) query a stream by stream identifier.
+                ./kick_off_stream(post) kick off a stream and all its subscribers by stream identifier.
+                ./list_push_rules(get) list all the rtmp push rules, the url parameters(e.g. ?key=xxx) are hidden.
+                ./add_push_rule(post) add a rtmp push rule, e.g. {\"name\": \"cdn\", \"source\": \"live/{stream}\", \"url\": \"rtmp://cdn/app/{stream}?key=xxx\"}.
+                ./remove_push_rule(post) remove a rtmp push rule and stop its push sessions by rule name.
+                ./list_push_sessions(get) list all the rtmp push sessions with their status.\n",
         )
     }
 
@@ -70,24 +172,119 @@ impl ApiService {
         Ok(String::from(""))
     }
 
-    async fn kick_off_client(&self, id: KickOffClient) -> Result<String> {
-        let id_result = Uuid::from_str2(&id.id);
+    async fn kick_off_client(&self, id: KickOffClient) -> ApiResult<String> {
+        let id = Uuid::from_str2(&id.id)
+            .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "invalid id"))?;
 
-        if let Some(id) = id_result {
-            let hub_event = define::StreamHubEvent::ApiKickClient { id };
+        let hub_event = define::StreamHubEvent::ApiKickClient { id };
+        self.send_event(hub_event)?;
 
-            if let Err(err) = self.channel_event_producer.send(hub_event) {
-                log::error!("send api kick_off_client event error: {}", err);
+        Ok(String::from("ok"))
+    }
+
+    async fn list_streams(&self) -> ApiResult<Vec<StreamDetail>> {
+        Ok(define::query_stream_details(&self.channel_event_producer, None).await?)
+    }
+
+    async fn list_publishers(&self) -> ApiResult<Vec<PublisherItem>> {
+        let publishers = self
+            .list_streams()
+            .await?
+            .into_iter()
+            .map(|detail| PublisherItem {
+                identifier: detail.identifier,
+                publisher: detail.publisher,
+            })
+            .collect();
+
+        Ok(publishers)
+    }
+
+    async fn list_subscribers(&self) -> ApiResult<Vec<SubscriberItem>> {
+        let mut subscribers = Vec::new();
+        for detail in self.list_streams().await? {
+            for subscriber in detail.subscribers {
+                subscribers.push(SubscriberItem {
+                    identifier: detail.identifier.clone(),
+                    subscriber,
+                });
             }
         }
 
+        Ok(subscribers)
+    }
+
+    async fn query_stream(&self, identifier: StreamIdentifier) -> ApiResult<StreamDetail> {
+        define::query_stream_details(&self.channel_event_producer, Some(identifier))
+            .await?
+            .pop()
+            .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, "stream not found"))
+    }
+
+    async fn kick_off_stream(&self, identifier: StreamIdentifier) -> ApiResult<String> {
+        self.query_stream(identifier.clone()).await?;
+
+        let hub_event = define::StreamHubEvent::ApiKickStream { identifier };
+        self.send_event(hub_event)?;
+
         Ok(String::from("ok"))
     }
+
+    //the url parameters may carry the secrets(e.g. ?key=xxx), so they are not returned
+    fn list_push_rules(&self) -> ApiResult<Vec<PushRule>> {
+        let rules = self
+            .get_push_rule_manager()?
+            .rules()
+            .into_iter()
+            .map(|rule| PushRule {
+                url: rule.url_without_parameters().to_string(),
+                ..rule
+            })
+            .collect();
+        Ok(rules)
+    }
+
+    fn add_push_rule(&self, rule: PushRule) -> ApiResult<String> {
+        self.get_push_rule_manager()?.add_rule(rule)?;
+        Ok(String::from("ok"))
+    }
+
+    fn remove_push_rule(&self, name: &str) -> ApiResult<String> {
+        self.get_push_rule_manager()?.remove_rule(name)?;
+        Ok(String::from("ok"))
+    }
+
+    fn list_push_sessions(&self) -> ApiResult<Vec<PushSessionStatus>> {
+        Ok(self.get_push_rule_manager()?.sessions())
+    }
+
+    fn get_push_rule_manager(&self) -> ApiResult<&PushRuleManager> {
+        self.push_rule_manager
+            .as_ref()
+            .ok_or_else(|| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "rtmp is not enabled"))
+    }
+
+    fn send_event(&self, hub_event: define::StreamHubEvent) -> ApiResult<()> {
+        self.channel_event_producer.send(hub_event).map_err(|err| {
+            log::error!("send api event error: {}", err);
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "stream hub is unavailable",
+            )
+        })
+    }
 }
 
-pub async fn run(producer: StreamHubEventSender, port: usize) {
+pub async fn run(
+    producer: StreamHubEventSender,
+    push_rule_manager: Option<PushRuleManager>,
+    address: String,
+    port: usize,
+    tls_acceptor: Option<ReloadableTlsAcceptor>,
+) {
     let api = Arc::new(ApiService {
         channel_event_producer: producer,
+        push_rule_manager,
     });
 
     let api_root = api.clone();
@@ -102,21 +299,252 @@ pub async fn run(producer: StreamHubEventSender, port: usize) {
     };
 
     let kick_off = api.clone();
-    let kick = move |Json(id): Json<KickOffClient>| async move {
-        match kick_off.kick_off_client(id).await {
-            Ok(response) => response,
-            Err(_) => "error".to_owned(),
-        }
+    let kick =
+        move |Json(id): Json<KickOffClient>| async move { kick_off.kick_off_client(id).await };
+
+    let list_api = api.clone();
+    let list_streams = move || async move { list_api.list_streams().await.map(Json) };
+
+    let publishers_api = api.clone();
+    let list_publishers = move || async move { publishers_api.list_publishers().await.map(Json) };
+
+    let subscribers_api = api.clone();
+    let list_subscribers =
+        move || async move { subscribers_api.list_subscribers().await.map(Json) };
+
+    let query_api = api.clone();
+    let query_stream = move |Json(request): Json<StreamRequest>| async move {
+        query_api.query_stream(request.identifier).await.map(Json)
     };
 
+    let kick_stream_api = api.clone();
+    let kick_stream = move |Json(request): Json<StreamRequest>| async move {
+        kick_stream_api.kick_off_stream(request.identifier).await
+    };
+
+    let list_rules_api = api.clone();
+    let list_push_rules = move || async move { list_rules_api.list_push_rules().map(Json) };
+
+    let add_rule_api = api.clone();
+    let add_push_rule =
+        move |Json(rule): Json<PushRule>| async move { add_rule_api.add_push_rule(rule) };
+
+    let remove_rule_api = api.clone();
+    let remove_push_rule = move |Json(request): Json<RemovePushRule>| async move {
+        remove_rule_api.remove_push_rule(&request.name)
+    };
+
+    let push_sessions_api = api.clone();
+    let list_push_sessions =
+        move || async move { push_sessions_api.list_push_sessions().map(Json) };
+
     let app = Router::new()
         .route("/", get(root))
         .route("/get_stream_status", get(status))
-        .route("/kick_off_client", post(kick));
+        .route("/kick_off_client", post(kick))
+        .route("/list_streams", get(list_streams))
+        .route("/list_publishers", get(list_publishers))
+        .route("/list_subscribers", get(list_subscribers))
+        .route("/query_stream", post(query_stream))
+        .route("/kick_off_stream", post(kick_stream))
+        .route("/list_push_rules", get(list_push_rules))
+        .route("/add_push_rule", post(add_push_rule))
+        .route("/remove_push_rule", post(remove_push_rule))
+        .route("/list_push_sessions", get(list_push_sessions));
+
+    let socket_addr: SocketAddr = match format!("{address}:{port}").parse() {
+        Ok(addr) => addr,
+        Err(err) => {
+            log::error!("Http api server address: {} is invalid: {}", address, err);
+            return;
+        }
+    };
+
+    match tls_acceptor {
+        Some(acceptor) => {
+            let mut listener = match TlsListener::bind(&socket_addr, acceptor).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    log::error!("Http api server bind {} error: {}", socket_addr, err);
+                    return;
+                }
+            };
+            let incoming = accept::poll_fn(move |cx| {
+                listener
+                    .poll_accept(cx)
+                    .map(|conn| conn.map(|(stream, _)| Ok::<_, std::io::Error>(stream)))
+            });
+
+            log::info!("Http api server listening on https://{}", socket_addr);
+            axum::Server::builder(incoming)
+                .serve(app.into_make_service())
+                .await
+                .unwrap();
+        }
+        None => {
+            log::info!("Http api server listening on http://{}", socket_addr);
+            axum::Server::bind(&socket_addr)
+                .serve(app.into_make_service())
+                .await
+                .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::ApiService,
+        axum::http::StatusCode,
+        rtmp::relay::{push_rule::PushRule, rule_push_client::PushRuleManager},
+        streamhub::{
+            define::{
+                NotifyInfo, PublishType, PublisherInfo, StreamHubEvent, SubscribeType,
+                SubscriberInfo,
+            },
+            statistics::{PublisherStatistics, StreamDetail, SubscriberStatistics},
+            stream::StreamIdentifier,
+            utils::{RandomDigitCount, Uuid},
+        },
+        tokio::sync::mpsc,
+    };
+
+    fn identifier() -> StreamIdentifier {
+        StreamIdentifier::Rtmp {
+            app_name: String::from("live"),
+            stream_name: String::from("test"),
+        }
+    }
 
-    log::info!("Http api server listening on http://:{}", port);
-    axum::Server::bind(&([127, 0, 0, 1], port as u16).into())
-        .serve(app.into_make_service())
-        .await
+    fn stream_detail() -> StreamDetail {
+        let notify_info = NotifyInfo {
+            request_url: String::from("rtmp://127.0.0.1/live/test"),
+            remote_addr: String::from("127.0.0.1:50000"),
+        };
+        StreamDetail {
+            identifier: identifier(),
+            publisher: PublisherStatistics {
+                info: PublisherInfo {
+                    id: Uuid::new(RandomDigitCount::Four),
+                    pub_type: PublishType::PushRtmp,
+                    notify_info: notify_info.clone(),
+                },
+                start_time: String::from("2023-01-01 00:00:00"),
+                uptime: 10,
+                recv_bytes: 1000,
+            },
+            subscribers: vec![SubscriberStatistics {
+                info: SubscriberInfo {
+                    id: Uuid::new(RandomDigitCount::Four),
+                    sub_type: SubscribeType::PlayerRtmp,
+                    notify_info,
+                },
+                start_time: String::from("2023-01-01 00:00:05"),
+                send_bytes: 500,
+            }],
+            send_bytes: 500,
+            statistics: None,
+        }
+    }
+
+    //a stream hub which has only one stream, returns the kicked off streams
+    fn start_api_service() -> (ApiService, mpsc::UnboundedReceiver<StreamIdentifier>) {
+        let (event_sender, mut event_receiver) = mpsc::unbounded_channel();
+        let (kicked_sender, kicked_receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(event) = event_receiver.recv().await {
+                match event {
+                    StreamHubEvent::ApiListStreams {
+                        data_sender,
+                        size_sender,
+                    } => {
+                        data_sender.send(stream_detail()).unwrap();
+                        size_sender.send(1).unwrap();
+                    }
+                    StreamHubEvent::ApiQueryStream {
+                        identifier: id,
+                        data_sender,
+                        size_sender,
+                    } => {
+                        let size = if id == identifier() {
+                            data_sender.send(stream_detail()).unwrap();
+                            1
+                        } else {
+                            0
+                        };
+                        size_sender.send(size).unwrap();
+                    }
+                    StreamHubEvent::ApiKickStream { identifier } => {
+                        kicked_sender.send(identifier).unwrap();
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        let api = ApiService {
+            channel_event_producer: event_sender,
+            push_rule_manager: None,
+        };
+        (api, kicked_receiver)
+    }
+
+    #[tokio::test]
+    async fn test_list_streams() {
+        let (api, _) = start_api_service();
+
+        let streams = api.list_streams().await.ok().unwrap();
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].identifier, identifier());
+
+        let publishers = api.list_publishers().await.ok().unwrap();
+        assert_eq!(publishers.len(), 1);
+        let subscribers = api.list_subscribers().await.ok().unwrap();
+        assert_eq!(subscribers.len(), 1);
+
+        let value = serde_json::to_value(&subscribers[0]).unwrap();
+        assert_eq!(value["identifier"]["Rtmp"]["stream_name"], "test");
+        assert_eq!(value["send_bytes"], 500);
+    }
+
+    #[tokio::test]
+    async fn test_kick_off_stream() {
+        let (api, mut kicked_receiver) = start_api_service();
+
+        let not_found = StreamIdentifier::Rtmp {
+            app_name: String::from("live"),
+            stream_name: String::from("test2"),
+        };
+        match api.kick_off_stream(not_found).await {
+            Err(err) => assert_eq!(err.status, StatusCode::NOT_FOUND),
+            Ok(_) => panic!("kick off a stream which does not exist"),
+        }
+
+        assert_eq!(api.kick_off_stream(identifier()).await.ok().unwrap(), "ok");
+        assert_eq!(kicked_receiver.recv().await.unwrap(), identifier());
+        assert!(kicked_receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_push_rules() {
+        let (event_sender, _event_receiver) = mpsc::unbounded_channel();
+        let api = ApiService {
+            push_rule_manager: Some(PushRuleManager::new(event_sender.clone())),
+            channel_event_producer: event_sender,
+        };
+
+        let rule = PushRule::new(
+            String::from("cdn"),
+            String::from("live/{stream}"),
+            String::from("rtmp://cdn/app/{stream}?key=secret"),
+        )
         .unwrap();
+        assert_eq!(api.add_push_rule(rule).ok().unwrap(), "ok");
+
+        /*the secret key is not exposed*/
+        let rules = api.list_push_rules().ok().unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].name, "cdn");
+        assert_eq!(rules[0].url, "rtmp://cdn/app/{stream}");
+    }
 }
@@ -11,6 +11,8 @@ pub struct ConfigError {
 pub enum ConfigErrorValue {
     #[fail(display = "IO error: {}\n", _0)]
     IOError(Error),
+    #[fail(display = "invalid config: {}\n", _0)]
+    InvalidConfig(String),
 }
 
 impl From<Error> for ConfigError {
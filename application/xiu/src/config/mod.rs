@@ -10,10 +10,13 @@ pub struct Config {
     pub rtmp: Option<RtmpConfig>,
     pub rtsp: Option<RtspConfig>,
     pub webrtc: Option<WebRTCConfig>,
+    pub srt: Option<SrtConfig>,
     pub httpflv: Option<HttpFlvConfig>,
     pub hls: Option<HlsConfig>,
     pub httpapi: Option<HttpApiConfig>,
+    pub metrics: Option<MetricsConfig>,
     pub httpnotify: Option<HttpNotifierConfig>,
+    pub auth: Option<AuthConfig>,
     pub log: Option<LogConfig>,
 }
 
@@ -34,6 +37,8 @@ impl Config {
                 port: rtmp_port,
                 pull: None,
                 push: None,
+                push_rules: None,
+                tls: None,
             });
         }
 
@@ -42,6 +47,10 @@ impl Config {
             rtsp_config = Some(RtspConfig {
                 enabled: true,
                 port: rtsp_port,
+                pull: None,
+                push: None,
+                auth: None,
+                tls: None,
             });
         }
 
@@ -50,6 +59,7 @@ impl Config {
             webrtc_config = Some(WebRTCConfig {
                 enabled: true,
                 port: webrtc_port,
+                tls: None,
             });
         }
 
@@ -58,6 +68,7 @@ impl Config {
             httpflv_config = Some(HttpFlvConfig {
                 enabled: true,
                 port: httpflv_port,
+                tls: None,
             });
         }
 
@@ -67,6 +78,17 @@ impl Config {
                 enabled: true,
                 port: hls_port,
                 need_record: false,
+                fmp4: None,
+                low_latency: None,
+                target_duration: None,
+                playlist_window: None,
+                output_dir: None,
+                segment_name: None,
+                delete_on_unpublish: None,
+                keep_duration: None,
+                program_date_time: None,
+                apps: None,
+                tls: None,
             });
         }
 
@@ -79,10 +101,13 @@ impl Config {
             rtmp: rtmp_config,
             rtsp: rtsp_config,
             webrtc: webrtc_config,
+            srt: None,
             httpflv: httpflv_config,
             hls: hls_config,
             httpapi: None,
+            metrics: None,
             httpnotify: None,
+            auth: None,
             log: log_config,
         }
     }
@@ -95,12 +120,24 @@ pub struct RtmpConfig {
     pub gop_num: Option<usize>,
     pub pull: Option<RtmpPullConfig>,
     pub push: Option<Vec<RtmpPushConfig>>,
+    //push the matched streams to the remote servers, can be changed by the http api
+    pub push_rules: Option<Vec<RtmpPushRuleConfig>>,
+    //rtmps
+    pub tls: Option<TlsConfig>,
 }
 #[derive(Debug, Deserialize, Clone)]
 pub struct RtmpPullConfig {
     pub enabled: bool,
-    pub address: String,
-    pub port: u16,
+    //the single origin, the same as origins = ["address:port"]
+    pub address: Option<String>,
+    pub port: Option<u16>,
+    //the origins(ip:port) of the edge, the stream is pulled from the one picked
+    //by the consistent hashing of the stream name, and fails over to the others.
+    pub origins: Option<Vec<String>>,
+    //the interval(seconds) of checking the origins, default 5, 0 means disabled
+    pub health_check_interval: Option<u64>,
+    //refuse to pull the stream which has been relayed so many times, default 3
+    pub max_hops: Option<u32>,
 }
 #[derive(Debug, Deserialize, Clone)]
 pub struct RtmpPushConfig {
@@ -108,23 +145,95 @@ pub struct RtmpPushConfig {
     pub address: String,
     pub port: usize,
 }
+#[derive(Debug, Deserialize, Clone)]
+pub struct RtmpPushRuleConfig {
+    pub enabled: bool,
+    pub name: String,
+    pub source: String,
+    pub url: String,
+}
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct RtspConfig {
     pub enabled: bool,
     pub port: usize,
+    pub pull: Option<Vec<RtspPullConfig>>,
+    pub push: Option<Vec<RtspPushConfig>>,
+    pub auth: Option<RtspAuthConfig>,
+    //rtsps, it also accepts the rtsp over http tunnel
+    pub tls: Option<TlsConfig>,
+}
+#[derive(Debug, Deserialize, Clone)]
+pub struct RtspPullConfig {
+    pub enabled: bool,
+    //the local stream path, e.g. camera/door
+    pub stream_path: String,
+    //rtsp://[username:password@]host[:port]/path
+    pub url: String,
+    //"tcp"(default) or "udp"
+    pub transport: Option<String>,
+}
+#[derive(Debug, Deserialize, Clone)]
+pub struct RtspPushConfig {
+    pub enabled: bool,
+    pub address: String,
+    pub port: u16,
+    //the credentials of the remote server
+    pub username: Option<String>,
+    pub password: Option<String>,
+    //"tcp"(default) or "udp"
+    pub transport: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RtspAuthConfig {
+    pub enabled: bool,
+    pub realm: Option<String>,
+    //"digest"(default) or "basic"
+    pub scheme: Option<String>,
+    pub credentials: Vec<RtspCredentialConfig>,
+}
+#[derive(Debug, Deserialize, Clone)]
+pub struct RtspCredentialConfig {
+    //"camera/door", "camera/*" or "*"
+    pub path: String,
+    pub username: String,
+    pub password: String,
+    pub read: bool,
+    pub publish: bool,
+}
+
+//The certificate chain and the private key(PKCS8 or RSA) in PEM format, the
+//listener is started on another port and the files are reloaded on SIGHUP.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TlsConfig {
+    pub enabled: bool,
+    pub port: usize,
+    pub cert_path: String,
+    pub key_path: String,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct WebRTCConfig {
     pub enabled: bool,
     pub port: usize,
+    //whip/whep over https
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SrtConfig {
+    pub enabled: bool,
+    pub port: usize,
+    /*the receiver latency in milliseconds, the larger one of the two peers is used*/
+    pub latency_ms: Option<u64>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct HttpFlvConfig {
     pub enabled: bool,
     pub port: usize,
+    pub tls: Option<TlsConfig>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -133,6 +242,42 @@ pub struct HlsConfig {
     pub port: usize,
     //record or not
     pub need_record: bool,
+    //use the fragmented mp4(CMAF) segments instead of the mpegts ones
+    pub fmp4: Option<bool>,
+    //low latency hls with the partial segments and the blocking playlist reload
+    pub low_latency: Option<bool>,
+    //the target segment duration in seconds
+    pub target_duration: Option<i64>,
+    //how many segments are listed in the live playlist
+    pub playlist_window: Option<usize>,
+    //the root directory of the recorded files
+    pub output_dir: Option<String>,
+    //the segment name template, {app}, {stream} and {seq} are replaced
+    pub segment_name: Option<String>,
+    //remove the live playlist and segments once the stream is unpublished
+    pub delete_on_unpublish: Option<bool>,
+    //how long in seconds the ended stream is kept in memory if it is not deleted
+    pub keep_duration: Option<u64>,
+    //add #EXT-X-PROGRAM-DATE-TIME before each segment
+    pub program_date_time: Option<bool>,
+    //override the options above for the specified applications
+    pub apps: Option<Vec<HlsAppConfig>>,
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct HlsAppConfig {
+    pub name: String,
+    pub need_record: Option<bool>,
+    pub fmp4: Option<bool>,
+    pub low_latency: Option<bool>,
+    pub target_duration: Option<i64>,
+    pub playlist_window: Option<usize>,
+    pub output_dir: Option<String>,
+    pub segment_name: Option<String>,
+    pub delete_on_unpublish: Option<bool>,
+    pub keep_duration: Option<u64>,
+    pub program_date_time: Option<bool>,
 }
 
 pub enum LogLevel {
@@ -158,6 +303,17 @@ pub struct LogFile {
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct HttpApiConfig {
+    //default is "127.0.0.1"
+    pub address: Option<String>,
+    pub port: usize,
+    pub tls: Option<TlsConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    //default is "0.0.0.0"
+    pub address: Option<String>,
     pub port: usize,
 }
 
@@ -168,6 +324,34 @@ pub struct HttpNotifierConfig {
     pub on_unpublish: Option<String>,
     pub on_play: Option<String>,
     pub on_stop: Option<String>,
+    //wait for the on_publish/on_play responses, which can reject or redirect the session
+    pub blocking: Option<bool>,
+    pub timeout_ms: Option<u64>,
+    //in blocking mode, let the session go on if the notify server is unreachable
+    pub fail_open: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    pub auth_publish: bool,
+    pub auth_play: bool,
+    //[simple,hmac], used with the default secret
+    pub algorithm: Option<String>,
+    //the default secret for the apps which are not configured in apps
+    pub secret: Option<String>,
+    pub apps: Option<Vec<AuthAppConfig>>,
+    //the request is rejected if the callout does not return 2xx
+    pub callout_url: Option<String>,
+    pub callout_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuthAppConfig {
+    pub app_name: String,
+    //[simple,hmac]
+    pub algorithm: Option<String>,
+    pub secret: String,
 }
 
 pub fn load(cfg_path: &String) -> Result<Config, ConfigError> {
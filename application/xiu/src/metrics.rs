@@ -0,0 +1,54 @@
+use {
+    axum::{http::header, response::IntoResponse, routing::get, Router},
+    std::net::SocketAddr,
+    streamhub::{
+        define::{self, StreamHubEventSender},
+        statistics::{
+            metrics::{self, SessionCounters},
+            StreamDetail,
+        },
+    },
+};
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+async fn collect_stream_details(producer: &StreamHubEventSender) -> Vec<StreamDetail> {
+    define::query_stream_details(producer, None)
+        .await
+        .unwrap_or_else(|err| {
+            log::error!("metrics query stream details error: {}", err);
+            Vec::new()
+        })
+}
+
+pub async fn run(
+    producer: StreamHubEventSender,
+    session_counters: SessionCounters,
+    address: String,
+    port: usize,
+) {
+    let metrics_handler = move || async move {
+        let stream_details = collect_stream_details(&producer).await;
+        (
+            [(header::CONTENT_TYPE, CONTENT_TYPE)],
+            metrics::render(&stream_details, &session_counters),
+        )
+            .into_response()
+    };
+
+    let app = Router::new().route("/metrics", get(metrics_handler));
+
+    let socket_addr: SocketAddr = match format!("{address}:{port}").parse() {
+        Ok(addr) => addr,
+        Err(err) => {
+            log::error!("Metrics server address: {} is invalid: {}", address, err);
+            return;
+        }
+    };
+
+    log::info!("Metrics server listening on http://{}/metrics", socket_addr);
+    axum::Server::bind(&socket_addr)
+        .serve(app.into_make_service())
+        .await
+        .unwrap();
+}
@@ -1,33 +1,80 @@
-use rtmp::remuxer::RtmpRemuxer;
+use rtmp::remuxer::{
+    audio_transcoder::DropAudioTranscoder as RtmpDropAudioTranscoder, RtmpRemuxer,
+};
 
 use {
     super::api,
-    super::config::Config,
+    super::config::{
+        errors::{ConfigError, ConfigErrorValue},
+        Config, HlsAppConfig, HlsConfig, RtspPushConfig, TlsConfig,
+    },
+    super::metrics,
     //https://rustcc.cn/article?id=6dcbf032-0483-4980-8bfe-c64a7dfb33c7
     anyhow::Result,
+    bytesio::tls::ReloadableTlsAcceptor,
+    hls::define::HlsOptions,
     hls::remuxer::HlsRemuxer,
     hls::server as hls_server,
+    hls::store::HlsStore,
     httpflv::server as httpflv_server,
     rtmp::{
-        relay::{pull_client::PullClient, push_client::PushClient},
+        relay::{
+            origin::Origins,
+            pull_client::PullClient,
+            push_client::PushClient,
+            push_rule::PushRule,
+            rule_push_client::{PushRuleManager, RulePushClient},
+        },
         rtmp::RtmpServer,
     },
-    streamhub::{notify::Notifier, StreamsHub},
+    std::{collections::HashMap, sync::Arc, time::Duration},
+    streamhub::{
+        auth::{Auth, AuthAlgorithm, AuthSecret},
+        notify::Notifier,
+        StreamsHub,
+    },
     tokio,
-    xrtsp::rtsp::RtspServer,
-    xwebrtc::webrtc::WebRTCServer,
+    xrtsp::{
+        global_trait::Unmarshal,
+        relay::{
+            pull_client::{PullClient as RtspPullClient, RtspPullTarget},
+            push_client::{PushClient as RtspPushClient, RtspPushTarget},
+        },
+        remuxer::RtspRemuxer,
+        rtsp::RtspServer,
+        rtsp_auth::{AuthScheme, RtspCredential, RtspServerAuth},
+        rtsp_transport::ProtocolType,
+        rtsp_url::RtspUrl,
+    },
+    xsrt::srt::SrtServer,
+    xwebrtc::{
+        remuxer::{audio_transcoder::DropAudioTranscoder, WebRTCRemuxer},
+        webrtc::WebRTCServer,
+    },
 };
 
 pub struct Service {
     cfg: Config,
+    auth: Option<Arc<Auth>>,
+    /*the certificates of all the tls listeners, reloaded on SIGHUP*/
+    tls_acceptors: Vec<ReloadableTlsAcceptor>,
+    /*the rtmp push rules, shared with the http api*/
+    push_rule_manager: Option<PushRuleManager>,
 }
 
 impl Service {
     pub fn new(cfg: Config) -> Self {
-        Service { cfg }
+        Service {
+            cfg,
+            auth: None,
+            tls_acceptors: Vec::new(),
+            push_rule_manager: None,
+        }
     }
 
     pub async fn run(&mut self) -> Result<()> {
+        self.auth = self.gen_auth();
+
         let notifier = if let Some(httpnotifier) = &self.cfg.httpnotify {
             if !httpnotifier.enabled {
                 None
@@ -37,6 +84,9 @@ impl Service {
                     httpnotifier.on_unpublish.clone(),
                     httpnotifier.on_play.clone(),
                     httpnotifier.on_stop.clone(),
+                    httpnotifier.blocking.unwrap_or(false),
+                    Duration::from_millis(httpnotifier.timeout_ms.unwrap_or(3000)),
+                    httpnotifier.fail_open.unwrap_or(true),
                 ))
             }
         } else {
@@ -50,8 +100,13 @@ impl Service {
         self.start_rtmp(&mut stream_hub).await?;
         self.start_rtsp(&mut stream_hub).await?;
         self.start_webrtc(&mut stream_hub).await?;
+        self.start_srt(&mut stream_hub).await?;
         self.start_http_api_server(&mut stream_hub).await?;
+        self.start_metrics_server(&mut stream_hub).await?;
         self.start_rtmp_remuxer(&mut stream_hub).await?;
+        self.start_webrtc_remuxer(&mut stream_hub).await?;
+        self.start_rtsp_remuxer(&mut stream_hub).await?;
+        self.reload_tls_on_sighup();
 
         tokio::spawn(async move {
             stream_hub.run().await;
@@ -60,21 +115,180 @@ impl Service {
         Ok(())
     }
 
+    //load the certificate if the tls listener is enabled, return its port and acceptor
+    fn load_tls_acceptor(
+        tls_cfg: &Option<TlsConfig>,
+        tls_acceptors: &mut Vec<ReloadableTlsAcceptor>,
+    ) -> Result<Option<(usize, ReloadableTlsAcceptor)>> {
+        match tls_cfg {
+            Some(tls_cfg) if tls_cfg.enabled => {
+                let acceptor = ReloadableTlsAcceptor::new(&tls_cfg.cert_path, &tls_cfg.key_path)
+                    .map_err(|err| {
+                        anyhow::anyhow!("load certificate {} error: {}", tls_cfg.cert_path, err)
+                    })?;
+                tls_acceptors.push(acceptor.clone());
+                Ok(Some((tls_cfg.port, acceptor)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    #[cfg(unix)]
+    fn reload_tls_on_sighup(&self) {
+        if self.tls_acceptors.is_empty() {
+            return;
+        }
+
+        let tls_acceptors = self.tls_acceptors.clone();
+        tokio::spawn(async move {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(err) => {
+                    log::error!("register SIGHUP handler error: {}", err);
+                    return;
+                }
+            };
+
+            while hangup.recv().await.is_some() {
+                for acceptor in &tls_acceptors {
+                    match acceptor.reload() {
+                        Ok(()) => log::info!("reload certificate: {}", acceptor.cert_path()),
+                        Err(err) => log::error!(
+                            "reload certificate {} error: {}, keep the old one",
+                            acceptor.cert_path(),
+                            err
+                        ),
+                    }
+                }
+            }
+        });
+    }
+
+    #[cfg(not(unix))]
+    fn reload_tls_on_sighup(&self) {}
+
+    fn gen_auth(&self) -> Option<Arc<Auth>> {
+        let auth_cfg = self.cfg.auth.as_ref()?;
+        if !auth_cfg.enabled {
+            return None;
+        }
+
+        let gen_secret = |algorithm: &Option<String>, secret: &String| {
+            let algorithm = match algorithm.as_deref() {
+                Some("hmac") => AuthAlgorithm::Hmac,
+                Some("simple") | None => AuthAlgorithm::Simple,
+                Some(val) => {
+                    log::warn!("unknown auth algorithm: {}, use simple instead.", val);
+                    AuthAlgorithm::Simple
+                }
+            };
+            AuthSecret {
+                algorithm,
+                value: secret.clone(),
+            }
+        };
+
+        let default_secret = auth_cfg
+            .secret
+            .as_ref()
+            .map(|secret| gen_secret(&auth_cfg.algorithm, secret));
+
+        let mut app_secrets = HashMap::new();
+        if let Some(apps) = &auth_cfg.apps {
+            for app in apps {
+                app_secrets.insert(
+                    app.app_name.clone(),
+                    gen_secret(&app.algorithm, &app.secret),
+                );
+            }
+        }
+
+        let callout_timeout = Duration::from_millis(auth_cfg.callout_timeout_ms.unwrap_or(3000));
+
+        Some(Arc::new(Auth::new(
+            default_secret,
+            app_secrets,
+            auth_cfg.auth_publish,
+            auth_cfg.auth_play,
+            auth_cfg.callout_url.clone(),
+            callout_timeout,
+        )))
+    }
+
     async fn start_http_api_server(&mut self, stream_hub: &mut StreamsHub) -> Result<()> {
         let producer = stream_hub.get_hub_event_sender();
 
-        let http_api_port = if let Some(httpapi) = &self.cfg.httpapi {
-            httpapi.port
+        let (http_api_address, http_api_port) = if let Some(httpapi) = &self.cfg.httpapi {
+            (
+                httpapi
+                    .address
+                    .clone()
+                    .unwrap_or_else(|| String::from("127.0.0.1")),
+                httpapi.port,
+            )
         } else {
-            8000
+            (String::from("127.0.0.1"), 8000)
         };
 
+        if let Some(httpapi) = &self.cfg.httpapi {
+            if let Some((tls_port, tls_acceptor)) =
+                Self::load_tls_acceptor(&httpapi.tls, &mut self.tls_acceptors)?
+            {
+                let producer = producer.clone();
+                let address = http_api_address.clone();
+                let push_rule_manager = self.push_rule_manager.clone();
+                tokio::spawn(async move {
+                    api::run(
+                        producer,
+                        push_rule_manager,
+                        address,
+                        tls_port,
+                        Some(tls_acceptor),
+                    )
+                    .await;
+                });
+            }
+        }
+
+        let push_rule_manager = self.push_rule_manager.clone();
         tokio::spawn(async move {
-            api::run(producer, http_api_port).await;
+            api::run(
+                producer,
+                push_rule_manager,
+                http_api_address,
+                http_api_port,
+                None,
+            )
+            .await;
         });
         Ok(())
     }
 
+    async fn start_metrics_server(&mut self, stream_hub: &mut StreamsHub) -> Result<()> {
+        let metrics_cfg = &self.cfg.metrics;
+
+        if let Some(metrics_cfg_value) = metrics_cfg {
+            if !metrics_cfg_value.enabled {
+                return Ok(());
+            }
+
+            let producer = stream_hub.get_hub_event_sender();
+            let session_counters = stream_hub.get_session_counters();
+            let address = metrics_cfg_value
+                .address
+                .clone()
+                .unwrap_or_else(|| String::from("0.0.0.0"));
+            let port = metrics_cfg_value.port;
+
+            tokio::spawn(async move {
+                metrics::run(producer, session_counters, address, port).await;
+            });
+        }
+
+        Ok(())
+    }
+
     async fn start_rtmp(&mut self, stream_hub: &mut StreamsHub) -> Result<()> {
         let rtmp_cfg = &self.cfg.rtmp;
 
@@ -118,17 +332,63 @@ impl Service {
                     stream_hub.set_rtmp_push_enabled(true);
                 }
             }
+            /*push by rules, the rules can also be added or removed by the http api*/
+            let push_rule_manager = PushRuleManager::new(producer.clone());
+            if let Some(rule_cfg_values) = &rtmp_cfg_value.push_rules {
+                for rule_value in rule_cfg_values {
+                    if !rule_value.enabled {
+                        continue;
+                    }
+                    let rv = PushRule::new(
+                        rule_value.name.clone(),
+                        rule_value.source.clone(),
+                        rule_value.url.clone(),
+                    )
+                    .and_then(|rule| push_rule_manager.add_rule(rule));
+                    if let Err(err) = rv {
+                        log::error!("invalid rtmp push rule: {}, {}", rule_value.name, err);
+                    }
+                }
+            }
+            let mut rule_push_client = RulePushClient::new(
+                stream_hub.get_client_event_consumer(),
+                push_rule_manager.clone(),
+            );
+            tokio::spawn(async move {
+                if let Err(err) = rule_push_client.run().await {
+                    log::error!("rule push client error {}\n", err);
+                }
+            });
+            stream_hub.set_rtmp_push_enabled(true);
+            self.push_rule_manager = Some(push_rule_manager);
+
             /*static pull*/
             if let Some(pull_cfg_value) = &rtmp_cfg_value.pull {
                 if pull_cfg_value.enabled {
-                    let address = format!(
-                        "{ip}:{port}",
-                        ip = pull_cfg_value.address,
-                        port = pull_cfg_value.port
-                    );
-                    log::info!("start rtmp pull client from address: {}", address);
+                    let mut addresses = pull_cfg_value.origins.clone().unwrap_or_default();
+                    if let Some(address) = &pull_cfg_value.address {
+                        let port = pull_cfg_value.port.unwrap_or(1935);
+                        addresses.push(format!("{address}:{port}"));
+                    }
+                    if addresses.is_empty() {
+                        return Err(anyhow::anyhow!("no origin is configured for the rtmp pull"));
+                    }
+                    log::info!("start rtmp pull client from origins: {:?}", addresses);
+
+                    let origins = Origins::new(addresses);
+                    let health_check_interval = pull_cfg_value.health_check_interval.unwrap_or(5);
+                    if health_check_interval > 0 {
+                        let origins = origins.clone();
+                        tokio::spawn(async move {
+                            origins
+                                .check_health(Duration::from_secs(health_check_interval))
+                                .await;
+                        });
+                    }
+
                     let mut pull_client = PullClient::new(
-                        address,
+                        origins,
+                        pull_cfg_value.max_hops.unwrap_or(3),
                         stream_hub.get_client_event_consumer(),
                         producer.clone(),
                     );
@@ -146,7 +406,32 @@ impl Service {
             let listen_port = rtmp_cfg_value.port;
             let address = format!("0.0.0.0:{listen_port}");
 
-            let mut rtmp_server = RtmpServer::new(address, producer, gop_num);
+            if let Some((tls_port, tls_acceptor)) =
+                Self::load_tls_acceptor(&rtmp_cfg_value.tls, &mut self.tls_acceptors)?
+            {
+                let mut rtmps_server = RtmpServer::new(
+                    format!("0.0.0.0:{tls_port}"),
+                    producer.clone(),
+                    gop_num,
+                    self.auth.clone(),
+                    Some(tls_acceptor),
+                    stream_hub.get_session_counters(),
+                );
+                tokio::spawn(async move {
+                    if let Err(err) = rtmps_server.run().await {
+                        log::error!("rtmps server error: {}\n", err);
+                    }
+                });
+            }
+
+            let mut rtmp_server = RtmpServer::new(
+                address,
+                producer,
+                gop_num,
+                self.auth.clone(),
+                None,
+                stream_hub.get_session_counters(),
+            );
             tokio::spawn(async move {
                 if let Err(err) = rtmp_server.run().await {
                     log::error!("rtmp server error: {}\n", err);
@@ -158,35 +443,75 @@ impl Service {
     }
 
     async fn start_rtmp_remuxer(&mut self, stream_hub: &mut StreamsHub) -> Result<()> {
-        //The remuxer now is used for rtsp2rtmp, so both rtsp/rtmp cfg need to be enabled.
-        let mut rtsp_enabled = false;
-        if let Some(rtsp_cfg_value) = &self.cfg.rtsp {
-            if rtsp_cfg_value.enabled {
-                rtsp_enabled = true;
-            }
-        }
-        if !rtsp_enabled {
+        //The remuxer is used for rtsp2rtmp and webrtc2rtmp(whip), so the rtmp cfg
+        //and one of the rtsp/webrtc cfgs need to be enabled.
+        let rtmp_enabled = matches!(&self.cfg.rtmp, Some(cfg) if cfg.enabled);
+        let rtsp_enabled = matches!(&self.cfg.rtsp, Some(cfg) if cfg.enabled);
+        let webrtc_enabled = matches!(&self.cfg.webrtc, Some(cfg) if cfg.enabled);
+        if !rtmp_enabled || !(rtsp_enabled || webrtc_enabled) {
             return Ok(());
         }
 
-        let mut rtmp_enabled: bool = false;
-        if let Some(rtmp_cfg_value) = &self.cfg.rtmp {
-            if rtmp_cfg_value.enabled {
-                rtmp_enabled = true;
+        let event_producer = stream_hub.get_hub_event_sender();
+        let broadcast_event_receiver = stream_hub.get_client_event_consumer();
+        /*no opus to aac transcoder is built in now, the audio is dropped*/
+        let mut remuxer = RtmpRemuxer::new(
+            broadcast_event_receiver,
+            event_producer,
+            RtmpDropAudioTranscoder::creator(),
+        );
+        stream_hub.set_rtmp_remuxer_enabled(true);
+
+        tokio::spawn(async move {
+            if let Err(err) = remuxer.run().await {
+                log::error!("rtmp remuxer server error: {}\n", err);
             }
+        });
+        Ok(())
+    }
+
+    async fn start_webrtc_remuxer(&mut self, stream_hub: &mut StreamsHub) -> Result<()> {
+        //The remuxer is used for rtmp2webrtc(whep), so both rtmp/webrtc cfg need to be enabled.
+        let webrtc_enabled = matches!(&self.cfg.webrtc, Some(cfg) if cfg.enabled);
+        let rtmp_enabled = matches!(&self.cfg.rtmp, Some(cfg) if cfg.enabled);
+        if !webrtc_enabled || !rtmp_enabled {
+            return Ok(());
         }
-        if !rtmp_enabled {
+
+        let event_producer = stream_hub.get_hub_event_sender();
+        let broadcast_event_receiver = stream_hub.get_client_event_consumer();
+        /*no aac to opus transcoder is built in now, the audio is dropped*/
+        let mut remuxer = WebRTCRemuxer::new(
+            broadcast_event_receiver,
+            event_producer,
+            DropAudioTranscoder::creator(),
+        );
+        stream_hub.set_webrtc_remuxer_enabled(true);
+
+        tokio::spawn(async move {
+            if let Err(err) = remuxer.run().await {
+                log::error!("webrtc remuxer server error: {}\n", err);
+            }
+        });
+        Ok(())
+    }
+
+    async fn start_rtsp_remuxer(&mut self, stream_hub: &mut StreamsHub) -> Result<()> {
+        //The remuxer is used for rtmp2rtsp, so both rtmp/rtsp cfg need to be enabled.
+        let rtsp_enabled = matches!(&self.cfg.rtsp, Some(cfg) if cfg.enabled);
+        let rtmp_enabled = matches!(&self.cfg.rtmp, Some(cfg) if cfg.enabled);
+        if !rtsp_enabled || !rtmp_enabled {
             return Ok(());
         }
 
         let event_producer = stream_hub.get_hub_event_sender();
         let broadcast_event_receiver = stream_hub.get_client_event_consumer();
-        let mut remuxer = RtmpRemuxer::new(broadcast_event_receiver, event_producer);
-        stream_hub.set_rtmp_remuxer_enabled(true);
+        let mut remuxer = RtspRemuxer::new(broadcast_event_receiver, event_producer);
+        stream_hub.set_rtsp_remuxer_enabled(true);
 
         tokio::spawn(async move {
             if let Err(err) = remuxer.run().await {
-                log::error!("rtmp remuxer server error: {}\n", err);
+                log::error!("rtsp remuxer server error: {}\n", err);
             }
         });
         Ok(())
@@ -202,10 +527,110 @@ impl Service {
 
             let producer = stream_hub.get_hub_event_sender();
 
+            /*static push */
+            if let Some(push_cfg_values) = &rtsp_cfg_value.push {
+                let targets = rtsp_push_targets(push_cfg_values);
+                if !targets.is_empty() {
+                    log::info!("start rtsp push client, {} targets", targets.len());
+                    let mut push_client = RtspPushClient::new(
+                        targets,
+                        stream_hub.get_client_event_consumer(),
+                        producer.clone(),
+                    );
+                    tokio::spawn(async move {
+                        push_client.run().await;
+                    });
+
+                    stream_hub.set_rtsp_push_enabled(true);
+                }
+            }
+            /*static pull*/
+            if let Some(pull_cfg_values) = &rtsp_cfg_value.pull {
+                let mut targets = Vec::new();
+                for pull_value in pull_cfg_values {
+                    if !pull_value.enabled {
+                        continue;
+                    }
+                    let url = if let Some(url) = RtspUrl::unmarshal(&pull_value.url) {
+                        url
+                    } else {
+                        log::error!("invalid rtsp pull url: {}", pull_value.url);
+                        continue;
+                    };
+                    targets.push(RtspPullTarget {
+                        stream_path: pull_value.stream_path.clone(),
+                        url,
+                        protocol_type: get_rtsp_protocol_type(&pull_value.transport),
+                    });
+                }
+
+                if !targets.is_empty() {
+                    log::info!("start rtsp pull client, {} targets", targets.len());
+                    let mut pull_client = RtspPullClient::new(
+                        targets,
+                        stream_hub.get_client_event_consumer(),
+                        producer.clone(),
+                    );
+                    tokio::spawn(async move {
+                        pull_client.run().await;
+                    });
+
+                    stream_hub.set_rtsp_pull_enabled(true);
+                }
+            }
+
             let listen_port = rtsp_cfg_value.port;
             let address = format!("0.0.0.0:{listen_port}");
 
-            let mut rtsp_server = RtspServer::new(address, producer);
+            let rtsp_auth = match &rtsp_cfg_value.auth {
+                Some(auth_cfg) if auth_cfg.enabled => {
+                    let scheme = match auth_cfg.scheme.as_deref() {
+                        Some("basic") => AuthScheme::Basic,
+                        _ => AuthScheme::Digest,
+                    };
+                    let credentials = auth_cfg
+                        .credentials
+                        .iter()
+                        .map(|credential| RtspCredential {
+                            path: credential.path.clone(),
+                            username: credential.username.clone(),
+                            password: credential.password.clone(),
+                            read: credential.read,
+                            publish: credential.publish,
+                        })
+                        .collect();
+                    let realm = auth_cfg.realm.clone().unwrap_or(String::from("xiu"));
+                    Some(RtspServerAuth::new(realm, scheme, credentials))
+                }
+                _ => None,
+            };
+
+            if let Some((tls_port, tls_acceptor)) =
+                Self::load_tls_acceptor(&rtsp_cfg_value.tls, &mut self.tls_acceptors)?
+            {
+                let mut rtsps_server = RtspServer::new(
+                    format!("0.0.0.0:{tls_port}"),
+                    producer.clone(),
+                    self.auth.clone(),
+                    rtsp_auth.clone(),
+                    Some(tls_acceptor),
+                    stream_hub.get_session_counters(),
+                );
+                tokio::spawn(async move {
+                    if let Err(err) = rtsps_server.run().await {
+                        log::error!("rtsps server error: {}\n", err);
+                    }
+                });
+            }
+
+            let mut rtsp_server = RtspServer::new(
+                address,
+                producer,
+                self.auth.clone(),
+                rtsp_auth,
+                None,
+                stream_hub.get_session_counters(),
+            );
             tokio::spawn(async move {
                 if let Err(err) = rtsp_server.run().await {
                     log::error!("rtsp server error: {}\n", err);
@@ -229,7 +654,30 @@ impl Service {
             let listen_port = webrtc_cfg_value.port;
             let address = format!("0.0.0.0:{listen_port}");
 
-            let mut webrtc_server = WebRTCServer::new(address, producer);
+            if let Some((tls_port, tls_acceptor)) =
+                Self::load_tls_acceptor(&webrtc_cfg_value.tls, &mut self.tls_acceptors)?
+            {
+                let mut webrtc_tls_server = WebRTCServer::new(
+                    format!("0.0.0.0:{tls_port}"),
+                    producer.clone(),
+                    self.auth.clone(),
+                    Some(tls_acceptor),
+                    stream_hub.get_session_counters(),
+                );
+                tokio::spawn(async move {
+                    if let Err(err) = webrtc_tls_server.run().await {
+                        log::error!("webrtc https server error: {}\n", err);
+                    }
+                });
+            }
+
+            let mut webrtc_server = WebRTCServer::new(
+                address,
+                producer,
+                self.auth.clone(),
+                None,
+                stream_hub.get_session_counters(),
+            );
             tokio::spawn(async move {
                 if let Err(err) = webrtc_server.run().await {
                     log::error!("webrtc server error: {}\n", err);
@@ -240,6 +688,37 @@ impl Service {
         Ok(())
     }
 
+    async fn start_srt(&mut self, stream_hub: &mut StreamsHub) -> Result<()> {
+        let srt_cfg = &self.cfg.srt;
+
+        if let Some(srt_cfg_value) = srt_cfg {
+            if !srt_cfg_value.enabled {
+                return Ok(());
+            }
+
+            let producer = stream_hub.get_hub_event_sender();
+
+            let listen_port = srt_cfg_value.port;
+            let address = format!("0.0.0.0:{listen_port}");
+            let latency = Duration::from_millis(srt_cfg_value.latency_ms.unwrap_or(120));
+
+            let mut srt_server = SrtServer::new(
+                address,
+                producer,
+                latency,
+                self.auth.clone(),
+                stream_hub.get_session_counters(),
+            );
+            tokio::spawn(async move {
+                if let Err(err) = srt_server.run().await {
+                    log::error!("srt server error: {}\n", err);
+                }
+            });
+        }
+
+        Ok(())
+    }
+
     async fn start_httpflv(&mut self, stream_hub: &mut StreamsHub) -> Result<()> {
         let httpflv_cfg = &self.cfg.httpflv;
 
@@ -249,9 +728,34 @@ impl Service {
             }
             let port = httpflv_cfg_value.port;
             let event_producer = stream_hub.get_hub_event_sender();
+            let auth = self.auth.clone();
+            let session_counters = stream_hub.get_session_counters();
+
+            if let Some((tls_port, tls_acceptor)) =
+                Self::load_tls_acceptor(&httpflv_cfg_value.tls, &mut self.tls_acceptors)?
+            {
+                let event_producer = event_producer.clone();
+                let auth = auth.clone();
+                let session_counters = session_counters.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = httpflv_server::run(
+                        event_producer,
+                        tls_port,
+                        auth,
+                        Some(tls_acceptor),
+                        session_counters,
+                    )
+                    .await
+                    {
+                        log::error!("httpflv https server error: {}\n", err);
+                    }
+                });
+            }
 
             tokio::spawn(async move {
-                if let Err(err) = httpflv_server::run(event_producer, port).await {
+                if let Err(err) =
+                    httpflv_server::run(event_producer, port, auth, None, session_counters).await
+                {
                     log::error!("httpflv server error: {}\n", err);
                 }
             });
@@ -270,10 +774,15 @@ impl Service {
 
             let event_producer = stream_hub.get_hub_event_sender();
             let cient_event_consumer = stream_hub.get_client_event_consumer();
+            let hls_store = HlsStore::new();
+            let (default_options, app_options) = hls_options(hls_cfg_value)
+                .map_err(|err| anyhow::anyhow!("hls config error: {}", err))?;
             let mut hls_remuxer = HlsRemuxer::new(
                 cient_event_consumer,
                 event_producer,
-                hls_cfg_value.need_record,
+                default_options,
+                app_options,
+                hls_store.clone(),
             );
 
             tokio::spawn(async move {
@@ -284,8 +793,20 @@ impl Service {
 
             let port = hls_cfg_value.port;
 
+            if let Some((tls_port, tls_acceptor)) =
+                Self::load_tls_acceptor(&hls_cfg_value.tls, &mut self.tls_acceptors)?
+            {
+                let hls_store = hls_store.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = hls_server::run(tls_port, hls_store, Some(tls_acceptor)).await
+                    {
+                        log::error!("hls https server error: {}\n", err);
+                    }
+                });
+            }
+
             tokio::spawn(async move {
-                if let Err(err) = hls_server::run(port).await {
+                if let Err(err) = hls_server::run(port, hls_store, None).await {
                     log::error!("hls server error: {}\n", err);
                 }
             });
@@ -295,3 +816,188 @@ impl Service {
         Ok(())
     }
 }
+
+//the default hls options and the ones of the separately configured applications
+fn get_rtsp_protocol_type(transport: &Option<String>) -> ProtocolType {
+    match transport.as_deref() {
+        Some("udp") => ProtocolType::UDP,
+        _ => ProtocolType::TCP,
+    }
+}
+
+//the enabled rtsp push targets, the path of the url is replaced by the stream path
+fn rtsp_push_targets(cfg: &[RtspPushConfig]) -> Vec<RtspPushTarget> {
+    cfg.iter()
+        .filter(|push_value| push_value.enabled)
+        .map(|push_value| RtspPushTarget {
+            url: RtspUrl {
+                host: push_value.address.clone(),
+                port: push_value.port,
+                path: String::from(""),
+                username: push_value.username.clone(),
+                password: push_value.password.clone(),
+            },
+            protocol_type: get_rtsp_protocol_type(&push_value.transport),
+        })
+        .collect()
+}
+
+fn hls_options(cfg: &HlsConfig) -> Result<(HlsOptions, HashMap<String, HlsOptions>), ConfigError> {
+    let mut default_options = HlsOptions {
+        need_record: cfg.need_record,
+        ..Default::default()
+    };
+    override_hls_options(
+        &mut default_options,
+        &HlsAppConfig {
+            name: String::default(),
+            need_record: None,
+            fmp4: cfg.fmp4,
+            low_latency: cfg.low_latency,
+            target_duration: cfg.target_duration,
+            playlist_window: cfg.playlist_window,
+            output_dir: cfg.output_dir.clone(),
+            segment_name: cfg.segment_name.clone(),
+            delete_on_unpublish: cfg.delete_on_unpublish,
+            keep_duration: cfg.keep_duration,
+            program_date_time: cfg.program_date_time,
+        },
+    )?;
+
+    let mut app_options = HashMap::new();
+    for app_cfg in cfg.apps.iter().flatten() {
+        let mut options = default_options.clone();
+        override_hls_options(&mut options, app_cfg)?;
+        app_options.insert(app_cfg.name.clone(), options);
+    }
+
+    Ok((default_options, app_options))
+}
+
+fn override_hls_options(options: &mut HlsOptions, cfg: &HlsAppConfig) -> Result<(), ConfigError> {
+    if let Some(need_record) = cfg.need_record {
+        options.need_record = need_record;
+    }
+    if let Some(fmp4) = cfg.fmp4 {
+        options.fmp4 = fmp4;
+    }
+    if let Some(low_latency) = cfg.low_latency {
+        options.low_latency = low_latency;
+    }
+    if let Some(target_duration) = cfg.target_duration {
+        options.target_duration = target_duration;
+    }
+    if let Some(playlist_window) = cfg.playlist_window {
+        options.playlist_window = playlist_window;
+    }
+    if let Some(output_dir) = &cfg.output_dir {
+        options.output_dir = output_dir.clone();
+    }
+    if let Some(segment_name) = &cfg.segment_name {
+        options.segment_name = segment_name.clone();
+    }
+    if let Some(delete_on_unpublish) = cfg.delete_on_unpublish {
+        options.delete_on_unpublish = delete_on_unpublish;
+    }
+    if let Some(keep_duration) = cfg.keep_duration {
+        options.keep_duration = keep_duration;
+    }
+    if let Some(program_date_time) = cfg.program_date_time {
+        options.program_date_time = program_date_time;
+    }
+
+    let invalid_reason = if options.target_duration <= 0 {
+        Some("target_duration should be greater than 0")
+    } else if options.playlist_window == 0 {
+        Some("playlist_window should be at least 1")
+    } else if !options.segment_name.contains("{seq}") || options.segment_name.contains('/') {
+        Some("segment_name should contain {seq} and no slash")
+    } else {
+        None
+    };
+    match invalid_reason {
+        //the default options have no app name
+        Some(reason) if cfg.name.is_empty() => Err(ConfigError {
+            value: ConfigErrorValue::InvalidConfig(reason.to_string()),
+        }),
+        Some(reason) => Err(ConfigError {
+            value: ConfigErrorValue::InvalidConfig(format!("{} of app {}", reason, cfg.name)),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hls_options, rtsp_push_targets};
+    use crate::config::{HlsConfig, RtspConfig};
+    use xrtsp::rtsp_transport::ProtocolType;
+
+    #[test]
+    fn test_hls_options() {
+        let cfg: HlsConfig = toml::from_str(
+            r#"
+            enabled = true
+            port = 8080
+            need_record = false
+            playlist_window = 3
+            [[apps]]
+            name = "live"
+            segment_name = "{stream}-{seq}"
+            keep_duration = 60
+            "#,
+        )
+        .unwrap();
+        let (default_options, app_options) = hls_options(&cfg).unwrap();
+        assert_eq!(default_options.playlist_window, 3);
+        assert_eq!(app_options["live"].segment_name, "{stream}-{seq}");
+        assert_eq!(default_options.keep_duration, 600);
+        assert_eq!(app_options["live"].keep_duration, 60);
+
+        for invalid in [
+            "playlist_window = 0",
+            "target_duration = 0",
+            "segment_name = \"{stream}\"",
+            "[[apps]]\nname = \"live\"\nsegment_name = \"{app}/{seq}\"",
+        ] {
+            let cfg: HlsConfig = toml::from_str(&format!(
+                "enabled = true\nport = 8080\nneed_record = false\n{invalid}"
+            ))
+            .unwrap();
+            assert!(hls_options(&cfg).is_err());
+        }
+    }
+
+    #[test]
+    fn test_rtsp_push_targets() {
+        let cfg: RtspConfig = toml::from_str(
+            r#"
+            enabled = true
+            port = 554
+            [[push]]
+            enabled = true
+            address = "192.168.0.5"
+            port = 554
+            username = "admin"
+            password = "123456"
+            [[push]]
+            enabled = false
+            address = "192.168.0.6"
+            port = 554
+            [[push]]
+            enabled = true
+            address = "192.168.0.7"
+            port = 8554
+            transport = "udp"
+            "#,
+        )
+        .unwrap();
+        let targets = rtsp_push_targets(&cfg.push.unwrap());
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0].url.address(), "192.168.0.5:554");
+        assert_eq!(targets[0].url.username.as_deref(), Some("admin"));
+        assert_eq!(targets[0].protocol_type, ProtocolType::TCP);
+        assert_eq!(targets[1].url.address(), "192.168.0.7:8554");
+        assert_eq!(targets[1].protocol_type, ProtocolType::UDP);
+    }
+}
@@ -9,6 +9,7 @@ use std::time::Duration;
 
 use tokio::net::TcpStream;
 use tokio::time::sleep;
+use tokio_rustls::server::TlsStream;
 
 use futures::SinkExt;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -54,6 +55,29 @@ impl UdpIO {
 
         None
     }
+    //only bind the local port, the remote address is connected later,
+    //e.g. the rtsp client knows the server ports after the SETUP response.
+    pub async fn bind(local_port: u16) -> Option<Self> {
+        let local_address = format!("0.0.0.0:{local_port}");
+        match UdpSocket::bind(local_address).await {
+            Ok(local_socket) => Some(Self {
+                socket: local_socket,
+            }),
+            Err(err) => {
+                log::info!("bind local udp socket error: {}", err);
+                None
+            }
+        }
+    }
+    pub async fn connect(
+        &mut self,
+        remote_domain: String,
+        remote_port: u16,
+    ) -> Result<(), BytesIOError> {
+        let remote_address = format!("{remote_domain}:{remote_port}");
+        self.socket.connect(remote_address).await?;
+        Ok(())
+    }
     pub fn get_local_port(&self) -> Option<u16> {
         if let Ok(local_addr) = self.socket.local_addr() {
             log::info!("local address: {}", local_addr);
@@ -171,3 +195,50 @@ impl TNetIO for TcpIO {
         }
     }
 }
+
+//the tcp stream over tls on the server side, e.g. rtsps
+pub struct TlsIO {
+    stream: Framed<TlsStream<TcpStream>, BytesCodec>,
+}
+
+impl TlsIO {
+    pub fn new(stream: TlsStream<TcpStream>) -> Self {
+        Self {
+            stream: Framed::new(stream, BytesCodec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl TNetIO for TlsIO {
+    fn get_net_type(&self) -> NetType {
+        NetType::TCP
+    }
+
+    async fn write(&mut self, bytes: Bytes) -> Result<(), BytesIOError> {
+        self.stream.send(bytes).await?;
+
+        Ok(())
+    }
+
+    async fn read_timeout(&mut self, duration: Duration) -> Result<BytesMut, BytesIOError> {
+        match tokio::time::timeout(duration, self.read()).await {
+            Ok(rv) => rv,
+            Err(_) => Err(BytesIOError {
+                value: BytesIOErrorValue::TimeoutError,
+            }),
+        }
+    }
+
+    async fn read(&mut self) -> Result<BytesMut, BytesIOError> {
+        match self.stream.next().await {
+            Some(Ok(bytes)) => Ok(bytes),
+            Some(Err(err)) => Err(BytesIOError {
+                value: BytesIOErrorValue::IOError(err),
+            }),
+            None => Err(BytesIOError {
+                value: BytesIOErrorValue::NoneReturn,
+            }),
+        }
+    }
+}
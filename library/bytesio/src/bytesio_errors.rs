@@ -15,6 +15,8 @@ pub enum BytesIOErrorValue {
     TimeoutError,
     #[fail(display = "none return")]
     NoneReturn,
+    #[fail(display = "tls config error: {}\n", _0)]
+    TlsConfigError(String),
 }
 #[derive(Debug)]
 pub struct BytesIOError {
@@ -10,3 +10,4 @@ pub mod bytes_reader;
 pub mod bytes_writer;
 pub mod bytesio;
 pub mod bytesio_errors;
+pub mod tls;
@@ -28,14 +28,40 @@ pub mod frame_type {
     */
     pub const KEY_FRAME: u8 = 1;
     pub const INTER_FRAME: u8 = 2;
+    pub const COMMAND_FRAME: u8 = 5;
 }
 
-#[derive(Debug, Clone, Serialize, Default)]
+//the packet types of the enhanced rtmp(E-RTMP) extended video tag header
+pub mod ex_video_packet_type {
+    pub const SEQUENCE_START: u8 = 0;
+    pub const CODED_FRAMES: u8 = 1;
+    pub const SEQUENCE_END: u8 = 2;
+    //the composition time is zero and is not carried(hevc only)
+    pub const CODED_FRAMES_X: u8 = 3;
+    pub const METADATA: u8 = 4;
+    pub const MPEG2TS_SEQUENCE_START: u8 = 5;
+}
+
+//the video fourcc values of the enhanced rtmp
+pub mod fourcc {
+    pub const AV1: u32 = u32::from_be_bytes(*b"av01");
+    pub const VP9: u32 = u32::from_be_bytes(*b"vp09");
+    pub const HEVC: u32 = u32::from_be_bytes(*b"hvc1");
+
+    pub fn to_string(fourcc: u32) -> String {
+        String::from_utf8_lossy(&fourcc.to_be_bytes()).to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
 pub enum AvcCodecId {
     #[default]
     UNKNOWN = 0,
     H264 = 7,
     HEVC = 12,
+    //only carried by the enhanced rtmp using fourcc, no legacy codec id
+    AV1,
+    VP9,
 }
 
 pub fn u8_2_avc_codec_id(codec_id: u8) -> AvcCodecId {
@@ -46,6 +72,15 @@ pub fn u8_2_avc_codec_id(codec_id: u8) -> AvcCodecId {
     }
 }
 
+pub fn fourcc_2_avc_codec_id(value: u32) -> AvcCodecId {
+    match value {
+        fourcc::HEVC => AvcCodecId::HEVC,
+        fourcc::AV1 => AvcCodecId::AV1,
+        fourcc::VP9 => AvcCodecId::VP9,
+        _ => AvcCodecId::UNKNOWN,
+    }
+}
+
 pub mod tag_type {
     pub const AUDIO: u8 = 8;
     pub const VIDEO: u8 = 9;
@@ -58,6 +93,16 @@ pub mod h264_nal_type {
     pub const H264_NAL_PPS: u8 = 8;
     pub const H264_NAL_AUD: u8 = 9;
 }
+
+pub mod h265_nal_type {
+    //the IRAP(intra random access point) frames are 16~23
+    pub const H265_NAL_BLA_W_LP: u8 = 16;
+    pub const H265_NAL_RSV_IRAP_23: u8 = 23;
+    pub const H265_NAL_VPS: u8 = 32;
+    pub const H265_NAL_SPS: u8 = 33;
+    pub const H265_NAL_PPS: u8 = 34;
+    pub const H265_NAL_AUD: u8 = 35;
+}
 #[derive(Debug, Clone, Serialize, Default)]
 pub enum AacProfile {
     // @see @see ISO_IEC_14496-3-AAC-2001.pdf, page 23
@@ -5,10 +5,14 @@ use crate::{
 
 use {
     super::{
-        define::{aac_packet_type, avc_packet_type, tag_type, AvcCodecId, FlvData, SoundFormat},
+        define::{
+            aac_packet_type, avc_packet_type, ex_video_packet_type, tag_type, AvcCodecId, FlvData,
+            SoundFormat,
+        },
         errors::FlvDemuxerError,
         mpeg4_aac::Mpeg4AacProcessor,
         mpeg4_avc::Mpeg4AvcProcessor,
+        mpeg4_hevc::Mpeg4HevcProcessor,
     },
     byteorder::BigEndian,
     bytes::BytesMut,
@@ -114,12 +118,44 @@ impl FlvDemuxerVideoData {
 #[derive(Default)]
 pub struct FlvVideoTagDemuxer {
     avc_processor: Mpeg4AvcProcessor,
+    hevc_processor: Mpeg4HevcProcessor,
+    /*the codec of the latest sequence header*/
+    codec_id: AvcCodecId,
 }
 
 impl FlvVideoTagDemuxer {
     pub fn new() -> Self {
         Self {
             avc_processor: Mpeg4AvcProcessor::new(),
+            hevc_processor: Mpeg4HevcProcessor::default(),
+            codec_id: AvcCodecId::UNKNOWN,
+        }
+    }
+
+    pub fn codec_id(&self) -> AvcCodecId {
+        self.codec_id.clone()
+    }
+
+    pub fn codec_string(&self) -> Option<String> {
+        match self.codec_id {
+            AvcCodecId::H264 => Some(self.avc_processor.mpeg4_avc.codec_string()),
+            AvcCodecId::HEVC => Some(self.hevc_processor.mpeg4_hevc.codec_string()),
+            _ => None,
+        }
+    }
+
+    //(width, height) parsed from the SPS of the video sequence header
+    pub fn resolution(&self) -> (u32, u32) {
+        match self.codec_id {
+            AvcCodecId::H264 => (
+                self.avc_processor.mpeg4_avc.width,
+                self.avc_processor.mpeg4_avc.height,
+            ),
+            AvcCodecId::HEVC => (
+                self.hevc_processor.mpeg4_hevc.width,
+                self.hevc_processor.mpeg4_hevc.height,
+            ),
+            _ => (0, 0),
         }
     }
     pub fn demux(
@@ -130,11 +166,16 @@ impl FlvVideoTagDemuxer {
         let mut reader = BytesReader::new(data);
 
         let tag_header = VideoTagHeader::unmarshal(&mut reader)?;
+        if tag_header.codec() == AvcCodecId::HEVC {
+            return self.demux_hevc(timestamp, tag_header, reader);
+        }
+
         if tag_header.codec_id == AvcCodecId::H264 as u8 {
             match tag_header.avc_packet_type {
                 avc_packet_type::AVC_SEQHDR => {
                     self.avc_processor
                         .decoder_configuration_record_load(&mut reader)?;
+                    self.codec_id = AvcCodecId::H264;
 
                     return Ok(None);
                 }
@@ -157,6 +198,37 @@ impl FlvVideoTagDemuxer {
 
         Ok(None)
     }
+
+    //both the legacy(codec id 12) and the enhanced rtmp(fourcc hvc1) hevc tags
+    fn demux_hevc(
+        &mut self,
+        timestamp: u32,
+        tag_header: VideoTagHeader,
+        mut reader: BytesReader,
+    ) -> Result<Option<FlvDemuxerVideoData>, FlvDemuxerError> {
+        if tag_header.is_seq_header() {
+            self.hevc_processor
+                .decoder_configuration_record_load(&mut reader)?;
+            self.codec_id = AvcCodecId::HEVC;
+            return Ok(None);
+        }
+
+        let is_coded_frames = tag_header.avc_packet_type == avc_packet_type::AVC_NALU
+            || (tag_header.is_ex_header
+                && tag_header.avc_packet_type == ex_video_packet_type::CODED_FRAMES_X);
+        if !is_coded_frames {
+            return Ok(None);
+        }
+
+        let data = self.hevc_processor.hevc_mp4toannexb(&mut reader)?;
+        Ok(Some(FlvDemuxerVideoData {
+            codec_id: AvcCodecId::HEVC as u8,
+            pts: timestamp as i64 + tag_header.composition_time as i64,
+            dts: timestamp as i64,
+            frame_type: tag_header.frame_type,
+            data,
+        }))
+    }
 }
 
 #[derive(Default)]
@@ -171,6 +243,22 @@ impl FlvAudioTagDemuxer {
         }
     }
 
+    //none if the aac sequence header is not received
+    pub fn codec_string(&self) -> Option<String> {
+        if self.aac_processor.mpeg4_aac.profile == 0 {
+            return None;
+        }
+        Some(self.aac_processor.mpeg4_aac.codec_string())
+    }
+
+    //(sampling frequency, channels) parsed from the aac sequence header
+    pub fn audio_config(&self) -> (u32, u8) {
+        (
+            self.aac_processor.mpeg4_aac.sampling_frequency,
+            self.aac_processor.mpeg4_aac.channels,
+        )
+    }
+
     pub fn demux(
         &mut self,
         timestamp: u32,
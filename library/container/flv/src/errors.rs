@@ -185,6 +185,14 @@ impl From<BytesWriteError> for Mpeg4AvcHevcError {
     }
 }
 
+impl From<BitError> for Mpeg4AvcHevcError {
+    fn from(error: BitError) -> Self {
+        Mpeg4AvcHevcError {
+            value: MpegErrorValue::BitError(error),
+        }
+    }
+}
+
 impl From<H264Error> for Mpeg4AvcHevcError {
     fn from(error: H264Error) -> Self {
         Mpeg4AvcHevcError {
@@ -6,6 +6,7 @@ use {
         errors::{FlvDemuxerError, FlvMuxerError},
     },
     super::{Marshal, Unmarshal},
+    byteorder::BigEndian,
     bytes::BytesMut,
     bytesio::bytes_reader::BytesReader,
 };
@@ -142,9 +143,16 @@ pub struct VideoTagHeader {
         0: AVC sequence header
         1: AVC NALU
         2: AVC end of sequence (lower level NALU sequence ender is not required or supported)
+        For the extended video tag header it is the ex video packet type.
     */
     pub avc_packet_type: u8,
     pub composition_time: i32,
+    /*
+        The enhanced rtmp extended video tag header:
+        IsExHeader UB[1] | FrameType UB[3] | PacketType UB[4] | FourCC UI32
+    */
+    pub is_ex_header: bool,
+    pub fourcc: u32,
 }
 
 impl VideoTagHeader {
@@ -154,6 +162,38 @@ impl VideoTagHeader {
             codec_id: 0,
             avc_packet_type: 0,
             composition_time: 0,
+            is_ex_header: false,
+            fourcc: 0,
+        }
+    }
+
+    pub fn codec(&self) -> define::AvcCodecId {
+        if self.is_ex_header {
+            define::fourcc_2_avc_codec_id(self.fourcc)
+        } else {
+            define::u8_2_avc_codec_id(self.codec_id)
+        }
+    }
+
+    pub fn is_key_frame(&self) -> bool {
+        self.frame_type == define::frame_type::KEY_FRAME
+    }
+
+    //AVC_SEQHDR and SEQUENCE_START are both 0
+    pub fn is_seq_header(&self) -> bool {
+        match self.codec() {
+            define::AvcCodecId::UNKNOWN => false,
+            _ => self.avc_packet_type == define::avc_packet_type::AVC_SEQHDR,
+        }
+    }
+
+    fn has_composition_time(&self) -> bool {
+        if self.is_ex_header {
+            self.fourcc == define::fourcc::HEVC
+                && self.avc_packet_type == define::ex_video_packet_type::CODED_FRAMES
+        } else {
+            self.codec_id == define::AvcCodecId::H264 as u8
+                || self.codec_id == define::AvcCodecId::HEVC as u8
         }
     }
 }
@@ -166,22 +206,24 @@ impl Unmarshal<&mut BytesReader, Result<Self, FlvDemuxerError>> for VideoTagHead
         let mut tag_header = VideoTagHeader::defalut();
 
         let flags = reader.read_u8()?;
-        tag_header.frame_type = flags >> 4;
-        tag_header.codec_id = flags & 0x0f;
+        tag_header.is_ex_header = flags >> 7 == 1;
 
-        if tag_header.codec_id == define::AvcCodecId::H264 as u8
-            || tag_header.codec_id == define::AvcCodecId::HEVC as u8
-        {
-            tag_header.avc_packet_type = reader.read_u8()?;
-            tag_header.composition_time = 0;
+        if tag_header.is_ex_header {
+            tag_header.frame_type = (flags >> 4) & 0x07;
+            tag_header.avc_packet_type = flags & 0x0f;
+            tag_header.fourcc = reader.read_u32::<BigEndian>()?;
+        } else {
+            tag_header.frame_type = flags >> 4;
+            tag_header.codec_id = flags & 0x0f;
 
-            //bigend 3bytes
-            for _ in 0..3 {
-                let time = reader.read_u8()?;
-                //print!("==time0=={}\n", time);
-                //print!("==time1=={}\n", self.tag.composition_time);
-                tag_header.composition_time = (tag_header.composition_time << 8) + time as i32;
+            if tag_header.has_composition_time() {
+                tag_header.avc_packet_type = reader.read_u8()?;
             }
+        }
+
+        if tag_header.has_composition_time() {
+            //bigend 3bytes
+            tag_header.composition_time = reader.read_u24::<BigEndian>()? as i32;
             //transfer to signed i24
             if tag_header.composition_time & (1 << 23) != 0 {
                 let sign_extend_mask = 0xff_ff << 23;
@@ -198,21 +240,55 @@ impl Marshal<Result<BytesMut, FlvMuxerError>> for VideoTagHeader {
     fn marshal(&self) -> Result<BytesMut, FlvMuxerError> {
         let mut writer = BytesWriter::default();
 
-        let byte_1st = self.frame_type << 4 | self.codec_id;
-        writer.write_u8(byte_1st)?;
-
-        if self.codec_id == define::AvcCodecId::H264 as u8
-            || self.codec_id == define::AvcCodecId::HEVC as u8
-        {
-            writer.write_u8(self.avc_packet_type)?;
+        if self.is_ex_header {
+            let byte_1st = 0x80 | (self.frame_type & 0x07) << 4 | (self.avc_packet_type & 0x0f);
+            writer.write_u8(byte_1st)?;
+            writer.write_u32::<BigEndian>(self.fourcc)?;
+        } else {
+            let byte_1st = self.frame_type << 4 | self.codec_id;
+            writer.write_u8(byte_1st)?;
 
-            let mut cts = self.composition_time;
-            for _ in 0..3 {
-                writer.write_u8((cts & 0xFF) as u8)?;
-                cts >>= 8;
+            if self.has_composition_time() {
+                writer.write_u8(self.avc_packet_type)?;
             }
         }
 
+        if self.has_composition_time() {
+            writer.write_u24::<BigEndian>((self.composition_time & 0xFF_FFFF) as u32)?;
+        }
+
         Ok(writer.extract_current_bytes())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{define, BytesReader, Marshal, Unmarshal, VideoTagHeader};
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_ex_video_tag_header() {
+        let mut tag_header = VideoTagHeader::defalut();
+        tag_header.is_ex_header = true;
+        tag_header.frame_type = define::frame_type::KEY_FRAME;
+        tag_header.avc_packet_type = define::ex_video_packet_type::CODED_FRAMES;
+        tag_header.fourcc = define::fourcc::HEVC;
+        tag_header.composition_time = -40;
+
+        let data = tag_header.marshal().unwrap();
+        assert_eq!(&data[..], &[0x91, b'h', b'v', b'c', b'1', 0xFF, 0xFF, 0xD8]);
+
+        let header = VideoTagHeader::unmarshal(&mut BytesReader::new(data)).unwrap();
+        assert!(header.is_key_frame());
+        assert_eq!(header.codec(), define::AvcCodecId::HEVC);
+        assert_eq!(header.composition_time, -40);
+
+        //av1 has no composition time
+        let data = BytesMut::from(&[0x90, b'a', b'v', b'0', b'1', 0x0A][..]);
+        let mut reader = BytesReader::new(data);
+        let header = VideoTagHeader::unmarshal(&mut reader).unwrap();
+        assert!(header.is_seq_header());
+        assert_eq!(header.codec(), define::AvcCodecId::AV1);
+        assert_eq!(reader.len(), 1);
+    }
+}
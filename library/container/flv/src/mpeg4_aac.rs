@@ -47,6 +47,11 @@ impl Mpeg4Aac {
             npce: 0,
         }
     }
+
+    //RFC6381 codecs parameter, the profile is the audio object type, e.g.: mp4a.40.2
+    pub fn codec_string(&self) -> String {
+        format!("mp4a.40.{}", self.profile)
+    }
 }
 
 pub struct Mpeg4AacProcessor {
@@ -118,6 +118,14 @@ impl Mpeg4Avc {
             bit_depth_luma_minus8: 0,
         }
     }
+
+    //RFC6381 codecs parameter, e.g.: avc1.64001F
+    pub fn codec_string(&self) -> String {
+        format!(
+            "avc1.{:02X}{:02X}{:02X}",
+            self.profile, self.compatibility, self.level
+        )
+    }
 }
 
 #[derive(Default)]
@@ -1,25 +1,82 @@
-use {super::errors::Mpeg4AvcHevcError, byteorder::BigEndian, bytesio::bytes_reader::BytesReader};
-#[allow(dead_code)]
-#[derive(Default)]
+use {
+    super::{define::h265_nal_type, errors::Mpeg4AvcHevcError},
+    byteorder::BigEndian,
+    bytes::BytesMut,
+    bytesio::{bits_reader::BitsReader, bytes_reader::BytesReader, bytes_writer::BytesWriter},
+    h264_decoder::utils::read_uev,
+};
+
+const H265_START_CODE: [u8; 4] = [0x00, 0x00, 0x00, 0x01];
+
+#[derive(Clone, Default)]
+pub struct HevcNaluArray {
+    pub array_completeness: u8,
+    pub nal_unit_type: u8,
+    pub nalus: Vec<BytesMut>,
+}
 
+#[derive(Default)]
 pub struct Mpeg4Hevc {
-    configuration_version: u8, // 1-only
-    general_profile_space: u8, // 2bit,[0,3]
-    general_tier_flag: u8,     // 1bit,[0,1]
-    general_profile_idc: u8,   // 5bit,[0,31]
-    general_profile_compatibility_flags: u32,
-    general_constraint_indicator_flags: u64,
-    general_level_idc: u8,
-    min_spatial_segmentation_idc: u16,
-    parallelism_type: u8,        // 2bit,[0,3]
-    chroma_format: u8,           // 2bit,[0,3]
-    bit_depth_luma_minus8: u8,   // 3bit,[0,7]
-    bit_depth_chroma_minus8: u8, // 3bit,[0,7]
-    avg_frame_rate: u16,
-    constant_frame_rate: u8,   // 2bit,[0,3]
-    num_temporal_layers: u8,   // 3bit,[0,7]
-    temporal_id_nested: u8,    // 1bit,[0,1]
-    length_size_minus_one: u8, // 2bit,[0,3]
+    pub configuration_version: u8, // 1-only
+    pub general_profile_space: u8, // 2bit,[0,3]
+    pub general_tier_flag: u8,     // 1bit,[0,1]
+    pub general_profile_idc: u8,   // 5bit,[0,31]
+    pub general_profile_compatibility_flags: u32,
+    pub general_constraint_indicator_flags: u64,
+    pub general_level_idc: u8,
+    pub min_spatial_segmentation_idc: u16,
+    pub parallelism_type: u8,        // 2bit,[0,3]
+    pub chroma_format: u8,           // 2bit,[0,3]
+    pub bit_depth_luma_minus8: u8,   // 3bit,[0,7]
+    pub bit_depth_chroma_minus8: u8, // 3bit,[0,7]
+    pub avg_frame_rate: u16,
+    pub constant_frame_rate: u8,   // 2bit,[0,3]
+    pub num_temporal_layers: u8,   // 3bit,[0,7]
+    pub temporal_id_nested: u8,    // 1bit,[0,1]
+    pub length_size_minus_one: u8, // 2bit,[0,3]
+    pub width: u32,
+    pub height: u32,
+
+    pub nalu_arrays: Vec<HevcNaluArray>,
+    pub vps_sps_pps_annexb_data: BytesWriter, // pice together all the vps/sps/pps data
+}
+
+impl Mpeg4Hevc {
+    //RFC6381 codecs parameter, e.g.: hvc1.1.6.L93.B0
+    pub fn codec_string(&self) -> String {
+        let profile_space = match self.general_profile_space {
+            1 => "A",
+            2 => "B",
+            3 => "C",
+            _ => "",
+        };
+        let tier = if self.general_tier_flag == 1 {
+            "H"
+        } else {
+            "L"
+        };
+
+        let mut codec = format!(
+            "hvc1.{}{}.{:X}.{}{}",
+            profile_space,
+            self.general_profile_idc,
+            self.general_profile_compatibility_flags.reverse_bits(),
+            tier,
+            self.general_level_idc
+        );
+
+        //6 bytes constraint flags, the trailing zero bytes are omitted
+        let constraint_bytes = &self.general_constraint_indicator_flags.to_be_bytes()[2..];
+        let len = constraint_bytes
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |idx| idx + 1);
+        for byte in &constraint_bytes[..len] {
+            codec += format!(".{byte:X}").as_str();
+        }
+
+        codec
+    }
 }
 
 #[derive(Default)]
@@ -47,7 +104,274 @@ impl Mpeg4HevcProcessor {
         self.mpeg4_hevc.parallelism_type = bytes_reader.read_u8()? & 0x03;
         self.mpeg4_hevc.chroma_format = bytes_reader.read_u8()? & 0x03;
         self.mpeg4_hevc.bit_depth_luma_minus8 = bytes_reader.read_u8()? & 0x07;
+        self.mpeg4_hevc.bit_depth_chroma_minus8 = bytes_reader.read_u8()? & 0x07;
+        self.mpeg4_hevc.avg_frame_rate = bytes_reader.read_u16::<BigEndian>()?;
+
+        let byte_21 = bytes_reader.read_u8()?;
+        self.mpeg4_hevc.constant_frame_rate = (byte_21 >> 6) & 0x03;
+        self.mpeg4_hevc.num_temporal_layers = (byte_21 >> 3) & 0x07;
+        self.mpeg4_hevc.temporal_id_nested = (byte_21 >> 2) & 0x01;
+        self.mpeg4_hevc.length_size_minus_one = byte_21 & 0x03;
+
+        self.mpeg4_hevc.nalu_arrays.clear();
+        self.mpeg4_hevc.vps_sps_pps_annexb_data.clear();
+
+        /*numOfArrays*/
+        let num_of_arrays = bytes_reader.read_u8()?;
+        for _ in 0..num_of_arrays {
+            let byte = bytes_reader.read_u8()?;
+            let mut nalu_array = HevcNaluArray {
+                array_completeness: (byte >> 7) & 0x01,
+                nal_unit_type: byte & 0x3F,
+                nalus: Vec::new(),
+            };
+
+            let num_nalus = bytes_reader.read_u16::<BigEndian>()?;
+            for _ in 0..num_nalus {
+                let nalu_length = bytes_reader.read_u16::<BigEndian>()?;
+                let nalu = bytes_reader.read_bytes(nalu_length as usize)?;
+
+                match nalu_array.nal_unit_type {
+                    h265_nal_type::H265_NAL_VPS
+                    | h265_nal_type::H265_NAL_SPS
+                    | h265_nal_type::H265_NAL_PPS => {
+                        if nalu_array.nal_unit_type == h265_nal_type::H265_NAL_SPS {
+                            (self.mpeg4_hevc.width, self.mpeg4_hevc.height) =
+                                Self::sps_resolution(&nalu[..])?;
+                        }
+                        self.mpeg4_hevc
+                            .vps_sps_pps_annexb_data
+                            .write(&H265_START_CODE)?;
+                        self.mpeg4_hevc.vps_sps_pps_annexb_data.write(&nalu[..])?;
+                    }
+                    _ => {}
+                }
+                nalu_array.nalus.push(nalu);
+            }
+            self.mpeg4_hevc.nalu_arrays.push(nalu_array);
+        }
+
+        log::info!("mpeg4 hevc codec: {}", self.mpeg4_hevc.codec_string());
+        log::info!(
+            "mpeg4 hevc resolution: {}x{}",
+            self.mpeg4_hevc.width,
+            self.mpeg4_hevc.height
+        );
+        /*clear the left bytes*/
+        bytes_reader.extract_remaining_bytes();
 
         Ok(self)
     }
+
+    pub fn decoder_configuration_record_save(&mut self) -> Result<BytesMut, Mpeg4AvcHevcError> {
+        let hevc = &self.mpeg4_hevc;
+        let mut bytes_writer = BytesWriter::new();
+
+        bytes_writer.write_u8(1)?;
+        bytes_writer.write_u8(
+            hevc.general_profile_space << 6
+                | (hevc.general_tier_flag & 0x01) << 5
+                | (hevc.general_profile_idc & 0x1F),
+        )?;
+        bytes_writer.write_u32::<BigEndian>(hevc.general_profile_compatibility_flags)?;
+        bytes_writer
+            .write_u32::<BigEndian>((hevc.general_constraint_indicator_flags >> 16) as u32)?;
+        bytes_writer.write_u16::<BigEndian>(hevc.general_constraint_indicator_flags as u16)?;
+        bytes_writer.write_u8(hevc.general_level_idc)?;
+        bytes_writer.write_u16::<BigEndian>(0xF000 | hevc.min_spatial_segmentation_idc)?;
+        bytes_writer.write_u8(0xFC | hevc.parallelism_type)?;
+        bytes_writer.write_u8(0xFC | hevc.chroma_format)?;
+        bytes_writer.write_u8(0xF8 | hevc.bit_depth_luma_minus8)?;
+        bytes_writer.write_u8(0xF8 | hevc.bit_depth_chroma_minus8)?;
+        bytes_writer.write_u16::<BigEndian>(hevc.avg_frame_rate)?;
+        bytes_writer.write_u8(
+            hevc.constant_frame_rate << 6
+                | (hevc.num_temporal_layers & 0x07) << 3
+                | (hevc.temporal_id_nested & 0x01) << 2
+                | (hevc.length_size_minus_one & 0x03),
+        )?;
+
+        bytes_writer.write_u8(hevc.nalu_arrays.len() as u8)?;
+        for nalu_array in &hevc.nalu_arrays {
+            bytes_writer.write_u8(nalu_array.array_completeness << 7 | nalu_array.nal_unit_type)?;
+            bytes_writer.write_u16::<BigEndian>(nalu_array.nalus.len() as u16)?;
+            for nalu in &nalu_array.nalus {
+                bytes_writer.write_u16::<BigEndian>(nalu.len() as u16)?;
+                bytes_writer.write(&nalu[..])?;
+            }
+        }
+
+        Ok(bytes_writer.extract_current_bytes())
+    }
+
+    //the vps/sps/pps are inserted before the IRAP frame if they are not carried
+    pub fn hevc_mp4toannexb(
+        &mut self,
+        bytes_reader: &mut BytesReader,
+    ) -> Result<BytesMut, Mpeg4AvcHevcError> {
+        let mut bytes_writer = BytesWriter::new();
+
+        let mut vps_sps_pps_flag = false;
+        while !bytes_reader.is_empty() {
+            let size = self.read_nalu_size(bytes_reader)?;
+            let nalu_type = (bytes_reader.advance_u8()? >> 1) & 0x3F;
+
+            match nalu_type {
+                h265_nal_type::H265_NAL_VPS
+                | h265_nal_type::H265_NAL_SPS
+                | h265_nal_type::H265_NAL_PPS => {
+                    vps_sps_pps_flag = true;
+                }
+                h265_nal_type::H265_NAL_BLA_W_LP..=h265_nal_type::H265_NAL_RSV_IRAP_23
+                    if !vps_sps_pps_flag =>
+                {
+                    vps_sps_pps_flag = true;
+                    bytes_writer.prepend(
+                        &self.mpeg4_hevc.vps_sps_pps_annexb_data.get_current_bytes()[..],
+                    )?;
+                }
+                _ => {}
+            }
+
+            bytes_writer.write(&H265_START_CODE)?;
+            let data = bytes_reader.read_bytes(size as usize)?;
+            bytes_writer.write(&data[..])?;
+        }
+
+        Ok(bytes_writer.extract_current_bytes())
+    }
+
+    //parse the SPS until pic_width/height_in_luma_samples and the conformance window
+    fn sps_resolution(sps: &[u8]) -> Result<(u32, u32), Mpeg4AvcHevcError> {
+        /*remove the emulation prevention bytes (0x000003)*/
+        let mut rbsp = BytesMut::with_capacity(sps.len());
+        let mut zero_count = 0;
+        for byte in sps.iter().skip(2) {
+            if zero_count >= 2 && *byte == 0x03 {
+                zero_count = 0;
+                continue;
+            }
+            zero_count = if *byte == 0 { zero_count + 1 } else { 0 };
+            rbsp.extend_from_slice(&[*byte]);
+        }
+
+        let mut bits_reader = BitsReader::new(BytesReader::new(rbsp));
+        /*sps_video_parameter_set_id*/
+        bits_reader.read_n_bits(4)?;
+        let max_sub_layers_minus1 = bits_reader.read_n_bits(3)? as usize;
+        /*sps_temporal_id_nesting_flag*/
+        bits_reader.read_bit()?;
+
+        /*profile_tier_level: general profile(88 bits) and general_level_idc(8 bits)*/
+        bits_reader.read_n_bits(48)?;
+        bits_reader.read_n_bits(48)?;
+        let mut sub_layer_flags = Vec::new();
+        for _ in 0..max_sub_layers_minus1 {
+            let profile_present = bits_reader.read_bit()?;
+            let level_present = bits_reader.read_bit()?;
+            sub_layer_flags.push((profile_present, level_present));
+        }
+        if max_sub_layers_minus1 > 0 {
+            for _ in max_sub_layers_minus1..8 {
+                bits_reader.read_n_bits(2)?;
+            }
+        }
+        for (profile_present, level_present) in sub_layer_flags {
+            if profile_present == 1 {
+                bits_reader.read_n_bits(44)?;
+                bits_reader.read_n_bits(44)?;
+            }
+            if level_present == 1 {
+                bits_reader.read_n_bits(8)?;
+            }
+        }
+
+        /*sps_seq_parameter_set_id*/
+        read_uev(&mut bits_reader)?;
+        let chroma_format_idc = read_uev(&mut bits_reader)?;
+        if chroma_format_idc == 3 {
+            /*separate_colour_plane_flag*/
+            bits_reader.read_bit()?;
+        }
+        let mut width = read_uev(&mut bits_reader)?;
+        let mut height = read_uev(&mut bits_reader)?;
+
+        if bits_reader.read_bit()? == 1 {
+            let (sub_width, sub_height) = match chroma_format_idc {
+                1 => (2, 2),
+                2 => (2, 1),
+                _ => (1, 1),
+            };
+            let left = read_uev(&mut bits_reader)?;
+            let right = read_uev(&mut bits_reader)?;
+            let top = read_uev(&mut bits_reader)?;
+            let bottom = read_uev(&mut bits_reader)?;
+            width = width.saturating_sub(sub_width * (left + right));
+            height = height.saturating_sub(sub_height * (top + bottom));
+        }
+
+        Ok((width, height))
+    }
+
+    fn read_nalu_size(&mut self, bytes_reader: &mut BytesReader) -> Result<u32, Mpeg4AvcHevcError> {
+        let mut size: u32 = 0;
+
+        for _ in 0..self.mpeg4_hevc.length_size_minus_one + 1 {
+            size = bytes_reader.read_u8()? as u32 + (size << 8);
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mpeg4HevcProcessor;
+    use bytes::BytesMut;
+    use bytesio::bytes_reader::BytesReader;
+
+    #[test]
+    fn test_hevc_decoder_configuration_record() {
+        let mut processor = Mpeg4HevcProcessor::default();
+        processor.mpeg4_hevc.general_profile_idc = 1;
+        processor.mpeg4_hevc.general_profile_compatibility_flags = 0x6000_0000;
+        processor.mpeg4_hevc.general_constraint_indicator_flags = 0xB000_0000_0000;
+        processor.mpeg4_hevc.general_level_idc = 93;
+        processor.mpeg4_hevc.length_size_minus_one = 3;
+        processor.mpeg4_hevc.nalu_arrays = vec![super::HevcNaluArray {
+            array_completeness: 1,
+            nal_unit_type: 32,
+            nalus: vec![BytesMut::from(&[0x40, 0x01, 0x0C][..])],
+        }];
+        let record = processor.decoder_configuration_record_save().unwrap();
+
+        let mut processor = Mpeg4HevcProcessor::default();
+        processor
+            .decoder_configuration_record_load(&mut BytesReader::new(record))
+            .unwrap();
+        assert_eq!(processor.mpeg4_hevc.codec_string(), "hvc1.1.6.L93.B0");
+
+        //an IRAP frame without the vps
+        let frame = BytesMut::from(&[0x00, 0x00, 0x00, 0x02, 0x26, 0x01][..]);
+        let annexb = processor
+            .hevc_mp4toannexb(&mut BytesReader::new(frame))
+            .unwrap();
+        assert_eq!(
+            &annexb[..],
+            &[0, 0, 0, 1, 0x40, 0x01, 0x0C, 0, 0, 0, 1, 0x26, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_hevc_sps_resolution() {
+        let sps = [
+            0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00,
+            0x00, 0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x16, 0x59, 0x59, 0xa4, 0x93,
+            0x2b, 0xc0, 0x5a, 0x02, 0x00, 0x00, 0x03, 0x00, 0x02, 0x00, 0x00, 0x03, 0x00, 0x3c,
+            0x10,
+        ];
+        assert_eq!(
+            Mpeg4HevcProcessor::sps_resolution(&sps[..]).unwrap(),
+            (1280, 720)
+        );
+    }
 }
@@ -0,0 +1,311 @@
+use {
+    super::{
+        define::{AudioConfig, Sample, VideoCodec, VideoConfig},
+        errors::Mp4Error,
+    },
+    byteorder::BigEndian,
+    bytes::BytesMut,
+    bytesio::bytes_writer::BytesWriter,
+};
+
+const UNITY_MATRIX: [u32; 9] = [0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000];
+
+//size(4 bytes) + type(4 bytes) + payloads
+pub fn mp4_box(box_type: &[u8; 4], payloads: &[&[u8]]) -> Result<BytesMut, Mp4Error> {
+    let size: usize = 8 + payloads.iter().map(|payload| payload.len()).sum::<usize>();
+
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(size as u32)?;
+    writer.write(box_type)?;
+    for payload in payloads {
+        writer.write(payload)?;
+    }
+    Ok(writer.extract_current_bytes())
+}
+
+//a box with version(1 byte) and flags(3 bytes) before the payload
+pub fn full_box(
+    box_type: &[u8; 4],
+    version: u8,
+    flags: u32,
+    payload: &[u8],
+) -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    writer.write_u8(version)?;
+    writer.write_u24::<BigEndian>(flags)?;
+    writer.write(payload)?;
+    mp4_box(box_type, &[&writer.extract_current_bytes()[..]])
+}
+
+pub fn ftyp() -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    /*major brand and minor version*/
+    writer.write(b"iso6")?;
+    writer.write_u32::<BigEndian>(0)?;
+    /*compatible brands*/
+    writer.write(b"iso6")?;
+    writer.write(b"cmfc")?;
+    writer.write(b"mp41")?;
+    mp4_box(b"ftyp", &[&writer.extract_current_bytes()[..]])
+}
+
+pub fn mvhd(next_track_id: u32) -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    /*creation_time and modification_time*/
+    writer.write_u32::<BigEndian>(0)?;
+    writer.write_u32::<BigEndian>(0)?;
+    /*timescale and duration*/
+    writer.write_u32::<BigEndian>(1000)?;
+    writer.write_u32::<BigEndian>(0)?;
+    /*rate 1.0 and volume 1.0*/
+    writer.write_u32::<BigEndian>(0x0001_0000)?;
+    writer.write_u16::<BigEndian>(0x0100)?;
+    writer.write(&[0; 10])?;
+    for value in UNITY_MATRIX {
+        writer.write_u32::<BigEndian>(value)?;
+    }
+    /*pre_defined*/
+    writer.write(&[0; 24])?;
+    writer.write_u32::<BigEndian>(next_track_id)?;
+    full_box(b"mvhd", 0, 0, &writer.extract_current_bytes()[..])
+}
+
+fn tkhd(track_id: u32, width: u32, height: u32, is_audio: bool) -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    /*creation_time and modification_time*/
+    writer.write_u32::<BigEndian>(0)?;
+    writer.write_u32::<BigEndian>(0)?;
+    writer.write_u32::<BigEndian>(track_id)?;
+    writer.write_u32::<BigEndian>(0)?;
+    /*duration*/
+    writer.write_u32::<BigEndian>(0)?;
+    writer.write(&[0; 8])?;
+    /*layer and alternate_group*/
+    writer.write_u16::<BigEndian>(0)?;
+    writer.write_u16::<BigEndian>(0)?;
+    writer.write_u16::<BigEndian>(if is_audio { 0x0100 } else { 0 })?;
+    writer.write_u16::<BigEndian>(0)?;
+    for value in UNITY_MATRIX {
+        writer.write_u32::<BigEndian>(value)?;
+    }
+    /*16.16 fixed-point*/
+    writer.write_u32::<BigEndian>(width << 16)?;
+    writer.write_u32::<BigEndian>(height << 16)?;
+    /*track_enabled | track_in_movie*/
+    full_box(b"tkhd", 0, 0x03, &writer.extract_current_bytes()[..])
+}
+
+fn mdhd(timescale: u32) -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(0)?;
+    writer.write_u32::<BigEndian>(0)?;
+    writer.write_u32::<BigEndian>(timescale)?;
+    writer.write_u32::<BigEndian>(0)?;
+    /*language: und*/
+    writer.write_u16::<BigEndian>(0x55C4)?;
+    writer.write_u16::<BigEndian>(0)?;
+    full_box(b"mdhd", 0, 0, &writer.extract_current_bytes()[..])
+}
+
+fn hdlr(is_audio: bool) -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(0)?;
+    if is_audio {
+        writer.write(b"soun")?;
+    } else {
+        writer.write(b"vide")?;
+    }
+    writer.write(&[0; 12])?;
+    if is_audio {
+        writer.write(b"SoundHandler\0")?;
+    } else {
+        writer.write(b"VideoHandler\0")?;
+    }
+    full_box(b"hdlr", 0, 0, &writer.extract_current_bytes()[..])
+}
+
+fn dinf() -> Result<BytesMut, Mp4Error> {
+    /*the media data is in the same file*/
+    let url = full_box(b"url ", 0, 0x01, &[])?;
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(1)?;
+    writer.write(&url[..])?;
+    let dref = full_box(b"dref", 0, 0, &writer.extract_current_bytes()[..])?;
+    mp4_box(b"dinf", &[&dref[..]])
+}
+
+fn stbl(sample_entry: &[u8]) -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(1)?;
+    writer.write(sample_entry)?;
+    let stsd = full_box(b"stsd", 0, 0, &writer.extract_current_bytes()[..])?;
+
+    /*the samples are described in the movie fragments*/
+    let empty_entries = 0_u32.to_be_bytes();
+    let stts = full_box(b"stts", 0, 0, &empty_entries)?;
+    let stsc = full_box(b"stsc", 0, 0, &empty_entries)?;
+    let stsz = full_box(b"stsz", 0, 0, &[0; 8])?;
+    let stco = full_box(b"stco", 0, 0, &empty_entries)?;
+
+    mp4_box(
+        b"stbl",
+        &[&stsd[..], &stts[..], &stsc[..], &stsz[..], &stco[..]],
+    )
+}
+
+fn visual_sample_entry(config: &VideoConfig) -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    writer.write(&[0; 6])?;
+    /*data_reference_index*/
+    writer.write_u16::<BigEndian>(1)?;
+    writer.write(&[0; 16])?;
+    writer.write_u16::<BigEndian>(config.width as u16)?;
+    writer.write_u16::<BigEndian>(config.height as u16)?;
+    /*72 dpi*/
+    writer.write_u32::<BigEndian>(0x0048_0000)?;
+    writer.write_u32::<BigEndian>(0x0048_0000)?;
+    writer.write_u32::<BigEndian>(0)?;
+    /*frame_count*/
+    writer.write_u16::<BigEndian>(1)?;
+    /*compressorname*/
+    writer.write(&[0; 32])?;
+    /*depth and pre_defined(-1)*/
+    writer.write_u16::<BigEndian>(0x0018)?;
+    writer.write_u16::<BigEndian>(0xFFFF)?;
+
+    let (entry_type, record_type) = match config.codec {
+        VideoCodec::H264 => (b"avc1", b"avcC"),
+        VideoCodec::H265 => (b"hvc1", b"hvcC"),
+    };
+    let record = mp4_box(record_type, &[&config.decoder_configuration_record[..]])?;
+    writer.write(&record[..])?;
+
+    mp4_box(entry_type, &[&writer.extract_current_bytes()[..]])
+}
+
+fn esds(track_id: u32, audio_specific_config: &[u8]) -> Result<BytesMut, Mp4Error> {
+    let mut decoder_specific_info = vec![0x05, audio_specific_config.len() as u8];
+    decoder_specific_info.extend_from_slice(audio_specific_config);
+
+    /*object type: mpeg-4 audio, stream type: audio stream*/
+    let mut decoder_config = vec![0x04, 13 + decoder_specific_info.len() as u8, 0x40, 0x15];
+    decoder_config.extend_from_slice(&[0; 11]);
+    decoder_config.extend_from_slice(&decoder_specific_info);
+
+    let sl_config = [0x06, 0x01, 0x02];
+
+    let mut es_descriptor = vec![0x03, (3 + decoder_config.len() + sl_config.len()) as u8];
+    es_descriptor.extend_from_slice(&(track_id as u16).to_be_bytes());
+    es_descriptor.push(0);
+    es_descriptor.extend_from_slice(&decoder_config);
+    es_descriptor.extend_from_slice(&sl_config);
+
+    full_box(b"esds", 0, 0, &es_descriptor)
+}
+
+fn audio_sample_entry(track_id: u32, config: &AudioConfig) -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    writer.write(&[0; 6])?;
+    writer.write_u16::<BigEndian>(1)?;
+    writer.write(&[0; 8])?;
+    /*the channels is 0 if it is defined in the program config element*/
+    let channels = if config.channels == 0 {
+        2
+    } else {
+        config.channels
+    };
+    writer.write_u16::<BigEndian>(channels as u16)?;
+    /*samplesize*/
+    writer.write_u16::<BigEndian>(16)?;
+    writer.write_u32::<BigEndian>(0)?;
+    writer.write_u32::<BigEndian>(config.sampling_frequency << 16)?;
+
+    let esds = esds(track_id, &config.audio_specific_config[..])?;
+    writer.write(&esds[..])?;
+
+    mp4_box(b"mp4a", &[&writer.extract_current_bytes()[..]])
+}
+
+pub fn video_trak(
+    track_id: u32,
+    timescale: u32,
+    config: &VideoConfig,
+) -> Result<BytesMut, Mp4Error> {
+    let vmhd = full_box(b"vmhd", 0, 0x01, &[0; 8])?;
+    let minf = mp4_box(
+        b"minf",
+        &[
+            &vmhd[..],
+            &dinf()?[..],
+            &stbl(&visual_sample_entry(config)?[..])?[..],
+        ],
+    )?;
+    let mdia = mp4_box(
+        b"mdia",
+        &[&mdhd(timescale)?[..], &hdlr(false)?[..], &minf[..]],
+    )?;
+    let tkhd = tkhd(track_id, config.width, config.height, false)?;
+
+    mp4_box(b"trak", &[&tkhd[..], &mdia[..]])
+}
+
+pub fn audio_trak(track_id: u32, config: &AudioConfig) -> Result<BytesMut, Mp4Error> {
+    let smhd = full_box(b"smhd", 0, 0, &[0; 4])?;
+    let sample_entry = audio_sample_entry(track_id, config)?;
+    let minf = mp4_box(
+        b"minf",
+        &[&smhd[..], &dinf()?[..], &stbl(&sample_entry[..])?[..]],
+    )?;
+    let mdia = mp4_box(
+        b"mdia",
+        &[
+            &mdhd(config.sampling_frequency)?[..],
+            &hdlr(true)?[..],
+            &minf[..],
+        ],
+    )?;
+    let tkhd = tkhd(track_id, 0, 0, true)?;
+
+    mp4_box(b"trak", &[&tkhd[..], &mdia[..]])
+}
+
+pub fn trex(track_id: u32) -> Result<BytesMut, Mp4Error> {
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(track_id)?;
+    /*default_sample_description_index*/
+    writer.write_u32::<BigEndian>(1)?;
+    /*default sample duration, size and flags*/
+    writer.write(&[0; 12])?;
+    full_box(b"trex", 0, 0, &writer.extract_current_bytes()[..])
+}
+
+pub fn mfhd(sequence_number: u32) -> Result<BytesMut, Mp4Error> {
+    full_box(b"mfhd", 0, 0, &sequence_number.to_be_bytes())
+}
+
+pub fn traf(
+    track_id: u32,
+    base_media_decode_time: u64,
+    samples: &[Sample],
+    data_offset: i32,
+) -> Result<BytesMut, Mp4Error> {
+    /*default-base-is-moof*/
+    let tfhd = full_box(b"tfhd", 0, 0x02_0000, &track_id.to_be_bytes())?;
+    let tfdt = full_box(b"tfdt", 1, 0, &base_media_decode_time.to_be_bytes())?;
+
+    let mut writer = BytesWriter::new();
+    writer.write_u32::<BigEndian>(samples.len() as u32)?;
+    writer.write_u32::<BigEndian>(data_offset as u32)?;
+    for sample in samples {
+        writer.write_u32::<BigEndian>(sample.duration)?;
+        writer.write_u32::<BigEndian>(sample.size)?;
+        writer.write_u32::<BigEndian>(sample.flags)?;
+        writer.write_u32::<BigEndian>(sample.composition_time_offset as u32)?;
+    }
+    /*version 1 for the signed composition time offsets, flags: data-offset-present,
+    sample-duration-present, sample-size-present, sample-flags-present and
+    sample-composition-time-offsets-present*/
+    let trun = full_box(b"trun", 1, 0x00_0F01, &writer.extract_current_bytes()[..])?;
+
+    mp4_box(b"traf", &[&tfhd[..], &tfdt[..], &trun[..]])
+}
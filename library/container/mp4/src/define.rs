@@ -0,0 +1,48 @@
+use bytes::BytesMut;
+
+pub const VIDEO_TRACK_ID: u32 = 1;
+pub const AUDIO_TRACK_ID: u32 = 2;
+
+/*the video timestamps are in 90kHz, the audio ones are in the sampling frequency*/
+pub const VIDEO_TIMESCALE: u32 = 90000;
+/*the duration of the last video sample if it cannot be computed, 30fps*/
+pub const VIDEO_DEFAULT_SAMPLE_DURATION: u32 = 3000;
+/*every aac frame contains 1024 samples*/
+pub const AAC_SAMPLES_PER_FRAME: u32 = 1024;
+
+pub mod sample_flags {
+    /*sample_depends_on = 2(does not depend on others)*/
+    pub const SYNC_SAMPLE: u32 = 0x0200_0000;
+    /*sample_depends_on = 1 and sample_is_non_sync_sample = 1*/
+    pub const NON_SYNC_SAMPLE: u32 = 0x0101_0000;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    H264,
+    H265,
+}
+
+#[derive(Debug, Clone)]
+pub struct VideoConfig {
+    pub codec: VideoCodec,
+    pub width: u32,
+    pub height: u32,
+    /*AVCDecoderConfigurationRecord or HEVCDecoderConfigurationRecord*/
+    pub decoder_configuration_record: BytesMut,
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioConfig {
+    pub sampling_frequency: u32,
+    pub channels: u8,
+    pub audio_specific_config: BytesMut,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub duration: u32,
+    pub size: u32,
+    pub flags: u32,
+    pub composition_time_offset: i32,
+}
@@ -0,0 +1,268 @@
+use {
+    super::{
+        boxes,
+        define::{
+            sample_flags, AudioConfig, Sample, VideoConfig, AAC_SAMPLES_PER_FRAME, AUDIO_TRACK_ID,
+            VIDEO_DEFAULT_SAMPLE_DURATION, VIDEO_TIMESCALE, VIDEO_TRACK_ID,
+        },
+        errors::{Mp4Error, Mp4ErrorValue},
+    },
+    bytes::BytesMut,
+    bytesio::bytes_writer::BytesWriter,
+};
+
+#[derive(Default)]
+struct TrackFragment {
+    /*the decode timestamps of the samples in the track timescale*/
+    dts: Vec<u64>,
+    samples: Vec<Sample>,
+    data: BytesWriter,
+}
+
+impl TrackFragment {
+    fn push(&mut self, dts: u64, sample: Sample, data: &[u8]) -> Result<(), Mp4Error> {
+        self.dts.push(dts);
+        self.samples.push(sample);
+        self.data.write(data)?;
+        Ok(())
+    }
+
+    //the sample durations are computed from the next decode timestamps,
+    //the last one reuses the previous duration.
+    fn fill_durations(&mut self, default_duration: u32) {
+        let mut last_duration = default_duration;
+        for idx in 0..self.samples.len() {
+            if let Some(next_dts) = self.dts.get(idx + 1) {
+                last_duration = next_dts.saturating_sub(self.dts[idx]) as u32;
+            }
+            self.samples[idx].duration = last_duration;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.dts.clear();
+        self.samples.clear();
+        self.data.clear();
+    }
+}
+
+//Mux the H264/H265/AAC frames into the fragmented mp4(CMAF) segments:
+//the init segment(ftyp + moov) and the media segments(moof + mdat).
+#[derive(Default)]
+pub struct Fmp4Muxer {
+    video_config: Option<VideoConfig>,
+    audio_config: Option<AudioConfig>,
+
+    sequence_number: u32,
+    video_fragment: TrackFragment,
+    audio_fragment: TrackFragment,
+}
+
+impl Fmp4Muxer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_video_config(&mut self, config: VideoConfig) {
+        self.video_config = Some(config);
+    }
+
+    pub fn set_audio_config(&mut self, config: AudioConfig) {
+        self.audio_config = Some(config);
+    }
+
+    pub fn init_segment(&self) -> Result<BytesMut, Mp4Error> {
+        if self.video_config.is_none() && self.audio_config.is_none() {
+            return Err(Mp4Error {
+                value: Mp4ErrorValue::NoTrackConfigured,
+            });
+        }
+
+        let mut moov = BytesWriter::new();
+        moov.write(&boxes::mvhd(AUDIO_TRACK_ID + 1)?[..])?;
+
+        let mut mvex = BytesWriter::new();
+        if let Some(config) = &self.video_config {
+            moov.write(&boxes::video_trak(VIDEO_TRACK_ID, VIDEO_TIMESCALE, config)?[..])?;
+            mvex.write(&boxes::trex(VIDEO_TRACK_ID)?[..])?;
+        }
+        if let Some(config) = &self.audio_config {
+            moov.write(&boxes::audio_trak(AUDIO_TRACK_ID, config)?[..])?;
+            mvex.write(&boxes::trex(AUDIO_TRACK_ID)?[..])?;
+        }
+        let mvex = boxes::mp4_box(b"mvex", &[&mvex.extract_current_bytes()[..]])?;
+        moov.write(&mvex[..])?;
+
+        let mut writer = BytesWriter::new();
+        writer.write(&boxes::ftyp()?[..])?;
+        writer.write(&boxes::mp4_box(b"moov", &[&moov.extract_current_bytes()[..]])?[..])?;
+        Ok(writer.extract_current_bytes())
+    }
+
+    pub fn has_video(&self) -> bool {
+        self.video_config.is_some()
+    }
+
+    //the timestamps are in milliseconds, the data is the length prefixed NAL units.
+    //the negative decode time is clamped to 0, it is unsigned in the fragment.
+    pub fn write_video(
+        &mut self,
+        dts: i64,
+        pts: i64,
+        is_key_frame: bool,
+        data: &[u8],
+    ) -> Result<(), Mp4Error> {
+        let timescale = VIDEO_TIMESCALE as i64 / 1000;
+        let sample = Sample {
+            duration: 0,
+            size: data.len() as u32,
+            flags: if is_key_frame {
+                sample_flags::SYNC_SAMPLE
+            } else {
+                sample_flags::NON_SYNC_SAMPLE
+            },
+            composition_time_offset: ((pts - dts) * timescale) as i32,
+        };
+        self.video_fragment
+            .push((dts.max(0) * timescale) as u64, sample, data)
+    }
+
+    //the timestamp is in milliseconds, the data is the raw aac frame.
+    pub fn write_audio(&mut self, dts: i64, data: &[u8]) -> Result<(), Mp4Error> {
+        let sampling_frequency = match &self.audio_config {
+            Some(config) => config.sampling_frequency as i64,
+            None => return Ok(()),
+        };
+        let sample = Sample {
+            duration: 0,
+            size: data.len() as u32,
+            flags: sample_flags::SYNC_SAMPLE,
+            composition_time_offset: 0,
+        };
+        self.audio_fragment.push(
+            (dts.max(0) * sampling_frequency / 1000) as u64,
+            sample,
+            data,
+        )
+    }
+
+    pub fn has_samples(&self) -> bool {
+        !self.video_fragment.is_empty() || !self.audio_fragment.is_empty()
+    }
+
+    //generate the media segment from the cached samples and clear them.
+    pub fn media_segment(&mut self) -> Result<BytesMut, Mp4Error> {
+        self.video_fragment
+            .fill_durations(VIDEO_DEFAULT_SAMPLE_DURATION);
+        self.audio_fragment.fill_durations(AAC_SAMPLES_PER_FRAME);
+        self.sequence_number += 1;
+
+        /*the data offsets are relative to the moof, so it is generated twice to get the size first*/
+        let moof_size = self.moof(0)?.len();
+        let moof = self.moof(moof_size)?;
+
+        let mut writer = BytesWriter::new();
+        writer.write(&moof[..])?;
+        let mdat = boxes::mp4_box(
+            b"mdat",
+            &[
+                &self.video_fragment.data.get_current_bytes()[..],
+                &self.audio_fragment.data.get_current_bytes()[..],
+            ],
+        )?;
+        writer.write(&mdat[..])?;
+
+        self.video_fragment.clear();
+        self.audio_fragment.clear();
+
+        Ok(writer.extract_current_bytes())
+    }
+
+    fn moof(&self, moof_size: usize) -> Result<BytesMut, Mp4Error> {
+        let mut writer = BytesWriter::new();
+        writer.write(&boxes::mfhd(self.sequence_number)?[..])?;
+
+        /*skip the mdat header*/
+        let mut data_offset = moof_size + 8;
+        for (track_id, fragment) in [
+            (VIDEO_TRACK_ID, &self.video_fragment),
+            (AUDIO_TRACK_ID, &self.audio_fragment),
+        ] {
+            if fragment.is_empty() {
+                continue;
+            }
+            let traf = boxes::traf(
+                track_id,
+                fragment.dts[0],
+                &fragment.samples[..],
+                data_offset as i32,
+            )?;
+            writer.write(&traf[..])?;
+            data_offset += fragment.data.len();
+        }
+
+        boxes::mp4_box(b"moof", &[&writer.extract_current_bytes()[..]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fmp4Muxer;
+    use crate::define::{AudioConfig, VideoCodec, VideoConfig};
+    use bytes::BytesMut;
+
+    fn read_u32(data: &[u8], offset: usize) -> usize {
+        u32::from_be_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ]) as usize
+    }
+
+    #[test]
+    fn test_fmp4_segments() {
+        let mut muxer = Fmp4Muxer::new();
+        assert!(muxer.init_segment().is_err());
+
+        muxer.set_video_config(VideoConfig {
+            codec: VideoCodec::H264,
+            width: 1280,
+            height: 720,
+            decoder_configuration_record: BytesMut::from(&[0x01, 0x64, 0x00, 0x1F, 0xFF][..]),
+        });
+        muxer.set_audio_config(AudioConfig {
+            sampling_frequency: 44100,
+            channels: 2,
+            audio_specific_config: BytesMut::from(&[0x12, 0x10][..]),
+        });
+
+        let init = muxer.init_segment().unwrap();
+        assert_eq!(&init[4..8], b"ftyp");
+        let ftyp_size = read_u32(&init[..], 0);
+        assert_eq!(&init[ftyp_size + 4..ftyp_size + 8], b"moov");
+        assert_eq!(ftyp_size + read_u32(&init[..], ftyp_size), init.len());
+
+        muxer.write_video(0, 40, true, &[0, 0, 0, 1, 0x65]).unwrap();
+        muxer.write_audio(10, &[0x21, 0x10]).unwrap();
+        muxer
+            .write_video(40, 80, false, &[0, 0, 0, 1, 0x41])
+            .unwrap();
+        assert!(muxer.has_samples());
+
+        let segment = muxer.media_segment().unwrap();
+        assert!(!muxer.has_samples());
+        assert_eq!(&segment[4..8], b"moof");
+        let moof_size = read_u32(&segment[..], 0);
+        assert_eq!(&segment[moof_size + 4..moof_size + 8], b"mdat");
+        /*the video samples are followed by the audio sample*/
+        assert_eq!(
+            &segment[moof_size + 8..],
+            &[0, 0, 0, 1, 0x65, 0, 0, 0, 1, 0x41, 0x21, 0x10]
+        );
+    }
+}
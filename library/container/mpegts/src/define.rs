@@ -12,6 +12,7 @@ pub mod epsi_stream_type {
     pub const PSI_STREAM_MP3: u8 = 0x04; // ISO/IEC 13818-3 Audio
     pub const PSI_STREAM_PRIVATE_DATA: u8 = 0x06;
     pub const PSI_STREAM_H264: u8 = 0x1b; // H.264
+    pub const PSI_STREAM_H265: u8 = 0x24; // H.265
     pub const PSI_STREAM_AAC: u8 = 0x0f;
     pub const PSI_STREAM_MPEG4_AAC: u8 = 0x1c;
     pub const PSI_STREAM_AUDIO_OPUS: u8 = 0x9c;
@@ -34,6 +35,10 @@ pub const PES_HEADER_LEN: u8 = 6; // 3-bytes packet_start_code_prefix + 1-byte s
 pub const TS_PAYLOAD_UNIT_START_INDICATOR: u8 = 0x40;
 
 pub const TS_PACKET_SIZE: usize = 188;
+pub const TS_SYNC_BYTE: u8 = 0x47;
+pub const TS_NULL_PACKET_PID: u16 = 0x1FFF;
+
+pub const AF_FLAG_DISCONTINUITY_INDICATOR: u8 = 0x80;
 
 pub const MPEG_FLAG_IDR_FRAME: u16 = 0x0001;
 pub const MPEG_FLAG_H264_H265_WITH_AUD: u16 = 0x8000;
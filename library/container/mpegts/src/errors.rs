@@ -27,6 +27,12 @@ pub enum MpegTsErrorValue {
 
     #[fail(display = "stream not found\n")]
     StreamNotFound,
+
+    #[fail(display = "invalid table id\n")]
+    InvalidTableId,
+
+    #[fail(display = "invalid pes start code\n")]
+    InvalidPesStartCode,
 }
 #[derive(Debug)]
 pub struct MpegTsError {
@@ -1,8 +1,13 @@
 use {
-    super::{crc32, define::epat_pid, errors::MpegTsError, pmt},
+    super::{
+        crc32,
+        define::epat_pid,
+        errors::{MpegTsError, MpegTsErrorValue},
+        pmt,
+    },
     byteorder::{BigEndian, LittleEndian},
     bytes::BytesMut,
-    bytesio::bytes_writer::BytesWriter,
+    bytesio::{bytes_reader::BytesReader, bytes_writer::BytesWriter},
 };
 
 #[derive(Debug, Clone)]
@@ -93,3 +98,58 @@ impl PatMuxer {
         Ok(self.bytes_writer.extract_current_bytes())
     }
 }
+
+#[derive(Default)]
+pub struct PatDemuxer {}
+
+impl PatDemuxer {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    //parse the program association section (without the pointer field),
+    //the crc32 is not verified.
+    pub fn read(&mut self, payload: BytesMut) -> Result<Pat, MpegTsError> {
+        let mut bytes_reader = BytesReader::new(payload);
+
+        /*table id*/
+        if bytes_reader.read_u8()? as u16 != epat_pid::PAT_TID_PAS {
+            return Err(MpegTsError {
+                value: MpegTsErrorValue::InvalidTableId,
+            });
+        }
+
+        /*section length*/
+        let section_length = (bytes_reader.read_u16::<BigEndian>()? & 0x0FFF) as usize;
+        let mut section_reader = BytesReader::new(bytes_reader.read_bytes(section_length)?);
+
+        let mut pat = Pat::new();
+        /*transport_stream_id*/
+        pat.transport_stream_id = section_reader.read_u16::<BigEndian>()?;
+        /*version_number*/
+        pat.version_number = (section_reader.read_u8()? >> 1) & 0x1F;
+        /*section_number*/
+        /*last_section_number*/
+        section_reader.read_u16::<BigEndian>()?;
+
+        /*the last 4 bytes are crc32*/
+        while section_reader.len() > 4 {
+            /*program number*/
+            let program_number = section_reader.read_u16::<BigEndian>()?;
+            /*PID*/
+            let pid = section_reader.read_u16::<BigEndian>()? & 0x1FFF;
+
+            //program number 0 is the network information table
+            if program_number == 0 {
+                continue;
+            }
+
+            let mut cur_pmt = pmt::Pmt::new();
+            cur_pmt.program_number = program_number;
+            cur_pmt.pid = pid;
+            pat.pmt.push(cur_pmt);
+        }
+
+        Ok(pat)
+    }
+}
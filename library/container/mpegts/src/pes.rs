@@ -1,7 +1,11 @@
 use {
-    super::{define, errors::MpegTsError},
+    super::{
+        define,
+        errors::{MpegTsError, MpegTsErrorValue},
+    },
+    byteorder::BigEndian,
     bytes::BytesMut,
-    bytesio::bytes_writer::BytesWriter,
+    bytesio::{bytes_reader::BytesReader, bytes_writer::BytesWriter},
 };
 
 #[derive(Debug, Clone)]
@@ -162,6 +166,12 @@ impl PesMuxer {
         {
             let header: [u8; 6] = [0x00, 0x00, 0x00, 0x01, 0x09, 0xF0];
             self.bytes_writer.write(&header)?;
+        } else if define::epsi_stream_type::PSI_STREAM_H265 == stream_data.codec_id
+            && !h264_h265_with_aud
+        {
+            //nal unit type 35(AUD), pic_type 2
+            let header: [u8; 7] = [0x00, 0x00, 0x00, 0x01, 0x46, 0x01, 0x50];
+            self.bytes_writer.write(&header)?;
         }
 
         let pes_payload_length =
@@ -182,3 +192,69 @@ impl PesMuxer {
         Ok(())
     }
 }
+
+#[derive(Default)]
+pub struct PesDemuxer {}
+
+impl PesDemuxer {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    //parse the pes header into the stream data, returns the pes payload length,
+    //0 means the length is unlimited(only for video).
+    pub fn read_pes_header(
+        &mut self,
+        bytes_reader: &mut BytesReader,
+        stream_data: &mut Pes,
+    ) -> Result<usize, MpegTsError> {
+        /*pes start code 3 bytes*/
+        if bytes_reader.read_u24::<BigEndian>()? != 0x000001 {
+            return Err(MpegTsError {
+                value: MpegTsErrorValue::InvalidPesStartCode,
+            });
+        }
+
+        /*stream id 1 byte*/
+        stream_data.stream_id = bytes_reader.read_u8()?;
+        /*pes packet length 2 bytes*/
+        let pes_packet_length = bytes_reader.read_u16::<BigEndian>()? as usize;
+
+        /*first flag 1 byte*/
+        let flags_1 = bytes_reader.read_u8()?;
+        stream_data.data_alignment_indicator = (flags_1 >> 2) & 0x01;
+        /*second flag 1 byte*/
+        let flags_2 = bytes_reader.read_u8()?;
+        /*pes header data length*/
+        let header_data_length = bytes_reader.read_u8()? as usize;
+        let header_data = bytes_reader.read_bytes(header_data_length)?;
+
+        stream_data.pts = define::PTS_NO_VALUE;
+        stream_data.dts = define::PTS_NO_VALUE;
+
+        /*The flags has 0x80 means that it has pts -- 5 bytes*/
+        if (flags_2 & 0x80) > 0 && header_data.len() >= 5 {
+            stream_data.pts = Self::read_timestamp(&header_data[0..5]);
+        }
+        /*The flags has 0x40 means that it has dts -- 5 bytes*/
+        if (flags_2 & 0x40) > 0 && header_data.len() >= 10 {
+            stream_data.dts = Self::read_timestamp(&header_data[5..10]);
+        } else {
+            stream_data.dts = stream_data.pts;
+        }
+
+        if pes_packet_length == 0 {
+            return Ok(0);
+        }
+        /*3 bytes for the two flags and the header data length*/
+        Ok(pes_packet_length.saturating_sub(3 + header_data_length))
+    }
+
+    fn read_timestamp(data: &[u8]) -> i64 {
+        (((data[0] as i64 >> 1) & 0x07) << 30) /* 30-32 */
+            | ((data[1] as i64) << 22) /* 22-29 */
+            | ((data[2] as i64 >> 1) << 15) /* 15-21 */
+            | ((data[3] as i64) << 7) /* 7-14 */
+            | (data[4] as i64 >> 1) /* 0-6 */
+    }
+}
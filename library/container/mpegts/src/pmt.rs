@@ -1,13 +1,13 @@
 use {
     super::{
         crc32,
-        define::{epat_pid, epsi_stream_type},
-        errors::MpegTsError,
-        pes,
+        define::{epat_pid, epes_stream_id, epsi_stream_type},
+        errors::{MpegTsError, MpegTsErrorValue},
+        pes, utils,
     },
     byteorder::{BigEndian, LittleEndian},
     bytes::BytesMut,
-    bytesio::bytes_writer::BytesWriter,
+    bytesio::{bytes_reader::BytesReader, bytes_writer::BytesWriter},
 };
 #[derive(Debug, Clone)]
 pub struct Pmt {
@@ -112,3 +112,74 @@ impl PmtMuxer {
         Ok(())
     }
 }
+
+#[derive(Default)]
+pub struct PmtDemuxer {}
+
+impl PmtDemuxer {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    //parse the program map section (without the pointer field) into the pmt
+    //whose pid and program number are got from the pat, the crc32 is not verified.
+    pub fn read(&mut self, pmt: &mut Pmt, payload: BytesMut) -> Result<(), MpegTsError> {
+        let mut bytes_reader = BytesReader::new(payload);
+
+        /*table id*/
+        if bytes_reader.read_u8()? as u16 != epat_pid::PAT_TID_PMS {
+            return Err(MpegTsError {
+                value: MpegTsErrorValue::InvalidTableId,
+            });
+        }
+
+        /*section_length*/
+        let section_length = (bytes_reader.read_u16::<BigEndian>()? & 0x0FFF) as usize;
+        let mut section_reader = BytesReader::new(bytes_reader.read_bytes(section_length)?);
+
+        /*program_number*/
+        pmt.program_number = section_reader.read_u16::<BigEndian>()?;
+        /*version_number*/
+        pmt.version_number = (section_reader.read_u8()? >> 1) & 0x1F;
+        /*section_number*/
+        /*last_section_number*/
+        section_reader.read_u16::<BigEndian>()?;
+        /*PCR_PID*/
+        pmt.pcr_pid = section_reader.read_u16::<BigEndian>()? & 0x1FFF;
+        /*program_info_length*/
+        let program_info_length = (section_reader.read_u16::<BigEndian>()? & 0x0FFF) as usize;
+        pmt.program_info = section_reader.read_bytes(program_info_length)?;
+
+        let mut streams = Vec::new();
+        /*the last 4 bytes are crc32*/
+        while section_reader.len() > 4 {
+            let mut stream = pes::Pes::new();
+            /*stream_type*/
+            stream.codec_id = section_reader.read_u8()?;
+            /*elementary_PID*/
+            stream.pid = section_reader.read_u16::<BigEndian>()? & 0x1FFF;
+            stream.program_number = pmt.program_number;
+            /*ES_info_length*/
+            let esinfo_length = (section_reader.read_u16::<BigEndian>()? & 0x0FFF) as usize;
+            stream.esinfo = section_reader.read_bytes(esinfo_length)?;
+            stream.esinfo_length = esinfo_length;
+
+            stream.stream_id = if utils::is_steam_type_video(stream.codec_id) {
+                epes_stream_id::PES_SID_VIDEO
+            } else if utils::is_steam_type_audio(stream.codec_id) {
+                epes_stream_id::PES_SID_AUDIO
+            } else {
+                epes_stream_id::PES_SID_PRIVATE_1
+            };
+
+            //keep the continuity counter of the existing streams
+            if let Some(old_stream) = pmt.streams.iter().find(|s| s.pid == stream.pid) {
+                stream.continuity_counter = old_stream.continuity_counter;
+            }
+            streams.push(stream);
+        }
+        pmt.streams = streams;
+
+        Ok(())
+    }
+}
@@ -4,11 +4,13 @@ use {
         define::{epat_pid, epes_stream_id, ts},
         errors::{MpegTsError, MpegTsErrorValue},
         pat, pes,
-        pes::PesMuxer,
+        pes::{PesDemuxer, PesMuxer},
         pmt, utils,
     },
+    byteorder::BigEndian,
     bytes::{BufMut, BytesMut},
     bytesio::{bytes_reader::BytesReader, bytes_writer::BytesWriter},
+    std::collections::HashMap,
 };
 
 pub struct TsMuxer {
@@ -437,3 +439,500 @@ impl TsMuxer {
         Ok(())
     }
 }
+
+//an elementary stream frame(a whole pes packet) demuxed from the ts stream,
+//the data is annexb for h264/h265 and adts for aac.
+#[derive(Debug, Clone)]
+pub struct TsFrame {
+    pub program_number: u16,
+    pub pid: u16,
+    /*the stream type in pmt, see define::epsi_stream_type*/
+    pub stream_type: u8,
+    pub stream_id: u8,
+    pub pts: i64,
+    pub dts: i64,
+    /*random access indicator of the adaptation field, it is set on the key frames*/
+    pub random_access: bool,
+    pub data: BytesMut,
+}
+
+//reassembles the pes packets of one elementary stream
+struct PesAssembler {
+    stream: pes::Pes,
+    /*none before receiving the first packet*/
+    last_continuity_counter: Option<u8>,
+    random_access: bool,
+    /*whole pes packet including the pes header*/
+    buffer: BytesMut,
+    /*false if waiting for the next payload unit start*/
+    started: bool,
+}
+
+impl PesAssembler {
+    fn new(stream: pes::Pes) -> Self {
+        Self {
+            stream,
+            last_continuity_counter: None,
+            random_access: false,
+            buffer: BytesMut::new(),
+            started: false,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.started = false;
+        self.random_access = false;
+    }
+
+    //the pes packet length in the pes header, 0 means unlimited length
+    fn expected_length(&self) -> Option<usize> {
+        if self.buffer.len() < define::PES_HEADER_LEN as usize {
+            return None;
+        }
+        let pes_packet_length = ((self.buffer[4] as usize) << 8) | self.buffer[5] as usize;
+        if pes_packet_length == 0 {
+            return None;
+        }
+        Some(define::PES_HEADER_LEN as usize + pes_packet_length)
+    }
+
+    fn extract_frame(&mut self) -> Result<Option<TsFrame>, MpegTsError> {
+        if !self.started || self.buffer.is_empty() {
+            return Ok(None);
+        }
+
+        let random_access = self.random_access;
+        let mut bytes_reader = BytesReader::new(self.buffer.split());
+        self.reset();
+
+        let payload_length =
+            PesDemuxer::new().read_pes_header(&mut bytes_reader, &mut self.stream)?;
+        let mut data = bytes_reader.extract_remaining_bytes();
+        if payload_length > 0 && payload_length < data.len() {
+            data.truncate(payload_length);
+        }
+
+        Ok(Some(TsFrame {
+            program_number: self.stream.program_number,
+            pid: self.stream.pid,
+            stream_type: self.stream.codec_id,
+            stream_id: self.stream.stream_id,
+            pts: self.stream.pts,
+            dts: self.stream.dts,
+            random_access,
+            data,
+        }))
+    }
+}
+
+pub struct TsDemuxer {
+    /*the bytes which are not enough for a ts packet*/
+    remaining_bytes: BytesMut,
+    pat: pat::Pat,
+    /*pmt pid to the pmt index in pat*/
+    pmt_pids: HashMap<u16, usize>,
+    /*elementary stream pid to the pes assembler*/
+    streams: HashMap<u16, PesAssembler>,
+    continuity_error_count: usize,
+    corrupt_packet_count: usize,
+}
+
+impl Default for TsDemuxer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TsDemuxer {
+    pub fn new() -> Self {
+        Self {
+            remaining_bytes: BytesMut::new(),
+            pat: pat::Pat::new(),
+            pmt_pids: HashMap::new(),
+            streams: HashMap::new(),
+            continuity_error_count: 0,
+            corrupt_packet_count: 0,
+        }
+    }
+
+    //the count of the lost ts packets detected by the continuity counter,
+    //the pes packet with lost ts packets is dropped.
+    pub fn continuity_error_count(&self) -> usize {
+        self.continuity_error_count
+    }
+
+    //the count of the ts packets and pes packets which can not be parsed,
+    //they are skipped and the demuxing goes on.
+    pub fn corrupt_packet_count(&self) -> usize {
+        self.corrupt_packet_count
+    }
+
+    pub fn pmts(&self) -> &Vec<pmt::Pmt> {
+        &self.pat.pmt
+    }
+
+    //the data can be any size, the incomplete ts packet is kept and
+    //demuxed with the next input data.
+    pub fn demux(&mut self, data: &[u8]) -> Result<Vec<TsFrame>, MpegTsError> {
+        self.remaining_bytes.extend_from_slice(data);
+
+        let mut frames = Vec::new();
+        loop {
+            //resync to the sync byte
+            match self
+                .remaining_bytes
+                .iter()
+                .position(|b| *b == define::TS_SYNC_BYTE)
+            {
+                Some(pos) => {
+                    let _ = self.remaining_bytes.split_to(pos);
+                }
+                None => {
+                    self.remaining_bytes.clear();
+                }
+            }
+
+            if self.remaining_bytes.len() < define::TS_PACKET_SIZE {
+                break;
+            }
+
+            let packet = self.remaining_bytes.split_to(define::TS_PACKET_SIZE);
+            //skip the corrupt packet and keep the frames demuxed so far
+            if self.read_ts_packet(packet, &mut frames).is_err() {
+                self.corrupt_packet_count += 1;
+            }
+        }
+
+        Ok(frames)
+    }
+
+    //returns the pes packets which are waiting for the next payload unit start.
+    pub fn flush(&mut self) -> Result<Vec<TsFrame>, MpegTsError> {
+        let mut frames = Vec::new();
+        let mut pids: Vec<u16> = self.streams.keys().copied().collect();
+        pids.sort_unstable();
+
+        for pid in pids {
+            if let Some(assembler) = self.streams.get_mut(&pid) {
+                match assembler.extract_frame() {
+                    Ok(Some(frame)) => frames.push(frame),
+                    Ok(None) => {}
+                    Err(_) => self.corrupt_packet_count += 1,
+                }
+            }
+        }
+        Ok(frames)
+    }
+
+    fn read_ts_packet(
+        &mut self,
+        packet: BytesMut,
+        frames: &mut Vec<TsFrame>,
+    ) -> Result<(), MpegTsError> {
+        let mut bytes_reader = BytesReader::new(packet);
+
+        /*sync byte*/
+        bytes_reader.read_u8()?;
+        let pid_with_flags = bytes_reader.read_u16::<BigEndian>()?;
+        /*transport error indicator*/
+        if (pid_with_flags & 0x8000) > 0 {
+            return Ok(());
+        }
+        /*payload unit start indicator*/
+        let payload_unit_start = (pid_with_flags & 0x4000) > 0;
+        /*PID 13 bits*/
+        let pid = pid_with_flags & 0x1FFF;
+
+        let flags = bytes_reader.read_u8()?;
+        /*adaption field control 2 bits*/
+        let adaptation_field_control = (flags >> 4) & 0x03;
+        /*continuity counter 4 bits*/
+        let continuity_counter = flags & 0x0F;
+
+        let mut discontinuity = false;
+        let mut random_access = false;
+        if (adaptation_field_control & 0x02) > 0 {
+            /*adaption filed length*/
+            let adaptation_field_length = bytes_reader.read_u8()? as usize;
+            if adaptation_field_length > 0 {
+                let adaptation_field = bytes_reader.read_bytes(adaptation_field_length)?;
+                discontinuity = (adaptation_field[0] & define::AF_FLAG_DISCONTINUITY_INDICATOR) > 0;
+                random_access = (adaptation_field[0] & define::AF_FLAG_RANDOM_ACCESS_INDICATOR) > 0;
+            }
+        }
+
+        /*no payload*/
+        if (adaptation_field_control & 0x01) == 0 || pid == define::TS_NULL_PACKET_PID {
+            return Ok(());
+        }
+
+        let payload = bytes_reader.extract_remaining_bytes();
+
+        if pid == epat_pid::PAT_TID_PAS {
+            if payload_unit_start {
+                self.read_pat(payload)?;
+            }
+        } else if let Some(pmt_index) = self.pmt_pids.get(&pid).copied() {
+            if payload_unit_start {
+                self.read_pmt(pmt_index, payload)?;
+            }
+        } else if let Some(assembler) = self.streams.get_mut(&pid) {
+            if let Some(last_continuity_counter) = assembler.last_continuity_counter {
+                let expected_continuity_counter = (last_continuity_counter + 1) % 16;
+                if !discontinuity && continuity_counter != expected_continuity_counter {
+                    //duplicate packet
+                    if continuity_counter == last_continuity_counter {
+                        return Ok(());
+                    }
+                    self.continuity_error_count += 1;
+                    assembler.reset();
+                }
+            }
+            assembler.last_continuity_counter = Some(continuity_counter);
+
+            if payload_unit_start {
+                if let Some(frame) = assembler.extract_frame()? {
+                    frames.push(frame);
+                }
+                assembler.started = true;
+                assembler.random_access = random_access;
+            }
+
+            if !assembler.started {
+                return Ok(());
+            }
+            assembler.buffer.extend_from_slice(&payload[..]);
+
+            if let Some(expected_length) = assembler.expected_length() {
+                if assembler.buffer.len() >= expected_length {
+                    if let Some(frame) = assembler.extract_frame()? {
+                        frames.push(frame);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    //skip the pointer field of the psi section
+    fn skip_pointer_field(payload: BytesMut) -> Result<BytesMut, MpegTsError> {
+        let mut bytes_reader = BytesReader::new(payload);
+        let pointer_field = bytes_reader.read_u8()? as usize;
+        bytes_reader.read_bytes(pointer_field)?;
+        Ok(bytes_reader.extract_remaining_bytes())
+    }
+
+    fn read_pat(&mut self, payload: BytesMut) -> Result<(), MpegTsError> {
+        let section = Self::skip_pointer_field(payload)?;
+        let pat = pat::PatDemuxer::new().read(section)?;
+
+        self.pmt_pids.clear();
+        let mut pmts = Vec::new();
+        for new_pmt in pat.pmt {
+            //keep the parsed streams if the pmt is not changed
+            let cur_pmt = match self.pat.pmt.iter().find(|p| p.pid == new_pmt.pid) {
+                Some(old_pmt) if old_pmt.program_number == new_pmt.program_number => {
+                    old_pmt.clone()
+                }
+                _ => new_pmt,
+            };
+            self.pmt_pids.insert(cur_pmt.pid, pmts.len());
+            pmts.push(cur_pmt);
+        }
+        self.pat.pmt = pmts;
+
+        Ok(())
+    }
+
+    fn read_pmt(&mut self, pmt_index: usize, payload: BytesMut) -> Result<(), MpegTsError> {
+        let section = Self::skip_pointer_field(payload)?;
+        let cur_pmt = &mut self.pat.pmt[pmt_index];
+        pmt::PmtDemuxer::new().read(cur_pmt, section)?;
+
+        /*keep the assembler only if its stream is not changed by the pmt update,
+        the streams removed from the pmts are dropped*/
+        let mut streams = HashMap::new();
+        for stream in self.pat.pmt.iter().flat_map(|pmt| pmt.streams.iter()) {
+            let assembler = match self.streams.remove(&stream.pid) {
+                Some(assembler) if assembler.stream.codec_id == stream.codec_id => assembler,
+                _ => PesAssembler::new(stream.clone()),
+            };
+            streams.insert(stream.pid, assembler);
+        }
+        self.streams = streams;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TsDemuxer, TsMuxer};
+    use crate::define::TS_PACKET_SIZE;
+    use crate::define::{epsi_stream_type, MPEG_FLAG_H264_H265_WITH_AUD, MPEG_FLAG_IDR_FRAME};
+    use bytes::BytesMut;
+
+    fn gen_payload(length: usize, seed: u8) -> BytesMut {
+        let mut payload = BytesMut::new();
+        //start with an aud so that the muxer does not insert one
+        payload.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x09, 0xF0]);
+        for i in 0..length {
+            payload.extend_from_slice(&[(i as u8).wrapping_add(seed)]);
+        }
+        payload
+    }
+
+    fn mux() -> (BytesMut, u16, u16, Vec<BytesMut>, Vec<BytesMut>) {
+        let mut ts_muxer = TsMuxer::new();
+        let video_pid = ts_muxer
+            .add_stream(epsi_stream_type::PSI_STREAM_H264, BytesMut::new())
+            .unwrap();
+        let audio_pid = ts_muxer
+            .add_stream(epsi_stream_type::PSI_STREAM_AAC, BytesMut::new())
+            .unwrap();
+
+        let mut videos = Vec::new();
+        let mut audios = Vec::new();
+        for i in 0..3 {
+            //the first video frame is larger than 0xFFFF
+            let video = gen_payload(if i == 0 { 70000 } else { 1000 + i * 100 }, i as u8);
+            let pts = 90000 + i as i64 * 3600;
+            let flags = if i == 0 {
+                MPEG_FLAG_IDR_FRAME | MPEG_FLAG_H264_H265_WITH_AUD
+            } else {
+                MPEG_FLAG_H264_H265_WITH_AUD
+            };
+            ts_muxer
+                .write(video_pid, pts + 7200, pts, flags, video.clone())
+                .unwrap();
+            videos.push(video);
+
+            let audio = gen_payload(300 + i * 10, 100 + i as u8);
+            ts_muxer
+                .write(audio_pid, pts, pts, 0, audio.clone())
+                .unwrap();
+            audios.push(audio);
+        }
+
+        (ts_muxer.get_data(), video_pid, audio_pid, videos, audios)
+    }
+
+    #[test]
+    fn test_demux_muxer_round_trip() {
+        let (data, video_pid, audio_pid, videos, audios) = mux();
+
+        let mut ts_demuxer = TsDemuxer::new();
+        let mut frames = Vec::new();
+        //feed the data with a size which is not aligned to the ts packet
+        for chunk in data.chunks(1000) {
+            frames.extend(ts_demuxer.demux(chunk).unwrap());
+        }
+        frames.extend(ts_demuxer.flush().unwrap());
+
+        assert_eq!(ts_demuxer.continuity_error_count(), 0);
+        assert_eq!(ts_demuxer.pmts().len(), 1);
+        assert_eq!(ts_demuxer.pmts()[0].streams.len(), 2);
+
+        let video_frames: Vec<_> = frames.iter().filter(|f| f.pid == video_pid).collect();
+        let audio_frames: Vec<_> = frames.iter().filter(|f| f.pid == audio_pid).collect();
+        assert_eq!(video_frames.len(), videos.len());
+        assert_eq!(audio_frames.len(), audios.len());
+
+        for (i, frame) in video_frames.iter().enumerate() {
+            assert_eq!(frame.stream_type, epsi_stream_type::PSI_STREAM_H264);
+            assert_eq!(frame.dts, 90000 + i as i64 * 3600);
+            assert_eq!(frame.pts, frame.dts + 7200);
+            assert_eq!(frame.random_access, i == 0);
+            assert_eq!(frame.data, videos[i]);
+        }
+
+        for (i, frame) in audio_frames.iter().enumerate() {
+            assert_eq!(frame.stream_type, epsi_stream_type::PSI_STREAM_AAC);
+            assert_eq!(frame.pts, 90000 + i as i64 * 3600);
+            assert_eq!(frame.dts, frame.pts);
+            assert_eq!(frame.data, audios[i]);
+        }
+    }
+
+    #[test]
+    fn test_demux_continuity_counter_error() {
+        let (data, video_pid, _, videos, _) = mux();
+
+        //drop the 10th packet which belongs to the first video frame
+        let mut lost_data = BytesMut::new();
+        for (i, packet) in data.chunks(TS_PACKET_SIZE).enumerate() {
+            if i != 10 {
+                lost_data.extend_from_slice(packet);
+            }
+        }
+
+        let mut ts_demuxer = TsDemuxer::new();
+        let mut frames = ts_demuxer.demux(&lost_data[..]).unwrap();
+        frames.extend(ts_demuxer.flush().unwrap());
+
+        assert_eq!(ts_demuxer.continuity_error_count(), 1);
+        let video_frames: Vec<_> = frames.iter().filter(|f| f.pid == video_pid).collect();
+        assert_eq!(video_frames.len(), videos.len() - 1);
+        assert_eq!(video_frames[0].data, videos[1]);
+    }
+
+    #[test]
+    fn test_demux_corrupt_packet() {
+        let (data, video_pid, audio_pid, videos, audios) = mux();
+
+        //the adaptation field length is larger than the packet
+        let mut corrupt_packet = vec![0xFF; TS_PACKET_SIZE];
+        corrupt_packet[..5].copy_from_slice(&[0x47, 0x00, 0x00, 0x30, 0xFF]);
+
+        //insert the corrupt packet between two packets of the first video frame
+        let mut corrupt_data = BytesMut::new();
+        for (i, packet) in data.chunks(TS_PACKET_SIZE).enumerate() {
+            if i == 10 {
+                corrupt_data.extend_from_slice(&corrupt_packet);
+            }
+            corrupt_data.extend_from_slice(packet);
+        }
+
+        let mut ts_demuxer = TsDemuxer::new();
+        let mut frames = ts_demuxer.demux(&corrupt_data[..]).unwrap();
+        frames.extend(ts_demuxer.flush().unwrap());
+
+        assert_eq!(ts_demuxer.corrupt_packet_count(), 1);
+        assert_eq!(ts_demuxer.continuity_error_count(), 0);
+        let video_frames: Vec<_> = frames.iter().filter(|f| f.pid == video_pid).collect();
+        let audio_frames: Vec<_> = frames.iter().filter(|f| f.pid == audio_pid).collect();
+        assert_eq!(video_frames.len(), videos.len());
+        assert_eq!(audio_frames.len(), audios.len());
+        assert_eq!(video_frames[0].data, videos[0]);
+    }
+
+    #[test]
+    fn test_demux_pmt_update() {
+        let (data, video_pid, audio_pid, _, _) = mux();
+
+        //the encoder restarts with h265 on the same pid and without audio
+        let mut ts_muxer = TsMuxer::new();
+        let hevc_pid = ts_muxer
+            .add_stream(epsi_stream_type::PSI_STREAM_H265, BytesMut::new())
+            .unwrap();
+        assert_eq!(hevc_pid, video_pid);
+        let flags = MPEG_FLAG_IDR_FRAME | MPEG_FLAG_H264_H265_WITH_AUD;
+        ts_muxer
+            .write(hevc_pid, 7200, 0, flags, gen_payload(1000, 0))
+            .unwrap();
+
+        let mut ts_demuxer = TsDemuxer::new();
+        ts_demuxer.demux(&data[..]).unwrap();
+        ts_demuxer.flush().unwrap();
+        assert!(ts_demuxer.streams.contains_key(&audio_pid));
+
+        let mut frames = ts_demuxer.demux(&ts_muxer.get_data()[..]).unwrap();
+        frames.extend(ts_demuxer.flush().unwrap());
+
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].stream_type, epsi_stream_type::PSI_STREAM_H265);
+        assert!(!ts_demuxer.streams.contains_key(&audio_pid));
+    }
+}
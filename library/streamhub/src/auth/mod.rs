@@ -0,0 +1,328 @@
+use {
+    crate::{
+        errors::{AuthError, AuthErrorValue},
+        stream::StreamIdentifier,
+    },
+    hmac::{Hmac, Mac, NewMac},
+    reqwest::Client,
+    serde::Serialize,
+    sha2::Sha256,
+    std::{
+        collections::HashMap,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthAction {
+    Publish,
+    Play,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthAlgorithm {
+    //the url parameter "token" must be equal to the secret.
+    Simple,
+    //the url parameters "expires"(unix timestamp in seconds) and "sign" must be provided,
+    //sign = hex(hmac_sha256(secret, "{app_name}/{stream_name}?expires={expires}")).
+    Hmac,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthSecret {
+    pub algorithm: AuthAlgorithm,
+    pub value: String,
+}
+
+#[derive(Serialize)]
+struct CalloutBody<'a> {
+    action: AuthAction,
+    protocol: &'a str,
+    app_name: &'a str,
+    stream_name: &'a str,
+    url_parameters: &'a str,
+    remote_addr: &'a str,
+}
+
+//Checks the publish/play requests from all the protocols(rtmp/rtsp/webrtc/httpflv).
+pub struct Auth {
+    request_client: Client,
+    /*used when the app has no secret of its own*/
+    default_secret: Option<AuthSecret>,
+    app_secrets: HashMap<String, AuthSecret>,
+    auth_publish: bool,
+    auth_play: bool,
+    /*the request is rejected if the callout does not return 2xx*/
+    callout_url: Option<String>,
+}
+
+impl Auth {
+    pub fn new(
+        default_secret: Option<AuthSecret>,
+        app_secrets: HashMap<String, AuthSecret>,
+        auth_publish: bool,
+        auth_play: bool,
+        callout_url: Option<String>,
+        callout_timeout: Duration,
+    ) -> Self {
+        let request_client = Client::builder()
+            .timeout(callout_timeout)
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            request_client,
+            default_secret,
+            app_secrets,
+            auth_publish,
+            auth_play,
+            callout_url,
+        }
+    }
+
+    pub async fn authenticate(
+        &self,
+        identifier: &StreamIdentifier,
+        action: AuthAction,
+        url_parameters: &str,
+        remote_addr: &str,
+    ) -> Result<(), AuthError> {
+        match action {
+            AuthAction::Publish if !self.auth_publish => return Ok(()),
+            AuthAction::Play if !self.auth_play => return Ok(()),
+            _ => {}
+        }
+
+        let (protocol, app_name, stream_name) = split_identifier(identifier);
+        self.check_secret(app_name, stream_name, url_parameters)?;
+
+        if let Some(callout_url) = &self.callout_url {
+            let body = CalloutBody {
+                action,
+                protocol,
+                app_name,
+                stream_name,
+                url_parameters,
+                remote_addr,
+            };
+
+            let response = self
+                .request_client
+                .post(callout_url)
+                .header("Content-Type", "application/json")
+                .body(serde_json::to_string(&body).unwrap_or_default())
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                return Err(AuthError {
+                    value: AuthErrorValue::CalloutRejected(response.status().as_u16()),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_secret(
+        &self,
+        app_name: &str,
+        stream_name: &str,
+        url_parameters: &str,
+    ) -> Result<(), AuthError> {
+        let secret = match self
+            .app_secrets
+            .get(app_name)
+            .or(self.default_secret.as_ref())
+        {
+            Some(secret) => secret,
+            None => return Ok(()),
+        };
+
+        let parameters = parse_url_parameters(url_parameters);
+
+        match secret.algorithm {
+            AuthAlgorithm::Simple => match parameters.get("token") {
+                Some(token) if constant_time_eq(token, &secret.value) => Ok(()),
+                Some(_) => Err(AuthError {
+                    value: AuthErrorValue::TokenIsNotCorrect,
+                }),
+                None => Err(AuthError {
+                    value: AuthErrorValue::NoToken,
+                }),
+            },
+            AuthAlgorithm::Hmac => {
+                let (expires, sign) = match (parameters.get("expires"), parameters.get("sign")) {
+                    (Some(expires), Some(sign)) => (expires, sign),
+                    _ => {
+                        return Err(AuthError {
+                            value: AuthErrorValue::NoSignature,
+                        });
+                    }
+                };
+
+                let expires_secs = expires.parse::<u64>().map_err(|_| AuthError {
+                    value: AuthErrorValue::SignatureIsNotCorrect,
+                })?;
+                let now_secs = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default();
+                if expires_secs < now_secs {
+                    return Err(AuthError {
+                        value: AuthErrorValue::SignatureExpired,
+                    });
+                }
+
+                let sign_bytes = hex::decode(sign).map_err(|_| AuthError {
+                    value: AuthErrorValue::SignatureIsNotCorrect,
+                })?;
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret.value.as_bytes())
+                    .expect("hmac accepts keys of any size");
+                mac.update(sign_content(app_name, stream_name, expires).as_bytes());
+                mac.verify(&sign_bytes).map_err(|_| AuthError {
+                    value: AuthErrorValue::SignatureIsNotCorrect,
+                })
+            }
+        }
+    }
+}
+
+//generate the "sign" url parameter for the hmac algorithm.
+pub fn sign(secret: &str, app_name: &str, stream_name: &str, expires: u64) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any size");
+    mac.update(sign_content(app_name, stream_name, &expires.to_string()).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+//compare the secrets in constant time to avoid leaking them by timing
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.bytes().zip(b.bytes()).fold(0, |rv, (x, y)| rv | (x ^ y)) == 0
+}
+
+fn sign_content(app_name: &str, stream_name: &str, expires: &str) -> String {
+    format!("{app_name}/{stream_name}?expires={expires}")
+}
+
+//returns (protocol, app_name, stream_name), the rtsp stream path "live/test"
+//is split into app name "live" and stream name "test".
+fn split_identifier(identifier: &StreamIdentifier) -> (&str, &str, &str) {
+    match identifier {
+        StreamIdentifier::Rtmp {
+            app_name,
+            stream_name,
+        } => ("rtmp", app_name, stream_name),
+        StreamIdentifier::Rtsp { stream_path } => match stream_path.split_once('/') {
+            Some((app_name, stream_name)) => ("rtsp", app_name, stream_name),
+            None => ("rtsp", "", stream_path),
+        },
+        StreamIdentifier::WebRTC {
+            app_name,
+            stream_name,
+        } => ("webrtc", app_name, stream_name),
+        StreamIdentifier::Unkonwn => ("unknown", "", ""),
+    }
+}
+
+fn parse_url_parameters(url_parameters: &str) -> HashMap<&str, &str> {
+    url_parameters
+        .trim_start_matches('?')
+        .split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{constant_time_eq, sign, Auth, AuthAlgorithm, AuthSecret};
+    use crate::errors::AuthErrorValue;
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn new_auth() -> Auth {
+        let mut app_secrets = HashMap::new();
+        app_secrets.insert(
+            String::from("signed"),
+            AuthSecret {
+                algorithm: AuthAlgorithm::Hmac,
+                value: String::from("hmac_secret"),
+            },
+        );
+
+        Auth::new(
+            Some(AuthSecret {
+                algorithm: AuthAlgorithm::Simple,
+                value: String::from("token_secret"),
+            }),
+            app_secrets,
+            true,
+            true,
+            None,
+            Duration::from_secs(3),
+        )
+    }
+
+    #[test]
+    fn test_simple_token() {
+        let auth = new_auth();
+
+        assert!(auth
+            .check_secret("live", "test", "token=token_secret")
+            .is_ok());
+        assert!(matches!(
+            auth.check_secret("live", "test", "token=wrong")
+                .unwrap_err()
+                .value,
+            AuthErrorValue::TokenIsNotCorrect
+        ));
+        assert!(matches!(
+            auth.check_secret("live", "test", "").unwrap_err().value,
+            AuthErrorValue::NoToken
+        ));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("123456", "123456"));
+        assert!(constant_time_eq("", ""));
+        assert!(!constant_time_eq("123456", "123457"));
+        assert!(!constant_time_eq("123456", "1234567"));
+    }
+
+    #[test]
+    fn test_hmac_sign() {
+        let auth = new_auth();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let expires = now + 60;
+        let sign_str = sign("hmac_secret", "signed", "test", expires);
+        let parameters = format!("expires={expires}&sign={sign_str}");
+        assert!(auth.check_secret("signed", "test", &parameters).is_ok());
+
+        //the sign is bound to the stream name
+        assert!(matches!(
+            auth.check_secret("signed", "test2", &parameters)
+                .unwrap_err()
+                .value,
+            AuthErrorValue::SignatureIsNotCorrect
+        ));
+
+        let expires = now - 1;
+        let sign_str = sign("hmac_secret", "signed", "test", expires);
+        let parameters = format!("expires={expires}&sign={sign_str}");
+        assert!(matches!(
+            auth.check_secret("signed", "test", &parameters)
+                .unwrap_err()
+                .value,
+            AuthErrorValue::SignatureExpired
+        ));
+    }
+}
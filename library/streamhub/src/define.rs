@@ -1,8 +1,9 @@
 use crate::utils;
 
 use {
-    super::errors::ChannelError,
-    crate::statistics::StreamStatistics,
+    super::errors::{ChannelError, ChannelErrorValue, NotifyError},
+    crate::notify::NotifyResponse,
+    crate::statistics::{StreamDetail, StreamStatistics},
     crate::stream::StreamIdentifier,
     async_trait::async_trait,
     bytes::BytesMut,
@@ -25,14 +26,47 @@ pub enum SubscribeType {
     PlayerHls,
     /* Remote/local client request playing rtsp stream.*/
     PlayerRtsp,
-    /* Local client request playing webrtc stream, it's used for protocol remux.*/
+    /* Remote client request playing webrtc stream(whep).*/
     PlayerWebrtc,
     /* Remote client request playing rtsp or webrtc(whep) raw rtp stream.*/
     PlayerRtp,
+    /* Remote client request playing mpegts stream over srt.*/
+    PlayerSrt,
     GenerateHls,
+    /* Local remuxer subscribes the rtsp/webrtc(whip) stream and remuxes it to rtmp.*/
+    RemuxRtmp,
+    /* Local remuxer subscribes the rtmp stream and remuxes it to rtsp.*/
+    RemuxRtsp,
+    /* Local remuxer subscribes the rtmp stream and remuxes it to webrtc.*/
+    RemuxWebrtc,
     /* Local client *subscribe* from local rtmp session
     and *publish* (relay push) the stream to remote server.*/
     PublisherRtmp,
+    /* Local client *subscribe* from local rtsp session
+    and *publish* (relay push) the stream to remote server.*/
+    PublisherRtsp,
+}
+
+impl SubscribeType {
+    /*only the remote players keep the stream pulled from the remote server,
+    the local remuxers, hls generators and relay push clients do not*/
+    pub fn keeps_pulled_stream(&self) -> bool {
+        match self {
+            SubscribeType::PlayerRtmp
+            | SubscribeType::PlayerHttpFlv
+            | SubscribeType::PlayerHls
+            | SubscribeType::PlayerRtsp
+            | SubscribeType::PlayerWebrtc
+            | SubscribeType::PlayerRtp
+            | SubscribeType::PlayerSrt => true,
+            SubscribeType::GenerateHls
+            | SubscribeType::RemuxRtmp
+            | SubscribeType::RemuxRtsp
+            | SubscribeType::RemuxWebrtc
+            | SubscribeType::PublisherRtmp
+            | SubscribeType::PublisherRtsp => false,
+        }
+    }
 }
 
 //session publish type
@@ -45,11 +79,20 @@ pub enum PublishType {
     RelayRtmp,
     /* Receive rtsp stream from remote push client */
     PushRtsp,
+    /* Local client *publish* the rtsp stream to local session,
+    the rtsp stream is pulled from remote server(e.g. IP camera).*/
     RelayRtsp,
     /* Receive webrtc stream from remote push client(whip),  */
     PushWebRTC,
     /* It used for publishing raw rtp data of rtsp/whbrtc(whip) */
     PushRtp,
+    /* Receive mpegts stream over srt from remote push client */
+    PushSrt,
+    /* The rtmp stream remuxed from the rtsp/webrtc(whip) stream,
+    it is not remuxed to the other protocols again. */
+    RemuxRtmp,
+    /* The rtsp stream remuxed from the rtmp stream, it is not remuxed back to rtmp again. */
+    RemuxRtsp,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -162,9 +205,63 @@ pub type TransmitterEventReceiver = mpsc::UnboundedReceiver<TransmitterEvent>;
 pub type AvStatisticSender = mpsc::UnboundedSender<StreamStatistics>;
 pub type AvStatisticReceiver = mpsc::UnboundedReceiver<StreamStatistics>;
 
+pub type StreamDetailSender = mpsc::UnboundedSender<StreamDetail>;
+pub type StreamDetailReceiver = mpsc::UnboundedReceiver<StreamDetail>;
+
 pub type StreamStatisticSizeSender = oneshot::Sender<usize>;
 pub type StreamStatisticSizeReceiver = oneshot::Sender<usize>;
 
+//the publish/subscribe result answered by the stream hub, the session is rejected if it is an error
+pub type PubSubResultSender = oneshot::Sender<Result<(), ChannelError>>;
+pub type PubSubResultReceiver = oneshot::Receiver<Result<(), ChannelError>>;
+
+//wait until the stream hub accepts or rejects the publish/subscribe event
+pub async fn wait_pub_sub_result(receiver: PubSubResultReceiver) -> Result<(), ChannelError> {
+    match receiver.await {
+        Ok(rv) => rv,
+        Err(_) => Err(ChannelError {
+            value: ChannelErrorValue::NoResult,
+        }),
+    }
+}
+
+//query the details of one stream, or all the streams if no identifier is given
+pub async fn query_stream_details(
+    producer: &StreamHubEventSender,
+    identifier: Option<StreamIdentifier>,
+) -> Result<Vec<StreamDetail>, ChannelError> {
+    let (data_sender, mut data_receiver) = mpsc::unbounded_channel();
+    let (size_sender, size_receiver) = oneshot::channel();
+    let hub_event = match identifier {
+        Some(identifier) => StreamHubEvent::ApiQueryStream {
+            identifier,
+            data_sender,
+            size_sender,
+        },
+        None => StreamHubEvent::ApiListStreams {
+            data_sender,
+            size_sender,
+        },
+    };
+    producer.send(hub_event).map_err(|_| ChannelError {
+        value: ChannelErrorValue::SendError,
+    })?;
+
+    let size = size_receiver.await.map_err(|_| ChannelError {
+        value: ChannelErrorValue::NoResult,
+    })?;
+    let mut details = Vec::new();
+    while details.len() < size {
+        match data_receiver.recv().await {
+            Some(detail) => details.push(detail),
+            //the stream exits before responding
+            None => break,
+        }
+    }
+
+    Ok(details)
+}
+
 #[async_trait]
 pub trait TStreamHandler: Send + Sync {
     async fn send_prior_data(
@@ -196,6 +293,8 @@ pub enum StreamHubEvent {
         info: SubscriberInfo,
         #[serde(skip_serializing)]
         sender: DataSender,
+        #[serde(skip_serializing)]
+        result_sender: PubSubResultSender,
     },
     UnSubscribe {
         identifier: StreamIdentifier,
@@ -208,6 +307,8 @@ pub enum StreamHubEvent {
         receiver: DataReceiver,
         #[serde(skip_serializing)]
         stream_handler: Arc<dyn TStreamHandler>,
+        #[serde(skip_serializing)]
+        result_sender: PubSubResultSender,
     },
     UnPublish {
         identifier: StreamIdentifier,
@@ -220,11 +321,40 @@ pub enum StreamHubEvent {
     },
     #[serde(skip_serializing)]
     ApiKickClient { id: Uuid },
+    /*list the publisher and subscribers of all the streams*/
+    #[serde(skip_serializing)]
+    ApiListStreams {
+        data_sender: StreamDetailSender,
+        size_sender: StreamStatisticSizeSender,
+    },
+    /*query the publisher and subscribers of one stream, size is 0 if not found*/
+    #[serde(skip_serializing)]
+    ApiQueryStream {
+        identifier: StreamIdentifier,
+        data_sender: StreamDetailSender,
+        size_sender: StreamStatisticSizeSender,
+    },
+    /*unpublish the stream and disconnect all its subscribers*/
+    #[serde(skip_serializing)]
+    ApiKickStream { identifier: StreamIdentifier },
 
     #[serde(skip_serializing)]
     Request {
         identifier: StreamIdentifier,
         sender: InformationSender,
+        //try to pull the stream from the remote server if it is not published
+        pull: bool,
+    },
+    /*the stream can not be pulled from the remote server, e.g. refused by the
+    relay hop limit, the subscribers waiting for it are dropped at once*/
+    #[serde(skip_serializing)]
+    PullFailed { identifier: StreamIdentifier },
+    /*the result of the blocking on_publish/on_play notify, it is sent back
+    to the hub by the check task with the publish/subscribe event*/
+    #[serde(skip_serializing)]
+    NotifyChecked {
+        event: Box<StreamHubEvent>,
+        result: Result<Option<NotifyResponse>, NotifyError>,
     },
 }
 
@@ -242,6 +372,9 @@ pub enum TransmitterEvent {
     Api {
         sender: AvStatisticSender,
     },
+    ApiDetail {
+        sender: StreamDetailSender,
+    },
     Request {
         sender: InformationSender,
     },
@@ -256,11 +389,23 @@ impl fmt::Display for TransmitterEvent {
 #[derive(Debug, Clone)]
 pub enum BroadcastEvent {
     /*Need publish(push) a stream to other rtmp server*/
-    Publish { identifier: StreamIdentifier },
-    UnPublish { identifier: StreamIdentifier },
-    /*Need subscribe(pull) a stream from other rtmp server*/
-    Subscribe { identifier: StreamIdentifier },
-    UnSubscribe { identifier: StreamIdentifier },
+    Publish {
+        identifier: StreamIdentifier,
+        info: PublisherInfo,
+    },
+    UnPublish {
+        identifier: StreamIdentifier,
+    },
+    /*Need subscribe(pull) a stream from other rtmp server, info is the
+    subscriber which triggers the pull, none if it is triggered by a request*/
+    Subscribe {
+        identifier: StreamIdentifier,
+        info: Option<SubscriberInfo>,
+    },
+    /*The last remote subscriber leaves, the pulled stream can be stopped*/
+    UnSubscribe {
+        identifier: StreamIdentifier,
+    },
 }
 
 //Used for kickoff
@@ -1,7 +1,10 @@
 use bytesio::bytes_errors::BytesReadError;
 use bytesio::bytes_errors::BytesWriteError;
 
-use {failure::Fail, std::fmt};
+use {
+    failure::{Backtrace, Fail},
+    std::fmt,
+};
 #[derive(Debug, Fail)]
 pub enum ChannelErrorValue {
     #[fail(display = "no app name\n")]
@@ -24,6 +27,10 @@ pub enum ChannelErrorValue {
     BytesWriteError(BytesWriteError),
     #[fail(display = "not correct data sender type\n")]
     NotCorrectDataSenderType,
+    #[fail(display = "rejected by notify: {}", _0)]
+    NotifyRejected(#[cause] NotifyError),
+    #[fail(display = "no result from stream hub\n")]
+    NoResult,
 }
 #[derive(Debug)]
 pub struct ChannelError {
@@ -36,6 +43,16 @@ impl fmt::Display for ChannelError {
     }
 }
 
+impl Fail for ChannelError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
+
 impl From<BytesReadError> for ChannelError {
     fn from(error: BytesReadError) -> Self {
         ChannelError {
@@ -59,3 +76,85 @@ impl From<BytesWriteError> for ChannelError {
 //         }
 //     }
 // }
+
+#[derive(Debug, Fail)]
+pub enum AuthErrorValue {
+    #[fail(display = "token is not provided\n")]
+    NoToken,
+    #[fail(display = "token is not correct\n")]
+    TokenIsNotCorrect,
+    #[fail(display = "sign or expires is not provided\n")]
+    NoSignature,
+    #[fail(display = "sign is not correct\n")]
+    SignatureIsNotCorrect,
+    #[fail(display = "signed url is expired\n")]
+    SignatureExpired,
+    #[fail(display = "rejected by auth callout, status code: {}\n", _0)]
+    CalloutRejected(u16),
+    #[fail(display = "auth callout request error: {}\n", _0)]
+    CalloutRequestError(#[cause] reqwest::Error),
+}
+#[derive(Debug)]
+pub struct AuthError {
+    pub value: AuthErrorValue,
+}
+
+impl From<reqwest::Error> for AuthError {
+    fn from(error: reqwest::Error) -> Self {
+        AuthError {
+            value: AuthErrorValue::CalloutRequestError(error),
+        }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for AuthError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum NotifyErrorValue {
+    #[fail(display = "rejected by notify server, status code: {}\n", _0)]
+    Rejected(u16),
+    #[fail(display = "notify request error: {}\n", _0)]
+    RequestError(#[cause] reqwest::Error),
+}
+#[derive(Debug)]
+pub struct NotifyError {
+    pub value: NotifyErrorValue,
+}
+
+impl From<reqwest::Error> for NotifyError {
+    fn from(error: reqwest::Error) -> Self {
+        NotifyError {
+            value: NotifyErrorValue::RequestError(error),
+        }
+    }
+}
+
+impl fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for NotifyError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
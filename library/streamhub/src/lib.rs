@@ -2,6 +2,7 @@ use define::{FrameDataReceiver, PacketDataReceiver, PacketDataSender};
 
 use crate::define::PacketData;
 
+pub mod auth;
 pub mod define;
 pub mod errors;
 pub mod notify;
@@ -10,24 +11,126 @@ pub mod stream;
 pub mod utils;
 
 use {
-    crate::notify::Notifier,
+    crate::notify::{Notifier, NotifyResponse},
     define::{
         AvStatisticSender, BroadcastEvent, BroadcastEventReceiver, BroadcastEventSender,
         DataReceiver, DataSender, FrameData, FrameDataSender, Information, PubSubInfo,
-        StreamHubEvent, StreamHubEventReceiver, StreamHubEventSender, StreamStatisticSizeSender,
-        SubscribeType, SubscriberInfo, TStreamHandler, TransmitterEvent, TransmitterEventReceiver,
-        TransmitterEventSender,
+        PublisherInfo, StreamDetailSender, StreamHubEvent, StreamHubEventReceiver,
+        StreamHubEventSender, StreamStatisticSizeSender, SubscriberInfo, TStreamHandler,
+        TransmitterEvent, TransmitterEventReceiver, TransmitterEventSender,
+    },
+    errors::{ChannelError, ChannelErrorValue, NotifyError},
+    statistics::{
+        metrics::SessionCounters, PublisherStatistics, StreamDetail, SubscriberStatistics,
     },
-    errors::{ChannelError, ChannelErrorValue},
     std::collections::HashMap,
     std::sync::Arc,
+    std::time::{Duration, Instant},
     stream::StreamIdentifier,
     tokio::sync::{broadcast, mpsc, mpsc::UnboundedReceiver, Mutex},
     utils::Uuid,
 };
 
+//the subscribers waiting for the pulled stream are dropped after this timeout
+const PENDING_SUBSCRIBER_TIMEOUT: Duration = Duration::from_secs(10);
+const PENDING_SUBSCRIBER_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+//the transmitted bytes are counted locally and merged into the statistics periodically
+const STATISTICS_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+fn current_time() -> String {
+    chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+//the stream itself and the stream remuxed from it between rtmp and rtsp
+fn same_stream_identifiers(identifier: &StreamIdentifier) -> Vec<StreamIdentifier> {
+    let remuxed = match identifier {
+        StreamIdentifier::Rtmp {
+            app_name,
+            stream_name,
+        } => Some(StreamIdentifier::Rtsp {
+            stream_path: format!("{app_name}/{stream_name}"),
+        }),
+        StreamIdentifier::Rtsp { stream_path } => {
+            stream_path
+                .split_once('/')
+                .map(|(app_name, stream_name)| StreamIdentifier::Rtmp {
+                    app_name: app_name.to_string(),
+                    stream_name: stream_name.to_string(),
+                })
+        }
+        _ => None,
+    };
+    std::iter::once(identifier.clone()).chain(remuxed).collect()
+}
+
+//the subscriber which waits for the stream being pulled
+struct PendingSubscriber {
+    info: SubscriberInfo,
+    sender: DataSender,
+    begin_time: Instant,
+}
+
+//the publisher and subscribers statistics of a stream
+pub struct SessionsStatistics {
+    publisher: PublisherStatistics,
+    subscribers: HashMap<Uuid, SubscriberStatistics>,
+    start_instant: Instant,
+    //the total bytes sent to all the subscribers, including the left ones
+    send_bytes: u64,
+}
+
+impl SessionsStatistics {
+    fn new(info: PublisherInfo) -> Self {
+        Self {
+            publisher: PublisherStatistics {
+                info,
+                start_time: current_time(),
+                uptime: 0,
+                recv_bytes: 0,
+            },
+            subscribers: HashMap::new(),
+            start_instant: Instant::now(),
+            send_bytes: 0,
+        }
+    }
+
+    fn on_send(&mut self, id: &Uuid, data_len: u64) {
+        self.send_bytes += data_len;
+        if let Some(subscriber) = self.subscribers.get_mut(id) {
+            subscriber.send_bytes += data_len;
+        }
+    }
+}
+
+//the bytes transmitted since the last update of the sessions statistics, so the
+//statistics lock is not taken for every frame/packet
+#[derive(Default)]
+struct TransmittedBytes {
+    recv_bytes: u64,
+    send_bytes: HashMap<Uuid, u64>,
+}
+
+impl TransmittedBytes {
+    fn on_send(&mut self, id: &Uuid, data_len: u64) {
+        *self.send_bytes.entry(*id).or_insert(0) += data_len;
+    }
+
+    async fn update(&mut self, sessions_statistics: &Mutex<SessionsStatistics>) {
+        if self.recv_bytes == 0 && self.send_bytes.is_empty() {
+            return;
+        }
+        let mut statistics = sessions_statistics.lock().await;
+        statistics.publisher.recv_bytes += self.recv_bytes;
+        for (id, data_len) in self.send_bytes.drain() {
+            statistics.on_send(&id, data_len);
+        }
+        self.recv_bytes = 0;
+    }
+}
+
 //receive data from ChannelsManager and send to players/subscribers
 pub struct Transmitter {
+    identifier: StreamIdentifier,
     //used for receiving Audio/Video data from publishers
     data_receiver: DataReceiver,
     //used for receiving event
@@ -37,20 +140,26 @@ pub struct Transmitter {
     //used for sending audio/video packet data to players/subscribers
     id_to_packet_sender: Arc<Mutex<HashMap<Uuid, PacketDataSender>>>,
     stream_handler: Arc<dyn TStreamHandler>,
+    //used for the http api to query the publisher and subscribers
+    sessions_statistics: Arc<Mutex<SessionsStatistics>>,
 }
 
 impl Transmitter {
     fn new(
+        identifier: StreamIdentifier,
+        info: PublisherInfo,
         data_receiver: DataReceiver,
         event_receiver: UnboundedReceiver<TransmitterEvent>,
         h: Arc<dyn TStreamHandler>,
     ) -> Self {
         Self {
+            identifier,
             data_receiver,
             event_receiver,
             id_to_frame_sender: Arc::new(Mutex::new(HashMap::new())),
             id_to_packet_sender: Arc::new(Mutex::new(HashMap::new())),
             stream_handler: h,
+            sessions_statistics: Arc::new(Mutex::new(SessionsStatistics::new(info))),
         }
     }
 
@@ -58,8 +167,11 @@ impl Transmitter {
         mut exit: broadcast::Receiver<()>,
         mut receiver: FrameDataReceiver,
         frame_senders: Arc<Mutex<HashMap<Uuid, FrameDataSender>>>,
+        sessions_statistics: Arc<Mutex<SessionsStatistics>>,
     ) {
         tokio::spawn(async move {
+            let mut transmitted = TransmittedBytes::default();
+            let mut update_interval = tokio::time::interval(STATISTICS_UPDATE_INTERVAL);
             loop {
                 tokio::select! {
                     data = receiver.recv() => {
@@ -70,29 +182,38 @@ impl Transmitter {
                                     data: _,
                                 } => {}
                                 FrameData::Audio { timestamp, data } => {
+                                    let data_len = data.len() as u64;
                                     let data = FrameData::Audio {
                                         timestamp,
                                         data: data.clone(),
                                     };
 
-                                    for (_, v) in frame_senders.lock().await.iter() {
+                                    transmitted.recv_bytes += data_len;
+                                    for (id, v) in frame_senders.lock().await.iter() {
                                         if let Err(audio_err) = v.send(data.clone()).map_err(|_| ChannelError {
                                             value: ChannelErrorValue::SendAudioError,
                                         }) {
                                             log::error!("Transmiter send error: {}", audio_err);
+                                        } else {
+                                            transmitted.on_send(id, data_len);
                                         }
                                     }
                                 }
                                 FrameData::Video { timestamp, data } => {
+                                    let data_len = data.len() as u64;
                                     let data = FrameData::Video {
                                         timestamp,
                                         data: data.clone(),
                                     };
-                                    for (_, v) in frame_senders.lock().await.iter() {
+
+                                    transmitted.recv_bytes += data_len;
+                                    for (id, v) in frame_senders.lock().await.iter() {
                                         if let Err(video_err) = v.send(data.clone()).map_err(|_| ChannelError {
                                             value: ChannelErrorValue::SendVideoError,
                                         }) {
                                             log::error!("Transmiter send error: {}", video_err);
+                                        } else {
+                                            transmitted.on_send(id, data_len);
                                         }
                                     }
                                 }
@@ -100,6 +221,9 @@ impl Transmitter {
                             }
                         }
                     }
+                    _ = update_interval.tick() => {
+                        transmitted.update(&sessions_statistics).await;
+                    }
                     _ = exit.recv()=>{
                         break;
                     }
@@ -112,8 +236,11 @@ impl Transmitter {
         mut exit: broadcast::Receiver<()>,
         mut receiver: PacketDataReceiver,
         packet_senders: Arc<Mutex<HashMap<Uuid, PacketDataSender>>>,
+        sessions_statistics: Arc<Mutex<SessionsStatistics>>,
     ) {
         tokio::spawn(async move {
+            let mut transmitted = TransmittedBytes::default();
+            let mut update_interval = tokio::time::interval(STATISTICS_UPDATE_INTERVAL);
             loop {
                 tokio::select! {
                     data = receiver.recv() => {
@@ -121,29 +248,38 @@ impl Transmitter {
                             match val {
 
                                 PacketData::Audio { timestamp, data } => {
+                                    let data_len = data.len() as u64;
                                     let data = PacketData::Audio {
                                         timestamp,
                                         data: data.clone(),
                                     };
 
-                                    for (_, v) in packet_senders.lock().await.iter() {
+                                    transmitted.recv_bytes += data_len;
+                                    for (id, v) in packet_senders.lock().await.iter() {
                                         if let Err(audio_err) = v.send(data.clone()).map_err(|_| ChannelError {
                                             value: ChannelErrorValue::SendAudioError,
                                         }) {
                                             log::error!("Transmiter send error: {}", audio_err);
+                                        } else {
+                                            transmitted.on_send(id, data_len);
                                         }
                                     }
                                 }
                                 PacketData::Video { timestamp, data } => {
+                                    let data_len = data.len() as u64;
                                     let data = PacketData::Video {
                                         timestamp,
                                         data: data.clone(),
                                     };
-                                    for (_, v) in packet_senders.lock().await.iter() {
+
+                                    transmitted.recv_bytes += data_len;
+                                    for (id, v) in packet_senders.lock().await.iter() {
                                         if let Err(video_err) = v.send(data.clone()).map_err(|_| ChannelError {
                                             value: ChannelErrorValue::SendVideoError,
                                         }) {
                                             log::error!("Transmiter send error: {}", video_err);
+                                        } else {
+                                            transmitted.on_send(id, data_len);
                                         }
                                     }
                                 }
@@ -151,6 +287,9 @@ impl Transmitter {
                             }
                         }
                     }
+                    _ = update_interval.tick() => {
+                        transmitted.update(&sessions_statistics).await;
+                    }
                     _ = exit.recv()=>{
                         break;
                     }
@@ -159,17 +298,27 @@ impl Transmitter {
         });
     }
     pub async fn receive_event_loop(
+        identifier: StreamIdentifier,
         stream_handler: Arc<dyn TStreamHandler>,
         exit: broadcast::Sender<()>,
         mut receiver: TransmitterEventReceiver,
         packet_senders: Arc<Mutex<HashMap<Uuid, PacketDataSender>>>,
         frame_senders: Arc<Mutex<HashMap<Uuid, FrameDataSender>>>,
+        sessions_statistics: Arc<Mutex<SessionsStatistics>>,
     ) {
         tokio::spawn(async move {
             loop {
                 if let Some(val) = receiver.recv().await {
                     match val {
                         TransmitterEvent::Subscribe { sender, info } => {
+                            sessions_statistics.lock().await.subscribers.insert(
+                                info.id,
+                                SubscriberStatistics {
+                                    info: info.clone(),
+                                    start_time: current_time(),
+                                    send_bytes: 0,
+                                },
+                            );
                             if let Err(err) = stream_handler
                                 .send_prior_data(sender.clone(), info.sub_type)
                                 .await
@@ -190,14 +339,16 @@ impl Transmitter {
                                 }
                             }
                         }
-                        TransmitterEvent::UnSubscribe { info } => match info.sub_type {
-                            SubscribeType::PlayerRtp | SubscribeType::PlayerWebrtc => {
-                                packet_senders.lock().await.remove(&info.id);
-                            }
-                            _ => {
-                                frame_senders.lock().await.remove(&info.id);
-                            }
-                        },
+                        TransmitterEvent::UnSubscribe { info } => {
+                            sessions_statistics
+                                .lock()
+                                .await
+                                .subscribers
+                                .remove(&info.id);
+                            //the remuxers of the same type subscribe both kinds of data
+                            packet_senders.lock().await.remove(&info.id);
+                            frame_senders.lock().await.remove(&info.id);
+                        }
                         TransmitterEvent::UnPublish {} => {
                             if let Err(err) = exit.send(()) {
                                 log::error!("TransmitterEvent::UnPublish send error: {}", err);
@@ -213,6 +364,28 @@ impl Transmitter {
                                 }
                             }
                         }
+                        TransmitterEvent::ApiDetail { sender } => {
+                            let (publisher, subscribers, send_bytes) = {
+                                let statistics = sessions_statistics.lock().await;
+                                let mut publisher = statistics.publisher.clone();
+                                publisher.uptime = statistics.start_instant.elapsed().as_secs();
+                                (
+                                    publisher,
+                                    statistics.subscribers.values().cloned().collect(),
+                                    statistics.send_bytes,
+                                )
+                            };
+                            let stream_detail = StreamDetail {
+                                identifier: identifier.clone(),
+                                publisher,
+                                subscribers,
+                                send_bytes,
+                                statistics: stream_handler.get_statistic_data().await,
+                            };
+                            if let Err(err) = sender.send(stream_detail) {
+                                log::info!("Transmitter send stream detail err: {}", err);
+                            }
+                        }
                         TransmitterEvent::Request { sender } => {
                             stream_handler.send_information(sender).await;
                         }
@@ -230,6 +403,7 @@ impl Transmitter {
                 tx.subscribe(),
                 receiver,
                 self.id_to_frame_sender.clone(),
+                self.sessions_statistics.clone(),
             )
             .await;
         }
@@ -239,16 +413,19 @@ impl Transmitter {
                 tx.subscribe(),
                 receiver,
                 self.id_to_packet_sender.clone(),
+                self.sessions_statistics.clone(),
             )
             .await;
         }
 
         Self::receive_event_loop(
+            self.identifier,
             self.stream_handler,
             tx,
             self.event_receiver,
             self.id_to_packet_sender,
             self.id_to_frame_sender,
+            self.sessions_statistics,
         )
         .await;
 
@@ -261,6 +438,8 @@ pub struct StreamsHub {
     streams: HashMap<StreamIdentifier, TransmitterEventSender>,
     //save info to kick off client
     streams_info: HashMap<Uuid, PubSubInfo>,
+    //the subscribers which wait for the streams being pulled from the remote servers
+    pending_subscribers: HashMap<StreamIdentifier, Vec<PendingSubscriber>>,
     //event is consumed in Channels, produced from other rtmp sessions
     hub_event_receiver: StreamHubEventReceiver,
     //event is produced from other rtmp sessions
@@ -270,13 +449,20 @@ pub struct StreamsHub {
     //The rtmp static push/pull and the hls transfer is triggered actively,
     //add a control switches separately.
     rtmp_push_enabled: bool,
+    rtsp_push_enabled: bool,
     rtmp_remuxer_enabled: bool,
+    webrtc_remuxer_enabled: bool,
+    rtsp_remuxer_enabled: bool,
     //enable rtmp pull
     rtmp_pull_enabled: bool,
+    //enable rtsp pull
+    rtsp_pull_enabled: bool,
     //enable hls
     hls_enabled: bool,
     //http notifier on sub/pub event
-    notifier: Option<Notifier>,
+    notifier: Option<Arc<Notifier>>,
+    //the client session counters of the protocol servers, used by the metrics
+    session_counters: SessionCounters,
 }
 
 impl StreamsHub {
@@ -287,14 +473,20 @@ impl StreamsHub {
         Self {
             streams: HashMap::new(),
             streams_info: HashMap::new(),
+            pending_subscribers: HashMap::new(),
             hub_event_receiver: event_consumer,
             hub_event_sender: event_producer,
             client_event_producer: client_producer,
             rtmp_push_enabled: false,
+            rtsp_push_enabled: false,
             rtmp_pull_enabled: false,
+            rtsp_pull_enabled: false,
             rtmp_remuxer_enabled: false,
+            webrtc_remuxer_enabled: false,
+            rtsp_remuxer_enabled: false,
             hls_enabled: false,
-            notifier,
+            notifier: notifier.map(Arc::new),
+            session_counters: SessionCounters::new(),
         }
     }
     pub async fn run(&mut self) {
@@ -305,14 +497,30 @@ impl StreamsHub {
         self.rtmp_push_enabled = enabled;
     }
 
+    pub fn set_rtsp_push_enabled(&mut self, enabled: bool) {
+        self.rtsp_push_enabled = enabled;
+    }
+
     pub fn set_rtmp_pull_enabled(&mut self, enabled: bool) {
         self.rtmp_pull_enabled = enabled;
     }
 
+    pub fn set_rtsp_pull_enabled(&mut self, enabled: bool) {
+        self.rtsp_pull_enabled = enabled;
+    }
+
     pub fn set_rtmp_remuxer_enabled(&mut self, enabled: bool) {
         self.rtmp_remuxer_enabled = enabled;
     }
 
+    pub fn set_webrtc_remuxer_enabled(&mut self, enabled: bool) {
+        self.webrtc_remuxer_enabled = enabled;
+    }
+
+    pub fn set_rtsp_remuxer_enabled(&mut self, enabled: bool) {
+        self.rtsp_remuxer_enabled = enabled;
+    }
+
     pub fn set_hls_enabled(&mut self, enabled: bool) {
         self.hls_enabled = enabled;
     }
@@ -325,8 +533,41 @@ impl StreamsHub {
         self.client_event_producer.subscribe()
     }
 
+    pub fn get_session_counters(&mut self) -> SessionCounters {
+        self.session_counters.clone()
+    }
+
     pub async fn event_loop(&mut self) {
-        while let Some(message) = self.hub_event_receiver.recv().await {
+        let mut check_interval = tokio::time::interval(PENDING_SUBSCRIBER_CHECK_INTERVAL);
+        loop {
+            let message = tokio::select! {
+                message = self.hub_event_receiver.recv() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+                _ = check_interval.tick() => {
+                    self.clean_pending_subscribers();
+                    continue;
+                }
+            };
+
+            let message = match message {
+                StreamHubEvent::NotifyChecked { event, result } => {
+                    match Self::on_notify_checked(*event, result) {
+                        Some(message) => message,
+                        None => continue,
+                    }
+                }
+                message => {
+                    //the blocking notify is checked in another task, do not block the event loop
+                    if let Some(notifier) = self.get_check_notifier(&message) {
+                        self.spawn_notify_check(notifier, message);
+                        continue;
+                    }
+                    message
+                }
+            };
+
             let event_serialize_str = if let Ok(data) = serde_json::to_string(&message) {
                 log::info!("event data: {}", data);
                 data
@@ -340,9 +581,10 @@ impl StreamsHub {
                     receiver,
                     info,
                     stream_handler,
+                    result_sender,
                 } => {
                     let rv = self
-                        .publish(identifier.clone(), receiver, stream_handler)
+                        .publish(identifier.clone(), info.clone(), receiver, stream_handler)
                         .await;
                     match rv {
                         Ok(()) => {
@@ -351,34 +593,41 @@ impl StreamsHub {
                             }
                             self.streams_info
                                 .insert(info.id, PubSubInfo::Publish { identifier });
+                            let _ = result_sender.send(Ok(()));
                         }
                         Err(err) => {
                             log::error!("event_loop Publish err: {}\n", err);
-                            continue;
+                            let _ = result_sender.send(Err(err));
                         }
                     }
                 }
 
-                StreamHubEvent::UnPublish {
-                    identifier,
-                    info: _,
-                } => {
-                    if let Err(err) = self.unpublish(&identifier) {
-                        log::error!(
+                StreamHubEvent::UnPublish { identifier, info } => {
+                    //the identifier may be rewritten by the on_publish notify
+                    let identifier = match self.streams_info.remove(&info.id) {
+                        Some(PubSubInfo::Publish { identifier }) => identifier,
+                        _ => identifier,
+                    };
+
+                    match self.unpublish(&identifier) {
+                        Ok(()) => {
+                            if let Some(notifier) = &self.notifier {
+                                notifier.on_unpublish_notify(event_serialize_str).await;
+                            }
+                        }
+                        //the stream may have been kicked off by the http api
+                        Err(err) => log::error!(
                             "event_loop Unpublish err: {} with identifier: {} \n",
                             err,
                             identifier
-                        );
-                    }
-
-                    if let Some(notifier) = &self.notifier {
-                        notifier.on_unpublish_notify(event_serialize_str).await;
+                        ),
                     }
                 }
                 StreamHubEvent::Subscribe {
                     identifier,
                     info,
                     sender,
+                    result_sender,
                 } => {
                     let sub_id = info.id;
                     let info_clone = info.clone();
@@ -396,19 +645,28 @@ impl StreamsHub {
                                     sub_info: info,
                                 },
                             );
+                            let _ = result_sender.send(Ok(()));
                         }
                         Err(err) => {
                             log::error!("event_loop Subscribe error: {}", err);
-                            continue;
+                            let _ = result_sender.send(Err(err));
                         }
                     }
                 }
                 StreamHubEvent::UnSubscribe { identifier, info } => {
+                    //the identifier may be rewritten by the on_play notify
+                    let (identifier, is_subscribed) = match self.streams_info.remove(&info.id) {
+                        Some(PubSubInfo::Subscribe { identifier, .. }) => (identifier, true),
+                        _ => (identifier, false),
+                    };
                     if self.unsubscribe(&identifier, info).is_ok() {
                         if let Some(notifier) = &self.notifier {
                             notifier.on_stop_notify(event_serialize_str).await;
                         }
                     }
+                    if is_subscribed {
+                        self.on_subscriber_leave(&identifier);
+                    }
                 }
 
                 StreamHubEvent::ApiStatistic {
@@ -426,12 +684,153 @@ impl StreamsHub {
                         notifier.on_unpublish_notify(event_serialize_str).await;
                     }
                 }
-                StreamHubEvent::Request { identifier, sender } => {
-                    if let Err(err) = self.request(&identifier, sender) {
+                StreamHubEvent::ApiListStreams {
+                    data_sender,
+                    size_sender,
+                } => {
+                    if let Err(err) = self.api_list_streams(data_sender, size_sender) {
+                        log::error!("event_loop api list streams error: {}", err);
+                    }
+                }
+                StreamHubEvent::ApiQueryStream {
+                    identifier,
+                    data_sender,
+                    size_sender,
+                } => {
+                    if let Err(err) = self.api_query_stream(&identifier, data_sender, size_sender) {
+                        log::error!("event_loop api query stream error: {}", err);
+                    }
+                }
+                StreamHubEvent::ApiKickStream { identifier } => {
+                    match self.api_kick_off_stream(&identifier) {
+                        Ok(()) => {
+                            if let Some(notifier) = &self.notifier {
+                                notifier.on_unpublish_notify(event_serialize_str).await;
+                            }
+                        }
+                        Err(err) => log::error!(
+                            "event_loop ApiKickStream err: {} with identifier: {}",
+                            err,
+                            identifier
+                        ),
+                    }
+                }
+                StreamHubEvent::Request {
+                    identifier,
+                    sender,
+                    pull,
+                } => {
+                    if let Err(err) = self.request(&identifier, sender, pull) {
                         log::error!("event_loop request error: {}", err);
                     }
                 }
+                StreamHubEvent::PullFailed { identifier } => {
+                    self.drop_pending_subscribers(&identifier);
+                }
+                StreamHubEvent::NotifyChecked { .. } => {}
+            }
+        }
+    }
+
+    //the notifier which the publish/subscribe event should be checked by
+    fn get_check_notifier(&self, event: &StreamHubEvent) -> Option<Arc<Notifier>> {
+        let notifier = self.notifier.as_ref()?;
+        let need_check = match event {
+            StreamHubEvent::Publish { .. } => notifier.need_publish_check(),
+            StreamHubEvent::Subscribe { .. } => notifier.need_play_check(),
+            _ => false,
+        };
+        need_check.then(|| notifier.clone())
+    }
+
+    fn spawn_notify_check(&self, notifier: Arc<Notifier>, event: StreamHubEvent) {
+        let body = serde_json::to_string(&event).unwrap_or_default();
+        let hub_event_sender = self.hub_event_sender.clone();
+
+        tokio::spawn(async move {
+            //the request is bounded by the notify timeout
+            let result = match &event {
+                StreamHubEvent::Publish { .. } => notifier.on_publish_check(&body).await,
+                _ => notifier.on_play_check(&body).await,
+            };
+            let checked_event = StreamHubEvent::NotifyChecked {
+                event: Box::new(event),
+                result,
+            };
+            if hub_event_sender.send(checked_event).is_err() {
+                log::error!("spawn_notify_check: send checked event err");
+            }
+        });
+    }
+
+    //redirect the checked publish/subscribe event, or answer the session with a reject
+    fn on_notify_checked(
+        event: StreamHubEvent,
+        result: Result<Option<NotifyResponse>, NotifyError>,
+    ) -> Option<StreamHubEvent> {
+        match (event, result) {
+            (
+                StreamHubEvent::Publish {
+                    identifier,
+                    result_sender,
+                    ..
+                }
+                | StreamHubEvent::Subscribe {
+                    identifier,
+                    result_sender,
+                    ..
+                },
+                Err(err),
+            ) => {
+                log::error!(
+                    "event_loop Publish/Subscribe is rejected: {}, identifier: {}",
+                    err,
+                    identifier
+                );
+                let _ = result_sender.send(Err(ChannelError {
+                    value: ChannelErrorValue::NotifyRejected(err),
+                }));
+                None
+            }
+            (
+                StreamHubEvent::Publish { result_sender, .. }
+                | StreamHubEvent::Subscribe { result_sender, .. },
+                _,
+            ) if result_sender.is_closed() => {
+                log::warn!("event_loop the session leaves before the notify is checked");
+                None
             }
+            (
+                StreamHubEvent::Publish {
+                    identifier,
+                    info,
+                    receiver,
+                    stream_handler,
+                    result_sender,
+                },
+                Ok(Some(response)),
+            ) => Some(StreamHubEvent::Publish {
+                identifier: response.rewrite(identifier),
+                info,
+                receiver,
+                stream_handler,
+                result_sender,
+            }),
+            (
+                StreamHubEvent::Subscribe {
+                    identifier,
+                    info,
+                    sender,
+                    result_sender,
+                },
+                Ok(Some(response)),
+            ) => Some(StreamHubEvent::Subscribe {
+                identifier: response.rewrite(identifier),
+                info,
+                sender,
+                result_sender,
+            }),
+            (event, _) => Some(event),
         }
     }
 
@@ -439,6 +838,7 @@ impl StreamsHub {
         &mut self,
         identifier: &StreamIdentifier,
         sender: mpsc::UnboundedSender<Information>,
+        pull: bool,
     ) -> Result<(), ChannelError> {
         if let Some(producer) = self.streams.get_mut(identifier) {
             let event = TransmitterEvent::Request { sender };
@@ -446,10 +846,34 @@ impl StreamsHub {
             producer.send(event).map_err(|_| ChannelError {
                 value: ChannelErrorValue::SendError,
             })?;
+        } else if pull && self.rtsp_pull_enabled {
+            //the rtsp players request the sdp before subscribing, so try to pull it here
+            self.pull_stream(identifier, None)?;
         }
         Ok(())
     }
 
+    //send subscribe info to pull clients
+    fn pull_stream(
+        &mut self,
+        identifier: &StreamIdentifier,
+        info: Option<SubscriberInfo>,
+    ) -> Result<(), ChannelError> {
+        log::info!("try to pull stream, identifier: {}", identifier);
+
+        let client_event = BroadcastEvent::Subscribe {
+            identifier: identifier.clone(),
+            info,
+        };
+        self.client_event_producer
+            .send(client_event)
+            .map_err(|_| ChannelError {
+                value: ChannelErrorValue::SendError,
+            })?;
+
+        Ok(())
+    }
+
     fn api_statistic(
         &mut self,
         data_sender: AvStatisticSender,
@@ -478,6 +902,70 @@ impl StreamsHub {
         Ok(())
     }
 
+    fn api_list_streams(
+        &mut self,
+        data_sender: StreamDetailSender,
+        size_sender: StreamStatisticSizeSender,
+    ) -> Result<(), ChannelError> {
+        let mut stream_count: usize = 0;
+        for (identifier, v) in &self.streams {
+            //skip the exited transmitter, do not fail the other streams
+            if let Err(err) = v.send(TransmitterEvent::ApiDetail {
+                sender: data_sender.clone(),
+            }) {
+                log::error!(
+                    "TransmitterEvent api detail send data err: {} with identifier: {}",
+                    err,
+                    identifier
+                );
+                continue;
+            }
+            stream_count += 1;
+        }
+
+        if let Err(err) = size_sender.send(stream_count) {
+            log::error!("TransmitterEvent api detail send size err: {}", err);
+            return Err(ChannelError {
+                value: ChannelErrorValue::SendError,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn api_query_stream(
+        &mut self,
+        identifier: &StreamIdentifier,
+        data_sender: StreamDetailSender,
+        size_sender: StreamStatisticSizeSender,
+    ) -> Result<(), ChannelError> {
+        let mut stream_count: usize = 0;
+        if let Some(producer) = self.streams.get(identifier) {
+            producer
+                .send(TransmitterEvent::ApiDetail {
+                    sender: data_sender,
+                })
+                .map_err(|_| ChannelError {
+                    value: ChannelErrorValue::SendError,
+                })?;
+            stream_count = 1;
+        }
+
+        size_sender.send(stream_count).map_err(|_| ChannelError {
+            value: ChannelErrorValue::SendError,
+        })
+    }
+
+    //unpublish the stream and forget its publisher, the subscribers exit once
+    //the transmitter is closed
+    fn api_kick_off_stream(&mut self, identifier: &StreamIdentifier) -> Result<(), ChannelError> {
+        self.unpublish(identifier)?;
+        self.streams_info.retain(
+            |_, info| !matches!(info, PubSubInfo::Publish { identifier: id } if id == identifier),
+        );
+        Ok(())
+    }
+
     fn api_kick_off_client(&mut self, uid: Uuid) {
         let info = if let Some(info) = self.streams_info.get(&uid) {
             info.clone()
@@ -530,19 +1018,22 @@ impl StreamsHub {
             return Ok(());
         }
 
-        if self.rtmp_pull_enabled {
-            log::info!("subscribe: try to pull stream, identifier: {}", identifer);
+        if self.rtmp_pull_enabled || self.rtsp_pull_enabled {
+            self.pull_stream(identifer, Some(sub_info.clone()))?;
 
-            let client_event = BroadcastEvent::Subscribe {
-                identifier: identifer.clone(),
-            };
-
-            //send subscribe info to pull clients
-            self.client_event_producer
-                .send(client_event)
-                .map_err(|_| ChannelError {
-                    value: ChannelErrorValue::SendError,
-                })?;
+            //the subscriber waits until the stream is pulled from the origin
+            if self.rtmp_pull_enabled && matches!(identifer, StreamIdentifier::Rtmp { .. }) {
+                log::info!("subscribe: wait for the pulled stream: {}", identifer);
+                self.pending_subscribers
+                    .entry(identifer.clone())
+                    .or_default()
+                    .push(PendingSubscriber {
+                        info: sub_info,
+                        sender,
+                        begin_time: Instant::now(),
+                    });
+                return Ok(());
+            }
         }
 
         Err(ChannelError {
@@ -564,19 +1055,101 @@ impl StreamsHub {
                 })?;
             }
             None => {
-                return Err(ChannelError {
-                    value: ChannelErrorValue::NoAppName,
-                })
+                if !self.remove_pending_subscriber(identifer, &sub_info.id) {
+                    return Err(ChannelError {
+                        value: ChannelErrorValue::NoAppName,
+                    });
+                }
             }
         }
 
         Ok(())
     }
 
+    fn remove_pending_subscriber(&mut self, identifier: &StreamIdentifier, id: &Uuid) -> bool {
+        if let Some(subscribers) = self.pending_subscribers.get_mut(identifier) {
+            let len = subscribers.len();
+            subscribers.retain(|subscriber| subscriber.info.id != *id);
+            let removed = subscribers.len() < len;
+            if subscribers.is_empty() {
+                self.pending_subscribers.remove(identifier);
+            }
+            return removed;
+        }
+        false
+    }
+
+    //drop the subscribers if the stream can not be pulled in time
+    fn clean_pending_subscribers(&mut self) {
+        let mut expired_subscribers = Vec::new();
+        self.pending_subscribers.retain(|identifier, subscribers| {
+            subscribers.retain(|subscriber| {
+                if subscriber.begin_time.elapsed() < PENDING_SUBSCRIBER_TIMEOUT {
+                    return true;
+                }
+                expired_subscribers.push((identifier.clone(), subscriber.info.id));
+                false
+            });
+            !subscribers.is_empty()
+        });
+
+        for (identifier, id) in expired_subscribers {
+            log::warn!(
+                "the stream is not pulled in time, drop the subscriber: {}, identifier: {}",
+                id,
+                identifier
+            );
+            self.streams_info.remove(&id);
+            self.on_subscriber_leave(&identifier);
+        }
+    }
+
+    //drop the subscribers at once if the stream can not be pulled
+    fn drop_pending_subscribers(&mut self, identifier: &StreamIdentifier) {
+        if let Some(subscribers) = self.pending_subscribers.remove(identifier) {
+            for subscriber in subscribers {
+                log::warn!(
+                    "the stream can not be pulled, drop the subscriber: {}, identifier: {}",
+                    subscriber.info.id,
+                    identifier
+                );
+                self.streams_info.remove(&subscriber.info.id);
+            }
+        }
+    }
+
+    //notify the pull clients to stop pulling the stream if no remote player is left
+    fn on_subscriber_leave(&mut self, identifier: &StreamIdentifier) {
+        if !self.rtmp_pull_enabled && !self.rtsp_pull_enabled {
+            return;
+        }
+
+        /*the pulled rtmp and rtsp streams are remuxed to each other, the players
+        of both keep the stream alive*/
+        let identifiers = same_stream_identifiers(identifier);
+        let has_remote_players = self.streams_info.values().any(|info| {
+            matches!(info, PubSubInfo::Subscribe { identifier: id, sub_info }
+                if identifiers.contains(id) && sub_info.sub_type.keeps_pulled_stream())
+        });
+        if has_remote_players {
+            return;
+        }
+
+        for identifier in identifiers {
+            let client_event = BroadcastEvent::UnSubscribe {
+                identifier: identifier.clone(),
+            };
+            if self.client_event_producer.send(client_event).is_err() {
+                log::warn!("no client receives the unsubscribe event: {}", identifier);
+            }
+        }
+    }
+
     //publish a stream
     pub async fn publish(
         &mut self,
         identifier: StreamIdentifier,
+        info: PublisherInfo,
         receiver: DataReceiver,
         handler: Arc<dyn TStreamHandler>,
     ) -> Result<(), ChannelError> {
@@ -587,7 +1160,13 @@ impl StreamsHub {
         }
 
         let (event_publisher, event_consumer) = mpsc::unbounded_channel();
-        let transmitter = Transmitter::new(receiver, event_consumer, handler);
+        let transmitter = Transmitter::new(
+            identifier.clone(),
+            info.clone(),
+            receiver,
+            event_consumer,
+            handler,
+        );
 
         let identifier_clone = identifier.clone();
 
@@ -601,10 +1180,28 @@ impl StreamsHub {
             log::info!("transmiter exits: idetifier: {}", identifier_clone);
         }
 
+        /*the subscribers waiting for the pulled stream start to receive data*/
+        if let Some(subscribers) = self.pending_subscribers.remove(&identifier) {
+            for subscriber in subscribers {
+                let event = TransmitterEvent::Subscribe {
+                    sender: subscriber.sender,
+                    info: subscriber.info,
+                };
+                if event_publisher.send(event).is_err() {
+                    log::error!("send pending subscriber error, identifier: {}", identifier);
+                }
+            }
+        }
         self.streams.insert(identifier.clone(), event_publisher);
 
-        if self.rtmp_push_enabled || self.hls_enabled || self.rtmp_remuxer_enabled {
-            let client_event = BroadcastEvent::Publish { identifier };
+        if self.rtmp_push_enabled
+            || self.rtsp_push_enabled
+            || self.hls_enabled
+            || self.rtmp_remuxer_enabled
+            || self.webrtc_remuxer_enabled
+            || self.rtsp_remuxer_enabled
+        {
+            let client_event = BroadcastEvent::Publish { identifier, info };
 
             //send publish info to push clients
             self.client_event_producer
@@ -618,14 +1215,29 @@ impl StreamsHub {
     }
 
     fn unpublish(&mut self, identifier: &StreamIdentifier) -> Result<(), ChannelError> {
-        match self.streams.get_mut(identifier) {
+        match self.streams.remove(identifier) {
             Some(producer) => {
-                let event = TransmitterEvent::UnPublish {};
-                producer.send(event).map_err(|_| ChannelError {
-                    value: ChannelErrorValue::SendError,
-                })?;
-                self.streams.remove(identifier);
+                //remove the exited transmitter as well
+                if producer.send(TransmitterEvent::UnPublish {}).is_err() {
+                    log::warn!("the transmitter has exited: {}", identifier);
+                }
                 log::info!("unpublish remove stream, stream identifier: {}", identifier);
+
+                if self.rtmp_push_enabled
+                    || self.rtsp_push_enabled
+                    || self.hls_enabled
+                    || self.rtmp_remuxer_enabled
+                    || self.webrtc_remuxer_enabled
+                    || self.rtsp_remuxer_enabled
+                {
+                    let client_event = BroadcastEvent::UnPublish {
+                        identifier: identifier.clone(),
+                    };
+                    //send unpublish info to push clients, the stream is removed anyway
+                    if self.client_event_producer.send(client_event).is_err() {
+                        log::warn!("no client receives the unpublish event: {}", identifier);
+                    }
+                }
             }
             None => {
                 return Err(ChannelError {
@@ -637,3 +1249,299 @@ impl StreamsHub {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{
+            define::{
+                wait_pub_sub_result, BroadcastEvent, DataReceiver, DataSender, InformationSender,
+                NotifyInfo, PubSubInfo, PublishType, PublisherInfo, StreamHubEvent, SubscribeType,
+                SubscriberInfo, TStreamHandler,
+            },
+            errors::{ChannelError, ChannelErrorValue},
+            notify::Notifier,
+            statistics::StreamStatistics,
+            stream::StreamIdentifier,
+            utils::{RandomDigitCount, Uuid},
+            StreamsHub,
+        },
+        async_trait::async_trait,
+        std::{sync::Arc, time::Duration},
+        tokio::{
+            net::TcpListener,
+            sync::{mpsc, oneshot},
+            time::timeout,
+        },
+    };
+
+    struct EmptyStreamHandler;
+
+    #[async_trait]
+    impl TStreamHandler for EmptyStreamHandler {
+        async fn send_prior_data(
+            &self,
+            _sender: DataSender,
+            _sub_type: SubscribeType,
+        ) -> Result<(), ChannelError> {
+            Ok(())
+        }
+        async fn get_statistic_data(&self) -> Option<StreamStatistics> {
+            None
+        }
+        async fn send_information(&self, _sender: InformationSender) {}
+    }
+
+    fn identifier(stream_name: &str) -> StreamIdentifier {
+        StreamIdentifier::Rtmp {
+            app_name: String::from("live"),
+            stream_name: stream_name.to_string(),
+        }
+    }
+
+    fn notify_info() -> NotifyInfo {
+        NotifyInfo {
+            request_url: String::from("rtmp://127.0.0.1/live/test"),
+            remote_addr: String::from("127.0.0.1:50000"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocking_notify_check() {
+        //the notify server accepts the connections but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/on_publish", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((connection, _)) = listener.accept().await {
+                connections.push(connection);
+            }
+        });
+
+        let notifier = Notifier::new(
+            Some(url.clone()),
+            None,
+            Some(url),
+            None,
+            true,
+            Duration::from_millis(500),
+            false,
+        );
+        let mut hub = StreamsHub::new(Some(notifier));
+        let hub_event_sender = hub.get_hub_event_sender();
+        tokio::spawn(async move { hub.run().await });
+
+        let (_frame_sender, frame_receiver) = mpsc::unbounded_channel();
+        let (result_sender, publish_result_receiver) = oneshot::channel();
+        let publish_event = StreamHubEvent::Publish {
+            identifier: identifier("test"),
+            info: PublisherInfo {
+                id: Uuid::new(RandomDigitCount::Four),
+                pub_type: PublishType::PushRtmp,
+                notify_info: notify_info(),
+            },
+            receiver: DataReceiver {
+                frame_receiver: Some(frame_receiver),
+                packet_receiver: None,
+            },
+            stream_handler: Arc::new(EmptyStreamHandler),
+            result_sender,
+        };
+        assert!(hub_event_sender.send(publish_event).is_ok());
+
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let (result_sender, subscribe_result_receiver) = oneshot::channel();
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: identifier("test"),
+            info: SubscriberInfo {
+                id: Uuid::new(RandomDigitCount::Four),
+                sub_type: SubscribeType::PlayerRtmp,
+                notify_info: notify_info(),
+            },
+            sender: DataSender::Frame { sender },
+            result_sender,
+        };
+        assert!(hub_event_sender.send(subscribe_event).is_ok());
+
+        //the event loop is not blocked while the notify is being checked
+        let (data_sender, _data_receiver) = mpsc::unbounded_channel();
+        let (size_sender, size_receiver) = oneshot::channel();
+        let query_event = StreamHubEvent::ApiQueryStream {
+            identifier: identifier("test"),
+            data_sender,
+            size_sender,
+        };
+        assert!(hub_event_sender.send(query_event).is_ok());
+        let size = timeout(Duration::from_millis(200), size_receiver).await;
+        assert!(matches!(size, Ok(Ok(0))));
+
+        //both are rejected after the notify times out
+        for result_receiver in [publish_result_receiver, subscribe_result_receiver] {
+            assert!(matches!(
+                wait_pub_sub_result(result_receiver).await,
+                Err(ChannelError {
+                    value: ChannelErrorValue::NotifyRejected(_)
+                })
+            ));
+        }
+    }
+
+    fn publisher_info() -> PublisherInfo {
+        PublisherInfo {
+            id: Uuid::new(RandomDigitCount::Four),
+            pub_type: PublishType::PushRtmp,
+            notify_info: notify_info(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_list_and_kick_stream() {
+        let mut hub = StreamsHub::new(None);
+        let mut frame_senders = Vec::new();
+        for stream_name in ["test", "test2"] {
+            let (frame_sender, frame_receiver) = mpsc::unbounded_channel();
+            frame_senders.push(frame_sender);
+            let info = publisher_info();
+            let receiver = DataReceiver {
+                frame_receiver: Some(frame_receiver),
+                packet_receiver: None,
+            };
+            hub.publish(
+                identifier(stream_name),
+                info.clone(),
+                receiver,
+                Arc::new(EmptyStreamHandler),
+            )
+            .await
+            .unwrap();
+            hub.streams_info.insert(
+                info.id,
+                PubSubInfo::Publish {
+                    identifier: identifier(stream_name),
+                },
+            );
+        }
+        //the transmitter of this stream has exited
+        let (dead_sender, _) = mpsc::unbounded_channel();
+        hub.streams.insert(identifier("dead"), dead_sender);
+
+        let list_streams = |hub: &mut StreamsHub| {
+            let (data_sender, data_receiver) = mpsc::unbounded_channel();
+            let (size_sender, size_receiver) = oneshot::channel();
+            hub.api_list_streams(data_sender, size_sender).unwrap();
+            (data_receiver, size_receiver)
+        };
+
+        let (mut data_receiver, size_receiver) = list_streams(&mut hub);
+        assert_eq!(size_receiver.await.unwrap(), 2);
+        for _ in 0..2 {
+            let detail = data_receiver.recv().await.unwrap();
+            assert_ne!(detail.identifier, identifier("dead"));
+        }
+
+        hub.api_kick_off_stream(&identifier("test")).unwrap();
+        hub.api_kick_off_stream(&identifier("dead")).unwrap();
+        assert!(hub.api_kick_off_stream(&identifier("test")).is_err());
+        assert_eq!(hub.streams_info.len(), 1);
+
+        let (mut data_receiver, size_receiver) = list_streams(&mut hub);
+        assert_eq!(size_receiver.await.unwrap(), 1);
+        let detail = data_receiver.recv().await.unwrap();
+        assert_eq!(detail.identifier, identifier("test2"));
+    }
+
+    #[tokio::test]
+    async fn test_on_subscriber_leave() {
+        let mut hub = StreamsHub::new(None);
+        hub.set_rtmp_pull_enabled(true);
+        let mut client_event_consumer = hub.get_client_event_consumer();
+
+        let subscribe = |hub: &mut StreamsHub, identifier, sub_type| {
+            let id = Uuid::new(RandomDigitCount::Four);
+            let sub_info = SubscriberInfo {
+                id,
+                sub_type,
+                notify_info: notify_info(),
+            };
+            hub.streams_info.insert(
+                id,
+                PubSubInfo::Subscribe {
+                    identifier,
+                    sub_info,
+                },
+            );
+            id
+        };
+        let mut unsubscribed_identifiers = || {
+            let mut identifiers = Vec::new();
+            while let Ok(BroadcastEvent::UnSubscribe { identifier }) =
+                client_event_consumer.try_recv()
+            {
+                identifiers.push(identifier);
+            }
+            identifiers
+        };
+        let rtsp_identifier = StreamIdentifier::Rtsp {
+            stream_path: String::from("live/test"),
+        };
+
+        //the relay push clients and local remuxers do not keep the pulled stream
+        subscribe(&mut hub, identifier("test"), SubscribeType::PublisherRtmp);
+        subscribe(&mut hub, identifier("test"), SubscribeType::RemuxRtsp);
+        subscribe(
+            &mut hub,
+            rtsp_identifier.clone(),
+            SubscribeType::PublisherRtsp,
+        );
+        hub.on_subscriber_leave(&identifier("test"));
+        assert_eq!(
+            unsubscribed_identifiers(),
+            vec![identifier("test"), rtsp_identifier.clone()]
+        );
+
+        //the player of the remuxed rtsp stream keeps it
+        let id = subscribe(&mut hub, rtsp_identifier.clone(), SubscribeType::PlayerRtsp);
+        hub.on_subscriber_leave(&identifier("test"));
+        assert!(unsubscribed_identifiers().is_empty());
+
+        hub.streams_info.remove(&id);
+        hub.on_subscriber_leave(&rtsp_identifier);
+        assert_eq!(
+            unsubscribed_identifiers(),
+            vec![rtsp_identifier, identifier("test")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pull_failed() {
+        let mut hub = StreamsHub::new(None);
+        hub.set_rtmp_pull_enabled(true);
+        let _client_event_consumer = hub.get_client_event_consumer();
+        let hub_event_sender = hub.get_hub_event_sender();
+        tokio::spawn(async move { hub.run().await });
+
+        //the subscriber waits for the stream being pulled
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: identifier("test"),
+            info: SubscriberInfo {
+                id: Uuid::new(RandomDigitCount::Four),
+                sub_type: SubscribeType::PlayerRtmp,
+                notify_info: notify_info(),
+            },
+            sender: DataSender::Frame { sender },
+            result_sender,
+        };
+        assert!(hub_event_sender.send(subscribe_event).is_ok());
+        assert!(wait_pub_sub_result(result_receiver).await.is_ok());
+
+        //it is dropped at once instead of waiting for the timeout
+        let pull_failed_event = StreamHubEvent::PullFailed {
+            identifier: identifier("test"),
+        };
+        assert!(hub_event_sender.send(pull_failed_event).is_ok());
+        let data = timeout(Duration::from_secs(1), receiver.recv()).await;
+        assert!(matches!(data, Ok(None)));
+    }
+}
@@ -1,10 +1,69 @@
-use reqwest::Client;
+use {
+    crate::{
+        errors::{NotifyError, NotifyErrorValue},
+        stream::StreamIdentifier,
+    },
+    reqwest::Client,
+    serde::Deserialize,
+    std::time::Duration,
+};
+
+/*The optional json body of the on_publish/on_play response, which
+is used for redirecting the session to another stream.*/
+#[derive(Debug, Default, Deserialize)]
+pub struct NotifyResponse {
+    pub app_name: Option<String>,
+    pub stream_name: Option<String>,
+}
+
+impl NotifyResponse {
+    pub fn rewrite(self, identifier: StreamIdentifier) -> StreamIdentifier {
+        match identifier {
+            StreamIdentifier::Rtmp {
+                app_name,
+                stream_name,
+            } => StreamIdentifier::Rtmp {
+                app_name: self.app_name.unwrap_or(app_name),
+                stream_name: self.stream_name.unwrap_or(stream_name),
+            },
+            StreamIdentifier::WebRTC {
+                app_name,
+                stream_name,
+            } => StreamIdentifier::WebRTC {
+                app_name: self.app_name.unwrap_or(app_name),
+                stream_name: self.stream_name.unwrap_or(stream_name),
+            },
+            StreamIdentifier::Rtsp { stream_path } => {
+                let (app_name, stream_name) = match stream_path.split_once('/') {
+                    Some((app_name, stream_name)) => (app_name, stream_name),
+                    None => ("", stream_path.as_str()),
+                };
+                let app_name = self.app_name.as_deref().unwrap_or(app_name);
+                let stream_name = self.stream_name.as_deref().unwrap_or(stream_name);
+                let stream_path = if app_name.is_empty() {
+                    stream_name.to_string()
+                } else {
+                    format!("{app_name}/{stream_name}")
+                };
+                StreamIdentifier::Rtsp { stream_path }
+            }
+            StreamIdentifier::Unkonwn => StreamIdentifier::Unkonwn,
+        }
+    }
+}
+
 pub struct Notifier {
     request_client: Client,
     on_publish_url: Option<String>,
     on_unpublish_url: Option<String>,
     on_play_url: Option<String>,
     on_stop_url: Option<String>,
+    /*wait for the on_publish/on_play responses, reject the session if
+    the response is not 2xx and redirect it if the body has a new name.*/
+    blocking: bool,
+    /*in blocking mode, let the session go on if the notify server
+    cannot be reached or times out.*/
+    fail_open: bool,
 }
 
 impl Notifier {
@@ -13,16 +72,101 @@ impl Notifier {
         on_unpublish_url: Option<String>,
         on_play_url: Option<String>,
         on_stop_url: Option<String>,
+        blocking: bool,
+        timeout: Duration,
+        fail_open: bool,
     ) -> Self {
+        let request_client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+
         Self {
-            request_client: reqwest::Client::new(),
+            request_client,
             on_publish_url,
             on_unpublish_url,
             on_play_url,
             on_stop_url,
+            blocking,
+            fail_open,
+        }
+    }
+
+    //whether the publish/subscribe should wait for the on_publish/on_play response
+    pub fn need_publish_check(&self) -> bool {
+        self.blocking && self.on_publish_url.is_some()
+    }
+
+    pub fn need_play_check(&self) -> bool {
+        self.blocking && self.on_play_url.is_some()
+    }
+
+    //called before publishing, only works in blocking mode.
+    pub async fn on_publish_check(
+        &self,
+        body: &str,
+    ) -> Result<Option<NotifyResponse>, NotifyError> {
+        match &self.on_publish_url {
+            Some(on_publish_url) if self.blocking => {
+                self.blocking_notify(on_publish_url, body).await
+            }
+            _ => Ok(None),
+        }
+    }
+
+    //called before subscribing, only works in blocking mode.
+    pub async fn on_play_check(&self, body: &str) -> Result<Option<NotifyResponse>, NotifyError> {
+        match &self.on_play_url {
+            Some(on_play_url) if self.blocking => self.blocking_notify(on_play_url, body).await,
+            _ => Ok(None),
         }
     }
+
+    async fn blocking_notify(
+        &self,
+        url: &str,
+        body: &str,
+    ) -> Result<Option<NotifyResponse>, NotifyError> {
+        let response = match self
+            .request_client
+            .post(url)
+            .body(body.to_string())
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => {
+                if self.fail_open {
+                    log::warn!("notify {} error: {}, fail open.", url, err);
+                    return Ok(None);
+                }
+                return Err(err.into());
+            }
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(NotifyError {
+                value: NotifyErrorValue::Rejected(status.as_u16()),
+            });
+        }
+
+        match response.bytes().await {
+            Ok(data) if !data.is_empty() => match serde_json::from_slice(&data) {
+                Ok(notify_response) => Ok(Some(notify_response)),
+                Err(err) => {
+                    log::warn!("notify {} response body is not valid: {}", url, err);
+                    Ok(None)
+                }
+            },
+            _ => Ok(None),
+        }
+    }
+
     pub async fn on_publish_notify(&self, body: String) {
+        if self.blocking {
+            return;
+        }
         if let Some(on_publish_url) = &self.on_publish_url {
             match self
                 .request_client
@@ -61,6 +205,9 @@ impl Notifier {
     }
 
     pub async fn on_play_notify(&self, body: String) {
+        if self.blocking {
+            return;
+        }
         if let Some(on_play_url) = &self.on_play_url {
             match self
                 .request_client
@@ -98,3 +245,36 @@ impl Notifier {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::NotifyResponse;
+    use crate::stream::StreamIdentifier;
+
+    #[test]
+    fn test_notify_response_rewrite() {
+        let response: NotifyResponse = serde_json::from_str(r#"{"stream_name": "vip"}"#).unwrap();
+        let identifier = response.rewrite(StreamIdentifier::Rtmp {
+            app_name: String::from("live"),
+            stream_name: String::from("test"),
+        });
+        assert_eq!(
+            identifier,
+            StreamIdentifier::Rtmp {
+                app_name: String::from("live"),
+                stream_name: String::from("vip"),
+            }
+        );
+
+        let response: NotifyResponse = serde_json::from_str(r#"{"app_name": "paid"}"#).unwrap();
+        let identifier = response.rewrite(StreamIdentifier::Rtsp {
+            stream_path: String::from("live/test"),
+        });
+        assert_eq!(
+            identifier,
+            StreamIdentifier::Rtsp {
+                stream_path: String::from("paid/test"),
+            }
+        );
+    }
+}
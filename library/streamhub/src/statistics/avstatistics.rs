@@ -62,6 +62,12 @@ impl AvStatistics {
         video_info.width = codec_info.width;
     }
 
+    //the codecs(hevc/av1/vp9) whose configuration records are not parsed
+    pub async fn notify_video_codec_id(&mut self, codec: AvcCodecId) {
+        let video_info = &mut self.stream_statistics.lock().await.video;
+        video_info.codec = codec;
+    }
+
     pub async fn notify_audio_statistics_info(&mut self, data_size: usize, aac_packet_type: u8) {
         match aac_packet_type {
             aac_packet_type::AAC_RAW => {
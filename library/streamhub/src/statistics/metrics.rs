@@ -0,0 +1,293 @@
+use {
+    super::StreamDetail,
+    crate::stream::StreamIdentifier,
+    std::{
+        collections::BTreeMap,
+        fmt::Display,
+        fmt::Write,
+        sync::{Arc, Mutex},
+    },
+};
+
+#[derive(Default, Clone, Copy)]
+struct Counters {
+    connects: u64,
+    disconnects: u64,
+    errors: u64,
+}
+
+//session counters of all the protocols, the key is the protocol name. It is
+//owned by the stream hub and shared with the protocol servers.
+#[derive(Default, Clone)]
+pub struct SessionCounters {
+    counters: Arc<Mutex<BTreeMap<&'static str, Counters>>>,
+}
+
+impl SessionCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&self, protocol: &'static str, f: impl FnOnce(&mut Counters)) {
+        if let Ok(mut counters) = self.counters.lock() {
+            f(counters.entry(protocol).or_default());
+        }
+    }
+
+    //called by the protocol servers when a client connects.
+    pub fn session_connected(&self, protocol: &'static str) {
+        self.update(protocol, |c| c.connects += 1);
+    }
+
+    //called by the protocol servers when a client session exits.
+    pub fn session_disconnected(&self, protocol: &'static str) {
+        self.update(protocol, |c| c.disconnects += 1);
+    }
+
+    //called by the protocol servers when a client session exits with error.
+    pub fn session_error(&self, protocol: &'static str) {
+        self.update(protocol, |c| c.errors += 1);
+    }
+
+    fn snapshot(&self) -> BTreeMap<&'static str, Counters> {
+        match self.counters.lock() {
+            Ok(counters) => counters.clone(),
+            Err(_) => BTreeMap::new(),
+        }
+    }
+}
+
+struct MetricsWriter {
+    out: String,
+}
+
+impl MetricsWriter {
+    fn header(&mut self, name: &str, metric_type: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {name} {help}");
+        let _ = writeln!(self.out, "# TYPE {name} {metric_type}");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{k}=\"{}\"", escape_label_value(v)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {value}");
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+//returns the (protocol, stream) labels of a stream.
+fn stream_labels(identifier: &StreamIdentifier) -> (&'static str, String) {
+    match identifier {
+        StreamIdentifier::Rtmp {
+            app_name,
+            stream_name,
+        } => ("rtmp", format!("{app_name}/{stream_name}")),
+        StreamIdentifier::Rtsp { stream_path } => ("rtsp", stream_path.clone()),
+        StreamIdentifier::WebRTC {
+            app_name,
+            stream_name,
+        } => ("webrtc", format!("{app_name}/{stream_name}")),
+        StreamIdentifier::Unkonwn => ("unknown", String::new()),
+    }
+}
+
+//render the stream details and session counters in the prometheus text format.
+pub fn render(stream_details: &[StreamDetail], session_counters: &SessionCounters) -> String {
+    let mut writer = MetricsWriter { out: String::new() };
+
+    let streams: Vec<(&StreamDetail, &'static str, String)> = stream_details
+        .iter()
+        .map(|detail| {
+            let (protocol, stream) = stream_labels(&detail.identifier);
+            (detail, protocol, stream)
+        })
+        .collect();
+
+    writer.header("xiu_streams", "gauge", "Number of the published streams.");
+    writer.sample("xiu_streams", &[], streams.len());
+
+    type GaugeValue = fn(&StreamDetail) -> f64;
+    let stream_gauges: [(&str, &str, &str, GaugeValue); 7] = [
+        (
+            "xiu_stream_video_bitrate_kbps",
+            "gauge",
+            "Video bitrate of the stream in kbit/s.",
+            |d| {
+                d.statistics
+                    .as_ref()
+                    .map_or(0.0, |s| s.video.bitrate as f64)
+            },
+        ),
+        (
+            "xiu_stream_video_fps",
+            "gauge",
+            "Video frame rate of the stream.",
+            |d| {
+                d.statistics
+                    .as_ref()
+                    .map_or(0.0, |s| s.video.frame_rate as f64)
+            },
+        ),
+        (
+            "xiu_stream_video_gop",
+            "gauge",
+            "Video GOP size of the stream in frames.",
+            |d| d.statistics.as_ref().map_or(0.0, |s| s.video.gop as f64),
+        ),
+        (
+            "xiu_stream_audio_bitrate_kbps",
+            "gauge",
+            "Audio bitrate of the stream in kbit/s.",
+            |d| {
+                d.statistics
+                    .as_ref()
+                    .map_or(0.0, |s| s.audio.bitrate as f64)
+            },
+        ),
+        (
+            "xiu_stream_publisher_uptime_seconds",
+            "gauge",
+            "Seconds since the stream is published.",
+            |d| d.publisher.uptime as f64,
+        ),
+        (
+            "xiu_stream_received_bytes_total",
+            "counter",
+            "Audio and video bytes received from the publisher.",
+            |d| d.publisher.recv_bytes as f64,
+        ),
+        (
+            "xiu_stream_sent_bytes_total",
+            "counter",
+            "Audio and video bytes sent to the subscribers.",
+            |d| d.send_bytes as f64,
+        ),
+    ];
+
+    for (name, metric_type, help, value) in stream_gauges {
+        writer.header(name, metric_type, help);
+        for (detail, protocol, stream) in &streams {
+            writer.sample(
+                name,
+                &[("protocol", protocol), ("stream", stream)],
+                value(detail),
+            );
+        }
+    }
+
+    writer.header(
+        "xiu_stream_subscribers",
+        "gauge",
+        "Number of the subscribers of the stream by subscribe type.",
+    );
+    for (detail, protocol, stream) in &streams {
+        let mut subscriber_counts: BTreeMap<String, usize> = BTreeMap::new();
+        for subscriber in &detail.subscribers {
+            *subscriber_counts
+                .entry(format!("{:?}", subscriber.info.sub_type))
+                .or_default() += 1;
+        }
+        for (sub_type, count) in subscriber_counts {
+            writer.sample(
+                "xiu_stream_subscribers",
+                &[
+                    ("protocol", protocol),
+                    ("stream", stream),
+                    ("type", &sub_type),
+                ],
+                count,
+            );
+        }
+    }
+
+    let session_counters = session_counters.snapshot();
+
+    type CounterValue = fn(&Counters) -> u64;
+    let session_metrics: [(&str, &str, CounterValue); 3] = [
+        (
+            "xiu_session_connects_total",
+            "Number of the client sessions connected.",
+            |c| c.connects,
+        ),
+        (
+            "xiu_session_disconnects_total",
+            "Number of the client sessions disconnected.",
+            |c| c.disconnects,
+        ),
+        (
+            "xiu_session_errors_total",
+            "Number of the client sessions exited with error.",
+            |c| c.errors,
+        ),
+    ];
+
+    for (name, help, value) in session_metrics {
+        writer.header(name, "counter", help);
+        for (protocol, counters) in &session_counters {
+            writer.sample(name, &[("protocol", protocol)], value(counters));
+        }
+    }
+
+    writer.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, SessionCounters};
+    use crate::{
+        define::{NotifyInfo, PublishType, PublisherInfo},
+        statistics::{PublisherStatistics, StreamDetail},
+        stream::StreamIdentifier,
+        utils::{RandomDigitCount, Uuid},
+    };
+
+    #[test]
+    fn test_render_metrics() {
+        let session_counters = SessionCounters::new();
+        session_counters.session_connected("rtmp");
+        session_counters.session_error("rtmp");
+
+        let detail = StreamDetail {
+            identifier: StreamIdentifier::Rtmp {
+                app_name: String::from("live"),
+                stream_name: String::from("te\"st"),
+            },
+            publisher: PublisherStatistics {
+                info: PublisherInfo {
+                    id: Uuid::new(RandomDigitCount::Four),
+                    pub_type: PublishType::PushRtmp,
+                    notify_info: NotifyInfo {
+                        request_url: String::new(),
+                        remote_addr: String::new(),
+                    },
+                },
+                start_time: String::new(),
+                uptime: 10,
+                recv_bytes: 1024,
+            },
+            subscribers: Vec::new(),
+            send_bytes: 2048,
+            statistics: None,
+        };
+
+        let metrics = render(&[detail], &session_counters);
+        assert!(metrics.contains("xiu_streams 1\n"));
+        assert!(metrics.contains(
+            "xiu_stream_received_bytes_total{protocol=\"rtmp\",stream=\"live/te\\\"st\"} 1024\n"
+        ));
+        assert!(metrics.contains("# TYPE xiu_stream_sent_bytes_total counter\n"));
+        assert!(metrics.contains("xiu_session_errors_total{protocol=\"rtmp\"} 1\n"));
+    }
+}
@@ -1,31 +1,35 @@
 pub mod avstatistics;
+pub mod metrics;
 
 use {
-    super::stream::StreamIdentifier,
+    super::{
+        define::{PublisherInfo, SubscriberInfo},
+        stream::StreamIdentifier,
+    },
     serde::Serialize,
     xflv::define::{AacProfile, AvcCodecId, AvcLevel, AvcProfile, SoundFormat},
 };
 
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct VideoInfo {
-    codec: AvcCodecId,
-    profile: AvcProfile,
-    level: AvcLevel,
-    width: u32,
-    height: u32,
+    pub codec: AvcCodecId,
+    pub profile: AvcProfile,
+    pub level: AvcLevel,
+    pub width: u32,
+    pub height: u32,
     #[serde(rename = "bitrate(kbits/s)")]
-    bitrate: f32,
-    frame_rate: usize,
-    gop: usize,
+    pub bitrate: f32,
+    pub frame_rate: usize,
+    pub gop: usize,
 }
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct AudioInfo {
-    sound_format: SoundFormat,
-    profile: AacProfile,
-    samplerate: u32,
-    channels: u8,
+    pub sound_format: SoundFormat,
+    pub profile: AacProfile,
+    pub samplerate: u32,
+    pub channels: u8,
     #[serde(rename = "bitrate(kbits/s)")]
-    bitrate: f32,
+    pub bitrate: f32,
 }
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct StreamStatistics {
@@ -34,6 +38,35 @@ pub struct StreamStatistics {
     pub audio: AudioInfo,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct PublisherStatistics {
+    pub info: PublisherInfo,
+    pub start_time: String,
+    /*seconds since the stream is published*/
+    pub uptime: u64,
+    /*the a/v data bytes received from the publisher*/
+    pub recv_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriberStatistics {
+    pub info: SubscriberInfo,
+    pub start_time: String,
+    /*the a/v data bytes sent to the subscriber*/
+    pub send_bytes: u64,
+}
+
+//the publisher and subscribers of a stream, used by the http api.
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamDetail {
+    pub identifier: StreamIdentifier,
+    pub publisher: PublisherStatistics,
+    pub subscribers: Vec<SubscriberStatistics>,
+    /*the a/v data bytes sent to all the subscribers since the stream is published*/
+    pub send_bytes: u64,
+    pub statistics: Option<StreamStatistics>,
+}
+
 impl StreamStatistics {
     pub fn new(identifier: StreamIdentifier) -> Self {
         Self {
@@ -1,6 +1,49 @@
 use xflv::demuxer::{FlvDemuxerAudioData, FlvDemuxerVideoData};
 
 pub const HLS_DURATION: u8 = 10;
+/*the fmp4 init segment(ftyp + moov) referenced by #EXT-X-MAP*/
+pub const FMP4_INIT_SEGMENT_NAME: &str = "init.mp4";
+/*the low latency hls partial segment target duration in milliseconds*/
+pub const LL_HLS_PART_TARGET: i64 = 500;
+
+//the hls options of an application(or the default ones)
+#[derive(Debug, Clone)]
+pub struct HlsOptions {
+    //the target segment duration in seconds
+    pub target_duration: i64,
+    //how many segments are listed in the live playlist
+    pub playlist_window: usize,
+    //the root directory of the recorded files: <output_dir>/<app>/<stream>
+    pub output_dir: String,
+    //the segment name without the extension, {app}, {stream} and {seq} are replaced
+    pub segment_name: String,
+    //remove the live playlist and segments once the stream is unpublished
+    pub delete_on_unpublish: bool,
+    //how long in seconds the ended stream is kept in memory if it is not deleted
+    pub keep_duration: u64,
+    //add #EXT-X-PROGRAM-DATE-TIME before each segment
+    pub program_date_time: bool,
+    pub need_record: bool,
+    pub fmp4: bool,
+    pub low_latency: bool,
+}
+
+impl Default for HlsOptions {
+    fn default() -> Self {
+        Self {
+            target_duration: 5,
+            playlist_window: 6,
+            output_dir: String::from("."),
+            segment_name: String::from("{seq}"),
+            delete_on_unpublish: true,
+            keep_duration: 600,
+            program_date_time: false,
+            need_record: false,
+            fmp4: false,
+            low_latency: false,
+        }
+    }
+}
 
 pub enum FlvDemuxerData {
     Video { data: FlvDemuxerVideoData },
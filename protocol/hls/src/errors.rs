@@ -6,6 +6,7 @@ use {
     std::fmt,
     tokio::sync::broadcast::error::RecvError,
     xflv::errors::FlvDemuxerError,
+    xmp4::errors::Mp4Error,
     xmpegts::errors::MpegTsError,
 };
 
@@ -38,6 +39,8 @@ pub enum MediaErrorValue {
     FlvDemuxerError(#[cause] FlvDemuxerError),
     #[fail(display = "mpegts error:{}\n", _0)]
     MpegTsError(#[cause] MpegTsError),
+    #[fail(display = "mp4 error:{}\n", _0)]
+    Mp4Error(#[cause] Mp4Error),
     #[fail(display = "write file error:{}\n", _0)]
     IOError(#[cause] std::io::Error),
 }
@@ -66,6 +69,14 @@ impl From<MpegTsError> for MediaError {
     }
 }
 
+impl From<Mp4Error> for MediaError {
+    fn from(error: Mp4Error) -> Self {
+        MediaError {
+            value: MediaErrorValue::Mp4Error(error),
+        }
+    }
+}
+
 impl From<Amf0WriteError> for MediaError {
     fn from(error: Amf0WriteError) -> Self {
         MediaError {
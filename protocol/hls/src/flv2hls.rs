@@ -1,9 +1,24 @@
 use {
-    super::{define::FlvDemuxerData, errors::MediaError, m3u8::M3u8},
+    super::{
+        define::{FlvDemuxerData, HlsOptions, LL_HLS_PART_TARGET},
+        errors::MediaError,
+        m3u8::M3u8,
+        store::HlsStore,
+    },
     bytes::BytesMut,
+    bytesio::bytes_reader::BytesReader,
     xflv::{
-        define::{frame_type, FlvData},
+        define::{
+            aac_packet_type, avc_packet_type, ex_video_packet_type, frame_type, AvcCodecId,
+            FlvData, SoundFormat,
+        },
         demuxer::{FlvAudioTagDemuxer, FlvVideoTagDemuxer},
+        flv_tag_header::{AudioTagHeader, VideoTagHeader},
+        Unmarshal,
+    },
+    xmp4::{
+        define::{AudioConfig, VideoCodec, VideoConfig},
+        fmp4::Fmp4Muxer,
     },
     xmpegts::{
         define::{epsi_stream_type, MPEG_FLAG_IDR_FRAME},
@@ -28,19 +43,33 @@ pub struct Flv2HlsRemuxer {
 
     video_pid: u16,
     audio_pid: u16,
+    /*h264 or h265, detected from the video sequence header*/
+    video_stream_type: u8,
+
+    /*generate the fmp4 segments if it is set, otherwise the mpegts segments*/
+    fmp4_muxer: Option<Fmp4Muxer>,
+    /*the init segment is rewritten before the next media segment if the sequence headers change*/
+    init_segment_changed: bool,
+
+    /*low latency hls: the state of the partial segment being generated*/
+    low_latency: bool,
+    last_part_dts: i64,
+    last_frame_dts: i64,
+    part_has_data: bool,
+    part_independent: bool,
 
     m3u8_handler: M3u8,
 }
 
 impl Flv2HlsRemuxer {
-    pub fn new(duration: i64, app_name: String, stream_name: String, need_record: bool) -> Self {
-        let mut ts_muxer = TsMuxer::new();
-        let audio_pid = ts_muxer
-            .add_stream(epsi_stream_type::PSI_STREAM_AAC, BytesMut::new())
-            .unwrap();
-        let video_pid = ts_muxer
-            .add_stream(epsi_stream_type::PSI_STREAM_H264, BytesMut::new())
-            .unwrap();
+    pub fn new(
+        app_name: String,
+        stream_name: String,
+        options: HlsOptions,
+        store: HlsStore,
+    ) -> Self {
+        let (ts_muxer, audio_pid, video_pid) =
+            Self::new_ts_muxer(epsi_stream_type::PSI_STREAM_H264);
 
         Self {
             video_demuxer: FlvVideoTagDemuxer::new(),
@@ -54,24 +83,86 @@ impl Flv2HlsRemuxer {
             last_dts: 0,
             last_pts: 0,
 
-            duration,
+            duration: options.target_duration,
             need_new_segment: false,
 
             video_pid,
             audio_pid,
+            video_stream_type: epsi_stream_type::PSI_STREAM_H264,
+
+            fmp4_muxer: if options.fmp4 {
+                Some(Fmp4Muxer::new())
+            } else {
+                None
+            },
+            init_segment_changed: false,
+
+            low_latency: options.low_latency,
+            last_part_dts: 0,
+            last_frame_dts: 0,
+            part_has_data: false,
+            part_independent: false,
+
+            m3u8_handler: M3u8::new(app_name, stream_name, &options, store),
+        }
+    }
+
+    fn new_ts_muxer(video_stream_type: u8) -> (TsMuxer, u16, u16) {
+        let mut ts_muxer = TsMuxer::new();
+        let audio_pid = ts_muxer
+            .add_stream(epsi_stream_type::PSI_STREAM_AAC, BytesMut::new())
+            .unwrap();
+        let video_pid = ts_muxer
+            .add_stream(video_stream_type, BytesMut::new())
+            .unwrap();
+        (ts_muxer, audio_pid, video_pid)
+    }
 
-            m3u8_handler: M3u8::new(duration, 6, app_name, stream_name, need_record),
+    //the PMT is rebuilt if the video codec differs from the current one.
+    fn update_video_stream_type(&mut self) {
+        let video_stream_type = match self.video_demuxer.codec_id() {
+            AvcCodecId::HEVC => epsi_stream_type::PSI_STREAM_H265,
+            _ => epsi_stream_type::PSI_STREAM_H264,
+        };
+        if video_stream_type == self.video_stream_type {
+            return;
         }
+
+        log::info!(
+            "hls video stream type changes from {:#x} to {:#x}",
+            self.video_stream_type,
+            video_stream_type
+        );
+        (self.ts_muxer, self.audio_pid, self.video_pid) = Self::new_ts_muxer(video_stream_type);
+        self.video_stream_type = video_stream_type;
+    }
+
+    fn update_codecs(&mut self) {
+        let codecs: Vec<String> = vec![
+            self.video_demuxer.codec_string(),
+            self.audio_demuxer.codec_string(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        self.m3u8_handler.set_codecs(codecs.join(","));
     }
 
     pub fn process_flv_data(&mut self, data: FlvData) -> Result<(), MediaError> {
+        if self.fmp4_muxer.is_some() {
+            return self.process_fmp4_data(data);
+        }
+
         let flv_demux_data: FlvDemuxerData = match data {
             FlvData::Audio { timestamp, data } => {
                 let audio_data = self.audio_demuxer.demux(timestamp, data)?;
                 FlvDemuxerData::Audio { data: audio_data }
             }
             FlvData::Video { timestamp, data } => {
-                if let Some(video_data) = self.video_demuxer.demux(timestamp, data)? {
+                let video_data = self.video_demuxer.demux(timestamp, data)?;
+                self.update_video_stream_type();
+
+                if let Some(video_data) = video_data {
                     FlvDemuxerData::Video { data: video_data }
                 } else {
                     return Ok(());
@@ -85,8 +176,200 @@ impl Flv2HlsRemuxer {
         Ok(())
     }
 
-    pub fn flush_remaining_data(&mut self) -> Result<(), MediaError> {
+    //the fmp4 samples keep the length prefixed NAL units and the raw aac frames,
+    //the demuxers only parse the sequence headers for the codec configs.
+    fn process_fmp4_data(&mut self, data: FlvData) -> Result<(), MediaError> {
+        match data {
+            FlvData::Video { timestamp, data } => {
+                let mut reader = BytesReader::new(data.clone());
+                let tag_header = VideoTagHeader::unmarshal(&mut reader)?;
+                let payload = reader.extract_remaining_bytes();
+
+                let codec = match tag_header.codec() {
+                    AvcCodecId::H264 => VideoCodec::H264,
+                    AvcCodecId::HEVC => VideoCodec::H265,
+                    _ => return Ok(()),
+                };
+
+                if tag_header.is_seq_header() {
+                    self.video_demuxer.demux(timestamp, data)?;
+                    let (width, height) = self.video_demuxer.resolution();
+                    if let Some(fmp4_muxer) = &mut self.fmp4_muxer {
+                        fmp4_muxer.set_video_config(VideoConfig {
+                            codec,
+                            width,
+                            height,
+                            decoder_configuration_record: payload,
+                        });
+                    }
+                    self.init_segment_changed = true;
+                    return Ok(());
+                }
+
+                let is_coded_frames = tag_header.avc_packet_type == avc_packet_type::AVC_NALU
+                    || (tag_header.is_ex_header
+                        && tag_header.avc_packet_type == ex_video_packet_type::CODED_FRAMES_X);
+                if !is_coded_frames {
+                    return Ok(());
+                }
+
+                let dts = timestamp as i64;
+                let pts = dts + tag_header.composition_time as i64;
+                let is_key_frame = tag_header.is_key_frame();
+                if is_key_frame && dts - self.last_ts_dts >= self.duration * 1000 {
+                    self.new_segment(dts, pts)?;
+                }
+                self.check_partial_segment(dts, is_key_frame)?;
+                self.last_dts = dts;
+                self.last_pts = pts;
+
+                if let Some(fmp4_muxer) = &mut self.fmp4_muxer {
+                    fmp4_muxer.write_video(dts, pts, is_key_frame, &payload[..])?;
+                }
+            }
+            FlvData::Audio { timestamp, data } => {
+                let mut reader = BytesReader::new(data.clone());
+                let tag_header = AudioTagHeader::unmarshal(&mut reader)?;
+                let payload = reader.extract_remaining_bytes();
+
+                if tag_header.sound_format != SoundFormat::AAC as u8 {
+                    return Ok(());
+                }
+
+                match tag_header.aac_packet_type {
+                    aac_packet_type::AAC_SEQHDR => {
+                        self.audio_demuxer.demux(timestamp, data)?;
+                        let (sampling_frequency, channels) = self.audio_demuxer.audio_config();
+                        if let Some(fmp4_muxer) = &mut self.fmp4_muxer {
+                            fmp4_muxer.set_audio_config(AudioConfig {
+                                sampling_frequency,
+                                channels,
+                                audio_specific_config: payload,
+                            });
+                        }
+                        self.init_segment_changed = true;
+                    }
+                    aac_packet_type::AAC_RAW => {
+                        let dts = timestamp as i64;
+                        //there is no key frame to cut the audio only stream
+                        let audio_only = matches!(&self.fmp4_muxer, Some(m) if !m.has_video());
+                        if audio_only && dts - self.last_ts_dts >= self.duration * 1000 {
+                            self.new_segment(dts, dts)?;
+                        }
+                        self.check_partial_segment(dts, false)?;
+                        self.last_dts = dts;
+                        self.last_pts = dts;
+
+                        if let Some(fmp4_muxer) = &mut self.fmp4_muxer {
+                            fmp4_muxer.write_audio(dts, &payload[..])?;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    //take the cached data of the current segment, the init segment is written first if needed.
+    fn take_segment_data(&mut self) -> Result<BytesMut, MediaError> {
+        if let Some(fmp4_muxer) = &mut self.fmp4_muxer {
+            if self.init_segment_changed {
+                self.m3u8_handler
+                    .write_init_segment(fmp4_muxer.init_segment()?)?;
+                self.init_segment_changed = false;
+            }
+            return Ok(fmp4_muxer.media_segment()?);
+        }
+
         let data = self.ts_muxer.get_data();
+        self.ts_muxer.reset();
+        Ok(data)
+    }
+
+    //the partial segments are already taken in the low latency mode
+    fn take_remaining_data(&mut self, dts: i64) -> Result<BytesMut, MediaError> {
+        if !self.low_latency {
+            return self.take_segment_data();
+        }
+        if self.part_has_data {
+            self.close_partial_segment(dts)?;
+        }
+        Ok(BytesMut::new())
+    }
+
+    //a new partial segment is started from the key frame, or before the frame
+    //with which the current one would exceed the part target.
+    fn check_partial_segment(&mut self, dts: i64, is_key_frame: bool) -> Result<(), MediaError> {
+        if !self.low_latency {
+            return Ok(());
+        }
+        let frame_interval = std::cmp::max(dts - self.last_frame_dts, 0);
+        self.last_frame_dts = dts;
+
+        if self.part_has_data
+            && (is_key_frame || dts + frame_interval - self.last_part_dts > LL_HLS_PART_TARGET)
+        {
+            self.close_partial_segment(dts)?;
+            self.m3u8_handler.refresh_playlist()?;
+        }
+
+        if !self.part_has_data {
+            self.last_part_dts = dts;
+            self.part_has_data = true;
+        }
+        self.part_independent |= is_key_frame;
+
+        Ok(())
+    }
+
+    fn close_partial_segment(&mut self, dts: i64) -> Result<(), MediaError> {
+        let has_samples = match &self.fmp4_muxer {
+            Some(fmp4_muxer) => fmp4_muxer.has_samples(),
+            None => true,
+        };
+        if has_samples {
+            let data = self.take_segment_data()?;
+            self.m3u8_handler.add_partial_segment(
+                dts - self.last_part_dts,
+                self.part_independent,
+                data,
+            )?;
+        }
+
+        self.part_has_data = false;
+        self.part_independent = false;
+        Ok(())
+    }
+
+    fn new_segment(&mut self, dts: i64, pts: i64) -> Result<(), MediaError> {
+        let mut discontinuity: bool = false;
+        if dts > self.last_ts_dts + 15 * 1000 {
+            discontinuity = true;
+        }
+        let data = self.take_remaining_data(dts)?;
+
+        self.m3u8_handler
+            .add_segment(dts - self.last_ts_dts, discontinuity, false, data)?;
+        self.update_codecs();
+        self.m3u8_handler.refresh_playlist()?;
+
+        self.last_ts_dts = dts;
+        self.last_ts_pts = pts;
+
+        Ok(())
+    }
+
+    pub fn flush_remaining_data(&mut self) -> Result<(), MediaError> {
+        if let Some(fmp4_muxer) = &self.fmp4_muxer {
+            if !fmp4_muxer.has_samples() {
+                return Ok(());
+            }
+        }
+
+        let data = self.take_remaining_data(self.last_dts)?;
         let mut discontinuity: bool = false;
         if self.last_dts > self.last_ts_dts + 15 * 1000 {
             discontinuity = true;
@@ -97,6 +380,7 @@ impl Flv2HlsRemuxer {
             true,
             data,
         )?;
+        self.update_codecs();
         self.m3u8_handler.refresh_playlist()?;
 
         Ok(())
@@ -142,21 +426,10 @@ impl Flv2HlsRemuxer {
         }
 
         if self.need_new_segment {
-            let mut discontinuity: bool = false;
-            if dts > self.last_ts_dts + 15 * 1000 {
-                discontinuity = true;
-            }
-            let data = self.ts_muxer.get_data();
-
-            self.m3u8_handler
-                .add_segment(dts - self.last_ts_dts, discontinuity, false, data)?;
-            self.m3u8_handler.refresh_playlist()?;
-
-            self.ts_muxer.reset();
-            self.last_ts_dts = dts;
-            self.last_ts_pts = pts;
+            self.new_segment(dts, pts)?;
             self.need_new_segment = false;
         }
+        self.check_partial_segment(dts, flags == MPEG_FLAG_IDR_FRAME)?;
 
         self.last_dts = dts;
         self.last_pts = pts;
@@ -173,6 +446,49 @@ impl Flv2HlsRemuxer {
 }
 #[cfg(test)]
 mod tests {
+    use super::Flv2HlsRemuxer;
+    use crate::{define::HlsOptions, store::HlsStore};
+    use bytes::BytesMut;
+    use xflv::define::FlvData;
+
+    #[test]
+    fn test_audio_only_fmp4_segments() {
+        let options = HlsOptions {
+            target_duration: 2,
+            segment_name: String::from("{stream}-{seq}"),
+            fmp4: true,
+            ..Default::default()
+        };
+        let store = HlsStore::new();
+        let mut remuxer = Flv2HlsRemuxer::new(
+            String::from("live"),
+            String::from("test"),
+            options,
+            store.clone(),
+        );
+
+        //aac lc, 44100Hz, stereo
+        let seq_header = FlvData::Audio {
+            timestamp: 0,
+            data: BytesMut::from(&[0xAF, 0x00, 0x12, 0x10][..]),
+        };
+        remuxer.process_flv_data(seq_header).unwrap();
+        //5 seconds of the aac frames
+        for i in 0..216 {
+            let audio = FlvData::Audio {
+                timestamp: i * 1024 * 1000 / 44100,
+                data: BytesMut::from(&[0xAF, 0x01, 0x21, 0x10, 0x04][..]),
+            };
+            remuxer.process_flv_data(audio).unwrap();
+        }
+
+        let playlist = store.get("live/test/test.m3u8").unwrap();
+        let playlist = String::from_utf8_lossy(&playlist[..]);
+        assert_eq!(playlist.matches("#EXTINF:").count(), 2);
+        assert!(playlist.contains("test-0.m4s"));
+        assert!(store.get("live/test/test-1.m4s").is_some());
+    }
+
     // use std::{
     //     env,
     //     fs::{self},
@@ -1,19 +1,24 @@
 use {
     super::{
+        define::HlsOptions,
         errors::{HlsError, HlsErrorValue},
         flv2hls::Flv2HlsRemuxer,
+        store::HlsStore,
     },
     rtmp::session::errors::{SessionError, SessionErrorValue},
     std::time::Duration,
     streamhub::{
         define::{
-            FrameData, FrameDataReceiver, NotifyInfo, StreamHubEvent, StreamHubEventSender,
-            SubscribeType, SubscriberInfo,
+            wait_pub_sub_result, FrameData, FrameDataReceiver, NotifyInfo, StreamHubEvent,
+            StreamHubEventSender, SubscribeType, SubscriberInfo,
         },
         stream::StreamIdentifier,
         utils::{RandomDigitCount, Uuid},
     },
-    tokio::{sync::mpsc, time::sleep},
+    tokio::{
+        sync::{mpsc, oneshot},
+        time::sleep,
+    },
     xflv::define::FlvData,
 };
 
@@ -33,8 +38,8 @@ impl FlvDataReceiver {
         app_name: String,
         stream_name: String,
         event_producer: StreamHubEventSender,
-        duration: i64,
-        need_record: bool,
+        options: HlsOptions,
+        store: HlsStore,
     ) -> Self {
         let (_, data_consumer) = mpsc::unbounded_channel();
         let subscriber_id = Uuid::new(RandomDigitCount::Four);
@@ -44,7 +49,7 @@ impl FlvDataReceiver {
             stream_name: stream_name.clone(),
             data_consumer,
             event_producer,
-            media_processor: Flv2HlsRemuxer::new(duration, app_name, stream_name, need_record),
+            media_processor: Flv2HlsRemuxer::new(app_name, stream_name, options, store),
             subscriber_id,
         }
     }
@@ -111,10 +116,12 @@ impl FlvDataReceiver {
             stream_name,
         };
 
+        let (result_sender, result_receiver) = oneshot::channel();
         let subscribe_event = StreamHubEvent::Subscribe {
             identifier,
             info: sub_info,
             sender: streamhub::define::DataSender::Frame { sender },
+            result_sender,
         };
 
         let rv = self.event_producer.send(subscribe_event);
@@ -126,6 +133,11 @@ impl FlvDataReceiver {
                 value: HlsErrorValue::SessionError(session_error),
             });
         }
+        if let Err(err) = wait_pub_sub_result(result_receiver).await {
+            return Err(HlsError {
+                value: HlsErrorValue::SessionError(err.into()),
+            });
+        }
 
         self.data_consumer = receiver;
 
@@ -135,7 +147,7 @@ impl FlvDataReceiver {
     pub async fn unsubscribe_from_rtmp_channels(&mut self) -> Result<(), HlsError> {
         let sub_info = SubscriberInfo {
             id: self.subscriber_id,
-            sub_type: SubscribeType::PlayerHls,
+            sub_type: SubscribeType::GenerateHls,
             notify_info: NotifyInfo {
                 request_url: String::from(""),
                 remote_addr: String::from(""),
@@ -5,5 +5,6 @@ pub mod flv_data_receiver;
 pub mod m3u8;
 pub mod remuxer;
 pub mod server;
+pub mod store;
 mod test_flv2hls;
 pub mod ts;
@@ -1,9 +1,23 @@
 use {
-    super::{errors::MediaError, ts::Ts},
+    super::{
+        define::{HlsOptions, FMP4_INIT_SEGMENT_NAME, LL_HLS_PART_TARGET},
+        errors::MediaError,
+        store::HlsStore,
+        ts::Ts,
+    },
     bytes::BytesMut,
-    std::{collections::VecDeque, fs, fs::File, io::Write},
+    chrono::{Duration, SecondsFormat, Utc},
+    std::{collections::VecDeque, fs::File, io::Write},
 };
 
+pub struct PartialSegment {
+    pub duration: i64,
+    /*contains an independent(key) frame*/
+    pub independent: bool,
+    pub name: String,
+    path: String,
+}
+
 pub struct Segment {
     /*ts duration*/
     pub duration: i64,
@@ -12,6 +26,10 @@ pub struct Segment {
     pub name: String,
     path: String,
     pub is_eof: bool,
+    /*the partial segments for the low latency hls*/
+    pub parts: Vec<PartialSegment>,
+    /*the wall clock time of the first sample, e.g.: 2023-01-01T08:00:00.000Z*/
+    pub program_date_time: Option<String>,
 }
 
 impl Segment {
@@ -28,6 +46,8 @@ impl Segment {
             name,
             path,
             is_eof,
+            parts: Vec::new(),
+            program_date_time: None,
         }
     }
 }
@@ -51,20 +71,37 @@ pub struct M3u8 {
     ts_handler: Ts,
 
     need_record: bool,
+    delete_on_unpublish: bool,
+    keep_duration: u64,
+    program_date_time: bool,
     vod_m3u8_content: String,
     vod_m3u8_name: String,
+
+    /*the master playlist which signals the CODECS of the live playlist*/
+    master_m3u8_name: String,
+    codecs: String,
+    /*the peak segment bitrate in bits per second*/
+    bandwidth: u64,
+    /*the segments are fmp4 with an init segment, or mpegts*/
+    fmp4: bool,
+
+    /*low latency hls: the partial segments of the segment being generated*/
+    low_latency: bool,
+    parts: Vec<PartialSegment>,
+    parts_data: BytesMut,
 }
 
 impl M3u8 {
     pub fn new(
-        duration: i64,
-        live_ts_count: usize,
         app_name: String,
         stream_name: String,
-        need_record: bool,
+        options: &HlsOptions,
+        store: HlsStore,
     ) -> Self {
-        let m3u8_folder = format!("./{app_name}/{stream_name}");
-        fs::create_dir_all(m3u8_folder.clone()).unwrap();
+        let need_record = options.need_record;
+        let (fmp4, low_latency) = (options.fmp4, options.low_latency);
+        /*the recorded files are written here*/
+        let m3u8_folder = format!("{}/{app_name}/{stream_name}", options.output_dir);
 
         let live_m3u8_name = format!("{stream_name}.m3u8");
         let vod_m3u8_name = if need_record {
@@ -73,19 +110,36 @@ impl M3u8 {
             String::default()
         };
 
+        /*EXT-X-MAP for the non I-frame playlists needs version 6+*/
+        let (mut version, segment_extension) = if fmp4 { (7, "m4s") } else { (3, "ts") };
+        /*EXT-X-SERVER-CONTROL and EXT-X-PART-INF*/
+        if low_latency {
+            version = std::cmp::max(version, 6);
+        }
+
         let mut m3u8 = Self {
-            version: 3,
+            version,
             sequence_no: 0,
-            duration,
-            live_ts_count,
+            duration: options.target_duration * 1000,
+            live_ts_count: options.playlist_window,
             segments: VecDeque::new(),
             m3u8_folder,
             live_m3u8_name,
-            ts_handler: Ts::new(app_name, stream_name),
+            ts_handler: Ts::new(app_name, stream_name, segment_extension, options, store),
             // record,
             need_record,
+            delete_on_unpublish: options.delete_on_unpublish,
+            keep_duration: options.keep_duration,
+            program_date_time: options.program_date_time,
             vod_m3u8_content: String::default(),
             vod_m3u8_name,
+            master_m3u8_name: String::from("master.m3u8"),
+            codecs: String::default(),
+            bandwidth: 0,
+            fmp4,
+            low_latency,
+            parts: Vec::new(),
+            parts_data: BytesMut::new(),
         };
 
         if need_record {
@@ -94,26 +148,62 @@ impl M3u8 {
         m3u8
     }
 
+    //the segment is made up of the partial segments added before and the remaining data
+    pub fn add_partial_segment(
+        &mut self,
+        duration: i64,
+        independent: bool,
+        data: BytesMut,
+    ) -> Result<(), MediaError> {
+        let (name, path) = self.ts_handler.write_part(self.parts.len(), data.clone());
+        self.parts_data.extend_from_slice(&data[..]);
+        self.parts.push(PartialSegment {
+            duration,
+            independent,
+            name,
+            path,
+        });
+
+        Ok(())
+    }
+
     pub fn add_segment(
         &mut self,
         duration: i64,
         discontinuity: bool,
         is_eof: bool,
-        ts_data: BytesMut,
+        mut ts_data: BytesMut,
     ) -> Result<(), MediaError> {
         let segment_count = self.segments.len();
 
         if segment_count >= self.live_ts_count {
-            let segment = self.segments.pop_front().unwrap();
-            if !self.need_record {
+            if let Some(segment) = self.segments.pop_front() {
                 self.ts_handler.delete(segment.path);
-            }
+                for part in segment.parts {
+                    self.ts_handler.delete(part.path);
+                }
 
-            self.sequence_no += 1;
+                self.sequence_no += 1;
+            }
         }
         self.duration = std::cmp::max(duration, self.duration);
+        if duration > 0 {
+            let bandwidth = ts_data.len() as u64 * 8 * 1000 / duration as u64;
+            self.bandwidth = std::cmp::max(bandwidth, self.bandwidth);
+        }
+        if !self.parts_data.is_empty() {
+            let mut data = self.parts_data.split();
+            data.extend_from_slice(&ts_data[..]);
+            ts_data = data;
+        }
         let (ts_name, ts_path) = self.ts_handler.write(ts_data)?;
-        let segment = Segment::new(duration, discontinuity, ts_name, ts_path, is_eof);
+        let mut segment = Segment::new(duration, discontinuity, ts_name, ts_path, is_eof);
+        segment.parts = std::mem::take(&mut self.parts);
+        if self.program_date_time {
+            let start_time = Utc::now() - Duration::milliseconds(duration);
+            segment.program_date_time =
+                Some(start_time.to_rfc3339_opts(SecondsFormat::Millis, true));
+        }
 
         if self.need_record {
             self.update_vod_m3u8(&segment);
@@ -124,21 +214,37 @@ impl M3u8 {
         Ok(())
     }
 
+    pub fn write_init_segment(&mut self, data: BytesMut) -> Result<(), MediaError> {
+        self.ts_handler.write_file(FMP4_INIT_SEGMENT_NAME, data)?;
+        Ok(())
+    }
+
     pub fn clear(&mut self) -> Result<(), MediaError> {
         if self.need_record {
             let vod_m3u8_path = format!("{}/{}", self.m3u8_folder, self.vod_m3u8_name);
             let mut file_handler = File::create(vod_m3u8_path).unwrap();
             self.vod_m3u8_content += "#EXT-X-ENDLIST\n";
             file_handler.write_all(self.vod_m3u8_content.as_bytes())?;
-        } else {
-            for segment in &self.segments {
-                self.ts_handler.delete(segment.path.clone());
-            }
         }
+        self.ts_handler.set_preload_hint(None);
 
-        //clear live m3u8
-        let live_m3u8_path = format!("{}/{}", self.m3u8_folder, self.live_m3u8_name);
-        fs::remove_file(live_m3u8_path)?;
+        /*the ended live playlist and its segments can still be played if they are kept,
+        they are removed from the memory after the keep duration*/
+        if !self.delete_on_unpublish {
+            self.ts_handler
+                .keep_live_files(std::time::Duration::from_secs(self.keep_duration));
+            return Ok(());
+        }
+
+        for segment in &self.segments {
+            self.ts_handler.delete(segment.path.clone());
+        }
+        for part in &self.parts {
+            self.ts_handler.delete(part.path.clone());
+        }
+        self.ts_handler.delete_file(FMP4_INIT_SEGMENT_NAME);
+        self.ts_handler.delete_file(&self.live_m3u8_name);
+        self.ts_handler.delete_file(&self.master_m3u8_name);
 
         Ok(())
     }
@@ -153,19 +259,38 @@ impl M3u8 {
             m3u8_header += "#EXT-X-PLAYLIST-TYPE:VOD\n";
             m3u8_header += "#EXT-X-ALLOW-CACHE:YES\n";
         } else {
+            if self.low_latency {
+                let part_target = LL_HLS_PART_TARGET as f64 / 1000.0;
+                m3u8_header += format!(
+                    "#EXT-X-SERVER-CONTROL:CAN_BLOCK_RELOAD=YES,PART-HOLD-BACK={:.3}\n",
+                    part_target * 3.0
+                )
+                .as_str();
+                m3u8_header += format!("#EXT-X-PART-INF:PART-TARGET={part_target:.3}\n").as_str();
+            }
             m3u8_header += format!("#EXT-X-MEDIA-SEQUENCE:{}\n", self.sequence_no).as_str();
         }
 
+        if self.fmp4 {
+            m3u8_header += format!("#EXT-X-MAP:URI=\"{FMP4_INIT_SEGMENT_NAME}\"\n").as_str();
+        }
+
         m3u8_header
     }
 
     pub fn refresh_playlist(&mut self) -> Result<String, MediaError> {
         let mut m3u8_content = self.generate_m3u8_header(false);
 
-        for segment in &self.segments {
+        /*the partial segments are only listed for the last two segments*/
+        let parts_start_index = self.segments.len().saturating_sub(2);
+        for (index, segment) in self.segments.iter().enumerate() {
             if segment.discontinuity {
                 m3u8_content += "#EXT-X-DISCONTINUITY\n";
             }
+            m3u8_content += Self::generate_program_date_time(segment).as_str();
+            if index >= parts_start_index {
+                m3u8_content += Self::generate_parts(&segment.parts).as_str();
+            }
             m3u8_content += format!(
                 "#EXTINF:{:.3}\n{}\n",
                 segment.duration as f64 / 1000.0,
@@ -179,18 +304,80 @@ impl M3u8 {
             }
         }
 
-        let m3u8_path = format!("{}/{}", self.m3u8_folder, self.live_m3u8_name);
+        let is_eof = self.segments.back().is_some_and(|segment| segment.is_eof);
+        if self.low_latency && !is_eof {
+            m3u8_content += Self::generate_parts(&self.parts).as_str();
+            m3u8_content += format!(
+                "#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"{}\"\n",
+                self.ts_handler.part_name(self.parts.len())
+            )
+            .as_str();
+            self.ts_handler.set_preload_hint(Some(self.parts.len()));
+        } else if self.low_latency {
+            self.ts_handler.set_preload_hint(None);
+        }
+
+        self.ts_handler.write_live_file(
+            &self.live_m3u8_name,
+            BytesMut::from(m3u8_content.as_bytes()),
+        );
 
-        let mut file_handler = File::create(m3u8_path).unwrap();
-        file_handler.write_all(m3u8_content.as_bytes())?;
+        if !self.codecs.is_empty() {
+            let master_m3u8_content = self.generate_master_m3u8();
+            self.ts_handler.write_live_file(
+                &self.master_m3u8_name,
+                BytesMut::from(master_m3u8_content.as_bytes()),
+            );
+        }
 
         Ok(m3u8_content)
     }
 
+    fn generate_program_date_time(segment: &Segment) -> String {
+        match &segment.program_date_time {
+            Some(date_time) => format!("#EXT-X-PROGRAM-DATE-TIME:{date_time}\n"),
+            None => String::default(),
+        }
+    }
+
+    fn generate_parts(parts: &[PartialSegment]) -> String {
+        let mut parts_content = String::default();
+        for part in parts {
+            parts_content += format!(
+                "#EXT-X-PART:DURATION={:.3},URI=\"{}\"{}\n",
+                part.duration as f64 / 1000.0,
+                part.name,
+                if part.independent {
+                    ",INDEPENDENT=YES"
+                } else {
+                    ""
+                }
+            )
+            .as_str();
+        }
+        parts_content
+    }
+
+    pub fn set_codecs(&mut self, codecs: String) {
+        self.codecs = codecs;
+    }
+
+    pub fn generate_master_m3u8(&self) -> String {
+        let mut m3u8_content = "#EXTM3U\n".to_string();
+        m3u8_content += format!("#EXT-X-VERSION:{}\n", self.version).as_str();
+        m3u8_content += format!(
+            "#EXT-X-STREAM-INF:BANDWIDTH={},CODECS=\"{}\"\n{}\n",
+            self.bandwidth, self.codecs, self.live_m3u8_name
+        )
+        .as_str();
+        m3u8_content
+    }
+
     pub fn update_vod_m3u8(&mut self, segment: &Segment) {
         if segment.discontinuity {
             self.vod_m3u8_content += "#EXT-X-DISCONTINUITY\n";
         }
+        self.vod_m3u8_content += Self::generate_program_date_time(segment).as_str();
         self.vod_m3u8_content += format!(
             "#EXTINF:{:.3}\n{}\n",
             segment.duration as f64 / 1000.0,
@@ -199,3 +386,83 @@ impl M3u8 {
         .as_str();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::M3u8;
+    use crate::{define::HlsOptions, store::HlsStore};
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_playlist_options() {
+        let options = HlsOptions {
+            target_duration: 2,
+            playlist_window: 2,
+            segment_name: String::from("{stream}-{seq}"),
+            program_date_time: true,
+            ..Default::default()
+        };
+        let store = HlsStore::new();
+        let mut m3u8 = M3u8::new(
+            String::from("live"),
+            String::from("test"),
+            &options,
+            store.clone(),
+        );
+
+        for _ in 0..3 {
+            m3u8.add_segment(2000, false, false, BytesMut::from(&b"ts"[..]))
+                .unwrap();
+        }
+        let content = m3u8.refresh_playlist().unwrap();
+        assert!(content.contains("#EXT-X-TARGETDURATION:2\n"));
+        assert!(content.contains("#EXT-X-MEDIA-SEQUENCE:1\n"));
+        assert_eq!(content.matches("#EXT-X-PROGRAM-DATE-TIME:").count(), 2);
+        assert!(content.contains("#EXTINF:2.000\ntest-2.ts\n"));
+        /*the segment out of the window is removed*/
+        assert!(store.get("live/test/test-0.ts").is_none());
+        assert!(store.get("live/test/test-2.ts").is_some());
+
+        m3u8.clear().unwrap();
+        assert!(store.get("live/test/test.m3u8").is_none());
+    }
+
+    #[test]
+    fn test_keep_on_unpublish() {
+        let options = HlsOptions {
+            delete_on_unpublish: false,
+            keep_duration: 0,
+            ..Default::default()
+        };
+        let store = HlsStore::new();
+        let new_m3u8 = || {
+            M3u8::new(
+                String::from("live"),
+                String::from("test"),
+                &options,
+                store.clone(),
+            )
+        };
+
+        let mut m3u8 = new_m3u8();
+        m3u8.add_segment(2000, false, false, BytesMut::from(&b"ts"[..]))
+            .unwrap();
+        m3u8.refresh_playlist().unwrap();
+        m3u8.clear().unwrap();
+        assert!(store.get("live/test/test.m3u8").is_some());
+
+        /*the kept files are removed once they expire*/
+        store.remove_expired_streams();
+        assert!(store.get("live/test/test.m3u8").is_none());
+        assert!(store.get("live/test/0.ts").is_none());
+
+        /*or once the stream is published again*/
+        let mut m3u8 = new_m3u8();
+        m3u8.add_segment(2000, false, false, BytesMut::from(&b"ts"[..]))
+            .unwrap();
+        m3u8.clear().unwrap();
+        assert!(store.get("live/test/0.ts").is_some());
+        new_m3u8();
+        assert!(store.get("live/test/0.ts").is_none());
+    }
+}
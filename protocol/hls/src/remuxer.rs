@@ -1,46 +1,73 @@
 use {
-    super::{errors::HlsError, flv_data_receiver::FlvDataReceiver},
+    super::{
+        define::HlsOptions, errors::HlsError, flv_data_receiver::FlvDataReceiver, store::HlsStore,
+    },
+    std::{collections::HashMap, time::Duration},
     streamhub::{
         define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
         stream::StreamIdentifier,
     },
 };
 
+const KEPT_STREAM_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
 pub struct HlsRemuxer {
     client_event_consumer: BroadcastEventReceiver,
     event_producer: StreamHubEventSender,
-    need_record: bool,
+    /*the options of the applications which are not configured separately*/
+    default_options: HlsOptions,
+    app_options: HashMap<String, HlsOptions>,
+    store: HlsStore,
 }
 
 impl HlsRemuxer {
     pub fn new(
         consumer: BroadcastEventReceiver,
         event_producer: StreamHubEventSender,
-        need_record: bool,
+        default_options: HlsOptions,
+        app_options: HashMap<String, HlsOptions>,
+        store: HlsStore,
     ) -> Self {
         Self {
             client_event_consumer: consumer,
             event_producer,
-            need_record,
+            default_options,
+            app_options,
+            store,
         }
     }
 
+    fn options(&self, app_name: &str) -> HlsOptions {
+        self.app_options
+            .get(app_name)
+            .unwrap_or(&self.default_options)
+            .clone()
+    }
+
     pub async fn run(&mut self) -> Result<(), HlsError> {
+        let mut check_interval = tokio::time::interval(KEPT_STREAM_CHECK_INTERVAL);
         loop {
-            let val = self.client_event_consumer.recv().await?;
+            let val = tokio::select! {
+                val = self.client_event_consumer.recv() => val?,
+                _ = check_interval.tick() => {
+                    self.store.remove_expired_streams();
+                    continue;
+                }
+            };
             match val {
-                BroadcastEvent::Publish { identifier } => {
+                BroadcastEvent::Publish { identifier, .. } => {
                     if let StreamIdentifier::Rtmp {
                         app_name,
                         stream_name,
                     } = identifier
                     {
+                        let options = self.options(&app_name);
                         let mut rtmp_subscriber = FlvDataReceiver::new(
                             app_name,
                             stream_name,
                             self.event_producer.clone(),
-                            5,
-                            self.need_record,
+                            options,
+                            self.store.clone(),
                         );
 
                         tokio::spawn(async move {
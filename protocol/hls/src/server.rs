@@ -1,23 +1,139 @@
 use {
+    super::store::HlsStore,
+    bytesio::tls::{ReloadableTlsAcceptor, TlsListener, TlsStream},
     hyper::{
+        server::{accept, conn::AddrStream},
         service::{make_service_fn, service_fn},
         Body, Request, Response, Server, StatusCode,
     },
-    tokio::fs::File,
-    tokio_util::codec::{BytesCodec, FramedRead},
+    std::{net::SocketAddr, time::Duration},
+    tokio::{net::TcpStream, time, time::Instant},
 };
 
 type GenericError = Box<dyn std::error::Error + Send + Sync>;
 type Result<T> = std::result::Result<T, GenericError>;
 static NOTFOUND: &[u8] = b"Not Found";
 
-async fn handle_connection(req: Request<Body>) -> Result<Response<Body>> {
+const PRELOAD_HINT_TIMEOUT: Duration = Duration::from_secs(3);
+
+//low latency hls blocking playlist reload: _HLS_msn=<N>[&_HLS_part=<M>]
+struct BlockingRequest {
+    msn: u64,
+    part: Option<u64>,
+}
+
+impl BlockingRequest {
+    fn parse(query: &str) -> Result<Option<Self>> {
+        let mut msn = None;
+        let mut part = None;
+        for pair in query.split('&') {
+            match pair.split_once('=') {
+                Some(("_HLS_msn", value)) => msn = Some(value.parse::<u64>()?),
+                Some(("_HLS_part", value)) => part = Some(value.parse::<u64>()?),
+                _ => {}
+            }
+        }
+
+        match msn {
+            Some(msn) => Ok(Some(Self { msn, part })),
+            None if part.is_some() => Err("_HLS_part without _HLS_msn".into()),
+            None => Ok(None),
+        }
+    }
+}
+
+//the position of the live playlist: the media sequence number of the segment
+//being generated and the count of its partial segments.
+#[derive(Default)]
+struct PlaylistPosition {
+    msn: u64,
+    parts: u64,
+    target_duration: u64,
+    is_eof: bool,
+}
+
+impl PlaylistPosition {
+    fn parse(content: &str) -> Self {
+        let mut position = Self::default();
+        for line in content.lines() {
+            if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+                position.msn = value.parse().unwrap_or(0);
+            } else if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+                position.target_duration = value.parse().unwrap_or(0);
+            } else if line.starts_with("#EXTINF:") {
+                position.msn += 1;
+                position.parts = 0;
+            } else if line.starts_with("#EXT-X-PART:") {
+                position.parts += 1;
+            } else if line.starts_with("#EXT-X-ENDLIST") {
+                position.is_eof = true;
+            }
+        }
+        position
+    }
+
+    fn is_ready(&self, request: &BlockingRequest) -> bool {
+        if self.is_eof || self.msn > request.msn {
+            return true;
+        }
+        matches!(request.part, Some(part) if self.msn == request.msn && part < self.parts)
+    }
+}
+
+//hold the playlist request until the requested segment or partial segment is available,
+//returns the error status if it cannot be satisfied.
+async fn wait_for_playlist(
+    store: &HlsStore,
+    key: &str,
+    request: &BlockingRequest,
+) -> Option<StatusCode> {
+    let start = Instant::now();
+    loop {
+        /*subscribe before checking so that no update is missed*/
+        let updated = store.notified();
+        /*the not found status is returned when sending the file*/
+        let content = store.get(key)?;
+        let position = PlaylistPosition::parse(&String::from_utf8_lossy(&content));
+
+        if position.is_ready(request) {
+            return None;
+        }
+        if request.msn > position.msn + 1 {
+            return Some(StatusCode::BAD_REQUEST);
+        }
+        let timeout = Duration::from_secs(3 * position.target_duration);
+        match timeout.checked_sub(start.elapsed()) {
+            Some(remaining) if !remaining.is_zero() => {
+                let _ = time::timeout(remaining, updated).await;
+            }
+            _ => return Some(StatusCode::SERVICE_UNAVAILABLE),
+        }
+    }
+}
+
+//the partial segment in the preload hint is requested before it is generated
+async fn wait_for_file(store: &HlsStore, key: &str) {
+    let deadline = Instant::now() + PRELOAD_HINT_TIMEOUT;
+    loop {
+        let updated = store.notified();
+        //the hint is removed if the stream ends
+        if store.get(key).is_some()
+            || !store.is_preload_hint(key)
+            || time::timeout_at(deadline, updated).await.is_err()
+        {
+            return;
+        }
+    }
+}
+
+async fn handle_connection(req: Request<Body>, store: HlsStore) -> Result<Response<Body>> {
     let path = req.uri().path();
 
-    let mut file_path: String = String::from("");
+    let mut key: String = String::from("");
 
     if path.ends_with(".m3u8") {
         //http://127.0.0.1/app_name/stream_name/stream_name.m3u8
+        //http://127.0.0.1/app_name/stream_name/master.m3u8
         let m3u8_index = path.find(".m3u8").unwrap();
 
         if m3u8_index > 0 {
@@ -26,12 +142,30 @@ async fn handle_connection(req: Request<Body>) -> Result<Response<Body>> {
 
             let app_name = String::from(rv[1]);
             let stream_name = String::from(rv[2]);
+            let m3u8_name = rv.get(3).copied().unwrap_or(rv[2]);
 
-            file_path = format!("./{app_name}/{stream_name}/{stream_name}.m3u8");
+            key = format!("{app_name}/{stream_name}/{m3u8_name}.m3u8");
+
+            if let Some(query) = req.uri().query() {
+                match BlockingRequest::parse(query) {
+                    Ok(Some(request)) => {
+                        if let Some(status) = wait_for_playlist(&store, &key, &request).await {
+                            return Ok(status_response(status));
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(_) => return Ok(status_response(StatusCode::BAD_REQUEST)),
+                }
+            }
         }
-    } else if path.ends_with(".ts") {
-        //http://127.0.0.1/app_name/stream_name/ts_name.m3u8
-        let ts_index = path.find(".ts").unwrap();
+    } else if let Some(extension) = [".ts", ".m4s", ".mp4"]
+        .iter()
+        .find(|extension| path.ends_with(*extension))
+    {
+        //http://127.0.0.1/app_name/stream_name/ts_name.ts
+        //http://127.0.0.1/app_name/stream_name/m4s_name.m4s
+        //http://127.0.0.1/app_name/stream_name/init.mp4
+        let ts_index = path.rfind(extension).unwrap();
 
         if ts_index > 0 {
             let (left, _) = path.split_at(ts_index);
@@ -42,11 +176,26 @@ async fn handle_connection(req: Request<Body>) -> Result<Response<Body>> {
             let stream_name = String::from(rv[2]);
             let ts_name = String::from(rv[3]);
 
-            file_path = format!("./{app_name}/{stream_name}/{ts_name}.ts");
+            key = format!("{app_name}/{stream_name}/{ts_name}{extension}");
+
+            /*only the partial segment advertised in the preload hint is waited for*/
+            if store.is_preload_hint(&key) {
+                wait_for_file(&store, &key).await;
+            }
         }
     }
 
-    simple_file_send(file_path.as_str()).await
+    match store.get(&key) {
+        Some(data) => Ok(Response::new(Body::from(data))),
+        None => Ok(not_found()),
+    }
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(status.to_string()))
+        .unwrap()
 }
 
 /// HTTP status code 404
@@ -57,28 +206,134 @@ fn not_found() -> Response<Body> {
         .unwrap()
 }
 
-async fn simple_file_send(filename: &str) -> Result<Response<Body>> {
-    // Serve a file by asynchronously reading it by chunks using tokio-util crate.
+//the live playlists and segments are served from the hls store
+pub async fn run(
+    port: usize,
+    store: HlsStore,
+    tls_acceptor: Option<ReloadableTlsAcceptor>,
+) -> Result<()> {
+    let listen_address = format!("0.0.0.0:{port}");
+    let sock_addr: SocketAddr = listen_address.parse().unwrap();
+
+    let gen_service = move || {
+        let store = store.clone();
+        service_fn(move |req| handle_connection(req, store.clone()))
+    };
+
+    match tls_acceptor {
+        Some(acceptor) => {
+            let mut listener = TlsListener::bind(&sock_addr, acceptor).await?;
+            let incoming = accept::poll_fn(move |cx| {
+                listener
+                    .poll_accept(cx)
+                    .map(|conn| conn.map(|(stream, _)| Ok::<_, GenericError>(stream)))
+            });
+            let new_service = make_service_fn(move |_: &TlsStream<TcpStream>| {
+                let service = gen_service();
+                async move { Ok::<_, GenericError>(service) }
+            });
+
+            let server = Server::builder(incoming).serve(new_service);
+            log::info!("Hls server listening on https://{}", sock_addr);
+            server.await?;
+        }
+        None => {
+            let new_service = make_service_fn(move |_: &AddrStream| {
+                let service = gen_service();
+                async move { Ok::<_, GenericError>(service) }
+            });
 
-    if let Ok(file) = File::open(filename).await {
-        let stream = FramedRead::new(file, BytesCodec::new());
-        let body = Body::wrap_stream(stream);
-        return Ok(Response::new(body));
+            let server = Server::bind(&sock_addr).serve(new_service);
+            log::info!("Hls server listening on http://{}", sock_addr);
+            server.await?;
+        }
     }
 
-    Ok(not_found())
+    Ok(())
 }
 
-pub async fn run(port: usize) -> Result<()> {
-    let listen_address = format!("0.0.0.0:{port}");
-    let sock_addr = listen_address.parse().unwrap();
+#[cfg(test)]
+mod tests {
+    use {
+        super::{handle_connection, BlockingRequest, PlaylistPosition},
+        crate::store::HlsStore,
+        hyper::{Body, Request, StatusCode},
+        std::time::Duration,
+        tokio::time::timeout,
+    };
 
-    let new_service =
-        make_service_fn(move |_| async { Ok::<_, GenericError>(service_fn(handle_connection)) });
+    #[test]
+    fn test_blocking_playlist_request() {
+        let content = "#EXTM3U\n#EXT-X-TARGETDURATION:5\n#EXT-X-MEDIA-SEQUENCE:3\n\
+            #EXT-X-PART:DURATION=0.500,URI=\"3.0.ts\",INDEPENDENT=YES\n#EXTINF:0.500\n3.ts\n\
+            #EXT-X-PART:DURATION=0.500,URI=\"4.0.ts\",INDEPENDENT=YES\n\
+            #EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"4.1.ts\"\n";
+        let position = PlaylistPosition::parse(content);
+        assert_eq!((position.msn, position.parts), (4, 1));
+        assert_eq!(position.target_duration, 5);
 
-    let server = Server::bind(&sock_addr).serve(new_service);
-    log::info!("Hls server listening on http://{}", sock_addr);
-    server.await?;
+        let request = BlockingRequest::parse("_HLS_msn=4&_HLS_part=0")
+            .unwrap()
+            .unwrap();
+        assert!(position.is_ready(&request));
+        let request = BlockingRequest::parse("_HLS_msn=4&_HLS_part=1")
+            .unwrap()
+            .unwrap();
+        assert!(!position.is_ready(&request));
+        let request = BlockingRequest::parse("_HLS_msn=3").unwrap().unwrap();
+        assert!(position.is_ready(&request));
 
-    Ok(())
+        assert!(BlockingRequest::parse("_HLS_part=1").is_err());
+        assert!(BlockingRequest::parse("token=abc").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_preload_hint_request() {
+        let store = HlsStore::new();
+        let request = |path: &str| Request::get(path).body(Body::empty()).unwrap();
+
+        //the missing segment which is not hinted is not waited for
+        store.set_preload_hint(
+            String::from("live/test"),
+            String::from("live/test/seg.3.1.ts"),
+        );
+        let response = timeout(
+            Duration::from_secs(1),
+            handle_connection(request("/live/test/seg.4.ts"), store.clone()),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        //the hinted partial segment is responded once it is generated
+        let writer = store.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            writer.insert(String::from("live/test/seg.3.1.ts"), "data".into());
+        });
+        let response = handle_connection(request("/live/test/seg.3.1.ts"), store.clone())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        //the stream ends before the hinted partial segment is generated
+        store.set_preload_hint(
+            String::from("live/test"),
+            String::from("live/test/seg.3.2.ts"),
+        );
+        let remover = store.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            remover.remove_preload_hint("live/test");
+        });
+        let response = timeout(
+            Duration::from_secs(1),
+            handle_connection(request("/live/test/seg.3.2.ts"), store),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
@@ -0,0 +1,110 @@
+use {
+    bytes::Bytes,
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex, RwLock},
+        time::{Duration, Instant},
+    },
+    tokio::sync::{futures::Notified, Notify},
+};
+
+//The live playlists and segments shared by the remuxers and the hls server,
+//keyed by the request path without the leading slash, e.g.: live/test/test.m3u8
+#[derive(Clone, Default)]
+pub struct HlsStore {
+    files: Arc<RwLock<HashMap<String, Bytes>>>,
+    /*the stream path(e.g.: live/test) to the key of the partial segment in its
+    preload hint, which may be requested before it is generated*/
+    preload_hints: Arc<RwLock<HashMap<String, String>>>,
+    /*the stream path to the expiration time of the ended stream which is kept*/
+    kept_streams: Arc<Mutex<HashMap<String, Instant>>>,
+    /*wake up the blocking playlist reload and the preload hint requests*/
+    updated: Arc<Notify>,
+}
+
+impl HlsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, key: String, data: Bytes) {
+        self.files.write().unwrap().insert(key, data);
+        self.updated.notify_waiters();
+    }
+
+    pub fn get(&self, key: &str) -> Option<Bytes> {
+        self.files.read().unwrap().get(key).cloned()
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.files.write().unwrap().remove(key);
+    }
+
+    //keep the files of the ended stream for the duration
+    pub fn keep_stream(&self, stream_path: String, duration: Duration) {
+        self.kept_streams
+            .lock()
+            .unwrap()
+            .insert(stream_path, Instant::now() + duration);
+    }
+
+    //remove all the files of the stream, e.g.: live/test
+    pub fn remove_stream(&self, stream_path: &str) {
+        let prefix = format!("{stream_path}/");
+        self.files
+            .write()
+            .unwrap()
+            .retain(|key, _| !key.starts_with(&prefix));
+        self.kept_streams.lock().unwrap().remove(stream_path);
+    }
+
+    pub fn remove_expired_streams(&self) {
+        let now = Instant::now();
+        let mut expired_streams = Vec::new();
+        self.kept_streams
+            .lock()
+            .unwrap()
+            .retain(|stream_path, expiration| {
+                if *expiration > now {
+                    return true;
+                }
+                expired_streams.push(stream_path.clone());
+                false
+            });
+
+        for stream_path in expired_streams {
+            log::info!("remove the expired hls stream: {}", stream_path);
+            self.remove_stream(&stream_path);
+        }
+    }
+
+    pub fn set_preload_hint(&self, stream_path: String, key: String) {
+        self.preload_hints.write().unwrap().insert(stream_path, key);
+    }
+
+    //the requests waiting for the hinted partial segment return at once
+    pub fn remove_preload_hint(&self, stream_path: &str) {
+        if self
+            .preload_hints
+            .write()
+            .unwrap()
+            .remove(stream_path)
+            .is_some()
+        {
+            self.updated.notify_waiters();
+        }
+    }
+
+    pub fn is_preload_hint(&self, key: &str) -> bool {
+        self.preload_hints
+            .read()
+            .unwrap()
+            .values()
+            .any(|hint| hint == key)
+    }
+
+    //the future is completed by the next insertion
+    pub fn notified(&self) -> Notified<'_> {
+        self.updated.notified()
+    }
+}
@@ -1,7 +1,9 @@
 #[cfg(test)]
 mod tests {
+    use crate::define::HlsOptions;
     use crate::errors::MediaError;
     use crate::flv2hls::Flv2HlsRemuxer;
+    use crate::store::HlsStore;
     use bytes::BytesMut;
     use xflv::define::FlvData;
 
@@ -64,8 +66,12 @@ mod tests {
         demuxer.read_flv_header()?;
 
         let start = Instant::now();
-        let mut media_demuxer =
-            Flv2HlsRemuxer::new(5, String::from("live"), String::from("test"), false);
+        let mut media_demuxer = Flv2HlsRemuxer::new(
+            String::from("live"),
+            String::from("test"),
+            HlsOptions::default(),
+            HlsStore::new(),
+        );
 
         loop {
             let data_ = demuxer.read_flv_tag();
@@ -1,35 +1,116 @@
 use {
-    super::errors::MediaError,
+    super::{define::HlsOptions, errors::MediaError, store::HlsStore},
     bytes::BytesMut,
-    std::{fs, fs::File, io::Write},
+    std::{fs, fs::File, io::Write, time::Duration},
 };
 
 pub struct Ts {
     ts_number: u32,
+    /*the segment name template, e.g.: {stream}-{seq}*/
+    segment_name: String,
+    /*the key prefix in the hls store, e.g.: live/test*/
     live_path: String,
+    /*ts or m4s*/
+    extension: String,
+    store: HlsStore,
+    /*the segments are also written to the disk if the stream is recorded*/
+    record_path: Option<String>,
 }
 
 impl Ts {
-    pub fn new(app_name: String, stream_name: String) -> Self {
-        let live_path = format!("./{app_name}/{stream_name}");
-        fs::create_dir_all(live_path.clone()).unwrap();
+    pub fn new(
+        app_name: String,
+        stream_name: String,
+        extension: &str,
+        options: &HlsOptions,
+        store: HlsStore,
+    ) -> Self {
+        let record_path = if options.need_record {
+            let record_path = format!("{}/{app_name}/{stream_name}", options.output_dir);
+            fs::create_dir_all(record_path.clone()).unwrap();
+            Some(record_path)
+        } else {
+            None
+        };
+
+        let segment_name = options
+            .segment_name
+            .replace("{app}", &app_name)
+            .replace("{stream}", &stream_name);
+
+        /*the files kept for the last publish of the stream are replaced*/
+        let live_path = format!("{app_name}/{stream_name}");
+        store.remove_stream(&live_path);
 
         Self {
             ts_number: 0,
+            segment_name,
             live_path,
+            extension: extension.to_string(),
+            store,
+            record_path,
         }
     }
     pub fn write(&mut self, data: BytesMut) -> Result<(String, String), MediaError> {
-        let ts_file_name = format!("{}.ts", self.ts_number);
-        let ts_file_path = format!("{}/{}", self.live_path, ts_file_name);
+        let ts_file_name = format!("{}.{}", self.segment_base_name(), self.extension);
         self.ts_number += 1;
 
-        let mut ts_file_handler = File::create(ts_file_path.clone())?;
-        ts_file_handler.write_all(&data[..])?;
-
+        let ts_file_path = self.write_file(&ts_file_name, data)?;
         Ok((ts_file_name, ts_file_path))
     }
+    fn segment_base_name(&self) -> String {
+        self.segment_name
+            .replace("{seq}", &self.ts_number.to_string())
+    }
+    //the partial segment of the segment which will be written next, e.g.: 3.0.ts
+    pub fn part_name(&self, part_index: usize) -> String {
+        format!(
+            "{}.{}.{}",
+            self.segment_base_name(),
+            part_index,
+            self.extension
+        )
+    }
+    //the live files are removed from the hls store once the duration expires
+    pub fn keep_live_files(&mut self, duration: Duration) {
+        self.store.keep_stream(self.live_path.clone(), duration);
+    }
+    //advertise the partial segment in the preload hint, or none if the stream ends
+    pub fn set_preload_hint(&mut self, part_index: Option<usize>) {
+        match part_index {
+            Some(part_index) => {
+                let key = format!("{}/{}", self.live_path, self.part_name(part_index));
+                self.store.set_preload_hint(self.live_path.clone(), key);
+            }
+            None => self.store.remove_preload_hint(&self.live_path),
+        }
+    }
+    pub fn write_part(&mut self, part_index: usize, data: BytesMut) -> (String, String) {
+        let part_file_name = self.part_name(part_index);
+        let part_file_path = self.write_live_file(&part_file_name, data);
+        (part_file_name, part_file_path)
+    }
+    //the live playlists and the partial segments are never recorded
+    pub fn write_live_file(&mut self, file_name: &str, data: BytesMut) -> String {
+        let file_path = format!("{}/{}", self.live_path, file_name);
+        self.store.insert(file_path.clone(), data.freeze());
+        file_path
+    }
+    //write the file which is not a media segment, e.g.: the fmp4 init segment
+    pub fn write_file(&mut self, file_name: &str, data: BytesMut) -> Result<String, MediaError> {
+        if let Some(record_path) = &self.record_path {
+            let mut file_handler = File::create(format!("{record_path}/{file_name}"))?;
+            file_handler.write_all(&data[..])?;
+        }
+
+        Ok(self.write_live_file(file_name, data))
+    }
+    //only remove the file from the hls store, the recorded one is kept
     pub fn delete(&mut self, ts_file_name: String) {
-        fs::remove_file(ts_file_name).unwrap();
+        self.store.remove(&ts_file_name);
+    }
+    pub fn delete_file(&mut self, file_name: &str) {
+        self.store
+            .remove(&format!("{}/{}", self.live_path, file_name));
     }
 }
@@ -0,0 +1,286 @@
+use {
+    super::{
+        define::tag_type,
+        errors::{HttpFLvError, HttpFLvErrorValue},
+    },
+    byteorder::BigEndian,
+    bytes::BytesMut,
+    bytesio::bytes_reader::BytesReader,
+    hyper::{body::HttpBody, Body, Client, Uri},
+    hyper_tls::HttpsConnector,
+    rtmp::{
+        amf0::{amf0_reader::Amf0Reader, amf0_writer::Amf0Writer, Amf0ValueType},
+        session::{common::Common, define::SessionType},
+    },
+    std::time::Duration,
+    streamhub::{
+        define::StreamHubEventSender,
+        utils::{RandomDigitCount, Uuid},
+    },
+};
+
+//flv header(9 bytes) + the first previous tag size(4 bytes)
+const FLV_HEADER_LENGTH: usize = 13;
+//tag type(1) + data size(3) + timestamp(3) + timestamp extended(1) + stream id(3)
+const FLV_TAG_HEADER_LENGTH: usize = 11;
+const PREVIOUS_TAG_SIZE_LENGTH: usize = 4;
+//the pull session is treated as broken if no data is received for a while
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The http-flv client which pulls the flv stream over http(s) from the remote
+// server and publishes it to the stream hub as the rtmp stream {app_name}/{stream_name}.
+pub struct HttpFlvClientSession {
+    url: String,
+    app_name: String,
+    stream_name: String,
+    rtmp_handler: Common,
+    publisher_id: Uuid,
+    is_published: bool,
+    /*configure how many gops will be cached.*/
+    gop_num: usize,
+
+    reader: BytesReader,
+    is_header_received: bool,
+}
+
+impl HttpFlvClientSession {
+    pub fn new(
+        url: String,
+        app_name: String,
+        stream_name: String,
+        event_producer: StreamHubEventSender,
+        gop_num: usize,
+    ) -> Self {
+        let mut rtmp_handler = Common::new(None, event_producer, SessionType::Client, None);
+        rtmp_handler.request_url = url.clone();
+
+        Self {
+            url,
+            app_name,
+            stream_name,
+            rtmp_handler,
+            publisher_id: Uuid::new(RandomDigitCount::Four),
+            is_published: false,
+            gop_num,
+            reader: BytesReader::new(BytesMut::new()),
+            is_header_received: false,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), HttpFLvError> {
+        let rv = self.pull().await;
+
+        if self.is_published {
+            self.rtmp_handler
+                .unpublish_to_channels(
+                    self.app_name.clone(),
+                    self.stream_name.clone(),
+                    self.publisher_id,
+                )
+                .await?;
+        }
+
+        rv
+    }
+
+    async fn pull(&mut self) -> Result<(), HttpFLvError> {
+        let uri: Uri = self.url.parse()?;
+        let client = Client::builder().build::<_, Body>(HttpsConnector::new());
+        let mut response = client.get(uri).await?;
+
+        if !response.status().is_success() {
+            return Err(HttpFLvError {
+                value: HttpFLvErrorValue::HttpStatusError(response.status().as_u16()),
+            });
+        }
+        log::info!("http-flv client receives the response, url: {}", self.url);
+
+        loop {
+            let data = match tokio::time::timeout(RECEIVE_TIMEOUT, response.body_mut().data()).await
+            {
+                Ok(Some(data)) => data?,
+                //the stream is finished by the server
+                Ok(None) => return Ok(()),
+                Err(_) => {
+                    return Err(HttpFLvError {
+                        value: HttpFLvErrorValue::ReceiveTimeout,
+                    });
+                }
+            };
+
+            self.reader.extend_from_slice(&data[..]);
+            self.read_flv_tags().await?;
+        }
+    }
+
+    async fn read_flv_tags(&mut self) -> Result<(), HttpFLvError> {
+        if !self.is_header_received {
+            if self.reader.len() < FLV_HEADER_LENGTH {
+                return Ok(());
+            }
+            let header = self.reader.read_bytes(FLV_HEADER_LENGTH)?;
+            if &header[..3] != b"FLV" {
+                return Err(HttpFLvError {
+                    value: HttpFLvErrorValue::NotFlvStream,
+                });
+            }
+            self.is_header_received = true;
+
+            self.rtmp_handler
+                .publish_to_channels(
+                    self.app_name.clone(),
+                    self.stream_name.clone(),
+                    self.publisher_id,
+                    self.gop_num,
+                )
+                .await?;
+            self.is_published = true;
+        }
+
+        //only read the complete tags, the remaining bytes wait for more data
+        while self.reader.len() >= FLV_TAG_HEADER_LENGTH {
+            let data_size = ((self.reader.get(1)? as usize) << 16)
+                | ((self.reader.get(2)? as usize) << 8)
+                | self.reader.get(3)? as usize;
+            if self.reader.len() < FLV_TAG_HEADER_LENGTH + data_size + PREVIOUS_TAG_SIZE_LENGTH {
+                break;
+            }
+
+            let tag_type = self.reader.read_u8()?;
+            self.reader.read_u24::<BigEndian>()?;
+            let timestamp = self.reader.read_u24::<BigEndian>()?;
+            let timestamp_ext = self.reader.read_u8()?;
+            /*stream id*/
+            self.reader.read_u24::<BigEndian>()?;
+            let mut body = self.reader.read_bytes(data_size)?;
+            /*previous tag size*/
+            self.reader.read_u32::<BigEndian>()?;
+
+            let timestamp = timestamp | ((timestamp_ext as u32) << 24);
+            match tag_type {
+                tag_type::VIDEO => {
+                    self.rtmp_handler
+                        .on_video_data(&mut body, &timestamp)
+                        .await?
+                }
+                tag_type::AUDIO => {
+                    self.rtmp_handler
+                        .on_audio_data(&mut body, &timestamp)
+                        .await?
+                }
+                tag_type::SCRIPT_DATA_AMF => {
+                    if let Some(mut metadata) = Self::gen_metadata(body)? {
+                        self.rtmp_handler
+                            .on_meta_data(&mut metadata, &timestamp)
+                            .await?
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /*The flv script tag only contains onMetaData, the @setDataFrame is added
+    back as the rtmp metadata message which is sent by the publishers.*/
+    fn gen_metadata(body: BytesMut) -> Result<Option<BytesMut>, HttpFLvError> {
+        let mut amf_reader = Amf0Reader::new(BytesReader::new(body.clone()));
+        match amf_reader.read_any() {
+            Ok(Amf0ValueType::UTF8String(name)) if name == "onMetaData" => {}
+            _ => return Ok(None),
+        }
+
+        let mut amf_writer = Amf0Writer::new();
+        amf_writer.write_string(&String::from("@setDataFrame"))?;
+        let mut metadata = amf_writer.extract_current_bytes();
+        metadata.extend_from_slice(&body[..]);
+
+        Ok(Some(metadata))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::HttpFlvClientSession,
+        streamhub::define::{FrameData, StreamHubEvent},
+        tokio::sync::mpsc,
+    };
+
+    fn gen_tag(tag_type: u8, timestamp: u32, body: &[u8]) -> Vec<u8> {
+        let mut tag = vec![tag_type];
+        tag.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        tag.extend_from_slice(&timestamp.to_be_bytes()[1..]);
+        tag.push((timestamp >> 24) as u8);
+        tag.extend_from_slice(&[0, 0, 0]);
+        tag.extend_from_slice(body);
+        tag.extend_from_slice(&(body.len() as u32 + 11).to_be_bytes());
+        tag
+    }
+
+    #[tokio::test]
+    async fn test_read_flv_tags() {
+        let (producer, mut consumer) = mpsc::unbounded_channel();
+        //accept the publish event like the stream hub
+        let hub = tokio::spawn(async move {
+            match consumer.recv().await {
+                Some(StreamHubEvent::Publish {
+                    receiver,
+                    result_sender,
+                    ..
+                }) => {
+                    result_sender.send(Ok(())).unwrap();
+                    receiver.frame_receiver.unwrap()
+                }
+                _ => panic!("the stream is not published"),
+            }
+        });
+        let mut session = HttpFlvClientSession::new(
+            String::from("http://127.0.0.1/live/test.flv"),
+            String::from("live"),
+            String::from("test"),
+            producer,
+            0,
+        );
+
+        let mut data = b"FLV\x01\x05\x00\x00\x00\x09\x00\x00\x00\x00".to_vec();
+        //onMetaData with an empty ecma array
+        let metadata = b"\x02\x00\x0aonMetaData\x08\x00\x00\x00\x00\x00\x00\x09";
+        data.extend(gen_tag(18, 0, metadata));
+        data.extend(gen_tag(9, 0x01000028, &[0x17, 0x01, 0, 0, 0, 0xaa]));
+        data.extend(gen_tag(8, 40, &[0xaf, 0x01, 0xbb]));
+
+        //the tags are split at arbitrary positions
+        for chunk in data.chunks(7) {
+            session.reader.extend_from_slice(chunk);
+            assert!(session.read_flv_tags().await.is_ok());
+        }
+        assert!(session.reader.is_empty());
+
+        let mut frame_receiver = hub.await.unwrap();
+
+        match frame_receiver.recv().await {
+            Some(FrameData::MetaData { data, .. }) => {
+                //@setDataFrame is added before onMetaData
+                assert_eq!(&data[..16], b"\x02\x00\x0d@setDataFrame");
+                assert_eq!(&data[16..], &metadata[..]);
+            }
+            _ => panic!("no metadata"),
+        }
+        match frame_receiver.recv().await {
+            Some(FrameData::Video { timestamp, data }) => {
+                assert_eq!(timestamp, 0x01000028);
+                assert_eq!(&data[..], &[0x17, 0x01, 0, 0, 0, 0xaa]);
+            }
+            _ => panic!("no video data"),
+        }
+        match frame_receiver.recv().await {
+            Some(FrameData::Audio { timestamp, data }) => {
+                assert_eq!(timestamp, 40);
+                assert_eq!(&data[..], &[0xaf, 0x01, 0xbb]);
+            }
+            _ => panic!("no audio data"),
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use {
+    bytesio::bytes_errors::BytesReadError,
     failure::Fail,
     futures::channel::mpsc::SendError,
-    xflv::errors::FlvMuxerError,
+    hyper::http::uri::InvalidUri,
     rtmp::{
         amf0::errors::Amf0WriteError, cache::errors::MetadataError, session::errors::SessionError,
     },
     std::fmt,
+    xflv::errors::FlvMuxerError,
 };
 
 #[derive(Debug)]
@@ -37,6 +39,18 @@ pub enum HttpFLvErrorValue {
     MetadataError(MetadataError),
     #[fail(display = "tokio mpsc error")]
     MpscSendError(SendError),
+    #[fail(display = "hyper error: {}", _0)]
+    HyperError(hyper::Error),
+    #[fail(display = "invalid uri: {}", _0)]
+    InvalidUri(InvalidUri),
+    #[fail(display = "http response status error: {}", _0)]
+    HttpStatusError(u16),
+    #[fail(display = "not a flv stream")]
+    NotFlvStream,
+    #[fail(display = "bytes read error: {}", _0)]
+    BytesReadError(BytesReadError),
+    #[fail(display = "no flv data received for a while")]
+    ReceiveTimeout,
 }
 
 impl From<SessionError> for HttpFLvError {
@@ -79,6 +93,30 @@ impl From<MetadataError> for HttpFLvError {
     }
 }
 
+impl From<hyper::Error> for HttpFLvError {
+    fn from(error: hyper::Error) -> Self {
+        HttpFLvError {
+            value: HttpFLvErrorValue::HyperError(error),
+        }
+    }
+}
+
+impl From<InvalidUri> for HttpFLvError {
+    fn from(error: InvalidUri) -> Self {
+        HttpFLvError {
+            value: HttpFLvErrorValue::InvalidUri(error),
+        }
+    }
+}
+
+impl From<BytesReadError> for HttpFLvError {
+    fn from(error: BytesReadError) -> Self {
+        HttpFLvError {
+            value: HttpFLvErrorValue::BytesReadError(error),
+        }
+    }
+}
+
 impl fmt::Display for HttpFLvError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Display::fmt(&self.value, f)
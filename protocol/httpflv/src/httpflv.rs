@@ -10,14 +10,14 @@ use {
     bytes::BytesMut,
     std::net::SocketAddr,
     streamhub::define::{
-        FrameData, FrameDataReceiver, NotifyInfo, StreamHubEvent, StreamHubEventSender,
-        SubscribeType, SubscriberInfo,
+        wait_pub_sub_result, FrameData, FrameDataReceiver, NotifyInfo, StreamHubEvent,
+        StreamHubEventSender, SubscribeType, SubscriberInfo,
     },
     streamhub::{
         stream::StreamIdentifier,
         utils::{RandomDigitCount, Uuid},
     },
-    tokio::sync::mpsc,
+    tokio::sync::{mpsc, oneshot},
     xflv::muxer::{FlvMuxer, HEADER_LENGTH},
 };
 
@@ -60,13 +60,6 @@ impl HttpFlv {
         }
     }
 
-    pub async fn run(&mut self) -> Result<(), HttpFLvError> {
-        self.subscribe_from_rtmp_channels().await?;
-        self.send_media_stream().await?;
-
-        Ok(())
-    }
-
     pub async fn send_media_stream(&mut self) -> Result<(), HttpFLvError> {
         self.muxer.write_flv_header()?;
         self.muxer.write_previous_tag_size(0)?;
@@ -172,10 +165,12 @@ impl HttpFlv {
             stream_name: self.stream_name.clone(),
         };
 
+        let (result_sender, result_receiver) = oneshot::channel();
         let subscribe_event = StreamHubEvent::Subscribe {
             identifier,
             info: sub_info,
             sender: streamhub::define::DataSender::Frame { sender },
+            result_sender,
         };
 
         let rv = self.event_producer.send(subscribe_event);
@@ -188,6 +183,9 @@ impl HttpFlv {
                 value: HttpFLvErrorValue::SessionError(session_error),
             });
         }
+        wait_pub_sub_result(result_receiver)
+            .await
+            .map_err(SessionError::from)?;
 
         self.data_consumer = receiver;
 
@@ -1,6 +1,7 @@
 extern crate hyper;
 extern crate rtmp;
 
+pub mod client_session;
 pub mod define;
 pub mod errors;
 pub mod httpflv;
@@ -1,23 +1,34 @@
 use {
     super::httpflv::HttpFlv,
+    bytesio::tls::{ReloadableTlsAcceptor, TlsListener, TlsStream},
     futures::channel::mpsc::unbounded,
     hyper::{
-        server::conn::AddrStream,
+        server::{accept, conn::AddrStream},
         service::{make_service_fn, service_fn},
         Body, Request, Response, Server, StatusCode,
     },
-    std::net::SocketAddr,
-    streamhub::define::StreamHubEventSender,
+    std::{net::SocketAddr, sync::Arc},
+    streamhub::{
+        auth::{Auth, AuthAction},
+        define::StreamHubEventSender,
+        statistics::metrics::SessionCounters,
+        stream::StreamIdentifier,
+    },
+    tokio::net::TcpStream,
 };
 
 type GenericError = Box<dyn std::error::Error + Send + Sync>;
 type Result<T> = std::result::Result<T, GenericError>;
 static NOTFOUND: &[u8] = b"Not Found";
+static UNAUTHORIZED: &[u8] = b"Unauthorized";
+static FORBIDDEN: &[u8] = b"Forbidden";
 
 async fn handle_connection(
     req: Request<Body>,
     event_producer: StreamHubEventSender, // event_producer: ChannelEventProducer
     remote_addr: SocketAddr,
+    auth: Option<Arc<Auth>>,
+    session_counters: SessionCounters,
 ) -> Result<Response<Body>> {
     let path = req.uri().path();
 
@@ -29,6 +40,29 @@ async fn handle_connection(
             let app_name = String::from(rv[1]);
             let stream_name = String::from(rv[2]);
 
+            if let Some(auth) = auth {
+                let identifier = StreamIdentifier::Rtmp {
+                    app_name: app_name.clone(),
+                    stream_name: stream_name.clone(),
+                };
+                let url_parameters = req.uri().query().unwrap_or_default();
+                if let Err(err) = auth
+                    .authenticate(
+                        &identifier,
+                        AuthAction::Play,
+                        url_parameters,
+                        &remote_addr.to_string(),
+                    )
+                    .await
+                {
+                    log::error!("httpflv auth error: {}, {}", identifier, err);
+                    return Ok(Response::builder()
+                        .status(StatusCode::UNAUTHORIZED)
+                        .body(UNAUTHORIZED.into())
+                        .unwrap());
+                }
+            }
+
             let (http_response_data_producer, http_response_data_consumer) = unbounded();
 
             let mut flv_hanlder = HttpFlv::new(
@@ -40,10 +74,22 @@ async fn handle_connection(
                 remote_addr,
             );
 
+            //the subscribe may be rejected by the on_play notify
+            if let Err(err) = flv_hanlder.subscribe_from_rtmp_channels().await {
+                log::error!("httpflv subscribe error: {}", err);
+                return Ok(Response::builder()
+                    .status(StatusCode::FORBIDDEN)
+                    .body(FORBIDDEN.into())
+                    .unwrap());
+            }
+
+            session_counters.session_connected("httpflv");
             tokio::spawn(async move {
-                if let Err(err) = flv_hanlder.run().await {
+                if let Err(err) = flv_hanlder.send_media_stream().await {
+                    session_counters.session_error("httpflv");
                     log::error!("flv handler run error {}\n", err);
                 }
+                session_counters.session_disconnected("httpflv");
             });
 
             let mut resp = Response::new(Body::wrap_stream(http_response_data_consumer));
@@ -60,25 +106,59 @@ async fn handle_connection(
     }
 }
 
-pub async fn run(event_producer: StreamHubEventSender, port: usize) -> Result<()> {
+pub async fn run(
+    event_producer: StreamHubEventSender,
+    port: usize,
+    auth: Option<Arc<Auth>>,
+    tls_acceptor: Option<ReloadableTlsAcceptor>,
+    session_counters: SessionCounters,
+) -> Result<()> {
     let listen_address = format!("0.0.0.0:{port}");
     let sock_addr = listen_address.parse().unwrap();
 
-    let new_service = make_service_fn(move |socket: &AddrStream| {
-        let remote_addr = socket.remote_addr();
+    let gen_service = move |remote_addr: SocketAddr| {
         let flv_copy = event_producer.clone();
-        async move {
-            Ok::<_, GenericError>(service_fn(move |req| {
-                handle_connection(req, flv_copy.clone(), remote_addr)
-            }))
-        }
-    });
-
-    let server = Server::bind(&sock_addr).serve(new_service);
+        let auth_copy = auth.clone();
+        let session_counters = session_counters.clone();
+        service_fn(move |req| {
+            handle_connection(
+                req,
+                flv_copy.clone(),
+                remote_addr,
+                auth_copy.clone(),
+                session_counters.clone(),
+            )
+        })
+    };
+
+    match tls_acceptor {
+        Some(acceptor) => {
+            let mut listener = TlsListener::bind(&sock_addr, acceptor).await?;
+            let incoming = accept::poll_fn(move |cx| {
+                listener
+                    .poll_accept(cx)
+                    .map(|conn| conn.map(|(stream, _)| Ok::<_, GenericError>(stream)))
+            });
+            let new_service = make_service_fn(move |socket: &TlsStream<TcpStream>| {
+                let service = socket.get_ref().0.peer_addr().map(&gen_service);
+                async move { service }
+            });
 
-    log::info!("Httpflv server listening on http://{}", sock_addr);
+            let server = Server::builder(incoming).serve(new_service);
+            log::info!("Httpflv server listening on https://{}", sock_addr);
+            server.await?;
+        }
+        None => {
+            let new_service = make_service_fn(move |socket: &AddrStream| {
+                let service = gen_service(socket.remote_addr());
+                async move { Ok::<_, GenericError>(service) }
+            });
 
-    server.await?;
+            let server = Server::bind(&sock_addr).serve(new_service);
+            log::info!("Httpflv server listening on http://{}", sock_addr);
+            server.await?;
+        }
+    }
 
     Ok(())
 }
@@ -5,4 +5,7 @@ pub const OBJECT: u8 = 0x03;
 pub const NULL: u8 = 0x05;
 pub const ECMA_ARRAY: u8 = 0x08;
 pub const OBJECT_END: u8 = 0x09;
+pub const STRICT_ARRAY: u8 = 0x0a;
 pub const LONG_STRING: u8 = 0x0c;
+//switch to AMF3 for the next value
+pub const AVMPLUS_OBJECT: u8 = 0x11;
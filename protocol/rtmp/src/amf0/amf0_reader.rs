@@ -1,5 +1,8 @@
 use {
-    super::{amf0_markers, errors::Amf0ReadErrorValue, Amf0ReadError, Amf0ValueType},
+    super::{
+        amf0_markers, amf3_reader::Amf3Reader, errors::Amf0ReadErrorValue, Amf0ReadError,
+        Amf0ValueType,
+    },
     byteorder::BigEndian,
     // bytes::BytesMut,
     bytesio::bytes_reader::BytesReader,
@@ -47,7 +50,9 @@ impl Amf0Reader {
             amf0_markers::OBJECT => self.read_object(),
             amf0_markers::NULL => self.read_null(),
             amf0_markers::ECMA_ARRAY => self.read_ecma_array(),
+            amf0_markers::STRICT_ARRAY => self.read_strict_array(),
             amf0_markers::LONG_STRING => self.read_long_string(),
+            amf0_markers::AVMPLUS_OBJECT => self.read_avmplus_object(),
             _ => Err(Amf0ReadError {
                 value: Amf0ReadErrorValue::UnknownMarker { marker: markers },
             }),
@@ -56,6 +61,10 @@ impl Amf0Reader {
     pub fn read_with_type(&mut self, specified_marker: u8) -> Result<Amf0ValueType, Amf0ReadError> {
         let marker = self.reader.advance_u8()?;
 
+        if marker == amf0_markers::AVMPLUS_OBJECT {
+            return self.read_avmplus_with_type(specified_marker);
+        }
+
         if marker != specified_marker {
             return Err(Amf0ReadError {
                 value: Amf0ReadErrorValue::WrongType,
@@ -146,6 +155,17 @@ impl Amf0Reader {
         Ok(Amf0ValueType::Object(properties))
     }
 
+    pub fn read_strict_array(&mut self) -> Result<Amf0ValueType, Amf0ReadError> {
+        let len = self.reader.read_u32::<BigEndian>()?;
+
+        let mut values = Vec::new();
+        for _ in 0..len {
+            values.push(self.read_any()?);
+        }
+
+        Ok(Amf0ValueType::StrictArray(values))
+    }
+
     pub fn read_long_string(&mut self) -> Result<Amf0ValueType, Amf0ReadError> {
         let l = self.reader.read_u32::<BigEndian>()?;
 
@@ -155,6 +175,46 @@ impl Amf0Reader {
         Ok(Amf0ValueType::LongUTF8String(val))
     }
 
+    //the AMF3 value is only consumed if its converted type is the specified one
+    fn read_avmplus_with_type(
+        &mut self,
+        specified_marker: u8,
+    ) -> Result<Amf0ValueType, Amf0ReadError> {
+        let mut peek_reader = Amf0Reader::new(BytesReader::new(self.reader.get_remaining_bytes()));
+        let value = peek_reader.read_any()?;
+
+        let is_matched = matches!(
+            (specified_marker, &value),
+            (amf0_markers::NUMBER, Amf0ValueType::Number(_))
+                | (amf0_markers::BOOLEAN, Amf0ValueType::Boolean(_))
+                | (amf0_markers::STRING, Amf0ValueType::UTF8String(_))
+                | (amf0_markers::OBJECT, Amf0ValueType::Object(_))
+                | (amf0_markers::NULL, Amf0ValueType::Null)
+                | (amf0_markers::ECMA_ARRAY, Amf0ValueType::EcmaArray(_))
+                | (amf0_markers::STRICT_ARRAY, Amf0ValueType::StrictArray(_))
+                | (amf0_markers::LONG_STRING, Amf0ValueType::LongUTF8String(_))
+        );
+        if !is_matched {
+            return Err(Amf0ReadError {
+                value: Amf0ReadErrorValue::WrongType,
+            });
+        }
+
+        self.reader
+            .read_bytes(self.reader.len() - peek_reader.reader.len())?;
+        Ok(value)
+    }
+
+    //the AMF3 value is converted to the AMF0 one if possible
+    pub fn read_avmplus_object(&mut self) -> Result<Amf0ValueType, Amf0ReadError> {
+        let remaining_bytes = self.reader.extract_remaining_bytes();
+        let mut amf3_reader = Amf3Reader::new(BytesReader::new(remaining_bytes));
+        let value = amf3_reader.read_any();
+        self.reader = amf3_reader.into_reader();
+
+        Ok(value?.into())
+    }
+
     // pub fn get_remaining_bytes(&mut self) -> BytesMut {
     //     return self.reader.get_remaining_bytes();
     // }
@@ -239,6 +299,26 @@ mod tests {
         print!("test")
     }
 
+    #[test]
+    fn test_strict_array() {
+        use super::super::amf0_writer::Amf0Writer;
+
+        let fourcc_list = Amf0ValueType::StrictArray(vec![
+            Amf0ValueType::UTF8String(String::from("av01")),
+            Amf0ValueType::UTF8String(String::from("hvc1")),
+        ]);
+        let mut properties = IndexMap::new();
+        properties.insert(String::from("fourCcList"), fourcc_list);
+        properties.insert(String::from("objectEncoding"), Amf0ValueType::Number(0.0));
+
+        let mut amf_writer = Amf0Writer::new();
+        amf_writer.write_object(&properties).unwrap();
+
+        let mut amf_reader = Amf0Reader::new(BytesReader::new(amf_writer.extract_current_bytes()));
+        let command_obj = amf_reader.read_with_type(amf0_markers::OBJECT).unwrap();
+        assert_eq!(command_obj, Amf0ValueType::Object(properties));
+    }
+
     // fn uint32_to_int24(num: u32) -> i32 {
     //     // 截取低24位
     //     let mut result = num & 0xFFFFFF;
@@ -1,5 +1,8 @@
 use {
-    super::{amf0_markers, errors::Amf0WriteErrorValue, Amf0ValueType, Amf0WriteError},
+    super::{
+        amf0_markers, amf3_writer::Amf3Writer, errors::Amf0WriteErrorValue, Amf0ValueType,
+        Amf0WriteError, Amf3ValueType,
+    },
     byteorder::BigEndian,
     bytes::BytesMut,
     bytesio::bytes_writer::BytesWriter,
@@ -30,8 +33,11 @@ impl Amf0Writer {
             Amf0ValueType::Null => self.write_null(),
             Amf0ValueType::Number(ref val) => self.write_number(val),
             Amf0ValueType::UTF8String(ref val) => self.write_string(val),
+            Amf0ValueType::LongUTF8String(ref val) => self.write_long_string(val),
             Amf0ValueType::Object(ref val) => self.write_object(val),
             Amf0ValueType::EcmaArray(ref val) => self.write_eacm_array(val),
+            Amf0ValueType::StrictArray(ref val) => self.write_strict_array(val),
+            Amf0ValueType::AvmPlus(ref val) => self.write_avmplus_object(val),
             _ => Ok(()),
         }
     }
@@ -62,6 +68,14 @@ impl Amf0Writer {
         Ok(())
     }
 
+    pub fn write_long_string(&mut self, value: &str) -> Result<(), Amf0WriteError> {
+        self.writer.write_u8(amf0_markers::LONG_STRING)?;
+        self.writer.write_u32::<BigEndian>(value.len() as u32)?;
+        self.writer.write(value.as_bytes())?;
+
+        Ok(())
+    }
+
     pub fn write_null(&mut self) -> Result<(), Amf0WriteError> {
         self.writer.write_u8(amf0_markers::NULL)?;
         Ok(())
@@ -107,6 +121,27 @@ impl Amf0Writer {
         Ok(())
     }
 
+    pub fn write_strict_array(&mut self, values: &[Amf0ValueType]) -> Result<(), Amf0WriteError> {
+        self.writer.write_u8(amf0_markers::STRICT_ARRAY)?;
+        self.writer.write_u32::<BigEndian>(values.len() as u32)?;
+
+        for value in values {
+            self.write_any(value)?;
+        }
+        Ok(())
+    }
+
+    //switch to AMF3 for the value, a new AMF3 context is used each time
+    pub fn write_avmplus_object(&mut self, value: &Amf3ValueType) -> Result<(), Amf0WriteError> {
+        let mut amf3_writer = Amf3Writer::new();
+        amf3_writer.write_any(value)?;
+
+        self.writer.write_u8(amf0_markers::AVMPLUS_OBJECT)?;
+        self.writer
+            .write(&amf3_writer.extract_current_bytes()[..])?;
+        Ok(())
+    }
+
     // pub async fn flush(&mut self) -> Result<(), Amf0WriteError> {
     //     self.writer.flush()?;
     // }
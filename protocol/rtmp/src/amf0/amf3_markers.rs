@@ -0,0 +1,24 @@
+pub const UNDEFINED: u8 = 0x00;
+pub const NULL: u8 = 0x01;
+pub const FALSE: u8 = 0x02;
+pub const TRUE: u8 = 0x03;
+pub const INTEGER: u8 = 0x04;
+pub const DOUBLE: u8 = 0x05;
+pub const STRING: u8 = 0x06;
+pub const XML_DOC: u8 = 0x07;
+pub const DATE: u8 = 0x08;
+pub const ARRAY: u8 = 0x09;
+pub const OBJECT: u8 = 0x0a;
+pub const XML: u8 = 0x0b;
+pub const BYTE_ARRAY: u8 = 0x0c;
+pub const VECTOR_INT: u8 = 0x0d;
+pub const VECTOR_UINT: u8 = 0x0e;
+pub const VECTOR_DOUBLE: u8 = 0x0f;
+pub const VECTOR_OBJECT: u8 = 0x10;
+pub const DICTIONARY: u8 = 0x11;
+
+//the range of the 29-bit integers, the larger ones are written as doubles
+pub const INTEGER_MAX: i32 = 0x0fff_ffff;
+pub const INTEGER_MIN: i32 = -0x1000_0000;
+//the max value of the U29 lengths and references
+pub const U29_MAX: u32 = 0x1fff_ffff;
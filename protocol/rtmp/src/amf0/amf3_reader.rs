@@ -0,0 +1,489 @@
+use {
+    super::{
+        amf3_markers,
+        define::{Amf3Object, Amf3Traits, Amf3ValueType},
+        errors::{Amf3ReadError, Amf3ReadErrorValue},
+    },
+    byteorder::BigEndian,
+    bytesio::bytes_reader::BytesReader,
+    indexmap::IndexMap,
+};
+
+/* The strings, the traits and the complex values(objects, arrays, dates, xmls,
+byte arrays, vectors and dictionaries) which have been read can be referenced
+by the following ones, a new reader is used for each AMF3 context. */
+pub struct Amf3Reader {
+    reader: BytesReader,
+    strings: Vec<String>,
+    traits: Vec<Amf3Traits>,
+    //none if the value is still being read(a circular reference)
+    objects: Vec<Option<Amf3ValueType>>,
+}
+
+//the U29 value is a reference(the low bit is 0) or an inline value
+enum U29Value {
+    Reference(usize),
+    Inline(u32),
+}
+
+impl Amf3Reader {
+    pub fn new(reader: BytesReader) -> Self {
+        Self {
+            reader,
+            strings: Vec::new(),
+            traits: Vec::new(),
+            objects: Vec::new(),
+        }
+    }
+
+    //return the remaining bytes after the AMF3 value
+    pub fn into_reader(self) -> BytesReader {
+        self.reader
+    }
+
+    pub fn read_all(&mut self) -> Result<Vec<Amf3ValueType>, Amf3ReadError> {
+        let mut results = vec![];
+        while !self.reader.is_empty() {
+            results.push(self.read_any()?);
+        }
+        Ok(results)
+    }
+
+    pub fn read_any(&mut self) -> Result<Amf3ValueType, Amf3ReadError> {
+        let marker = self.reader.read_u8()?;
+
+        match marker {
+            amf3_markers::UNDEFINED => Ok(Amf3ValueType::Undefined),
+            amf3_markers::NULL => Ok(Amf3ValueType::Null),
+            amf3_markers::FALSE => Ok(Amf3ValueType::Boolean(false)),
+            amf3_markers::TRUE => Ok(Amf3ValueType::Boolean(true)),
+            amf3_markers::INTEGER => self.read_integer(),
+            amf3_markers::DOUBLE => Ok(Amf3ValueType::Double(self.reader.read_f64::<BigEndian>()?)),
+            amf3_markers::STRING => Ok(Amf3ValueType::UTF8String(self.read_raw_string()?)),
+            amf3_markers::XML_DOC => self.read_xml(true),
+            amf3_markers::DATE => self.read_date(),
+            amf3_markers::ARRAY => self.read_array(),
+            amf3_markers::OBJECT => self.read_object(),
+            amf3_markers::XML => self.read_xml(false),
+            amf3_markers::BYTE_ARRAY => self.read_byte_array(),
+            amf3_markers::VECTOR_INT
+            | amf3_markers::VECTOR_UINT
+            | amf3_markers::VECTOR_DOUBLE
+            | amf3_markers::VECTOR_OBJECT => self.read_vector(marker),
+            amf3_markers::DICTIONARY => self.read_dictionary(),
+            _ => Err(Amf3ReadError {
+                value: Amf3ReadErrorValue::UnknownMarker { marker },
+            }),
+        }
+    }
+
+    //1 to 4 bytes, the first 3 bytes carry 7 bits each and the last one 8 bits
+    pub fn read_u29(&mut self) -> Result<u32, Amf3ReadError> {
+        let mut value: u32 = 0;
+        for _ in 0..3 {
+            let byte = self.reader.read_u8()?;
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        let byte = self.reader.read_u8()?;
+        Ok((value << 8) | byte as u32)
+    }
+
+    fn read_u29_value(&mut self) -> Result<U29Value, Amf3ReadError> {
+        let value = self.read_u29()?;
+        if value & 0x01 == 0 {
+            Ok(U29Value::Reference((value >> 1) as usize))
+        } else {
+            Ok(U29Value::Inline(value >> 1))
+        }
+    }
+
+    pub fn read_integer(&mut self) -> Result<Amf3ValueType, Amf3ReadError> {
+        let value = self.read_u29()?;
+        //sign extend the 29-bit integer
+        let value = ((value << 3) as i32) >> 3;
+        Ok(Amf3ValueType::Integer(value))
+    }
+
+    pub fn read_raw_string(&mut self) -> Result<String, Amf3ReadError> {
+        match self.read_u29_value()? {
+            U29Value::Reference(index) => match self.strings.get(index) {
+                Some(val) => Ok(val.clone()),
+                None => Err(Amf3ReadError {
+                    value: Amf3ReadErrorValue::InvalidStringReference(index),
+                }),
+            },
+            U29Value::Inline(length) => {
+                let bytes = self.reader.read_bytes(length as usize)?;
+                let val = String::from_utf8(bytes.to_vec())?;
+                //the empty string is never sent by reference
+                if !val.is_empty() {
+                    self.strings.push(val.clone());
+                }
+                Ok(val)
+            }
+        }
+    }
+
+    fn get_object(&self, index: usize) -> Result<Amf3ValueType, Amf3ReadError> {
+        match self.objects.get(index) {
+            Some(Some(val)) => Ok(val.clone()),
+            _ => Err(Amf3ReadError {
+                value: Amf3ReadErrorValue::InvalidObjectReference(index),
+            }),
+        }
+    }
+
+    //reserve the slot in the object table before reading the members
+    fn reserve_object(&mut self) -> usize {
+        self.objects.push(None);
+        self.objects.len() - 1
+    }
+
+    fn set_object(&mut self, index: usize, value: &Amf3ValueType) {
+        self.objects[index] = Some(value.clone());
+    }
+
+    pub fn read_xml(&mut self, is_document: bool) -> Result<Amf3ValueType, Amf3ReadError> {
+        let length = match self.read_u29_value()? {
+            U29Value::Reference(index) => return self.get_object(index),
+            U29Value::Inline(length) => length,
+        };
+
+        let bytes = self.reader.read_bytes(length as usize)?;
+        let val = String::from_utf8(bytes.to_vec())?;
+        let value = if is_document {
+            Amf3ValueType::XmlDocument(val)
+        } else {
+            Amf3ValueType::Xml(val)
+        };
+
+        let index = self.reserve_object();
+        self.set_object(index, &value);
+        Ok(value)
+    }
+
+    pub fn read_date(&mut self) -> Result<Amf3ValueType, Amf3ReadError> {
+        if let U29Value::Reference(index) = self.read_u29_value()? {
+            return self.get_object(index);
+        }
+
+        let value = Amf3ValueType::Date(self.reader.read_f64::<BigEndian>()?);
+        let index = self.reserve_object();
+        self.set_object(index, &value);
+        Ok(value)
+    }
+
+    pub fn read_array(&mut self) -> Result<Amf3ValueType, Amf3ReadError> {
+        let dense_count = match self.read_u29_value()? {
+            U29Value::Reference(index) => return self.get_object(index),
+            U29Value::Inline(count) => count,
+        };
+        let index = self.reserve_object();
+
+        //the associative part ends with an empty key
+        let mut assoc = IndexMap::new();
+        loop {
+            let key = self.read_raw_string()?;
+            if key.is_empty() {
+                break;
+            }
+            let val = self.read_any()?;
+            assoc.insert(key, val);
+        }
+
+        let mut dense = Vec::new();
+        for _ in 0..dense_count {
+            dense.push(self.read_any()?);
+        }
+
+        let value = Amf3ValueType::Array { assoc, dense };
+        self.set_object(index, &value);
+        Ok(value)
+    }
+
+    fn read_traits(&mut self, flags: u32) -> Result<Amf3Traits, Amf3ReadError> {
+        //the low bit of the flags is 0 if it is a traits reference
+        if flags & 0x01 == 0 {
+            let index = (flags >> 1) as usize;
+            return match self.traits.get(index) {
+                Some(traits) => Ok(traits.clone()),
+                None => Err(Amf3ReadError {
+                    value: Amf3ReadErrorValue::InvalidTraitsReference(index),
+                }),
+            };
+        }
+
+        let externalizable = flags & 0x02 != 0;
+        let dynamic = flags & 0x04 != 0;
+        let sealed_count = flags >> 3;
+
+        let class_name = self.read_raw_string()?;
+        let mut sealed_names = Vec::new();
+        if !externalizable {
+            for _ in 0..sealed_count {
+                sealed_names.push(self.read_raw_string()?);
+            }
+        }
+
+        let traits = Amf3Traits {
+            class_name,
+            dynamic,
+            externalizable,
+            sealed_names,
+        };
+        self.traits.push(traits.clone());
+        Ok(traits)
+    }
+
+    pub fn read_object(&mut self) -> Result<Amf3ValueType, Amf3ReadError> {
+        let flags = match self.read_u29_value()? {
+            U29Value::Reference(index) => return self.get_object(index),
+            U29Value::Inline(flags) => flags,
+        };
+        let traits = self.read_traits(flags)?;
+        //the content is only known by the class itself
+        if traits.externalizable {
+            return Err(Amf3ReadError {
+                value: Amf3ReadErrorValue::UnsupportedExternalizable(traits.class_name),
+            });
+        }
+        let index = self.reserve_object();
+
+        let mut sealed_properties = IndexMap::new();
+        for name in traits.sealed_names {
+            let val = self.read_any()?;
+            sealed_properties.insert(name, val);
+        }
+
+        let mut dynamic_properties = IndexMap::new();
+        if traits.dynamic {
+            loop {
+                let key = self.read_raw_string()?;
+                if key.is_empty() {
+                    break;
+                }
+                let val = self.read_any()?;
+                dynamic_properties.insert(key, val);
+            }
+        }
+
+        let value = Amf3ValueType::Object(Amf3Object {
+            class_name: traits.class_name,
+            dynamic: traits.dynamic,
+            sealed_properties,
+            dynamic_properties,
+        });
+        self.set_object(index, &value);
+        Ok(value)
+    }
+
+    pub fn read_byte_array(&mut self) -> Result<Amf3ValueType, Amf3ReadError> {
+        let length = match self.read_u29_value()? {
+            U29Value::Reference(index) => return self.get_object(index),
+            U29Value::Inline(length) => length,
+        };
+
+        let bytes = self.reader.read_bytes(length as usize)?;
+        let value = Amf3ValueType::ByteArray(bytes.to_vec());
+        let index = self.reserve_object();
+        self.set_object(index, &value);
+        Ok(value)
+    }
+
+    pub fn read_vector(&mut self, marker: u8) -> Result<Amf3ValueType, Amf3ReadError> {
+        let count = match self.read_u29_value()? {
+            U29Value::Reference(index) => return self.get_object(index),
+            U29Value::Inline(count) => count,
+        };
+        let fixed = self.reader.read_u8()? != 0;
+        let index = self.reserve_object();
+
+        let value = match marker {
+            amf3_markers::VECTOR_INT => {
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.reader.read_u32::<BigEndian>()? as i32);
+                }
+                Amf3ValueType::VectorInt { fixed, values }
+            }
+            amf3_markers::VECTOR_UINT => {
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.reader.read_u32::<BigEndian>()?);
+                }
+                Amf3ValueType::VectorUint { fixed, values }
+            }
+            amf3_markers::VECTOR_DOUBLE => {
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.reader.read_f64::<BigEndian>()?);
+                }
+                Amf3ValueType::VectorDouble { fixed, values }
+            }
+            _ => {
+                let type_name = self.read_raw_string()?;
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.read_any()?);
+                }
+                Amf3ValueType::VectorObject {
+                    fixed,
+                    type_name,
+                    values,
+                }
+            }
+        };
+
+        self.set_object(index, &value);
+        Ok(value)
+    }
+
+    pub fn read_dictionary(&mut self) -> Result<Amf3ValueType, Amf3ReadError> {
+        let count = match self.read_u29_value()? {
+            U29Value::Reference(index) => return self.get_object(index),
+            U29Value::Inline(count) => count,
+        };
+        let weak_keys = self.reader.read_u8()? != 0;
+        let index = self.reserve_object();
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let key = self.read_any()?;
+            let val = self.read_any()?;
+            entries.push((key, val));
+        }
+
+        let value = Amf3ValueType::Dictionary { weak_keys, entries };
+        self.set_object(index, &value);
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::amf3_writer::Amf3Writer;
+    use super::super::define::{Amf3Object, Amf3ValueType};
+    use super::Amf3Reader;
+
+    use bytes::BytesMut;
+    use bytesio::bytes_reader::BytesReader;
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_amf3_reader() {
+        let data: [u8; 43] = [
+            0x04, 0xff, 0xff, 0xff, 0xff, //integer -1
+            0x06, 0x07, 0x61, 0x70, 0x70, //string "app"
+            0x06, 0x00, //string "app" by reference
+            //anonymous dynamic object {app: "live", fpad: false}
+            0x0a, 0x0b, 0x01, 0x00, 0x06, 0x09, 0x6c, 0x69, 0x76, 0x65, 0x09, 0x66, 0x70, 0x61,
+            0x64, 0x02, 0x01, //
+            //array ["live", <the object above by reference>]
+            0x09, 0x05, 0x01, 0x06, 0x02, 0x0a, 0x00, //
+            0x0c, 0x05, 0x01, 0x02, //byte array
+            0x04, 0x80, 0x00, //integer 0 with a redundant byte
+        ];
+
+        let mut amf_reader = Amf3Reader::new(BytesReader::new(BytesMut::from(&data[..])));
+        let values = amf_reader.read_all().unwrap();
+
+        let mut dynamic_properties = IndexMap::new();
+        dynamic_properties.insert(
+            String::from("app"),
+            Amf3ValueType::UTF8String(String::from("live")),
+        );
+        dynamic_properties.insert(String::from("fpad"), Amf3ValueType::Boolean(false));
+        let object = Amf3ValueType::Object(Amf3Object {
+            class_name: String::new(),
+            dynamic: true,
+            sealed_properties: IndexMap::new(),
+            dynamic_properties,
+        });
+
+        assert_eq!(
+            values,
+            vec![
+                Amf3ValueType::Integer(-1),
+                Amf3ValueType::UTF8String(String::from("app")),
+                Amf3ValueType::UTF8String(String::from("app")),
+                object.clone(),
+                Amf3ValueType::Array {
+                    assoc: IndexMap::new(),
+                    dense: vec![Amf3ValueType::UTF8String(String::from("live")), object],
+                },
+                Amf3ValueType::ByteArray(vec![1, 2]),
+                Amf3ValueType::Integer(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_amf3_writer() {
+        let mut sealed_properties = IndexMap::new();
+        sealed_properties.insert(String::from("width"), Amf3ValueType::Integer(1920));
+        sealed_properties.insert(String::from("height"), Amf3ValueType::Integer(1080));
+        let object = Amf3ValueType::Object(Amf3Object {
+            class_name: String::from("VideoSize"),
+            dynamic: false,
+            sealed_properties,
+            dynamic_properties: IndexMap::new(),
+        });
+
+        let array = |first: Amf3ValueType| {
+            let mut assoc = IndexMap::new();
+            assoc.insert(
+                String::from("name"),
+                Amf3ValueType::UTF8String(String::from("width")),
+            );
+            Amf3ValueType::Array {
+                assoc,
+                dense: vec![
+                    first,
+                    Amf3ValueType::Integer(-0x1000_0000),
+                    Amf3ValueType::Date(1.5e12),
+                ],
+            }
+        };
+        let others = vec![
+            Amf3ValueType::VectorInt {
+                fixed: true,
+                values: vec![-1, 2],
+            },
+            Amf3ValueType::Dictionary {
+                weak_keys: false,
+                entries: vec![(Amf3ValueType::Integer(1), Amf3ValueType::Null)],
+            },
+        ];
+
+        let mut values = vec![
+            object.clone(),
+            //the traits and the strings are sent by reference
+            object.clone(),
+            array(Amf3ValueType::Integer(0x1000_0000)),
+        ];
+        values.extend(others.clone());
+
+        let mut amf_writer = Amf3Writer::new();
+        amf_writer.write_anys(&values).unwrap();
+        let bytes = amf_writer.extract_current_bytes();
+        //the second object is the traits reference(0x01) and 2 integers(3 bytes each)
+        assert!(bytes
+            .windows(8)
+            .any(|w| w == [0x0a, 0x01, 0x04, 0x8f, 0x00, 0x04, 0x88, 0x38]));
+
+        //the integer out of the 29-bit range is written as a double
+        let mut expected = vec![
+            object.clone(),
+            object,
+            array(Amf3ValueType::Double(268435456.0)),
+        ];
+        expected.extend(others);
+
+        let mut amf_reader = Amf3Reader::new(BytesReader::new(bytes));
+        assert_eq!(amf_reader.read_all().unwrap(), expected);
+    }
+}
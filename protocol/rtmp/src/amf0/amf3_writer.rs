@@ -0,0 +1,267 @@
+use {
+    super::{
+        amf3_markers,
+        define::{Amf3Object, Amf3Traits, Amf3ValueType},
+        errors::{Amf3WriteError, Amf3WriteErrorValue},
+    },
+    byteorder::BigEndian,
+    bytes::BytesMut,
+    bytesio::bytes_writer::BytesWriter,
+    indexmap::IndexMap,
+    std::collections::HashMap,
+};
+
+/* The repeated strings and traits are written by reference, the complex
+values are always written inline because the identities of them are not
+kept in Amf3ValueType. */
+#[derive(Default)]
+pub struct Amf3Writer {
+    writer: BytesWriter,
+    strings: HashMap<String, usize>,
+    traits: HashMap<Amf3Traits, usize>,
+}
+
+impl Amf3Writer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write_anys(&mut self, values: &[Amf3ValueType]) -> Result<(), Amf3WriteError> {
+        for val in values {
+            self.write_any(val)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_any(&mut self, value: &Amf3ValueType) -> Result<(), Amf3WriteError> {
+        match value {
+            Amf3ValueType::Undefined => self.write_marker(amf3_markers::UNDEFINED),
+            Amf3ValueType::Null => self.write_marker(amf3_markers::NULL),
+            Amf3ValueType::Boolean(false) => self.write_marker(amf3_markers::FALSE),
+            Amf3ValueType::Boolean(true) => self.write_marker(amf3_markers::TRUE),
+            Amf3ValueType::Integer(val) => self.write_integer(*val),
+            Amf3ValueType::Double(val) => self.write_double(*val),
+            Amf3ValueType::UTF8String(val) => {
+                self.write_marker(amf3_markers::STRING)?;
+                self.write_raw_string(val)
+            }
+            Amf3ValueType::XmlDocument(val) => self.write_xml(amf3_markers::XML_DOC, val),
+            Amf3ValueType::Date(val) => {
+                self.write_marker(amf3_markers::DATE)?;
+                self.write_inline_u29(0)?;
+                self.writer.write_f64::<BigEndian>(*val)?;
+                Ok(())
+            }
+            Amf3ValueType::Array { assoc, dense } => self.write_array(assoc, dense),
+            Amf3ValueType::Object(object) => self.write_object(object),
+            Amf3ValueType::Xml(val) => self.write_xml(amf3_markers::XML, val),
+            Amf3ValueType::ByteArray(val) => {
+                self.write_marker(amf3_markers::BYTE_ARRAY)?;
+                self.write_inline_u29(val.len())?;
+                self.writer.write(val)?;
+                Ok(())
+            }
+            Amf3ValueType::VectorInt { fixed, values } => {
+                self.write_vector_header(amf3_markers::VECTOR_INT, values.len(), *fixed)?;
+                for val in values {
+                    self.writer.write_u32::<BigEndian>(*val as u32)?;
+                }
+                Ok(())
+            }
+            Amf3ValueType::VectorUint { fixed, values } => {
+                self.write_vector_header(amf3_markers::VECTOR_UINT, values.len(), *fixed)?;
+                for val in values {
+                    self.writer.write_u32::<BigEndian>(*val)?;
+                }
+                Ok(())
+            }
+            Amf3ValueType::VectorDouble { fixed, values } => {
+                self.write_vector_header(amf3_markers::VECTOR_DOUBLE, values.len(), *fixed)?;
+                for val in values {
+                    self.writer.write_f64::<BigEndian>(*val)?;
+                }
+                Ok(())
+            }
+            Amf3ValueType::VectorObject {
+                fixed,
+                type_name,
+                values,
+            } => {
+                self.write_vector_header(amf3_markers::VECTOR_OBJECT, values.len(), *fixed)?;
+                self.write_raw_string(type_name)?;
+                self.write_anys(values)
+            }
+            Amf3ValueType::Dictionary { weak_keys, entries } => {
+                self.write_marker(amf3_markers::DICTIONARY)?;
+                self.write_inline_u29(entries.len())?;
+                self.writer.write_u8(*weak_keys as u8)?;
+                for (key, val) in entries {
+                    self.write_any(key)?;
+                    self.write_any(val)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn write_marker(&mut self, marker: u8) -> Result<(), Amf3WriteError> {
+        self.writer.write_u8(marker)?;
+        Ok(())
+    }
+
+    pub fn write_u29(&mut self, value: u32) -> Result<(), Amf3WriteError> {
+        if value < 0x80 {
+            self.writer.write_u8(value as u8)?;
+        } else if value < 0x4000 {
+            self.writer.write_u8(((value >> 7) | 0x80) as u8)?;
+            self.writer.write_u8((value & 0x7f) as u8)?;
+        } else if value < 0x20_0000 {
+            self.writer.write_u8(((value >> 14) | 0x80) as u8)?;
+            self.writer.write_u8((((value >> 7) & 0x7f) | 0x80) as u8)?;
+            self.writer.write_u8((value & 0x7f) as u8)?;
+        } else if value <= amf3_markers::U29_MAX {
+            self.writer.write_u8(((value >> 22) | 0x80) as u8)?;
+            self.writer
+                .write_u8((((value >> 15) & 0x7f) | 0x80) as u8)?;
+            self.writer.write_u8((((value >> 8) & 0x7f) | 0x80) as u8)?;
+            self.writer.write_u8((value & 0xff) as u8)?;
+        } else {
+            return Err(Amf3WriteError {
+                value: Amf3WriteErrorValue::LengthTooLong(value as usize),
+            });
+        }
+        Ok(())
+    }
+
+    //the length/count(or the flags) with the low bit 1
+    fn write_inline_u29(&mut self, value: usize) -> Result<(), Amf3WriteError> {
+        if value > (amf3_markers::U29_MAX >> 1) as usize {
+            return Err(Amf3WriteError {
+                value: Amf3WriteErrorValue::LengthTooLong(value),
+            });
+        }
+        self.write_u29(((value as u32) << 1) | 0x01)
+    }
+
+    pub fn write_integer(&mut self, value: i32) -> Result<(), Amf3WriteError> {
+        if !(amf3_markers::INTEGER_MIN..=amf3_markers::INTEGER_MAX).contains(&value) {
+            return self.write_double(value as f64);
+        }
+        self.write_marker(amf3_markers::INTEGER)?;
+        self.write_u29(value as u32 & amf3_markers::U29_MAX)
+    }
+
+    pub fn write_double(&mut self, value: f64) -> Result<(), Amf3WriteError> {
+        self.write_marker(amf3_markers::DOUBLE)?;
+        self.writer.write_f64::<BigEndian>(value)?;
+        Ok(())
+    }
+
+    pub fn write_raw_string(&mut self, value: &str) -> Result<(), Amf3WriteError> {
+        if let Some(index) = self.strings.get(value) {
+            return self.write_u29((*index as u32) << 1);
+        }
+
+        self.write_inline_u29(value.len())?;
+        self.writer.write(value.as_bytes())?;
+        //the empty string is never sent by reference
+        if !value.is_empty() {
+            self.strings.insert(value.to_string(), self.strings.len());
+        }
+        Ok(())
+    }
+
+    fn write_xml(&mut self, marker: u8, value: &str) -> Result<(), Amf3WriteError> {
+        self.write_marker(marker)?;
+        self.write_inline_u29(value.len())?;
+        self.writer.write(value.as_bytes())?;
+        Ok(())
+    }
+
+    fn write_properties(
+        &mut self,
+        properties: &IndexMap<String, Amf3ValueType>,
+    ) -> Result<(), Amf3WriteError> {
+        for (key, val) in properties {
+            self.write_raw_string(key)?;
+            self.write_any(val)?;
+        }
+        //end with an empty key
+        self.write_raw_string("")
+    }
+
+    pub fn write_array(
+        &mut self,
+        assoc: &IndexMap<String, Amf3ValueType>,
+        dense: &[Amf3ValueType],
+    ) -> Result<(), Amf3WriteError> {
+        self.write_marker(amf3_markers::ARRAY)?;
+        self.write_inline_u29(dense.len())?;
+        self.write_properties(assoc)?;
+        self.write_anys(dense)
+    }
+
+    pub fn write_object(&mut self, object: &Amf3Object) -> Result<(), Amf3WriteError> {
+        self.write_marker(amf3_markers::OBJECT)?;
+
+        let traits = Amf3Traits {
+            class_name: object.class_name.clone(),
+            dynamic: object.dynamic,
+            externalizable: false,
+            sealed_names: object.sealed_properties.keys().cloned().collect(),
+        };
+
+        if let Some(index) = self.traits.get(&traits) {
+            //U29O-traits-ref: the index and 0b01
+            self.write_u29(((*index as u32) << 2) | 0x01)?;
+        } else {
+            let sealed_count = traits.sealed_names.len();
+            if sealed_count > (amf3_markers::U29_MAX >> 4) as usize {
+                return Err(Amf3WriteError {
+                    value: Amf3WriteErrorValue::LengthTooLong(sealed_count),
+                });
+            }
+            //U29O-traits: the sealed member count, dynamic, not externalizable and 0b011
+            let flags = ((sealed_count as u32) << 4) | ((traits.dynamic as u32) << 3) | 0x03;
+            self.write_u29(flags)?;
+            self.write_raw_string(&traits.class_name)?;
+            for name in &traits.sealed_names {
+                self.write_raw_string(name)?;
+            }
+            self.traits.insert(traits, self.traits.len());
+        }
+
+        for val in object.sealed_properties.values() {
+            self.write_any(val)?;
+        }
+        if object.dynamic {
+            self.write_properties(&object.dynamic_properties)?;
+        }
+        Ok(())
+    }
+
+    fn write_vector_header(
+        &mut self,
+        marker: u8,
+        count: usize,
+        fixed: bool,
+    ) -> Result<(), Amf3WriteError> {
+        self.write_marker(marker)?;
+        self.write_inline_u29(count)?;
+        self.writer.write_u8(fixed as u8)?;
+        Ok(())
+    }
+
+    pub fn extract_current_bytes(&mut self) -> BytesMut {
+        self.writer.extract_current_bytes()
+    }
+
+    pub fn len(&self) -> usize {
+        self.writer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -8,10 +8,111 @@ pub enum Amf0ValueType {
     Object(IndexMap<String, Amf0ValueType>),
     Null,
     EcmaArray(IndexMap<String, Amf0ValueType>),
+    StrictArray(Vec<Amf0ValueType>),
     LongUTF8String(String),
+    //the AMF3 value which has no AMF0 counterpart, e.g. ByteArray
+    AvmPlus(Box<Amf3ValueType>),
     END,
 }
 
+#[derive(PartialEq, Clone, Debug)]
+pub enum Amf3ValueType {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Integer(i32),
+    Double(f64),
+    UTF8String(String),
+    XmlDocument(String),
+    //milliseconds since the epoch(UTC)
+    Date(f64),
+    Array {
+        assoc: IndexMap<String, Amf3ValueType>,
+        dense: Vec<Amf3ValueType>,
+    },
+    Object(Amf3Object),
+    Xml(String),
+    ByteArray(Vec<u8>),
+    VectorInt {
+        fixed: bool,
+        values: Vec<i32>,
+    },
+    VectorUint {
+        fixed: bool,
+        values: Vec<u32>,
+    },
+    VectorDouble {
+        fixed: bool,
+        values: Vec<f64>,
+    },
+    VectorObject {
+        fixed: bool,
+        type_name: String,
+        values: Vec<Amf3ValueType>,
+    },
+    Dictionary {
+        weak_keys: bool,
+        entries: Vec<(Amf3ValueType, Amf3ValueType)>,
+    },
+}
+
+//the class name is empty for the anonymous objects
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Amf3Object {
+    pub class_name: String,
+    pub dynamic: bool,
+    pub sealed_properties: IndexMap<String, Amf3ValueType>,
+    pub dynamic_properties: IndexMap<String, Amf3ValueType>,
+}
+
+//the class definition shared by the objects of the same class
+#[derive(PartialEq, Eq, Hash, Clone, Debug, Default)]
+pub struct Amf3Traits {
+    pub class_name: String,
+    pub dynamic: bool,
+    pub externalizable: bool,
+    pub sealed_names: Vec<String>,
+}
+
+impl From<Amf3ValueType> for Amf0ValueType {
+    fn from(value: Amf3ValueType) -> Self {
+        match value {
+            Amf3ValueType::Undefined | Amf3ValueType::Null => Amf0ValueType::Null,
+            Amf3ValueType::Boolean(val) => Amf0ValueType::Boolean(val),
+            Amf3ValueType::Integer(val) => Amf0ValueType::Number(val as f64),
+            Amf3ValueType::Double(val) => Amf0ValueType::Number(val),
+            Amf3ValueType::UTF8String(val) => {
+                if val.len() > u16::MAX as usize {
+                    Amf0ValueType::LongUTF8String(val)
+                } else {
+                    Amf0ValueType::UTF8String(val)
+                }
+            }
+            Amf3ValueType::Array { assoc, dense } => {
+                let dense = dense.into_iter().map(Amf0ValueType::from);
+                if assoc.is_empty() {
+                    Amf0ValueType::StrictArray(dense.collect())
+                } else {
+                    //the dense part is keyed by the index like the ECMA arrays
+                    let mut properties: IndexMap<String, Amf0ValueType> =
+                        dense.enumerate().map(|(i, v)| (i.to_string(), v)).collect();
+                    properties.extend(assoc.into_iter().map(|(k, v)| (k, v.into())));
+                    Amf0ValueType::EcmaArray(properties)
+                }
+            }
+            Amf3ValueType::Object(object) => Amf0ValueType::Object(
+                object
+                    .sealed_properties
+                    .into_iter()
+                    .chain(object.dynamic_properties)
+                    .map(|(k, v)| (k, v.into()))
+                    .collect(),
+            ),
+            others => Amf0ValueType::AvmPlus(Box::new(others)),
+        }
+    }
+}
+
 // pub struct Amf0Object {
 //     pub key: String,
 //     pub value: Amf0ValueType,
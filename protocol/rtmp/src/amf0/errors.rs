@@ -16,6 +16,8 @@ pub enum Amf0ReadErrorValue {
     BytesReadError(BytesReadError),
     #[fail(display = "wrong type")]
     WrongType,
+    #[fail(display = "amf3 read error: {}\n", _0)]
+    Amf3ReadError(Amf3ReadError),
 }
 
 #[derive(Debug)]
@@ -39,6 +41,14 @@ impl From<BytesReadError> for Amf0ReadError {
     }
 }
 
+impl From<Amf3ReadError> for Amf0ReadError {
+    fn from(error: Amf3ReadError) -> Self {
+        Amf0ReadError {
+            value: Amf0ReadErrorValue::Amf3ReadError(error),
+        }
+    }
+}
+
 #[derive(Debug, Fail)]
 pub enum Amf0WriteErrorValue {
     #[fail(display = "normal string too long")]
@@ -47,6 +57,8 @@ pub enum Amf0WriteErrorValue {
     BufferWriteError(io::Error),
     #[fail(display = "bytes write error\n")]
     BytesWriteError(BytesWriteError),
+    #[fail(display = "amf3 write error: {}\n", _0)]
+    Amf3WriteError(Amf3WriteError),
 }
 
 #[derive(Debug)]
@@ -70,6 +82,80 @@ impl From<BytesWriteError> for Amf0WriteError {
     }
 }
 
+impl From<Amf3WriteError> for Amf0WriteError {
+    fn from(error: Amf3WriteError) -> Self {
+        Amf0WriteError {
+            value: Amf0WriteErrorValue::Amf3WriteError(error),
+        }
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum Amf3ReadErrorValue {
+    #[fail(display = "Encountered unknown marker: {}\n", marker)]
+    UnknownMarker { marker: u8 },
+    #[fail(display = "parser string error: {}\n", _0)]
+    StringParseError(#[cause] string::FromUtf8Error),
+    #[fail(display = "bytes read error :{}\n", _0)]
+    BytesReadError(BytesReadError),
+    #[fail(display = "invalid string reference: {}\n", _0)]
+    InvalidStringReference(usize),
+    #[fail(display = "invalid object reference: {}\n", _0)]
+    InvalidObjectReference(usize),
+    #[fail(display = "invalid traits reference: {}\n", _0)]
+    InvalidTraitsReference(usize),
+    #[fail(display = "the externalizable class is not supported: {}\n", _0)]
+    UnsupportedExternalizable(String),
+}
+
+#[derive(Debug)]
+pub struct Amf3ReadError {
+    pub value: Amf3ReadErrorValue,
+}
+
+impl From<Amf3ReadErrorValue> for Amf3ReadError {
+    fn from(val: Amf3ReadErrorValue) -> Self {
+        Amf3ReadError { value: val }
+    }
+}
+
+impl From<string::FromUtf8Error> for Amf3ReadError {
+    fn from(error: string::FromUtf8Error) -> Self {
+        Amf3ReadError {
+            value: Amf3ReadErrorValue::StringParseError(error),
+        }
+    }
+}
+
+impl From<BytesReadError> for Amf3ReadError {
+    fn from(error: BytesReadError) -> Self {
+        Amf3ReadError {
+            value: Amf3ReadErrorValue::BytesReadError(error),
+        }
+    }
+}
+
+#[derive(Debug, Fail)]
+pub enum Amf3WriteErrorValue {
+    #[fail(display = "the length is larger than 2^29-1: {}\n", _0)]
+    LengthTooLong(usize),
+    #[fail(display = "bytes write error\n")]
+    BytesWriteError(BytesWriteError),
+}
+
+#[derive(Debug)]
+pub struct Amf3WriteError {
+    pub value: Amf3WriteErrorValue,
+}
+
+impl From<BytesWriteError> for Amf3WriteError {
+    fn from(error: BytesWriteError) -> Self {
+        Amf3WriteError {
+            value: Amf3WriteErrorValue::BytesWriteError(error),
+        }
+    }
+}
+
 impl fmt::Display for Amf0ReadError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Display::fmt(&self.value, f)
@@ -101,3 +187,35 @@ impl Fail for Amf0WriteError {
         self.value.backtrace()
     }
 }
+
+impl fmt::Display for Amf3ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for Amf3ReadError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
+
+impl fmt::Display for Amf3WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for Amf3WriteError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
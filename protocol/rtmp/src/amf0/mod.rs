@@ -3,9 +3,12 @@ pub mod amf0_writer;
 pub mod define;
 pub mod errors;
 pub mod amf0_markers;
+pub mod amf3_markers;
+pub mod amf3_reader;
+pub mod amf3_writer;
 
 
 
-pub use self::errors::{Amf0ReadError,Amf0WriteError};
-pub use self::define::Amf0ValueType;
+pub use self::errors::{Amf0ReadError,Amf0WriteError,Amf3ReadError,Amf3WriteError};
+pub use self::define::{Amf0ValueType,Amf3ValueType};
 //pub use self::amf0_markers::;
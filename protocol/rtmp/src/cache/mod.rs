@@ -147,16 +147,24 @@ impl Cache {
         let mut reader = BytesReader::new(chunk_body.clone());
         let tag_header = VideoTagHeader::unmarshal(&mut reader)?;
 
-        let is_key_frame = tag_header.frame_type == define::frame_type::KEY_FRAME;
+        let is_key_frame = tag_header.is_key_frame();
         self.gops.save_frame_data(channel_data, is_key_frame);
 
-        if is_key_frame && tag_header.avc_packet_type == define::avc_packet_type::AVC_SEQHDR {
-            let mut avc_processor = Mpeg4AvcProcessor::default();
-            avc_processor.decoder_configuration_record_load(&mut reader)?;
-
-            self.av_statistics
-                .notify_video_codec_info(&avc_processor.mpeg4_avc)
-                .await;
+        if is_key_frame && tag_header.is_seq_header() {
+            match tag_header.codec() {
+                define::AvcCodecId::H264 => {
+                    let mut avc_processor = Mpeg4AvcProcessor::default();
+                    avc_processor.decoder_configuration_record_load(&mut reader)?;
+
+                    self.av_statistics
+                        .notify_video_codec_info(&avc_processor.mpeg4_avc)
+                        .await;
+                }
+                //hevc/av1/vp9 from the legacy or enhanced rtmp, cached as they are
+                codec => {
+                    self.av_statistics.notify_video_codec_id(codec).await;
+                }
+            }
 
             self.video_seq = chunk_body.clone();
             self.video_timestamp = timestamp;
@@ -4,7 +4,7 @@ use {
         errors::{MessageError, MessageErrorValue},
     },
     crate::{
-        amf0::{amf0_markers, amf0_reader::Amf0Reader},
+        amf0::{amf0_markers, amf0_reader::Amf0Reader, amf0_writer::Amf0Writer},
         chunk::ChunkInfo,
         protocol_control_messages::reader::ProtocolControlMessageReader,
         user_control_messages::reader::EventMessagesReader,
@@ -26,6 +26,8 @@ impl MessageParser {
 
         match self.chunk_info.message_header.msg_type_id {
             msg_type_id::COMMAND_AMF0 | msg_type_id::COMMAND_AMF3 => {
+                /*the AMF3 command starts with a format byte(0), the values are
+                AMF0 encoded and each one can switch to AMF3 by the avmplus marker*/
                 if self.chunk_info.message_header.msg_type_id == msg_type_id::COMMAND_AMF3 {
                     reader.read_u8()?;
                 }
@@ -109,7 +111,21 @@ impl MessageParser {
                     ProtocolControlMessageReader::new(reader).read_set_peer_bandwidth()?;
                 return Ok(RtmpMessageData::SetPeerBandwidth { properties });
             }
-            msg_type_id::DATA_AMF0 | msg_type_id::DATA_AMF3 => {
+            msg_type_id::DATA_AMF3 => {
+                /*skip the format byte(0) which some encoders omit, and convert the AMF3 values
+                to the AMF0 ones, then the metadata can be cached and sent as the AMF0 data*/
+                if !reader.is_empty() && reader.get(0)? == 0 {
+                    reader.read_u8()?;
+                }
+                let values = Amf0Reader::new(reader).read_all()?;
+                let mut amf_writer = Amf0Writer::new();
+                amf_writer.write_anys(&values)?;
+
+                return Ok(RtmpMessageData::AmfData {
+                    raw_data: amf_writer.extract_current_bytes(),
+                });
+            }
+            msg_type_id::DATA_AMF0 => {
                 //let values = Amf0Reader::new(reader).read_all()?;
                 return Ok(RtmpMessageData::AmfData {
                     raw_data: reader.extract_remaining_bytes(),
@@ -192,4 +208,110 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_amf3_message_parse() {
+        use crate::amf0::{
+            amf0_writer::Amf0Writer,
+            define::{Amf3Object, Amf3ValueType},
+            Amf0ValueType,
+        };
+        use crate::chunk::define::{chunk_type, csid_type};
+        use crate::chunk::ChunkInfo;
+        use crate::messages::define::{msg_type_id, RtmpMessageData};
+        use indexmap::IndexMap;
+
+        let parse = |msg_type_id: u8, values: Vec<Amf0ValueType>, has_format_byte: bool| {
+            let mut amf_writer = Amf0Writer::new();
+            amf_writer.write_anys(&values).unwrap();
+            let mut payload = bytes::BytesMut::new();
+            if has_format_byte {
+                payload.extend_from_slice(&[0u8]);
+            }
+            payload.extend_from_slice(&amf_writer.extract_current_bytes());
+
+            let chunk_info = ChunkInfo::new(
+                csid_type::COMMAND_AMF0_AMF3,
+                chunk_type::TYPE_0,
+                0,
+                payload.len() as u32,
+                msg_type_id,
+                0,
+                payload,
+            );
+            MessageParser::new(chunk_info).parse().unwrap()
+        };
+
+        //connect with the AMF3 command object
+        let mut dynamic_properties = IndexMap::new();
+        dynamic_properties.insert(
+            String::from("app"),
+            Amf3ValueType::UTF8String(String::from("live")),
+        );
+        dynamic_properties.insert(String::from("objectEncoding"), Amf3ValueType::Integer(3));
+        let command_object = Amf0ValueType::AvmPlus(Box::new(Amf3ValueType::Object(Amf3Object {
+            class_name: String::new(),
+            dynamic: true,
+            sealed_properties: IndexMap::new(),
+            dynamic_properties,
+        })));
+
+        let message = parse(
+            msg_type_id::COMMAND_AMF3,
+            vec![
+                Amf0ValueType::UTF8String(String::from("connect")),
+                Amf0ValueType::Number(1.0),
+                command_object,
+            ],
+            true,
+        );
+        let mut properties = IndexMap::new();
+        properties.insert(
+            String::from("app"),
+            Amf0ValueType::UTF8String(String::from("live")),
+        );
+        properties.insert(String::from("objectEncoding"), Amf0ValueType::Number(3.0));
+        match message {
+            RtmpMessageData::Amf0Command {
+                command_name,
+                command_object,
+                ..
+            } => {
+                assert_eq!(
+                    command_name,
+                    Amf0ValueType::UTF8String(String::from("connect"))
+                );
+                assert_eq!(command_object, Amf0ValueType::Object(properties));
+            }
+            _ => panic!("not a command"),
+        }
+
+        //the AMF3 metadata is converted to the AMF0 one
+        let mut assoc = IndexMap::new();
+        assoc.insert(String::from("width"), Amf3ValueType::Double(1280.0));
+        let values = vec![
+            Amf0ValueType::UTF8String(String::from("onMetaData")),
+            Amf0ValueType::AvmPlus(Box::new(Amf3ValueType::Array {
+                assoc,
+                dense: Vec::new(),
+            })),
+        ];
+        let mut properties = IndexMap::new();
+        properties.insert(String::from("width"), Amf0ValueType::Number(1280.0));
+        let mut amf_writer = Amf0Writer::new();
+        amf_writer
+            .write_anys(&vec![
+                Amf0ValueType::UTF8String(String::from("onMetaData")),
+                Amf0ValueType::EcmaArray(properties),
+            ])
+            .unwrap();
+        let expected = amf_writer.extract_current_bytes();
+        //with or without the format byte
+        for has_format_byte in [true, false] {
+            match parse(msg_type_id::DATA_AMF3, values.clone(), has_format_byte) {
+                RtmpMessageData::AmfData { raw_data } => assert_eq!(raw_data, expected),
+                _ => panic!("not a data message"),
+            }
+        }
+    }
 }
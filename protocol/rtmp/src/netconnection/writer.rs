@@ -24,6 +24,7 @@ pub struct ConnectProperties {
     pub object_encoding: Option<f64>,
     pub page_url: Option<String>, // http://host/sample.html
     pub pub_type: Option<String>,
+    pub fourcc_list: Option<Vec<String>>, // enhanced rtmp video codecs, e.g.: ["av01","vp09","hvc1"]
 }
 
 impl ConnectProperties {
@@ -41,6 +42,7 @@ impl ConnectProperties {
             object_encoding: Some(0_f64),
             page_url: Some("".to_string()),
             pub_type: Some("nonprivate".to_string()),
+            fourcc_list: None,
         }
     }
     pub fn new_none() -> Self {
@@ -57,6 +59,7 @@ impl ConnectProperties {
             object_encoding: None,
             page_url: None,
             pub_type: None,
+            fourcc_list: None,
         }
     }
 }
@@ -177,6 +180,18 @@ impl NetConnection {
                 Amf0ValueType::Number(object_encoding),
             );
         }
+
+        if let Some(fourcc_list) = &properties.fourcc_list {
+            properties_map.insert(
+                String::from("fourCcList"),
+                Amf0ValueType::StrictArray(
+                    fourcc_list
+                        .iter()
+                        .map(|fourcc| Amf0ValueType::UTF8String(fourcc.clone()))
+                        .collect(),
+                ),
+            );
+        }
         self.amf0_writer.write_object(&properties_map)?;
 
         self.write_chunk().await
@@ -191,6 +206,7 @@ impl NetConnection {
         level: &str,
         description: &str,
         encoding: &f64,
+        fourcc_list: &Option<Vec<String>>,
     ) -> Result<(), NetConnectionError> {
         self.amf0_writer.write_string(&String::from("_result"))?;
         self.amf0_writer.write_number(transaction_id)?;
@@ -205,6 +221,17 @@ impl NetConnection {
             String::from("capabilities"),
             Amf0ValueType::Number(*capabilities),
         );
+        if let Some(fourcc_list) = fourcc_list {
+            properties_map_a.insert(
+                String::from("fourCcList"),
+                Amf0ValueType::StrictArray(
+                    fourcc_list
+                        .iter()
+                        .map(|fourcc| Amf0ValueType::UTF8String(fourcc.clone()))
+                        .collect(),
+                ),
+            );
+        }
 
         self.amf0_writer.write_object(&properties_map_a)?;
 
@@ -1,4 +1,5 @@
 use {
+    crate::session::errors::SessionError,
     failure::Fail,
     std::{fmt, io::Error},
     tokio::sync::broadcast::error::RecvError,
@@ -22,8 +23,16 @@ pub enum PushClientErrorValue {
 
     #[fail(display = "send error\n")]
     SendError,
-    #[fail(display = "io error\n")]
+    #[fail(display = "io error: {}\n", _0)]
     IOError(Error),
+    #[fail(display = "session error: {}\n", _0)]
+    SessionError(#[cause] SessionError),
+    #[fail(display = "invalid push rule: {}\n", _0)]
+    InvalidPushRule(String),
+    #[fail(display = "push rule: {} already exists\n", _0)]
+    PushRuleExists(String),
+    #[fail(display = "push rule: {} is not found\n", _0)]
+    PushRuleNotFound(String),
 }
 
 impl From<Error> for ClientError {
@@ -34,6 +43,14 @@ impl From<Error> for ClientError {
     }
 }
 
+impl From<SessionError> for ClientError {
+    fn from(error: SessionError) -> Self {
+        ClientError {
+            value: PushClientErrorValue::SessionError(error),
+        }
+    }
+}
+
 impl From<RecvError> for ClientError {
     fn from(error: RecvError) -> Self {
         ClientError {
@@ -1,3 +1,6 @@
+pub mod origin;
 pub mod pull_client;
 pub mod push_client;
+pub mod push_rule;
+pub mod rule_push_client;
 pub mod errors;
\ No newline at end of file
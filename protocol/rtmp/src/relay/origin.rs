@@ -0,0 +1,166 @@
+use {
+    sha2::{Digest, Sha256},
+    std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    },
+    tokio::{net::TcpStream, time::timeout},
+};
+
+//the virtual nodes of each origin on the hash ring, make the streams spread evenly
+const VIRTUAL_NODE_COUNT: usize = 100;
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+struct Origin {
+    //ip:port
+    address: String,
+    is_healthy: AtomicBool,
+}
+
+/*
+ The upstream origins of an edge node. Each stream is mapped to one origin by the
+ consistent hashing of its name, so the edges pull the same stream from the same
+ origin, and only the streams of a removed origin are remapped. The other origins
+ on the ring are the failover candidates in order.
+*/
+#[derive(Clone)]
+pub struct Origins {
+    origins: Arc<Vec<Origin>>,
+    //(hash of the virtual node, index of the origin), sorted by the hash
+    ring: Arc<Vec<(u64, usize)>>,
+}
+
+impl Origins {
+    pub fn new(addresses: Vec<String>) -> Self {
+        let mut ring = Vec::new();
+        for (index, address) in addresses.iter().enumerate() {
+            for node in 0..VIRTUAL_NODE_COUNT {
+                ring.push((Self::hash(&format!("{address}#{node}")), index));
+            }
+        }
+        ring.sort_unstable();
+
+        let origins = addresses
+            .into_iter()
+            .map(|address| Origin {
+                address,
+                is_healthy: AtomicBool::new(true),
+            })
+            .collect();
+
+        Self {
+            origins: Arc::new(origins),
+            ring: Arc::new(ring),
+        }
+    }
+
+    //the origins to pull the stream from in order, the unhealthy ones are tried at last
+    pub fn candidates(&self, key: &str) -> Vec<String> {
+        let hash = Self::hash(key);
+        let start = self
+            .ring
+            .partition_point(|(node_hash, _)| *node_hash < hash);
+
+        let mut indexes: Vec<usize> = Vec::with_capacity(self.origins.len());
+        for (_, index) in self.ring[start..].iter().chain(self.ring[..start].iter()) {
+            if !indexes.contains(index) {
+                indexes.push(*index);
+                if indexes.len() == self.origins.len() {
+                    break;
+                }
+            }
+        }
+
+        let (healthy, unhealthy): (Vec<&Origin>, Vec<&Origin>) = indexes
+            .into_iter()
+            .map(|index| &self.origins[index])
+            .partition(|origin| origin.is_healthy.load(Ordering::Relaxed));
+
+        healthy
+            .into_iter()
+            .chain(unhealthy)
+            .map(|origin| origin.address.clone())
+            .collect()
+    }
+
+    pub fn set_healthy(&self, address: &str, is_healthy: bool) {
+        for origin in self
+            .origins
+            .iter()
+            .filter(|origin| origin.address == address)
+        {
+            if origin.is_healthy.swap(is_healthy, Ordering::Relaxed) != is_healthy {
+                log::info!(
+                    "origin: {} becomes {}",
+                    address,
+                    if is_healthy { "healthy" } else { "unhealthy" }
+                );
+            }
+        }
+    }
+
+    //check whether the origins can be connected periodically
+    pub async fn check_health(&self, interval: Duration) {
+        loop {
+            for origin in self.origins.iter() {
+                let is_healthy = matches!(
+                    timeout(HEALTH_CHECK_TIMEOUT, TcpStream::connect(&origin.address)).await,
+                    Ok(Ok(_))
+                );
+                self.set_healthy(&origin.address, is_healthy);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    fn hash(key: &str) -> u64 {
+        let digest = Sha256::digest(key.as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Origins;
+
+    #[test]
+    fn test_origin_candidates() {
+        let addresses: Vec<String> = (1..=3).map(|i| format!("192.168.0.{i}:1935")).collect();
+        let origins = Origins::new(addresses.clone());
+
+        let keys: Vec<String> = (0..100).map(|i| format!("live/test{i}")).collect();
+        let mut counts = [0; 3];
+        for key in &keys {
+            let candidates = origins.candidates(key);
+            assert_eq!(candidates.len(), 3);
+            assert_eq!(candidates, origins.candidates(key));
+            counts[addresses.iter().position(|a| *a == candidates[0]).unwrap()] += 1;
+        }
+        assert!(counts.iter().all(|count| *count > 10));
+
+        //only the streams of the removed origin are remapped, to their next candidates
+        let removed = Origins::new(addresses[..2].to_vec());
+        for key in &keys {
+            let candidates = origins.candidates(key);
+            let first = if candidates[0] == addresses[2] {
+                &candidates[1]
+            } else {
+                &candidates[0]
+            };
+            assert_eq!(&removed.candidates(key)[0], first);
+        }
+
+        //the unhealthy origin is tried at last
+        let key = &keys[0];
+        let first = origins.candidates(key)[0].clone();
+        origins.set_healthy(&first, false);
+        assert_eq!(origins.candidates(key).last(), Some(&first));
+        origins.set_healthy(&first, true);
+        assert_eq!(origins.candidates(key)[0], first);
+    }
+}
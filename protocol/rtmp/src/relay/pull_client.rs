@@ -1,27 +1,71 @@
-use streamhub::stream::StreamIdentifier;
-
 use {
-    super::errors::ClientError,
+    super::{errors::ClientError, origin::Origins},
     crate::session::client_session::{ClientSession, ClientType},
-    streamhub::define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
-    tokio::net::TcpStream,
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+    streamhub::{
+        define::{
+            BroadcastEvent, BroadcastEventReceiver, StreamHubEvent, StreamHubEventSender,
+            SubscriberInfo,
+        },
+        stream::StreamIdentifier,
+    },
+    tokio::{
+        net::TcpStream,
+        sync::{broadcast::error::RecvError, oneshot},
+        time::sleep,
+    },
 };
 
+//how many times the stream has been relayed is carried in the play url,
+//e.g. rtmp://origin:1935/live/test?xiu_hops=1
+pub const HOPS_PARAMETER: &str = "xiu_hops";
+const INITIAL_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+//stop pulling after failing to pull the stream from all the origins so many
+//times continuously, it will be pulled again when a new subscriber arrives.
+const MAX_RETRY_TIMES: u32 = 10;
+//the retry interval is reset if a session has lasted so long
+const STABLE_SESSION_DURATION: Duration = Duration::from_secs(30);
+
+/*(app name, stream name)*/
+type StreamKey = (String, String);
+
+struct PullTask {
+    id: u64,
+    /*the pull task exits once this sender is dropped*/
+    _cancel_sender: oneshot::Sender<()>,
+}
+
+// Pull the rtmp streams from the origins on demand(edge mode): the pull session
+// starts when the first subscriber of the stream arrives, and stops when the
+// last one leaves.
 pub struct PullClient {
-    address: String,
+    origins: Origins,
+    //refuse to pull the streams which have been relayed so many times(relay loop)
+    max_hops: u32,
+    //the streams which are being pulled
+    pulling_streams: Arc<Mutex<HashMap<StreamKey, PullTask>>>,
+    next_task_id: u64,
     client_event_consumer: BroadcastEventReceiver,
     channel_event_producer: StreamHubEventSender,
 }
 
 impl PullClient {
     pub fn new(
-        address: String,
+        origins: Origins,
+        max_hops: u32,
         consumer: BroadcastEventReceiver,
         producer: StreamHubEventSender,
     ) -> Self {
         Self {
-            address,
-
+            origins,
+            max_hops,
+            pulling_streams: Arc::new(Mutex::new(HashMap::new())),
+            next_task_id: 0,
             client_event_consumer: consumer,
             channel_event_producer: producer,
         }
@@ -29,39 +73,225 @@ impl PullClient {
 
     pub async fn run(&mut self) -> Result<(), ClientError> {
         loop {
-            let event = self.client_event_consumer.recv().await?;
+            let event = match self.client_event_consumer.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(count)) => {
+                    log::warn!("pull client lagged {} events", count);
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            match event {
+                BroadcastEvent::Subscribe {
+                    identifier:
+                        StreamIdentifier::Rtmp {
+                            app_name,
+                            stream_name,
+                        },
+                    info,
+                } => self.on_subscribe(app_name, stream_name, info),
+                BroadcastEvent::UnSubscribe {
+                    identifier:
+                        StreamIdentifier::Rtmp {
+                            app_name,
+                            stream_name,
+                        },
+                } => {
+                    /*the pull session is stopped by dropping the cancel sender*/
+                    let key = (app_name, stream_name);
+                    if self.pulling_streams.lock().unwrap().remove(&key).is_some() {
+                        log::info!("stop pulling stream: {}/{}, no subscriber", key.0, key.1);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn on_subscribe(
+        &mut self,
+        app_name: String,
+        stream_name: String,
+        info: Option<SubscriberInfo>,
+    ) {
+        let key = (app_name.clone(), stream_name.clone());
+        let mut pulling_streams = self.pulling_streams.lock().unwrap();
+        if pulling_streams.contains_key(&key) {
+            return;
+        }
+
+        let hops = info
+            .map(|info| get_hops(&info.notify_info.request_url))
+            .unwrap_or(0);
+        if hops >= self.max_hops {
+            log::warn!(
+                "refuse to pull stream: {}/{}, it has been relayed {} times, maybe there is a relay loop",
+                app_name,
+                stream_name,
+                hops
+            );
+            Self::on_pull_failed(&self.channel_event_producer, app_name, stream_name);
+            return;
+        }
+
+        log::info!(
+            "receive pull event, app_name: {}, stream_name: {}",
+            app_name,
+            stream_name
+        );
+        let id = self.next_task_id;
+        self.next_task_id += 1;
+        let (cancel_sender, cancel_receiver) = oneshot::channel();
+        pulling_streams.insert(
+            key.clone(),
+            PullTask {
+                id,
+                _cancel_sender: cancel_sender,
+            },
+        );
+
+        let origins = self.origins.clone();
+        let producer = self.channel_event_producer.clone();
+        let pulling_streams = self.pulling_streams.clone();
+        tokio::spawn(async move {
+            Self::pull_with_retry(
+                &origins,
+                &app_name,
+                &stream_name,
+                hops + 1,
+                producer,
+                cancel_receiver,
+            )
+            .await;
+
+            let mut pulling_streams = pulling_streams.lock().unwrap();
+            //the stream may be pulled again by a new task after this one is cancelled
+            if matches!(pulling_streams.get(&key), Some(task) if task.id == id) {
+                pulling_streams.remove(&key);
+            }
+        });
+    }
+
+    async fn pull_with_retry(
+        origins: &Origins,
+        app_name: &str,
+        stream_name: &str,
+        hops: u32,
+        producer: StreamHubEventSender,
+        mut cancel_receiver: oneshot::Receiver<()>,
+    ) {
+        let mut retry_times = 0;
+        let mut retry_interval = INITIAL_RETRY_INTERVAL;
+
+        loop {
+            let begin_time = Instant::now();
+            /*fail over to the next origin if the stream can not be pulled from this one*/
+            for address in origins.candidates(&format!("{app_name}/{stream_name}")) {
+                let stream = tokio::select! {
+                    rv = TcpStream::connect(&address) => rv,
+                    _ = &mut cancel_receiver => return,
+                };
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        log::error!("connect origin: {} error: {}", address, err);
+                        origins.set_healthy(&address, false);
+                        continue;
+                    }
+                };
+                origins.set_healthy(&address, true);
 
-            if let BroadcastEvent::Subscribe {
-                identifier:
-                    StreamIdentifier::Rtmp {
-                        app_name,
-                        stream_name,
-                    },
-            } = event
-            {
                 log::info!(
-                    "receive pull event, app_name :{}, stream_name: {}",
+                    "pull stream: {}/{} from origin: {}",
                     app_name,
-                    stream_name
+                    stream_name,
+                    address
                 );
-                let stream = TcpStream::connect(self.address.clone()).await?;
-
-                let mut client_session = ClientSession::new(
+                let mut session = ClientSession::new(
                     stream,
                     ClientType::Play,
-                    self.address.clone(),
-                    app_name.clone(),
-                    stream_name.clone(),
-                    self.channel_event_producer.clone(),
+                    address.clone(),
+                    app_name.to_string(),
+                    format!("{stream_name}?{HOPS_PARAMETER}={hops}"),
+                    producer.clone(),
                     0,
                 );
+                match session.run_until_cancelled(&mut cancel_receiver).await {
+                    Some(Ok(())) => {}
+                    Some(Err(err)) => log::error!(
+                        "pull stream: {}/{} from origin: {} error: {}",
+                        app_name,
+                        stream_name,
+                        address,
+                        err
+                    ),
+                    None => return,
+                }
+            }
 
-                tokio::spawn(async move {
-                    if let Err(err) = client_session.run().await {
-                        log::error!("client_session as pull client run error: {}", err);
-                    }
-                });
+            if begin_time.elapsed() > STABLE_SESSION_DURATION {
+                retry_times = 0;
+                retry_interval = INITIAL_RETRY_INTERVAL;
+            }
+
+            retry_times += 1;
+            if retry_times > MAX_RETRY_TIMES {
+                log::error!(
+                    "pull client stops after retrying {} times, stream: {}/{}",
+                    MAX_RETRY_TIMES,
+                    app_name,
+                    stream_name
+                );
+                Self::on_pull_failed(&producer, app_name.to_string(), stream_name.to_string());
+                return;
+            }
+
+            tokio::select! {
+                _ = sleep(retry_interval) => {}
+                _ = &mut cancel_receiver => return,
             }
+            retry_interval = std::cmp::min(retry_interval * 2, MAX_RETRY_INTERVAL);
+        }
+    }
+
+    //the subscribers waiting for the stream are dropped by the hub at once
+    fn on_pull_failed(producer: &StreamHubEventSender, app_name: String, stream_name: String) {
+        let event = StreamHubEvent::PullFailed {
+            identifier: StreamIdentifier::Rtmp {
+                app_name,
+                stream_name,
+            },
+        };
+        if producer.send(event).is_err() {
+            log::error!("send pull failed event error");
         }
     }
 }
+
+//the hops of the pulled stream requested by another edge
+fn get_hops(request_url: &str) -> u32 {
+    request_url
+        .split_once('?')
+        .and_then(|(_, parameters)| {
+            parameters
+                .split('&')
+                .filter_map(|parameter| parameter.split_once('='))
+                .find(|(key, _)| *key == HOPS_PARAMETER)
+        })
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_hops;
+
+    #[test]
+    fn test_get_hops() {
+        assert_eq!(get_hops("rtmp://127.0.0.1:1935/live/test"), 0);
+        assert_eq!(get_hops("rtmp://127.0.0.1:1935/live/test?xiu_hops=2"), 2);
+        assert_eq!(get_hops("live/test?token=abc&xiu_hops=1"), 1);
+        assert_eq!(get_hops("live/test?xiu_hops=x"), 0);
+    }
+}
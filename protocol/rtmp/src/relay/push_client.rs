@@ -2,7 +2,7 @@ use {
     super::errors::ClientError,
     crate::session::client_session::{ClientSession, ClientType},
     streamhub::{
-        define::{StreamHubEventSender, BroadcastEvent, BroadcastEventReceiver},
+        define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
         stream::StreamIdentifier,
     },
     tokio::net::TcpStream,
@@ -35,7 +35,7 @@ impl PushClient {
             let val = self.client_event_consumer.recv().await?;
 
             match val {
-                BroadcastEvent::Publish { identifier } => {
+                BroadcastEvent::Publish { identifier, .. } => {
                     if let StreamIdentifier::Rtmp {
                         app_name,
                         stream_name,
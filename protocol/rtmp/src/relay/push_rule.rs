@@ -0,0 +1,137 @@
+use {
+    super::errors::{ClientError, PushClientErrorValue},
+    crate::utils::RtmpUrlParser,
+    serde::{Deserialize, Serialize},
+};
+
+const APP_WILDCARD: &str = "{app}";
+const STREAM_WILDCARD: &str = "{stream}";
+
+/*
+ A rule which forwards the matched local rtmp streams to a remote rtmp server.
+ source: {app}/{stream} pattern, each part can be a wildcard or a literal name,
+         e.g. "live/{stream}", "live/test", "{app}/{stream}"
+ url: the push url template, {app} and {stream} are replaced by the names of
+      the local stream, e.g. "rtmp://cdn.example.com/app/{stream}?key=secret"
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushRule {
+    pub name: String,
+    pub source: String,
+    pub url: String,
+}
+
+impl PushRule {
+    pub fn new(name: String, source: String, url: String) -> Result<Self, ClientError> {
+        let rule = Self { name, source, url };
+        rule.check()?;
+        Ok(rule)
+    }
+
+    pub fn check(&self) -> Result<(), ClientError> {
+        if self.name.is_empty() {
+            return Err(Self::invalid("the rule name is empty"));
+        }
+
+        match self.source.split_once('/') {
+            Some((app_name, stream_name))
+                if Self::is_valid_part(app_name, APP_WILDCARD)
+                    && Self::is_valid_part(stream_name, STREAM_WILDCARD) => {}
+            _ => {
+                return Err(Self::invalid(&format!(
+                    "the source: {} should be {{app}}/{{stream}}, e.g. live/{{stream}}",
+                    self.source
+                )));
+            }
+        }
+
+        /*make sure the generated urls can be parsed*/
+        let mut parser = RtmpUrlParser::new(self.gen_url("app", "stream"));
+        if !self.url.starts_with("rtmp://") || parser.parse_url().is_err() {
+            return Err(Self::invalid(&format!(
+                "the url: {} should be rtmp://host[:port]/app/stream[?parameters]",
+                self.url_without_parameters()
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn matches(&self, app_name: &str, stream_name: &str) -> bool {
+        match self.source.split_once('/') {
+            Some((app_pattern, stream_pattern)) => {
+                Self::match_part(app_pattern, APP_WILDCARD, app_name)
+                    && Self::match_part(stream_pattern, STREAM_WILDCARD, stream_name)
+            }
+            None => false,
+        }
+    }
+
+    pub fn gen_url(&self, app_name: &str, stream_name: &str) -> String {
+        self.url
+            .replace(APP_WILDCARD, app_name)
+            .replace(STREAM_WILDCARD, stream_name)
+    }
+
+    //do not expose the parameters, they may contain the secret keys
+    pub fn url_without_parameters(&self) -> &str {
+        self.url.split('?').next().unwrap_or_default()
+    }
+
+    fn is_valid_part(part: &str, wildcard: &str) -> bool {
+        part == wildcard || (!part.is_empty() && !part.contains(['{', '}', '/']))
+    }
+
+    fn match_part(pattern: &str, wildcard: &str, name: &str) -> bool {
+        pattern == wildcard || pattern == name
+    }
+
+    fn invalid(reason: &str) -> ClientError {
+        ClientError {
+            value: PushClientErrorValue::InvalidPushRule(reason.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PushRule;
+
+    #[test]
+    fn test_push_rule() {
+        let rule = PushRule::new(
+            String::from("cdn"),
+            String::from("live/{stream}"),
+            String::from("rtmp://cdn.example.com/{app}/{stream}_hd?key=secret"),
+        )
+        .unwrap();
+        assert!(rule.matches("live", "test"));
+        assert!(!rule.matches("vod", "test"));
+        assert_eq!(
+            rule.gen_url("live", "test"),
+            "rtmp://cdn.example.com/live/test_hd?key=secret"
+        );
+        assert_eq!(
+            rule.url_without_parameters(),
+            "rtmp://cdn.example.com/{app}/{stream}_hd"
+        );
+
+        let rule = PushRule::new(
+            String::from("youtube"),
+            String::from("{app}/test"),
+            String::from("rtmp://a.rtmp.youtube.com/live2/xxxx-xxxx"),
+        )
+        .unwrap();
+        assert!(rule.matches("live", "test"));
+        assert!(!rule.matches("live", "test2"));
+
+        for (source, url) in [
+            ("live", "rtmp://cdn.example.com/app/{stream}"),
+            ("live/{app}", "rtmp://cdn.example.com/app/{stream}"),
+            ("live/{stream}", "rtsp://cdn.example.com/app/{stream}"),
+            ("live/{stream}", "rtmp://cdn.example.com/{stream}"),
+        ] {
+            assert!(PushRule::new(String::from("x"), source.to_string(), url.to_string()).is_err());
+        }
+    }
+}
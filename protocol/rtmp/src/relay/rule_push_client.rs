@@ -0,0 +1,396 @@
+use {
+    super::{
+        errors::{ClientError, PushClientErrorValue},
+        push_rule::PushRule,
+    },
+    crate::{
+        session::client_session::{ClientSession, ClientType},
+        utils::RtmpUrlParser,
+    },
+    serde::Serialize,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+    streamhub::{
+        define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
+        stream::StreamIdentifier,
+    },
+    tokio::{
+        net::TcpStream,
+        sync::{broadcast::error::RecvError, oneshot},
+        time::sleep,
+    },
+};
+
+const INITIAL_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+//the retry interval is reset if a push session has lasted so long
+const STABLE_SESSION_DURATION: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PushSessionState {
+    Connecting,
+    Pushing,
+    Retrying,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PushSessionStatus {
+    pub rule: String,
+    pub app_name: String,
+    pub stream_name: String,
+    //the push url without the parameters
+    pub url: String,
+    pub state: PushSessionState,
+    pub retry_times: u32,
+    pub last_error: Option<String>,
+    pub start_time: String,
+}
+
+/*(rule name, app name, stream name)*/
+type SessionKey = (String, String, String);
+
+struct PushSession {
+    id: u64,
+    status: PushSessionStatus,
+    /*the push task exits once this sender is dropped*/
+    _cancel_sender: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct PushRulesState {
+    rules: Vec<PushRule>,
+    /*the published local rtmp streams*/
+    streams: BTreeSet<(String, String)>,
+    sessions: BTreeMap<SessionKey, PushSession>,
+    next_session_id: u64,
+}
+
+// The handle to add/remove the push rules at runtime and query the push
+// sessions, it is shared by the rule push client and the http api.
+#[derive(Clone)]
+pub struct PushRuleManager {
+    state: Arc<Mutex<PushRulesState>>,
+    channel_event_producer: StreamHubEventSender,
+}
+
+impl PushRuleManager {
+    pub fn new(producer: StreamHubEventSender) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PushRulesState::default())),
+            channel_event_producer: producer,
+        }
+    }
+
+    pub fn add_rule(&self, rule: PushRule) -> Result<(), ClientError> {
+        rule.check()?;
+
+        let mut state = self.state.lock().unwrap();
+        if state.rules.iter().any(|r| r.name == rule.name) {
+            return Err(ClientError {
+                value: PushClientErrorValue::PushRuleExists(rule.name),
+            });
+        }
+        log::info!(
+            "add push rule: {}, source: {}, url: {}",
+            rule.name,
+            rule.source,
+            rule.url_without_parameters()
+        );
+
+        let matched_streams: Vec<(String, String)> = state
+            .streams
+            .iter()
+            .filter(|(app_name, stream_name)| rule.matches(app_name, stream_name))
+            .cloned()
+            .collect();
+        for (app_name, stream_name) in matched_streams {
+            self.start_session(&mut state, &rule, app_name, stream_name);
+        }
+        state.rules.push(rule);
+
+        Ok(())
+    }
+
+    pub fn remove_rule(&self, name: &str) -> Result<(), ClientError> {
+        let mut state = self.state.lock().unwrap();
+        let len = state.rules.len();
+        state.rules.retain(|rule| rule.name != name);
+        if state.rules.len() == len {
+            return Err(ClientError {
+                value: PushClientErrorValue::PushRuleNotFound(name.to_string()),
+            });
+        }
+        log::info!("remove push rule: {}", name);
+
+        /*the push sessions of this rule are stopped by dropping the cancel senders*/
+        state
+            .sessions
+            .retain(|(rule_name, _, _), _| rule_name != name);
+
+        Ok(())
+    }
+
+    pub fn rules(&self) -> Vec<PushRule> {
+        self.state.lock().unwrap().rules.clone()
+    }
+
+    pub fn sessions(&self) -> Vec<PushSessionStatus> {
+        let state = self.state.lock().unwrap();
+        state
+            .sessions
+            .values()
+            .map(|session| session.status.clone())
+            .collect()
+    }
+
+    fn on_publish(&self, app_name: String, stream_name: String) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .streams
+            .insert((app_name.clone(), stream_name.clone()));
+
+        let matched_rules: Vec<PushRule> = state
+            .rules
+            .iter()
+            .filter(|rule| rule.matches(&app_name, &stream_name))
+            .cloned()
+            .collect();
+        for rule in matched_rules {
+            self.start_session(&mut state, &rule, app_name.clone(), stream_name.clone());
+        }
+    }
+
+    fn on_unpublish(&self, app_name: &str, stream_name: &str) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .streams
+            .remove(&(app_name.to_string(), stream_name.to_string()));
+        state
+            .sessions
+            .retain(|(_, app, stream), _| app != app_name || stream != stream_name);
+    }
+
+    fn start_session(
+        &self,
+        state: &mut PushRulesState,
+        rule: &PushRule,
+        app_name: String,
+        stream_name: String,
+    ) {
+        let key = (rule.name.clone(), app_name.clone(), stream_name.clone());
+        if state.sessions.contains_key(&key) {
+            return;
+        }
+
+        let mut parser = RtmpUrlParser::new(rule.gen_url(&app_name, &stream_name));
+        if let Err(err) = parser.parse_url() {
+            log::error!("push rule: {} generates an invalid url: {}", rule.name, err);
+            return;
+        }
+        parser.append_port(String::from("1935"));
+
+        let id = state.next_session_id;
+        state.next_session_id += 1;
+        let (cancel_sender, cancel_receiver) = oneshot::channel();
+        let status = PushSessionStatus {
+            rule: rule.name.clone(),
+            app_name,
+            stream_name,
+            url: parser
+                .raw_url
+                .split('?')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            state: PushSessionState::Connecting,
+            retry_times: 0,
+            last_error: None,
+            start_time: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        };
+        log::info!(
+            "start push session, rule: {}, stream: {}/{}, url: {}",
+            status.rule,
+            status.app_name,
+            status.stream_name,
+            status.url
+        );
+        state.sessions.insert(
+            key.clone(),
+            PushSession {
+                id,
+                status,
+                _cancel_sender: cancel_sender,
+            },
+        );
+
+        let task = PushTask {
+            manager: self.clone(),
+            key,
+            id,
+            parser,
+        };
+        tokio::spawn(async move {
+            task.run(cancel_receiver).await;
+        });
+    }
+
+    fn update_status(&self, key: &SessionKey, id: u64, f: impl FnOnce(&mut PushSessionStatus)) {
+        let mut state = self.state.lock().unwrap();
+        match state.sessions.get_mut(key) {
+            //the session may have been stopped and started again with the same key
+            Some(session) if session.id == id => f(&mut session.status),
+            _ => {}
+        }
+    }
+}
+
+// Push one local stream to the remote server, reconnect until it is cancelled.
+struct PushTask {
+    manager: PushRuleManager,
+    key: SessionKey,
+    id: u64,
+    parser: RtmpUrlParser,
+}
+
+impl PushTask {
+    async fn run(&self, mut cancel_receiver: oneshot::Receiver<()>) {
+        let (rule_name, app_name, stream_name) = self.key.clone();
+        let mut retry_interval = INITIAL_RETRY_INTERVAL;
+
+        loop {
+            self.manager.update_status(&self.key, self.id, |status| {
+                status.state = PushSessionState::Connecting;
+            });
+
+            let begin_time = Instant::now();
+            let rv = match self
+                .push(&app_name, &stream_name, &mut cancel_receiver)
+                .await
+            {
+                Some(rv) => rv,
+                None => break,
+            };
+
+            let error = match rv {
+                Ok(()) => String::from("the remote server closed the session"),
+                Err(err) => err.to_string().trim_end().to_string(),
+            };
+            log::error!(
+                "push session error, rule: {}, stream: {}/{}, error: {}",
+                rule_name,
+                app_name,
+                stream_name,
+                error
+            );
+
+            if begin_time.elapsed() > STABLE_SESSION_DURATION {
+                retry_interval = INITIAL_RETRY_INTERVAL;
+            }
+            self.manager.update_status(&self.key, self.id, |status| {
+                status.state = PushSessionState::Retrying;
+                status.retry_times += 1;
+                status.last_error = Some(error);
+            });
+
+            tokio::select! {
+                _ = sleep(retry_interval) => {}
+                _ = &mut cancel_receiver => break,
+            }
+            retry_interval = std::cmp::min(retry_interval * 2, MAX_RETRY_INTERVAL);
+        }
+
+        log::info!(
+            "push session stopped, rule: {}, stream: {}/{}",
+            rule_name,
+            app_name,
+            stream_name
+        );
+    }
+
+    //returns none if the push task is cancelled
+    async fn push(
+        &self,
+        app_name: &str,
+        stream_name: &str,
+        cancel_receiver: &mut oneshot::Receiver<()>,
+    ) -> Option<Result<(), ClientError>> {
+        let stream = tokio::select! {
+            rv = TcpStream::connect(self.parser.raw_domain_name.clone()) => rv,
+            _ = &mut *cancel_receiver => return None,
+        };
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => return Some(Err(err.into())),
+        };
+        let mut session = ClientSession::new(
+            stream,
+            ClientType::Publish,
+            self.parser.raw_domain_name.clone(),
+            self.parser.app_name.clone(),
+            self.parser.raw_stream_name.clone(),
+            self.manager.channel_event_producer.clone(),
+            0,
+        );
+        session.subscribe(app_name.to_string(), stream_name.to_string());
+
+        self.manager.update_status(&self.key, self.id, |status| {
+            status.state = PushSessionState::Pushing;
+        });
+
+        let rv = session.run_until_cancelled(cancel_receiver).await?;
+        Some(rv.map_err(ClientError::from))
+    }
+}
+
+// Push the published local rtmp streams according to the push rules.
+pub struct RulePushClient {
+    client_event_consumer: BroadcastEventReceiver,
+    manager: PushRuleManager,
+}
+
+impl RulePushClient {
+    pub fn new(consumer: BroadcastEventReceiver, manager: PushRuleManager) -> Self {
+        Self {
+            client_event_consumer: consumer,
+            manager,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), ClientError> {
+        log::info!("rule push client run...");
+
+        loop {
+            let event = match self.client_event_consumer.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(count)) => {
+                    log::warn!("rule push client lagged {} events", count);
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            match event {
+                BroadcastEvent::Publish {
+                    identifier:
+                        StreamIdentifier::Rtmp {
+                            app_name,
+                            stream_name,
+                        },
+                    ..
+                } => self.manager.on_publish(app_name, stream_name),
+                BroadcastEvent::UnPublish {
+                    identifier:
+                        StreamIdentifier::Rtmp {
+                            app_name,
+                            stream_name,
+                        },
+                } => self.manager.on_unpublish(&app_name, &stream_name),
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use {super::errors::RtmpRemuxerError, bytes::BytesMut, std::sync::Arc};
+
+pub struct AacFrame {
+    //the presentation timestamp in milliseconds
+    pub timestamp: u32,
+    //the raw aac frame without the adts header
+    pub data: BytesMut,
+}
+
+//Transcode the Opus audio of the WebRTC(WHIP) stream into the AAC audio of the RTMP stream.
+pub trait TAudioTranscoder: Send {
+    //the AudioSpecificConfig of the output aac stream, it is used to generate
+    //the aac sequence header, None if the transcoder is not ready yet.
+    fn audio_specific_config(&self) -> Option<BytesMut>;
+    //the input is the payload of one opus rtp packet, the output may contain zero or more aac frames.
+    fn transcode(
+        &mut self,
+        timestamp: u32,
+        opus_packet: BytesMut,
+    ) -> Result<Vec<AacFrame>, RtmpRemuxerError>;
+}
+
+//a transcoder is created for each remuxed stream
+pub type AudioTranscoderCreator = Arc<dyn Fn() -> Box<dyn TAudioTranscoder> + Send + Sync>;
+
+//The fallback transcoder which drops all the audio packets,
+//the remuxed rtmp stream only contains the video.
+#[derive(Default)]
+pub struct DropAudioTranscoder {}
+
+impl DropAudioTranscoder {
+    pub fn creator() -> AudioTranscoderCreator {
+        Arc::new(|| Box::<DropAudioTranscoder>::default())
+    }
+}
+
+impl TAudioTranscoder for DropAudioTranscoder {
+    fn audio_specific_config(&self) -> Option<BytesMut> {
+        None
+    }
+
+    fn transcode(
+        &mut self,
+        _timestamp: u32,
+        _opus_packet: BytesMut,
+    ) -> Result<Vec<AacFrame>, RtmpRemuxerError> {
+        Ok(Vec::new())
+    }
+}
@@ -5,9 +5,11 @@ use {
     bytesio::bytes_errors::{BytesReadError, BytesWriteError},
     failure::Fail,
     std::fmt,
+    streamhub::errors::ChannelError,
     tokio::sync::broadcast::error::RecvError,
     xflv::errors::FlvMuxerError,
     xflv::errors::Mpeg4AvcHevcError,
+    xrtsp::rtp::errors::UnPackerError,
 };
 
 pub struct RtmpRemuxerError {
@@ -36,7 +38,21 @@ pub enum RtmpRemuxerErrorValue {
     FlvMuxerError(#[cause] FlvMuxerError),
     #[fail(display = "stream hub event send error\n")]
     StreamHubEventSendErr,
+    #[fail(display = "stream hub error: {}\n", _0)]
+    ChannelError(#[cause] ChannelError),
+    #[fail(display = "rtp unpacker error:{}\n", _0)]
+    UnPackerError(#[cause] UnPackerError),
+    #[fail(display = "audio transcode error:{}\n", _0)]
+    AudioTranscodeError(String),
 }
+impl From<ChannelError> for RtmpRemuxerError {
+    fn from(error: ChannelError) -> Self {
+        RtmpRemuxerError {
+            value: RtmpRemuxerErrorValue::ChannelError(error),
+        }
+    }
+}
+
 impl From<RecvError> for RtmpRemuxerError {
     fn from(error: RecvError) -> Self {
         RtmpRemuxerError {
@@ -101,6 +117,14 @@ impl From<FlvMuxerError> for RtmpRemuxerError {
     }
 }
 
+impl From<UnPackerError> for RtmpRemuxerError {
+    fn from(error: UnPackerError) -> Self {
+        RtmpRemuxerError {
+            value: RtmpRemuxerErrorValue::UnPackerError(error),
+        }
+    }
+}
+
 impl fmt::Display for RtmpRemuxerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Display::fmt(&self.value, f)
@@ -1,25 +1,38 @@
+pub mod audio_transcoder;
 pub mod errors;
 pub mod rtsp2rtmp;
+pub mod utils;
+pub mod webrtc2rtmp;
 
 use streamhub::{
-    define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
+    define::{BroadcastEvent, BroadcastEventReceiver, PublishType, StreamHubEventSender},
     stream::StreamIdentifier,
 };
 
-use self::{errors::RtmpRemuxerError, rtsp2rtmp::Rtsp2RtmpRemuxerSession};
+use self::{
+    audio_transcoder::AudioTranscoderCreator, errors::RtmpRemuxerError,
+    rtsp2rtmp::Rtsp2RtmpRemuxerSession, webrtc2rtmp::WebRTC2RtmpRemuxerSession,
+};
 
 //Receive publish event from stream hub and
 //remux from other protocols to rtmp
 pub struct RtmpRemuxer {
     receiver: BroadcastEventReceiver,
     event_producer: StreamHubEventSender,
+    /*used to transcode the opus audio of the webrtc streams*/
+    audio_transcoder_creator: AudioTranscoderCreator,
 }
 
 impl RtmpRemuxer {
-    pub fn new(receiver: BroadcastEventReceiver, event_producer: StreamHubEventSender) -> Self {
+    pub fn new(
+        receiver: BroadcastEventReceiver,
+        event_producer: StreamHubEventSender,
+        audio_transcoder_creator: AudioTranscoderCreator,
+    ) -> Self {
         Self {
             receiver,
             event_producer,
+            audio_transcoder_creator,
         }
     }
     pub async fn run(&mut self) -> Result<(), RtmpRemuxerError> {
@@ -29,8 +42,11 @@ impl RtmpRemuxer {
             let val = self.receiver.recv().await?;
             log::info!("{:?}", val);
             match val {
-                BroadcastEvent::Publish { identifier } => {
-                    if let StreamIdentifier::Rtsp { stream_path } = identifier {
+                BroadcastEvent::Publish { identifier, info } => match identifier {
+                    /*the rtsp stream remuxed from rtmp is not remuxed back*/
+                    StreamIdentifier::Rtsp { stream_path }
+                        if info.pub_type != PublishType::RemuxRtsp =>
+                    {
                         let mut session =
                             Rtsp2RtmpRemuxerSession::new(stream_path, self.event_producer.clone());
                         tokio::spawn(async move {
@@ -39,7 +55,25 @@ impl RtmpRemuxer {
                             }
                         });
                     }
-                }
+                    /*only the whip streams are remuxed, the rtp streams remuxed from rtmp are skipped*/
+                    StreamIdentifier::WebRTC {
+                        app_name,
+                        stream_name,
+                    } if info.pub_type == PublishType::PushWebRTC => {
+                        let mut session = WebRTC2RtmpRemuxerSession::new(
+                            app_name,
+                            stream_name,
+                            self.event_producer.clone(),
+                            (self.audio_transcoder_creator)(),
+                        );
+                        tokio::spawn(async move {
+                            if let Err(err) = session.run().await {
+                                log::error!("webrtc2rtmp session error: {}\n", err);
+                            }
+                        });
+                    }
+                    _ => {}
+                },
                 _ => {
                     log::trace!("other infos...");
                 }
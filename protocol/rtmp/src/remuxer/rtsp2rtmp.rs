@@ -1,34 +1,31 @@
 use bytes::BytesMut;
-use bytesio::{bytes_reader::BytesReader, bytes_writer::BytesWriter};
+use bytesio::bytes_reader::BytesReader;
 use h264_decoder::sps::SpsParser;
-use indexmap::IndexMap;
-use streamhub::define::{DataSender, VideoCodecType};
-use xflv::{
-    define::h264_nal_type::{H264_NAL_IDR, H264_NAL_PPS, H264_NAL_SPS},
-    flv_tag_header::{AudioTagHeader, VideoTagHeader},
-    mpeg4_avc::{Mpeg4Avc, Mpeg4AvcProcessor, Pps, Sps},
-    Marshal,
-};
+use streamhub::define::{DataSender, PublishType, VideoCodecType};
+use xflv::define::h264_nal_type::{H264_NAL_IDR, H264_NAL_PPS, H264_NAL_SPS};
 
-use crate::{
-    amf0::{amf0_writer::Amf0Writer, Amf0ValueType},
-    session::define::SessionType,
-};
+use crate::session::define::SessionType;
 
-use super::errors::{RtmpRemuxerError, RtmpRemuxerErrorValue};
+use super::{
+    errors::{RtmpRemuxerError, RtmpRemuxerErrorValue},
+    utils,
+};
 
 use {
     crate::session::common::Common,
     std::time::Duration,
     streamhub::{
         define::{
-            FrameData, FrameDataReceiver, NotifyInfo, StreamHubEvent, StreamHubEventSender,
-            SubscribeType, SubscriberInfo,
+            wait_pub_sub_result, FrameData, FrameDataReceiver, NotifyInfo, StreamHubEvent,
+            StreamHubEventSender, SubscribeType, SubscriberInfo,
         },
         stream::StreamIdentifier,
         utils::{RandomDigitCount, Uuid},
     },
-    tokio::{sync::mpsc, time::sleep},
+    tokio::{
+        sync::{mpsc, oneshot},
+        time::sleep,
+    },
 };
 pub struct Rtsp2RtmpRemuxerSession {
     event_producer: StreamHubEventSender,
@@ -56,6 +53,10 @@ impl Rtsp2RtmpRemuxerSession {
     pub fn new(stream_path: String, event_producer: StreamHubEventSender) -> Self {
         let (_, data_consumer) = mpsc::unbounded_channel();
 
+        let mut rtmp_handler = Common::new(None, event_producer.clone(), SessionType::Server, None);
+        /*mark the rtmp stream so that it will not be remuxed back to rtsp*/
+        rtmp_handler.publish_type = Some(PublishType::RemuxRtmp);
+
         let eles: Vec<&str> = stream_path.splitn(2, '/').collect();
         let (app_name, stream_name) = if eles.len() < 2 {
             log::warn!(
@@ -73,7 +74,7 @@ impl Rtsp2RtmpRemuxerSession {
             stream_name,
             data_receiver: data_consumer,
             event_producer: event_producer.clone(),
-            rtmp_handler: Common::new(None, event_producer, SessionType::Server, None),
+            rtmp_handler,
             subscribe_id: Uuid::new(RandomDigitCount::Four),
             publishe_id: Uuid::new(RandomDigitCount::Four),
             video_clock_rate: 1000,
@@ -119,19 +120,21 @@ impl Rtsp2RtmpRemuxerSession {
 
         let sub_info = SubscriberInfo {
             id: self.subscribe_id,
-            sub_type: SubscribeType::PlayerRtmp,
+            sub_type: SubscribeType::RemuxRtmp,
             notify_info: NotifyInfo {
                 request_url: String::from(""),
                 remote_addr: String::from(""),
             },
         };
 
+        let (result_sender, result_receiver) = oneshot::channel();
         let subscribe_event = StreamHubEvent::Subscribe {
             identifier: StreamIdentifier::Rtsp {
                 stream_path: self.stream_path.clone(),
             },
             info: sub_info,
             sender: DataSender::Frame { sender },
+            result_sender,
         };
 
         if self.event_producer.send(subscribe_event).is_err() {
@@ -139,6 +142,7 @@ impl Rtsp2RtmpRemuxerSession {
                 value: RtmpRemuxerErrorValue::StreamHubEventSendErr,
             });
         }
+        wait_pub_sub_result(result_receiver).await?;
 
         self.data_receiver = receiver;
         Ok(())
@@ -147,7 +151,7 @@ impl Rtsp2RtmpRemuxerSession {
     pub async fn unsubscribe_rtsp(&mut self) -> Result<(), RtmpRemuxerError> {
         let sub_info = SubscriberInfo {
             id: self.subscribe_id,
-            sub_type: SubscribeType::PlayerRtsp,
+            sub_type: SubscribeType::RemuxRtmp,
             notify_info: NotifyInfo {
                 request_url: String::from(""),
                 remote_addr: String::from(""),
@@ -224,28 +228,13 @@ impl Rtsp2RtmpRemuxerSession {
         if self.base_audio_timestamp == 0 {
             self.base_audio_timestamp = timestamp;
         }
-        let mut audio_tag_header = AudioTagHeader {
-            sound_format: 10,
-            sound_rate: 3,
-            sound_size: 1,
-            sound_type: 1,
-            aac_packet_type: 0,
-        };
-
-        if audio_data.len() > 5 {
-            audio_tag_header.aac_packet_type = 1;
-        }
-
-        let tag_header_data = audio_tag_header.marshal()?;
-
-        let mut writer = BytesWriter::new();
-        writer.write(&tag_header_data)?;
-        writer.write(audio_data)?;
+        let aac_packet_type = if audio_data.len() > 5 { 1 } else { 0 };
+        let mut audio_tag_data = utils::gen_rtmp_aac_data(aac_packet_type, audio_data)?;
 
         let timestamp_adjust =
             (timestamp - self.base_audio_timestamp) / (self.audio_clock_rate / 1000);
         self.rtmp_handler
-            .on_audio_data(&mut writer.extract_current_bytes(), &timestamp_adjust)
+            .on_audio_data(&mut audio_tag_data, &timestamp_adjust)
             .await?;
 
         Ok(())
@@ -315,15 +304,14 @@ impl Rtsp2RtmpRemuxerSession {
             }
         }
 
-        if sps.is_some() && pps.is_some() {
-            let mut meta_data = self.gen_rtmp_meta_data(width, height)?;
+        if let (Some(sps), Some(pps)) = (sps, pps) {
+            let mut meta_data = utils::gen_rtmp_meta_data(width, height)?;
             self.rtmp_handler.on_meta_data(&mut meta_data, &0).await?;
 
-            let mut seq_header =
-                self.gen_rtmp_video_seq_header(sps.unwrap(), pps.unwrap(), profile, level)?;
+            let mut seq_header = utils::gen_rtmp_video_seq_header(sps, pps, profile, level)?;
             self.rtmp_handler.on_video_data(&mut seq_header, &0).await?;
         } else {
-            let mut frame_data = self.gen_rtmp_video_frame_data(nalu_vec, contains_idr)?;
+            let mut frame_data = utils::gen_rtmp_video_frame_data(nalu_vec, contains_idr)?;
 
             let timestamp_adjust =
                 (timestamp - self.base_video_timestamp) / (self.video_clock_rate / 1000);
@@ -334,84 +322,4 @@ impl Rtsp2RtmpRemuxerSession {
 
         Ok(())
     }
-
-    fn gen_rtmp_meta_data(&self, width: u32, height: u32) -> Result<BytesMut, RtmpRemuxerError> {
-        let mut amf_writer = Amf0Writer::new();
-        amf_writer.write_string(&String::from("@setDataFrame"))?;
-        amf_writer.write_string(&String::from("onMetaData"))?;
-
-        let mut properties = IndexMap::new();
-        properties.insert(String::from("width"), Amf0ValueType::Number(width as f64));
-        properties.insert(String::from("height"), Amf0ValueType::Number(height as f64));
-        properties.insert(String::from("videocodecid"), Amf0ValueType::Number(7.));
-        properties.insert(String::from("audiocodecid"), Amf0ValueType::Number(10.));
-        amf_writer.write_eacm_array(&properties)?;
-
-        Ok(amf_writer.extract_current_bytes())
-    }
-    fn gen_rtmp_video_seq_header(
-        &self,
-        sps: BytesMut,
-        pps: BytesMut,
-        profile: u8,
-        level: u8,
-    ) -> Result<BytesMut, RtmpRemuxerError> {
-        let video_tag_header = VideoTagHeader {
-            frame_type: 1,
-            codec_id: 7,
-            avc_packet_type: 0,
-            composition_time: 0,
-        };
-        let tag_header_data = video_tag_header.marshal()?;
-
-        let mut processor = Mpeg4AvcProcessor {
-            mpeg4_avc: Mpeg4Avc {
-                profile,
-                compatibility: 0,
-                level,
-                nalu_length: 4,
-                nb_pps: 1,
-                sps: vec![Sps { data: sps }],
-                nb_sps: 1,
-                pps: vec![Pps { data: pps }],
-                ..Default::default()
-            },
-        };
-        let mpegavc_data = processor.decoder_configuration_record_save()?;
-
-        let mut writer = BytesWriter::new();
-        writer.write(&tag_header_data)?;
-        writer.write(&mpegavc_data)?;
-
-        Ok(writer.extract_current_bytes())
-    }
-
-    fn gen_rtmp_video_frame_data(
-        &self,
-        nalus: Vec<BytesMut>,
-        contains_idr: bool,
-    ) -> Result<BytesMut, RtmpRemuxerError> {
-        let frame_type = if contains_idr { 1 } else { 2 };
-        let video_tag_header = VideoTagHeader {
-            frame_type,
-            codec_id: 7,
-            avc_packet_type: 1,
-            composition_time: 0,
-        };
-        let tag_header_data = video_tag_header.marshal()?;
-
-        let mut processor = Mpeg4AvcProcessor {
-            mpeg4_avc: Mpeg4Avc {
-                nalu_length: 4,
-                ..Default::default()
-            },
-        };
-        let mpegavc_data = processor.nalus_to_mpeg4avc(nalus)?;
-
-        let mut writer = BytesWriter::new();
-        writer.write(&tag_header_data)?;
-        writer.write(&mpegavc_data)?;
-
-        Ok(writer.extract_current_bytes())
-    }
 }
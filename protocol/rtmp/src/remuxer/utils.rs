@@ -0,0 +1,111 @@
+use {
+    super::errors::RtmpRemuxerError,
+    crate::amf0::{amf0_writer::Amf0Writer, Amf0ValueType},
+    bytes::BytesMut,
+    bytesio::bytes_writer::BytesWriter,
+    indexmap::IndexMap,
+    xflv::{
+        flv_tag_header::{AudioTagHeader, VideoTagHeader},
+        mpeg4_avc::{Mpeg4Avc, Mpeg4AvcProcessor, Pps, Sps},
+        Marshal,
+    },
+};
+
+pub fn gen_rtmp_meta_data(width: u32, height: u32) -> Result<BytesMut, RtmpRemuxerError> {
+    let mut amf_writer = Amf0Writer::new();
+    amf_writer.write_string(&String::from("@setDataFrame"))?;
+    amf_writer.write_string(&String::from("onMetaData"))?;
+
+    let mut properties = IndexMap::new();
+    properties.insert(String::from("width"), Amf0ValueType::Number(width as f64));
+    properties.insert(String::from("height"), Amf0ValueType::Number(height as f64));
+    properties.insert(String::from("videocodecid"), Amf0ValueType::Number(7.));
+    properties.insert(String::from("audiocodecid"), Amf0ValueType::Number(10.));
+    amf_writer.write_eacm_array(&properties)?;
+
+    Ok(amf_writer.extract_current_bytes())
+}
+
+pub fn gen_rtmp_video_seq_header(
+    sps: BytesMut,
+    pps: BytesMut,
+    profile: u8,
+    level: u8,
+) -> Result<BytesMut, RtmpRemuxerError> {
+    let video_tag_header = VideoTagHeader {
+        frame_type: 1,
+        codec_id: 7,
+        avc_packet_type: 0,
+        composition_time: 0,
+        ..VideoTagHeader::defalut()
+    };
+    let tag_header_data = video_tag_header.marshal()?;
+
+    let mut processor = Mpeg4AvcProcessor {
+        mpeg4_avc: Mpeg4Avc {
+            profile,
+            compatibility: 0,
+            level,
+            nalu_length: 4,
+            nb_pps: 1,
+            sps: vec![Sps { data: sps }],
+            nb_sps: 1,
+            pps: vec![Pps { data: pps }],
+            ..Default::default()
+        },
+    };
+    let mpegavc_data = processor.decoder_configuration_record_save()?;
+
+    let mut writer = BytesWriter::new();
+    writer.write(&tag_header_data)?;
+    writer.write(&mpegavc_data)?;
+
+    Ok(writer.extract_current_bytes())
+}
+
+pub fn gen_rtmp_video_frame_data(
+    nalus: Vec<BytesMut>,
+    contains_idr: bool,
+) -> Result<BytesMut, RtmpRemuxerError> {
+    let frame_type = if contains_idr { 1 } else { 2 };
+    let video_tag_header = VideoTagHeader {
+        frame_type,
+        codec_id: 7,
+        avc_packet_type: 1,
+        composition_time: 0,
+        ..VideoTagHeader::defalut()
+    };
+    let tag_header_data = video_tag_header.marshal()?;
+
+    let mut processor = Mpeg4AvcProcessor {
+        mpeg4_avc: Mpeg4Avc {
+            nalu_length: 4,
+            ..Default::default()
+        },
+    };
+    let mpegavc_data = processor.nalus_to_mpeg4avc(nalus)?;
+
+    let mut writer = BytesWriter::new();
+    writer.write(&tag_header_data)?;
+    writer.write(&mpegavc_data)?;
+
+    Ok(writer.extract_current_bytes())
+}
+
+//aac_packet_type 0: the data is the AudioSpecificConfig, 1: the data is a raw aac frame
+pub fn gen_rtmp_aac_data(aac_packet_type: u8, data: &[u8]) -> Result<BytesMut, RtmpRemuxerError> {
+    let audio_tag_header = AudioTagHeader {
+        sound_format: 10,
+        sound_rate: 3,
+        sound_size: 1,
+        sound_type: 1,
+        aac_packet_type,
+    };
+    let tag_header_data = audio_tag_header.marshal()?;
+
+    let mut writer = BytesWriter::new();
+    writer.write(&tag_header_data)?;
+    writer.write(data)?;
+
+    Ok(writer.extract_current_bytes())
+}
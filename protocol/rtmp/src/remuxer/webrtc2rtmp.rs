@@ -0,0 +1,418 @@
+use {
+    super::{
+        audio_transcoder::TAudioTranscoder,
+        errors::{RtmpRemuxerError, RtmpRemuxerErrorValue},
+        utils,
+    },
+    crate::session::{common::Common, define::SessionType},
+    bytes::BytesMut,
+    bytesio::bytes_reader::BytesReader,
+    h264_decoder::sps::SpsParser,
+    std::time::Duration,
+    streamhub::{
+        define::{
+            wait_pub_sub_result, DataSender, FrameData, FrameDataReceiver, NotifyInfo, PacketData,
+            PacketDataReceiver, PublishType, StreamHubEvent, StreamHubEventSender, SubscribeType,
+            SubscriberInfo,
+        },
+        stream::StreamIdentifier,
+        utils::{RandomDigitCount, Uuid},
+    },
+    tokio::{
+        sync::{mpsc, oneshot},
+        time::sleep,
+    },
+    xflv::define::h264_nal_type::{H264_NAL_IDR, H264_NAL_PPS, H264_NAL_SPS},
+    xrtsp::rtp::{
+        define::ANNEXB_NALU_START_CODE,
+        rtp_h264::RtpH264UnPacker,
+        utils::{TUnPacker, Unmarshal},
+        RtpPacket,
+    },
+};
+
+/*the rtp clock rate of h264 is always 90kHz(RFC 6184)*/
+const VIDEO_CLOCK_RATE: u32 = 90000;
+/*the rtp clock rate of opus is always 48kHz(RFC 7587)*/
+const OPUS_CLOCK_RATE: u32 = 48000;
+
+//Group the nalus with the same rtp timestamp into one access unit(video frame).
+#[derive(Default)]
+struct AccessUnitAssembler {
+    timestamp: u32,
+    nalus: Vec<BytesMut>,
+}
+
+impl AccessUnitAssembler {
+    //return the previous access unit if the timestamp is changed
+    fn push(&mut self, timestamp: u32, nalu: BytesMut) -> Option<(u32, Vec<BytesMut>)> {
+        let access_unit = if timestamp != self.timestamp {
+            self.flush()
+        } else {
+            None
+        };
+        self.timestamp = timestamp;
+        self.nalus.push(nalu);
+        access_unit
+    }
+
+    fn flush(&mut self) -> Option<(u32, Vec<BytesMut>)> {
+        if self.nalus.is_empty() {
+            return None;
+        }
+        Some((self.timestamp, std::mem::take(&mut self.nalus)))
+    }
+}
+
+pub struct WebRTC2RtmpRemuxerSession {
+    event_producer: StreamHubEventSender,
+    //RTMP
+    app_name: String,
+    stream_name: String,
+    rtmp_handler: Common,
+    publishe_id: Uuid,
+    //WebRTC
+    data_receiver: PacketDataReceiver,
+    subscribe_id: Uuid,
+    video_unpacker: RtpH264UnPacker,
+    /*receive the nalus from the h264 unpacker*/
+    nalu_receiver: FrameDataReceiver,
+    access_unit_assembler: AccessUnitAssembler,
+    sps: Option<BytesMut>,
+    pps: Option<BytesMut>,
+    video_seq_header_sent: bool,
+    audio_transcoder: Box<dyn TAudioTranscoder>,
+    audio_seq_header_sent: bool,
+    base_video_timestamp: Option<u32>,
+    base_audio_timestamp: Option<u32>,
+}
+
+impl WebRTC2RtmpRemuxerSession {
+    pub fn new(
+        app_name: String,
+        stream_name: String,
+        event_producer: StreamHubEventSender,
+        audio_transcoder: Box<dyn TAudioTranscoder>,
+    ) -> Self {
+        let (_, data_consumer) = mpsc::unbounded_channel();
+
+        let (nalu_sender, nalu_receiver) = mpsc::unbounded_channel();
+        let mut video_unpacker = RtpH264UnPacker::new();
+        video_unpacker.on_frame_handler(Box::new(move |frame: FrameData| {
+            if let Err(err) = nalu_sender.send(frame) {
+                log::error!("send nalu err: {}", err);
+            }
+            Ok(())
+        }));
+
+        let mut rtmp_handler = Common::new(None, event_producer.clone(), SessionType::Server, None);
+        /*mark the rtmp stream so that it will not be remuxed back to webrtc*/
+        rtmp_handler.publish_type = Some(PublishType::RemuxRtmp);
+
+        Self {
+            app_name,
+            stream_name,
+            data_receiver: data_consumer,
+            event_producer,
+            rtmp_handler,
+            subscribe_id: Uuid::new(RandomDigitCount::Four),
+            publishe_id: Uuid::new(RandomDigitCount::Four),
+            video_unpacker,
+            nalu_receiver,
+            access_unit_assembler: AccessUnitAssembler::default(),
+            sps: None,
+            pps: None,
+            video_seq_header_sent: false,
+            audio_transcoder,
+            audio_seq_header_sent: false,
+            base_video_timestamp: None,
+            base_audio_timestamp: None,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), RtmpRemuxerError> {
+        self.publish_rtmp().await?;
+        self.subscribe_webrtc().await?;
+        self.receive_webrtc_data().await?;
+
+        Ok(())
+    }
+
+    pub async fn publish_rtmp(&mut self) -> Result<(), RtmpRemuxerError> {
+        self.rtmp_handler
+            .publish_to_channels(
+                self.app_name.clone(),
+                self.stream_name.clone(),
+                self.publishe_id,
+                1,
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn unpublish_rtmp(&mut self) -> Result<(), RtmpRemuxerError> {
+        self.rtmp_handler
+            .unpublish_to_channels(
+                self.app_name.clone(),
+                self.stream_name.clone(),
+                self.publishe_id,
+            )
+            .await?;
+        Ok(())
+    }
+
+    fn get_subscriber_info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            id: self.subscribe_id,
+            sub_type: SubscribeType::RemuxRtmp,
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
+        }
+    }
+
+    pub async fn subscribe_webrtc(&mut self) -> Result<(), RtmpRemuxerError> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: StreamIdentifier::WebRTC {
+                app_name: self.app_name.clone(),
+                stream_name: self.stream_name.clone(),
+            },
+            info: self.get_subscriber_info(),
+            sender: DataSender::Packet { sender },
+            result_sender,
+        };
+
+        if self.event_producer.send(subscribe_event).is_err() {
+            return Err(RtmpRemuxerError {
+                value: RtmpRemuxerErrorValue::StreamHubEventSendErr,
+            });
+        }
+        wait_pub_sub_result(result_receiver).await?;
+
+        self.data_receiver = receiver;
+        Ok(())
+    }
+
+    pub async fn unsubscribe_webrtc(&mut self) -> Result<(), RtmpRemuxerError> {
+        let subscribe_event = StreamHubEvent::UnSubscribe {
+            identifier: StreamIdentifier::WebRTC {
+                app_name: self.app_name.clone(),
+                stream_name: self.stream_name.clone(),
+            },
+            info: self.get_subscriber_info(),
+        };
+        if let Err(err) = self.event_producer.send(subscribe_event) {
+            log::error!("unsubscribe_from_channels err {}\n", err);
+        }
+
+        Ok(())
+    }
+
+    pub async fn receive_webrtc_data(&mut self) -> Result<(), RtmpRemuxerError> {
+        let mut retry_count = 0;
+
+        let rv: Result<(), RtmpRemuxerError> = async {
+            loop {
+                if let Some(data) = self.data_receiver.recv().await {
+                    match data {
+                        PacketData::Video { timestamp: _, data } => {
+                            self.on_webrtc_video(data).await?;
+                        }
+                        PacketData::Audio { timestamp: _, data } => {
+                            self.on_webrtc_audio(data).await?;
+                        }
+                    }
+                    retry_count = 0;
+                } else {
+                    sleep(Duration::from_millis(100)).await;
+                    retry_count += 1;
+                }
+
+                if retry_count > 10 {
+                    return Ok(());
+                }
+            }
+        }
+        .await;
+
+        //clean up even if the remuxing fails
+        self.unsubscribe_webrtc().await?;
+        self.unpublish_rtmp().await?;
+        rv
+    }
+
+    async fn on_webrtc_video(&mut self, rtp_data: BytesMut) -> Result<(), RtmpRemuxerError> {
+        /*the marker bit is set on the last packet of an access unit*/
+        let marker = rtp_data.len() > 1 && rtp_data[1] & 0x80 != 0;
+
+        let mut reader = BytesReader::new(rtp_data);
+        self.video_unpacker.unpack(&mut reader)?;
+
+        while let Ok(frame) = self.nalu_receiver.try_recv() {
+            if let FrameData::Video {
+                timestamp,
+                mut data,
+            } = frame
+            {
+                if data.len() <= ANNEXB_NALU_START_CODE.len() {
+                    continue;
+                }
+                let nalu = data.split_off(ANNEXB_NALU_START_CODE.len());
+                if let Some((timestamp, nalus)) = self.access_unit_assembler.push(timestamp, nalu) {
+                    self.on_access_unit(timestamp, nalus).await?;
+                }
+            }
+        }
+
+        if marker {
+            if let Some((timestamp, nalus)) = self.access_unit_assembler.flush() {
+                self.on_access_unit(timestamp, nalus).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn on_access_unit(
+        &mut self,
+        timestamp: u32,
+        nalus: Vec<BytesMut>,
+    ) -> Result<(), RtmpRemuxerError> {
+        let mut contains_idr = false;
+        let mut parameter_sets_changed = false;
+        let mut frame_nalus = Vec::new();
+
+        for nalu in nalus {
+            match nalu[0] & 0x1F {
+                H264_NAL_SPS => {
+                    if self.sps.as_ref() != Some(&nalu) {
+                        self.sps = Some(nalu);
+                        parameter_sets_changed = true;
+                    }
+                }
+                H264_NAL_PPS => {
+                    if self.pps.as_ref() != Some(&nalu) {
+                        self.pps = Some(nalu);
+                        parameter_sets_changed = true;
+                    }
+                }
+                nalu_type => {
+                    if nalu_type == H264_NAL_IDR {
+                        contains_idr = true;
+                    }
+                    frame_nalus.push(nalu);
+                }
+            }
+        }
+
+        if parameter_sets_changed {
+            if let (Some(sps), Some(pps)) = (self.sps.clone(), self.pps.clone()) {
+                self.send_video_seq_header(sps, pps).await?;
+            }
+        }
+
+        /*the frames can not be decoded before the sequence header*/
+        if !self.video_seq_header_sent || frame_nalus.is_empty() {
+            return Ok(());
+        }
+
+        let base_timestamp = *self.base_video_timestamp.get_or_insert(timestamp);
+        let timestamp_adjust = timestamp.wrapping_sub(base_timestamp) / (VIDEO_CLOCK_RATE / 1000);
+
+        let mut frame_data = utils::gen_rtmp_video_frame_data(frame_nalus, contains_idr)?;
+        self.rtmp_handler
+            .on_video_data(&mut frame_data, &timestamp_adjust)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn send_video_seq_header(
+        &mut self,
+        sps: BytesMut,
+        pps: BytesMut,
+    ) -> Result<(), RtmpRemuxerError> {
+        let mut sps_reader = BytesReader::new(sps.clone());
+        sps_reader.read_u8()?;
+
+        let mut sps_parser = SpsParser::new(sps_reader);
+        let (width, height) = sps_parser.parse().unwrap_or((0, 0));
+        log::info!("webrtc2rtmp width:{}x{}", width, height);
+
+        let mut meta_data = utils::gen_rtmp_meta_data(width, height)?;
+        self.rtmp_handler.on_meta_data(&mut meta_data, &0).await?;
+
+        let mut seq_header = utils::gen_rtmp_video_seq_header(
+            sps,
+            pps,
+            sps_parser.sps.profile_idc,
+            sps_parser.sps.level_idc,
+        )?;
+        self.rtmp_handler.on_video_data(&mut seq_header, &0).await?;
+        self.video_seq_header_sent = true;
+
+        Ok(())
+    }
+
+    async fn on_webrtc_audio(&mut self, rtp_data: BytesMut) -> Result<(), RtmpRemuxerError> {
+        let mut reader = BytesReader::new(rtp_data);
+        let rtp_packet = RtpPacket::unmarshal(&mut reader)?;
+
+        let timestamp = rtp_packet.header.timestamp;
+        let base_timestamp = *self.base_audio_timestamp.get_or_insert(timestamp);
+        let timestamp_adjust = timestamp.wrapping_sub(base_timestamp) / (OPUS_CLOCK_RATE / 1000);
+
+        let aac_frames = self
+            .audio_transcoder
+            .transcode(timestamp_adjust, rtp_packet.payload)?;
+
+        for aac_frame in aac_frames {
+            if !self.audio_seq_header_sent {
+                match self.audio_transcoder.audio_specific_config() {
+                    Some(audio_specific_config) => {
+                        let mut seq_header = utils::gen_rtmp_aac_data(0, &audio_specific_config)?;
+                        self.rtmp_handler.on_audio_data(&mut seq_header, &0).await?;
+                        self.audio_seq_header_sent = true;
+                    }
+                    None => continue,
+                }
+            }
+
+            let mut frame_data = utils::gen_rtmp_aac_data(1, &aac_frame.data)?;
+            self.rtmp_handler
+                .on_audio_data(&mut frame_data, &aac_frame.timestamp)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccessUnitAssembler;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_access_unit_assembler() {
+        let mut assembler = AccessUnitAssembler::default();
+        assert!(assembler.flush().is_none());
+
+        assert!(assembler.push(3000, BytesMut::from(&[0x67][..])).is_none());
+        assert!(assembler.push(3000, BytesMut::from(&[0x68][..])).is_none());
+        assert!(assembler.push(3000, BytesMut::from(&[0x65][..])).is_none());
+
+        let (timestamp, nalus) = assembler.push(6000, BytesMut::from(&[0x41][..])).unwrap();
+        assert_eq!(timestamp, 3000);
+        assert_eq!(nalus.len(), 3);
+
+        let (timestamp, nalus) = assembler.flush().unwrap();
+        assert_eq!(timestamp, 6000);
+        assert_eq!(&nalus[0][..], &[0x41]);
+        assert!(assembler.flush().is_none());
+    }
+}
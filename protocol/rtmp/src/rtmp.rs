@@ -1,7 +1,10 @@
-use streamhub::define::StreamHubEventSender;
+use streamhub::{auth::Auth, define::StreamHubEventSender, statistics::metrics::SessionCounters};
 
 use super::session::server_session;
+use bytesio::bytesio::{TNetIO, TcpIO, TlsIO};
+use bytesio::tls::ReloadableTlsAcceptor;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tokio::io::Error;
 use tokio::net::TcpListener;
 
@@ -9,14 +12,28 @@ pub struct RtmpServer {
     address: String,
     event_producer: StreamHubEventSender,
     gop_num: usize,
+    auth: Option<Arc<Auth>>,
+    //rtmps if it is set
+    tls_acceptor: Option<ReloadableTlsAcceptor>,
+    session_counters: SessionCounters,
 }
 
 impl RtmpServer {
-    pub fn new(address: String, event_producer: StreamHubEventSender, gop_num: usize) -> Self {
+    pub fn new(
+        address: String,
+        event_producer: StreamHubEventSender,
+        gop_num: usize,
+        auth: Option<Arc<Auth>>,
+        tls_acceptor: Option<ReloadableTlsAcceptor>,
+        session_counters: SessionCounters,
+    ) -> Self {
         Self {
             address,
             event_producer,
             gop_num,
+            auth,
+            tls_acceptor,
+            session_counters,
         }
     }
 
@@ -24,18 +41,45 @@ impl RtmpServer {
         let socket_addr: &SocketAddr = &self.address.parse().unwrap();
         let listener = TcpListener::bind(socket_addr).await?;
 
-        log::info!("Rtmp server listening on tcp://{}", socket_addr);
+        if self.tls_acceptor.is_some() {
+            log::info!("Rtmps server listening on tcp://{}", socket_addr);
+        } else {
+            log::info!("Rtmp server listening on tcp://{}", socket_addr);
+        }
         loop {
-            let (tcp_stream, _) = listener.accept().await?;
+            let (tcp_stream, remote_addr) = listener.accept().await?;
             //tcp_stream.set_keepalive(Some(Duration::from_secs(30)))?;
 
-            let mut session = server_session::ServerSession::new(
-                tcp_stream,
-                self.event_producer.clone(),
-                self.gop_num,
-            );
+            let tls_acceptor = self.tls_acceptor.clone();
+            let event_producer = self.event_producer.clone();
+            let gop_num = self.gop_num;
+            let auth = self.auth.clone();
+            let session_counters = self.session_counters.clone();
+
             tokio::spawn(async move {
+                /*do the tls handshake in the session task, so that a slow client
+                does not block the listener*/
+                let net_io: Box<dyn TNetIO + Send + Sync> = match tls_acceptor {
+                    Some(acceptor) => match acceptor.accept(tcp_stream).await {
+                        Ok(tls_stream) => Box::new(TlsIO::new(tls_stream)),
+                        Err(err) => {
+                            log::error!("rtmps tls handshake error: {}", err);
+                            return;
+                        }
+                    },
+                    None => Box::new(TcpIO::new(tcp_stream)),
+                };
+
+                let mut session = server_session::ServerSession::new(
+                    net_io,
+                    Some(remote_addr),
+                    event_producer,
+                    gop_num,
+                    auth,
+                );
+                session_counters.session_connected("rtmp");
                 if let Err(err) = session.run().await {
+                    session_counters.session_error("rtmp");
                     log::error!(
                         "session run error: session_type: {}, app_name: {}, stream_name: {}, err: {}",
                         session.common.session_type,
@@ -44,6 +88,7 @@ impl RtmpServer {
                         err
                     );
                 }
+                session_counters.session_disconnected("rtmp");
             });
         }
     }
@@ -25,16 +25,23 @@ use {
     bytesio::{
         bytes_writer::AsyncBytesWriter,
         bytesio::{TNetIO, TcpIO},
+        bytesio_errors::{BytesIOError, BytesIOErrorValue},
     },
     indexmap::IndexMap,
-    std::sync::Arc,
+    std::{sync::Arc, time::Duration},
     //crate::utils::print::print,
     streamhub::define::StreamHubEventSender,
     streamhub::utils::RandomDigitCount,
     streamhub::utils::Uuid,
-    tokio::{net::TcpStream, sync::Mutex},
+    tokio::{
+        net::TcpStream,
+        sync::{oneshot, Mutex},
+    },
 };
 
+//the pull session is treated as broken if no data is received for a while
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[allow(dead_code)]
 enum ClientSessionState {
     Handshake,
@@ -88,6 +95,9 @@ pub struct ClientSession {
     sub_stream_name: Option<String>,
     /*configure how many gops will be cached.*/
     gop_num: usize,
+    /*used to clean up the stream hub when the session is finished*/
+    is_published: bool,
+    is_subscribed: bool,
 }
 
 impl ClientSession {
@@ -118,7 +128,8 @@ impl ClientSession {
             None
         };
 
-        let common = Common::new(packetizer, event_producer, SessionType::Client, remote_addr);
+        let mut common = Common::new(packetizer, event_producer, SessionType::Client, remote_addr);
+        common.request_url = format!("rtmp://{raw_domain_name}/{app_name}/{raw_stream_name}");
 
         let (stream_name, _) = RtmpUrlParser::default()
             .set_raw_stream_name(raw_stream_name.clone())
@@ -139,10 +150,61 @@ impl ClientSession {
             sub_app_name: None,
             sub_stream_name: None,
             gop_num,
+            is_published: false,
+            is_subscribed: false,
         }
     }
 
     pub async fn run(&mut self) -> Result<(), SessionError> {
+        let rv = self.run_loop().await;
+        self.clean_up().await?;
+        rv
+    }
+
+    /*clean up so that the stream can be published or subscribed again by a new session,
+    it should also be called if the run future is dropped before finishing.*/
+    pub async fn clean_up(&mut self) -> Result<(), SessionError> {
+        if self.is_published {
+            self.is_published = false;
+            self.common
+                .unpublish_to_channels(
+                    self.app_name.clone(),
+                    self.stream_name.clone(),
+                    self.session_id,
+                )
+                .await?;
+        }
+        if self.is_subscribed {
+            self.is_subscribed = false;
+            let (app_name, stream_name) = self.get_subscribed_stream();
+            self.common
+                .unsubscribe_from_channels(app_name, stream_name, self.session_id)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /*Run until the session finishes or the cancel sender is dropped, returns none
+    if it is cancelled. The stream hub is cleaned up in both cases.*/
+    pub async fn run_until_cancelled(
+        &mut self,
+        cancel_receiver: &mut oneshot::Receiver<()>,
+    ) -> Option<Result<(), SessionError>> {
+        let rv = tokio::select! {
+            rv = self.run() => Some(rv),
+            _ = cancel_receiver => None,
+        };
+
+        if rv.is_none() {
+            if let Err(err) = self.clean_up().await {
+                log::error!("client session clean up error: {}", err);
+            }
+        }
+        rv
+    }
+
+    async fn run_loop(&mut self) -> Result<(), SessionError> {
         loop {
             match self.state {
                 ClientSessionState::Handshake => {
@@ -181,7 +243,18 @@ impl ClientSession {
                 ClientSessionState::WaitStateChange => {}
             }
 
-            let data = self.io.lock().await.read().await?;
+            let data = if self.client_type == ClientType::Play {
+                match tokio::time::timeout(RECEIVE_TIMEOUT, self.io.lock().await.read()).await {
+                    Ok(data) => data?,
+                    Err(_) => {
+                        return Err(SessionError::from(BytesIOError {
+                            value: BytesIOErrorValue::TimeoutError,
+                        }));
+                    }
+                }
+            } else {
+                self.io.lock().await.read().await?
+            };
             self.unpacketizer.extend_data(&data[..]);
 
             loop {
@@ -261,12 +334,15 @@ impl ClientSession {
                 self.on_stream_is_recorded(stream_id)?;
             }
             RtmpMessageData::AudioData { data } => {
+                self.publish_to_local().await?;
                 self.common.on_audio_data(data, timestamp).await?
             }
             RtmpMessageData::VideoData { data } => {
+                self.publish_to_local().await?;
                 self.common.on_video_data(data, timestamp).await?
             }
             RtmpMessageData::AmfData { raw_data } => {
+                self.publish_to_local().await?;
                 self.common.on_meta_data(raw_data, timestamp).await?;
             }
 
@@ -345,6 +421,12 @@ impl ClientSession {
             app_name = self.app_name
         );
         properties.app = Some(self.app_name.clone());
+        properties.fourcc_list = Some(
+            define::FOURCC_LIST
+                .iter()
+                .map(|fourcc| fourcc.to_string())
+                .collect(),
+        );
 
         match self.client_type {
             ClientType::Play => {
@@ -523,37 +605,15 @@ impl ClientSession {
                 "NetStream.Publish.Start" => {
                     self.state = ClientSessionState::StartPublish;
                     //subscribe from local session and publish to remote rtmp server
-                    if let (Some(app_name), Some(stream_name)) =
-                        (&self.sub_app_name, &self.sub_stream_name)
-                    {
-                        self.common
-                            .subscribe_from_channels(
-                                app_name.clone(),
-                                stream_name.clone(),
-                                self.session_id,
-                            )
-                            .await?;
-                    } else {
-                        self.common
-                            .subscribe_from_channels(
-                                self.app_name.clone(),
-                                self.stream_name.clone(),
-                                self.session_id,
-                            )
-                            .await?;
-                    }
+                    let (app_name, stream_name) = self.get_subscribed_stream();
+                    self.common
+                        .subscribe_from_channels(app_name, stream_name, self.session_id)
+                        .await?;
+                    self.is_subscribed = true;
                 }
                 "NetStream.Publish.Reset" => {}
                 "NetStream.Play.Start" => {
-                    //pull from remote rtmp server and publish to local session
-                    self.common
-                        .publish_to_channels(
-                            self.app_name.clone(),
-                            self.stream_name.clone(),
-                            self.session_id,
-                            self.gop_num,
-                        )
-                        .await?
+                    log::info!("[C <- S] play start: {}", self.stream_name);
                 }
                 _ => {}
             }
@@ -562,8 +622,38 @@ impl ClientSession {
         Ok(())
     }
 
+    /*Pull from remote rtmp server and publish to local session. The stream is
+    published until the first media data arrives, because the remote server may
+    respond Play.Start before it finds the stream(e.g. an origin without it).*/
+    async fn publish_to_local(&mut self) -> Result<(), SessionError> {
+        if self.client_type != ClientType::Play || self.is_published {
+            return Ok(());
+        }
+
+        self.common
+            .publish_to_channels(
+                self.app_name.clone(),
+                self.stream_name.clone(),
+                self.session_id,
+                self.gop_num,
+            )
+            .await?;
+        self.is_published = true;
+
+        Ok(())
+    }
+
     pub fn subscribe(&mut self, app_name: String, stream_name: String) {
         self.sub_app_name = Some(app_name);
         self.sub_stream_name = Some(stream_name);
     }
+
+    //the local stream which is pushed to the remote server
+    fn get_subscribed_stream(&self) -> (String, String) {
+        if let (Some(app_name), Some(stream_name)) = (&self.sub_app_name, &self.sub_stream_name) {
+            (app_name.clone(), stream_name.clone())
+        } else {
+            (self.app_name.clone(), self.stream_name.clone())
+        }
+    }
 }
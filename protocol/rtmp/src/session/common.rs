@@ -21,16 +21,16 @@ use {
     std::{net::SocketAddr, sync::Arc},
     streamhub::{
         define::{
-            FrameData, FrameDataReceiver, FrameDataSender, InformationSender, NotifyInfo,
-            PublishType, PublisherInfo, StreamHubEvent, StreamHubEventSender, SubscribeType,
-            SubscriberInfo, TStreamHandler,
+            wait_pub_sub_result, FrameData, FrameDataReceiver, FrameDataSender, InformationSender,
+            NotifyInfo, PublishType, PublisherInfo, StreamHubEvent, StreamHubEventSender,
+            SubscribeType, SubscriberInfo, TStreamHandler,
         },
         errors::{ChannelError, ChannelErrorValue},
         statistics::StreamStatistics,
         stream::StreamIdentifier,
         utils::Uuid,
     },
-    tokio::sync::{mpsc, Mutex},
+    tokio::sync::{mpsc, oneshot, Mutex},
 };
 
 pub struct Common {
@@ -44,10 +44,12 @@ pub struct Common {
     pub session_type: SessionType,
 
     /*save the client side socket connected to the SeverSession */
-    remote_addr: Option<SocketAddr>,
+    pub remote_addr: Option<SocketAddr>,
     /*request URL from client*/
     pub request_url: String,
     pub stream_handler: Arc<RtmpStreamHandler>,
+    /*overrides the publish type derived from the session type, e.g.: the remuxed streams*/
+    pub publish_type: Option<PublishType>,
 }
 
 impl Common {
@@ -71,6 +73,7 @@ impl Common {
             remote_addr,
             request_url: String::default(),
             stream_handler: Arc::new(RtmpStreamHandler::new()),
+            publish_type: None,
             //cache: None,
         }
     }
@@ -273,9 +276,10 @@ impl Common {
             String::from("unknown")
         };
 
-        let pub_type = match self.session_type {
-            SessionType::Client => PublishType::RelayRtmp,
-            SessionType::Server => PublishType::PushRtmp,
+        let pub_type = match (&self.publish_type, &self.session_type) {
+            (Some(pub_type), _) => pub_type.clone(),
+            (None, SessionType::Client) => PublishType::RelayRtmp,
+            (None, SessionType::Server) => PublishType::PushRtmp,
         };
 
         PublisherInfo {
@@ -309,10 +313,12 @@ impl Common {
             stream_name,
         };
 
+        let (result_sender, result_receiver) = oneshot::channel();
         let subscribe_event = StreamHubEvent::Subscribe {
             identifier,
             info: self.get_subscriber_info(sub_id),
             sender: DataSender::Frame { sender },
+            result_sender,
         };
         let rv = self.event_producer.send(subscribe_event);
 
@@ -321,6 +327,7 @@ impl Common {
                 value: SessionErrorValue::StreamHubEventSendErr,
             });
         }
+        wait_pub_sub_result(result_receiver).await?;
 
         self.data_receiver = receiver;
 
@@ -362,6 +369,7 @@ impl Common {
             .await;
 
         let (sender, receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
 
         let publish_event = StreamHubEvent::Publish {
             identifier: StreamIdentifier::Rtmp {
@@ -374,6 +382,7 @@ impl Common {
                 packet_receiver: None,
                 frame_receiver: Some(receiver),
             },
+            result_sender,
         };
 
         if self.event_producer.send(publish_event).is_err() {
@@ -381,6 +390,7 @@ impl Common {
                 value: SessionErrorValue::StreamHubEventSendErr,
             });
         }
+        wait_pub_sub_result(result_receiver).await?;
 
         self.data_sender = sender;
         Ok(())
@@ -513,6 +523,7 @@ impl TStreamHandler for RtmpStreamHandler {
                 SubscribeType::PlayerRtmp
                 | SubscribeType::PlayerHttpFlv
                 | SubscribeType::PlayerHls
+                | SubscribeType::PlayerSrt
                 | SubscribeType::GenerateHls => {
                     if let Some(gops_data) = cache.get_gops_data() {
                         for gop in gops_data {
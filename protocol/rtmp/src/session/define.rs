@@ -13,6 +13,11 @@ pub const FMSVER: &str = "FMS/3,0,1,123";
 pub const CAPABILITIES: f64 = 31.0;
 pub const LEVEL: &str = "status";
 
+//the video codecs supported by the enhanced rtmp
+pub const FOURCC_LIST: [&str; 3] = ["av01", "vp09", "hvc1"];
+//the client can use it to signal that it can forward any codec
+pub const FOURCC_WILDCARD: &str = "*";
+
 pub const OBJENCODING_AMF0: f64 = 0.0;
 pub const OBJENCODING_AMF3: f64 = 3.0;
 
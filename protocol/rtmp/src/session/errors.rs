@@ -13,6 +13,7 @@ use {
     bytesio::{bytes_errors::BytesWriteError, bytesio_errors::BytesIOError},
     failure::{Backtrace, Fail},
     std::fmt,
+    streamhub::errors::{AuthError, ChannelError},
 };
 
 #[derive(Debug)]
@@ -50,6 +51,10 @@ pub enum SessionErrorValue {
     HandshakeError(#[cause] HandshakeError),
     #[fail(display = "cache error name: {}\n", _0)]
     CacheError(#[cause] CacheError),
+    #[fail(display = "auth error: {}\n", _0)]
+    AuthError(#[cause] AuthError),
+    #[fail(display = "stream hub error: {}\n", _0)]
+    ChannelError(#[cause] ChannelError),
 
     #[fail(display = "amf0 count not correct error\n")]
     Amf0ValueCountNotCorrect,
@@ -179,6 +184,22 @@ impl From<CacheError> for SessionError {
     }
 }
 
+impl From<ChannelError> for SessionError {
+    fn from(error: ChannelError) -> Self {
+        SessionError {
+            value: SessionErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<AuthError> for SessionError {
+    fn from(error: AuthError) -> Self {
+        SessionError {
+            value: SessionErrorValue::AuthError(error),
+        }
+    }
+}
+
 impl fmt::Display for SessionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Display::fmt(&self.value, f)
@@ -23,17 +23,17 @@ use {
         utils::RtmpUrlParser,
     },
     bytes::BytesMut,
-    bytesio::{
-        bytes_writer::AsyncBytesWriter,
-        bytesio::{TNetIO, TcpIO},
-    },
+    bytesio::{bytes_writer::AsyncBytesWriter, bytesio::TNetIO},
     indexmap::IndexMap,
-    std::{sync::Arc, time::Duration},
+    std::{net::SocketAddr, sync::Arc, time::Duration},
     streamhub::{
+        auth::{Auth, AuthAction},
         define::StreamHubEventSender,
+        errors::AuthError,
+        stream::StreamIdentifier,
         utils::{RandomDigitCount, Uuid},
     },
-    tokio::{net::TcpStream, sync::Mutex},
+    tokio::sync::Mutex,
 };
 
 enum ServerSessionState {
@@ -64,19 +64,24 @@ pub struct ServerSession {
     pub common: Common,
     /*configure how many gops will be cached.*/
     gop_num: usize,
+    /*check the publish/play requests, no check if it is none.*/
+    auth: Option<Arc<Auth>>,
 }
 
 impl ServerSession {
-    pub fn new(stream: TcpStream, event_producer: StreamHubEventSender, gop_num: usize) -> Self {
-        let remote_addr = if let Ok(addr) = stream.peer_addr() {
+    //the net io is a plain tcp connection or a tls one(rtmps)
+    pub fn new(
+        net_io: Box<dyn TNetIO + Send + Sync>,
+        remote_addr: Option<SocketAddr>,
+        event_producer: StreamHubEventSender,
+        gop_num: usize,
+        auth: Option<Arc<Auth>>,
+    ) -> Self {
+        if let Some(addr) = remote_addr {
             log::info!("server session: {}", addr.to_string());
-            Some(addr)
-        } else {
-            None
-        };
+        }
 
-        let tcp_io: Box<dyn TNetIO + Send + Sync> = Box::new(TcpIO::new(stream));
-        let net_io = Arc::new(Mutex::new(tcp_io));
+        let net_io = Arc::new(Mutex::new(net_io));
 
         Self {
             app_name: String::from(""),
@@ -97,6 +102,7 @@ impl ServerSession {
             has_remaing_data: false,
             connect_properties: ConnectProperties::default(),
             gop_num,
+            auth,
         }
     }
 
@@ -400,6 +406,19 @@ impl ServerSession {
                         self.connect_properties.object_encoding = Some(*object_encoding);
                     }
                 }
+                "fourCcList" => {
+                    if let Amf0ValueType::StrictArray(fourcc_list) = value {
+                        self.connect_properties.fourcc_list = Some(
+                            fourcc_list
+                                .iter()
+                                .filter_map(|fourcc| match fourcc {
+                                    Amf0ValueType::UTF8String(fourcc) => Some(fourcc.clone()),
+                                    _ => None,
+                                })
+                                .collect(),
+                        );
+                    }
+                }
                 _ => {
                     log::warn!("unknown connect properties: {}:{:?}", property, value);
                 }
@@ -456,12 +475,30 @@ impl ServerSession {
                 define::LEVEL,
                 &String::from("Connection Succeeded."),
                 encoding,
+                &self.negotiate_fourcc_list(),
             )
             .await?;
 
         Ok(())
     }
 
+    //replies the enhanced rtmp codecs which are supported by both sides,
+    //nothing is replied to the legacy clients.
+    fn negotiate_fourcc_list(&self) -> Option<Vec<String>> {
+        let client_fourcc_list = self.connect_properties.fourcc_list.as_ref()?;
+        let wildcard = client_fourcc_list
+            .iter()
+            .any(|fourcc| fourcc == define::FOURCC_WILDCARD);
+
+        Some(
+            define::FOURCC_LIST
+                .iter()
+                .filter(|fourcc| wildcard || client_fourcc_list.iter().any(|v| v == *fourcc))
+                .map(|fourcc| fourcc.to_string())
+                .collect(),
+        )
+    }
+
     pub async fn on_create_stream(&mut self, transaction_id: &f64) -> Result<(), SessionError> {
         let mut netconnection = NetConnection::new(Arc::clone(&self.io));
         netconnection
@@ -572,6 +609,45 @@ impl ServerSession {
             break;
         }
 
+        let raw_stream_name = stream_name.unwrap();
+
+        (self.stream_name, self.url_parameters) = RtmpUrlParser::default()
+            .set_raw_stream_name(raw_stream_name.clone())
+            .parse_raw_stream_name();
+
+        /*Now it can update the request url*/
+        self.common.request_url = self.get_request_url(raw_stream_name);
+        let rv = match self.authenticate(AuthAction::Play).await {
+            Ok(()) => {
+                self.common
+                    .subscribe_from_channels(
+                        self.app_name.clone(),
+                        self.stream_name.clone(),
+                        self.session_id,
+                    )
+                    .await
+            }
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = rv {
+            log::error!(
+                "[ S->C ] [NetStream.Play.Failed]  app_name: {}, stream_name: {}, err: {}",
+                self.app_name,
+                self.stream_name,
+                err
+            );
+            let mut netstream = NetStreamWriter::new(Arc::clone(&self.io));
+            netstream
+                .write_on_status(
+                    transaction_id,
+                    "error",
+                    "NetStream.Play.Failed",
+                    err.to_string().trim_end(),
+                )
+                .await?;
+            return Err(err);
+        }
+
         let mut event_messages = EventMessagesWriter::new(AsyncBytesWriter::new(self.io.clone()));
         event_messages.write_stream_begin(*stream_id).await?;
         log::info!(
@@ -620,12 +696,6 @@ impl ServerSession {
 
         event_messages.write_stream_is_record(*stream_id).await?;
 
-        let raw_stream_name = stream_name.unwrap();
-
-        (self.stream_name, self.url_parameters) = RtmpUrlParser::default()
-            .set_raw_stream_name(raw_stream_name.clone())
-            .parse_raw_stream_name();
-
         log::info!(
             "[ S->C ] [stream is record]  app_name: {}, stream_name: {}, url parameters: {}",
             self.app_name,
@@ -633,16 +703,6 @@ impl ServerSession {
             self.url_parameters
         );
 
-        /*Now it can update the request url*/
-        self.common.request_url = self.get_request_url(raw_stream_name);
-        self.common
-            .subscribe_from_channels(
-                self.app_name.clone(),
-                self.stream_name.clone(),
-                self.session_id,
-            )
-            .await?;
-
         self.state = ServerSessionState::Play;
 
         Ok(())
@@ -694,6 +754,38 @@ impl ServerSession {
             self.url_parameters
         );
 
+        let rv = match self.authenticate(AuthAction::Publish).await {
+            Ok(()) => {
+                self.common
+                    .publish_to_channels(
+                        self.app_name.clone(),
+                        self.stream_name.clone(),
+                        self.session_id,
+                        self.gop_num,
+                    )
+                    .await
+            }
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = rv {
+            log::error!(
+                "[ S->C ] [NetStream.Publish.BadName]  app_name: {}, stream_name: {}, err: {}",
+                self.app_name,
+                self.stream_name,
+                err
+            );
+            let mut netstream = NetStreamWriter::new(Arc::clone(&self.io));
+            netstream
+                .write_on_status(
+                    transaction_id,
+                    "error",
+                    "NetStream.Publish.BadName",
+                    err.to_string().trim_end(),
+                )
+                .await?;
+            return Err(err);
+        }
+
         log::info!(
             "[ S->C ] [stream begin]  app_name: {}, stream_name: {}, url parameters: {}",
             self.app_name,
@@ -714,15 +806,23 @@ impl ServerSession {
             self.stream_name
         );
 
-        self.common
-            .publish_to_channels(
-                self.app_name.clone(),
-                self.stream_name.clone(),
-                self.session_id,
-                self.gop_num,
-            )
-            .await?;
+        Ok(())
+    }
 
+    async fn authenticate(&self, action: AuthAction) -> Result<(), AuthError> {
+        if let Some(auth) = &self.auth {
+            let identifier = StreamIdentifier::Rtmp {
+                app_name: self.app_name.clone(),
+                stream_name: self.stream_name.clone(),
+            };
+            let remote_addr = if let Some(addr) = self.common.remote_addr {
+                addr.to_string()
+            } else {
+                String::from("unknown")
+            };
+            auth.authenticate(&identifier, action, &self.url_parameters, &remote_addr)
+                .await?;
+        }
         Ok(())
     }
 }
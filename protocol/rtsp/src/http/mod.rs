@@ -7,10 +7,12 @@ use indexmap::IndexMap;
 pub struct RtspRequest {
     pub method: String,
     pub url: String,
-    //url = "rtsp://{}:{}/{}", address, port, path
+    //url = "rtsp(s)://{}:{}/{}", address, port, path
     pub address: String,
     pub port: u16,
     pub path: String,
+    //the query string after '?', e.g. token=xxx
+    pub path_parameters: Option<String>,
     pub version: String,
     pub headers: IndexMap<String, String>,
     pub body: Option<String>,
@@ -37,11 +39,19 @@ impl Unmarshal for RtspRequest {
                 if let Some(url) = fields.next() {
                     rtsp_request.url = url.to_string();
 
-                    if let Some(val) = url.strip_prefix("rtsp://") {
+                    if let Some(val) = url
+                        .strip_prefix("rtsp://")
+                        .or_else(|| url.strip_prefix("rtsps://"))
+                    {
                         if let Some(index) = val.find('/') {
-                            let path = &url[7 + index + 1..];
-                            rtsp_request.path = String::from(path);
-                            let address_with_port = &url[7..7 + index];
+                            let path = &val[index + 1..];
+                            if let Some((path, path_parameters)) = path.split_once('?') {
+                                rtsp_request.path = String::from(path);
+                                rtsp_request.path_parameters = Some(String::from(path_parameters));
+                            } else {
+                                rtsp_request.path = String::from(path);
+                            }
+                            let address_with_port = &val[..index];
 
                             let (address_val, port_val) =
                                 rtsp_utils::scanf!(address_with_port, ':', String, u16);
@@ -111,6 +121,16 @@ pub struct RtspResponse {
     pub body: Option<String>,
 }
 
+impl RtspResponse {
+    //the header names from the remote servers are case-insensitive
+    pub fn get_header(&self, header_name: &str) -> Option<&String> {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(header_name))
+            .map(|(_, value)| value)
+    }
+}
+
 impl Unmarshal for RtspResponse {
     fn unmarshal(request_data: &str) -> Option<Self> {
         let mut rtsp_response = RtspResponse::default();
@@ -301,6 +321,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_rtsp_request_path_parameters() {
+        let data = "DESCRIBE rtsp://127.0.0.1:5544/live/test?token=123456 RTSP/1.0\r\n\
+        CSeq: 2\r\n\
+        \r\n";
+
+        let request = RtspRequest::unmarshal(data).unwrap();
+        assert_eq!(request.path, "live/test");
+        assert_eq!(request.path_parameters, Some(String::from("token=123456")));
+        assert_eq!(request.port, 5544);
+    }
+
     #[test]
     fn test_http_status_code() {
         let stats_code = http::StatusCode::OK;
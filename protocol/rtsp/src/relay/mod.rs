@@ -0,0 +1,2 @@
+pub mod pull_client;
+pub mod push_client;
@@ -0,0 +1,235 @@
+use {
+    crate::{
+        rtsp_transport::ProtocolType,
+        rtsp_url::RtspUrl,
+        session::client_session::{ClientType, RtspClientSession},
+        session::errors::SessionError,
+    },
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    },
+    streamhub::{
+        define::{BroadcastEvent, BroadcastEventReceiver, StreamHubEventSender},
+        stream::StreamIdentifier,
+    },
+    tokio::{
+        net::TcpStream,
+        sync::{broadcast::error::RecvError, oneshot},
+        time::sleep,
+    },
+};
+
+const INITIAL_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+//stop pulling after failing to pull the stream so many times continuously,
+//it will be pulled again when a new subscriber arrives.
+const MAX_RETRY_TIMES: u32 = 10;
+//the retry interval is reset if a session has lasted so long
+const STABLE_SESSION_DURATION: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct RtspPullTarget {
+    //the local stream path, e.g. camera/door
+    pub stream_path: String,
+    pub url: RtspUrl,
+    pub protocol_type: ProtocolType,
+}
+
+struct PullTask {
+    id: u64,
+    /*the pull task exits once this sender is dropped*/
+    _cancel_sender: oneshot::Sender<()>,
+}
+
+// Pull the rtsp streams from the remote servers(e.g. the IP cameras) on demand:
+// the pull session starts when the first subscriber of the stream arrives, and
+// stops when the last one leaves.
+pub struct PullClient {
+    targets: HashMap<String, RtspPullTarget>,
+    //the stream paths which are being pulled
+    pulling_streams: Arc<Mutex<HashMap<String, PullTask>>>,
+    next_task_id: u64,
+    client_event_consumer: BroadcastEventReceiver,
+    channel_event_producer: StreamHubEventSender,
+}
+
+impl PullClient {
+    pub fn new(
+        targets: Vec<RtspPullTarget>,
+        consumer: BroadcastEventReceiver,
+        producer: StreamHubEventSender,
+    ) -> Self {
+        Self {
+            targets: targets
+                .into_iter()
+                .map(|target| (target.stream_path.clone(), target))
+                .collect(),
+            pulling_streams: Arc::new(Mutex::new(HashMap::new())),
+            next_task_id: 0,
+            client_event_consumer: consumer,
+            channel_event_producer: producer,
+        }
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            let event = match self.client_event_consumer.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(count)) => {
+                    log::warn!("rtsp pull client lagged {} events", count);
+                    continue;
+                }
+                Err(RecvError::Closed) => {
+                    return;
+                }
+            };
+
+            match event {
+                BroadcastEvent::Subscribe { identifier, .. } => {
+                    if let Some(stream_path) = get_stream_path(identifier) {
+                        self.on_subscribe(stream_path);
+                    }
+                }
+                BroadcastEvent::UnSubscribe { identifier } => {
+                    /*the pull session is stopped by dropping the cancel sender*/
+                    if let Some(stream_path) = get_stream_path(identifier) {
+                        if self
+                            .pulling_streams
+                            .lock()
+                            .unwrap()
+                            .remove(&stream_path)
+                            .is_some()
+                        {
+                            log::info!("stop pulling rtsp stream: {}, no subscriber", stream_path);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn on_subscribe(&mut self, stream_path: String) {
+        let target = if let Some(target) = self.targets.get(&stream_path) {
+            target.clone()
+        } else {
+            return;
+        };
+
+        let mut pulling_streams = self.pulling_streams.lock().unwrap();
+        if pulling_streams.contains_key(&stream_path) {
+            return;
+        }
+
+        log::info!(
+            "receive rtsp pull event, stream path: {}, url: {}",
+            stream_path,
+            target.url.address()
+        );
+        let id = self.next_task_id;
+        self.next_task_id += 1;
+        let (cancel_sender, cancel_receiver) = oneshot::channel();
+        pulling_streams.insert(
+            stream_path.clone(),
+            PullTask {
+                id,
+                _cancel_sender: cancel_sender,
+            },
+        );
+
+        let pulling_streams = self.pulling_streams.clone();
+        let producer = self.channel_event_producer.clone();
+        tokio::spawn(async move {
+            Self::pull_with_retry(&target, producer, cancel_receiver).await;
+
+            let mut pulling_streams = pulling_streams.lock().unwrap();
+            //the stream may be pulled again by a new task after this one is cancelled
+            if matches!(pulling_streams.get(&stream_path), Some(task) if task.id == id) {
+                pulling_streams.remove(&stream_path);
+            }
+        });
+    }
+
+    async fn pull_with_retry(
+        target: &RtspPullTarget,
+        producer: StreamHubEventSender,
+        mut cancel_receiver: oneshot::Receiver<()>,
+    ) {
+        let mut retry_times = 0;
+        let mut retry_interval = INITIAL_RETRY_INTERVAL;
+
+        loop {
+            let begin_time = Instant::now();
+            match Self::pull(target, producer.clone(), &mut cancel_receiver).await {
+                Some(Ok(())) => {}
+                Some(Err(err)) => log::error!(
+                    "rtsp pull client session error, stream path: {}, err: {}",
+                    target.stream_path,
+                    err
+                ),
+                None => return,
+            }
+
+            if begin_time.elapsed() > STABLE_SESSION_DURATION {
+                retry_times = 0;
+                retry_interval = INITIAL_RETRY_INTERVAL;
+            }
+
+            retry_times += 1;
+            if retry_times > MAX_RETRY_TIMES {
+                log::error!(
+                    "rtsp pull client stops after retrying {} times, stream path: {}",
+                    MAX_RETRY_TIMES,
+                    target.stream_path
+                );
+                return;
+            }
+
+            tokio::select! {
+                _ = sleep(retry_interval) => {}
+                _ = &mut cancel_receiver => return,
+            }
+            retry_interval = std::cmp::min(retry_interval * 2, MAX_RETRY_INTERVAL);
+        }
+    }
+
+    //return None if the pull is cancelled
+    async fn pull(
+        target: &RtspPullTarget,
+        producer: StreamHubEventSender,
+        cancel_receiver: &mut oneshot::Receiver<()>,
+    ) -> Option<Result<(), SessionError>> {
+        let stream = tokio::select! {
+            rv = TcpStream::connect(target.url.address()) => rv,
+            _ = &mut *cancel_receiver => return None,
+        };
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => return Some(Err(err.into())),
+        };
+
+        let mut client_session = RtspClientSession::new(
+            stream,
+            ClientType::Pull,
+            target.url.clone(),
+            target.stream_path.clone(),
+            target.protocol_type.clone(),
+            producer,
+        );
+        client_session.run_until_cancelled(cancel_receiver).await
+    }
+}
+
+//the rtsp stream is remuxed to the rtmp stream {app_name}/{stream_name}
+fn get_stream_path(identifier: StreamIdentifier) -> Option<String> {
+    match identifier {
+        StreamIdentifier::Rtsp { stream_path } => Some(stream_path),
+        StreamIdentifier::Rtmp {
+            app_name,
+            stream_name,
+        } => Some(format!("{app_name}/{stream_name}")),
+        _ => None,
+    }
+}
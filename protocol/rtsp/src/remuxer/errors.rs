@@ -0,0 +1,89 @@
+use {
+    bytesio::bytes_errors::BytesReadError,
+    failure::{Backtrace, Fail},
+    std::fmt,
+    streamhub::errors::ChannelError,
+    tokio::sync::broadcast::error::RecvError,
+    xflv::errors::{FlvDemuxerError, Mpeg4AvcHevcError},
+};
+
+#[derive(Debug)]
+pub struct RtspRemuxerError {
+    pub value: RtspRemuxerErrorValue,
+}
+
+#[derive(Debug, Fail)]
+pub enum RtspRemuxerErrorValue {
+    #[fail(display = "receive error: {}\n", _0)]
+    RecvError(#[cause] RecvError),
+    #[fail(display = "bytes read error: {}\n", _0)]
+    BytesReadError(#[cause] BytesReadError),
+    #[fail(display = "flv demuxer error: {}\n", _0)]
+    FlvDemuxerError(#[cause] FlvDemuxerError),
+    #[fail(display = "mpeg avc error: {}\n", _0)]
+    MpegAvcError(#[cause] Mpeg4AvcHevcError),
+    #[fail(display = "stream hub event send error\n")]
+    StreamHubEventSendErr,
+    #[fail(display = "stream hub error: {}\n", _0)]
+    ChannelError(#[cause] ChannelError),
+    #[fail(display = "frame data send error\n")]
+    FrameDataSendErr,
+    #[fail(display = "invalid sps\n")]
+    InvalidSps,
+}
+
+impl From<ChannelError> for RtspRemuxerError {
+    fn from(error: ChannelError) -> Self {
+        RtspRemuxerError {
+            value: RtspRemuxerErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<RecvError> for RtspRemuxerError {
+    fn from(error: RecvError) -> Self {
+        RtspRemuxerError {
+            value: RtspRemuxerErrorValue::RecvError(error),
+        }
+    }
+}
+
+impl From<BytesReadError> for RtspRemuxerError {
+    fn from(error: BytesReadError) -> Self {
+        RtspRemuxerError {
+            value: RtspRemuxerErrorValue::BytesReadError(error),
+        }
+    }
+}
+
+impl From<FlvDemuxerError> for RtspRemuxerError {
+    fn from(error: FlvDemuxerError) -> Self {
+        RtspRemuxerError {
+            value: RtspRemuxerErrorValue::FlvDemuxerError(error),
+        }
+    }
+}
+
+impl From<Mpeg4AvcHevcError> for RtspRemuxerError {
+    fn from(error: Mpeg4AvcHevcError) -> Self {
+        RtspRemuxerError {
+            value: RtspRemuxerErrorValue::MpegAvcError(error),
+        }
+    }
+}
+
+impl fmt::Display for RtspRemuxerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for RtspRemuxerError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
@@ -0,0 +1,59 @@
+pub mod errors;
+pub mod rtmp2rtsp;
+
+use streamhub::{
+    define::{BroadcastEvent, BroadcastEventReceiver, PublishType, StreamHubEventSender},
+    stream::StreamIdentifier,
+};
+
+use self::{errors::RtspRemuxerError, rtmp2rtsp::Rtmp2RtspRemuxerSession};
+
+//Receive publish event from stream hub and
+//remux from rtmp to rtsp for the rtsp players
+pub struct RtspRemuxer {
+    receiver: BroadcastEventReceiver,
+    event_producer: StreamHubEventSender,
+}
+
+impl RtspRemuxer {
+    pub fn new(receiver: BroadcastEventReceiver, event_producer: StreamHubEventSender) -> Self {
+        Self {
+            receiver,
+            event_producer,
+        }
+    }
+    pub async fn run(&mut self) -> Result<(), RtspRemuxerError> {
+        log::info!("rtsp remuxer start...");
+
+        loop {
+            let val = self.receiver.recv().await?;
+            match val {
+                BroadcastEvent::Publish { identifier, info } => {
+                    /*the rtmp stream remuxed from rtsp/whip is not remuxed again*/
+                    if info.pub_type == PublishType::RemuxRtmp {
+                        continue;
+                    }
+                    if let StreamIdentifier::Rtmp {
+                        app_name,
+                        stream_name,
+                    } = identifier
+                    {
+                        let mut session = Rtmp2RtspRemuxerSession::new(
+                            app_name,
+                            stream_name,
+                            self.event_producer.clone(),
+                        );
+                        tokio::spawn(async move {
+                            if let Err(err) = session.run().await {
+                                log::error!("rtmp2rtsp session error: {}\n", err);
+                            }
+                        });
+                    }
+                }
+                _ => {
+                    log::trace!("other infos...");
+                }
+            }
+        }
+    }
+}
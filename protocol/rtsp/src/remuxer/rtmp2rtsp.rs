@@ -0,0 +1,454 @@
+use {
+    super::errors::{RtspRemuxerError, RtspRemuxerErrorValue},
+    crate::{global_trait::Unmarshal as SdpUnmarshal, sdp::Sdp, session::RtspStreamHandler},
+    base64::{engine::general_purpose, Engine as _},
+    bytes::BytesMut,
+    bytesio::bytes_reader::BytesReader,
+    std::{sync::Arc, time::Duration},
+    streamhub::{
+        define::{
+            wait_pub_sub_result, DataReceiver, DataSender, FrameData, FrameDataReceiver,
+            FrameDataSender, NotifyInfo, PublishType, PublisherInfo, StreamHubEvent,
+            StreamHubEventSender, SubscribeType, SubscriberInfo,
+        },
+        stream::StreamIdentifier,
+        utils::{RandomDigitCount, Uuid},
+    },
+    tokio::{
+        sync::{mpsc, oneshot},
+        time::sleep,
+    },
+    xflv::{
+        define::{aac_packet_type, AvcCodecId, SoundFormat},
+        demuxer::{FlvAudioTagDemuxer, FlvVideoTagDemuxer},
+        flv_tag_header::{AudioTagHeader, VideoTagHeader},
+        mpeg4_avc::Mpeg4AvcProcessor,
+        Unmarshal,
+    },
+};
+
+const VIDEO_PAYLOAD_TYPE: u8 = 96;
+const AUDIO_PAYLOAD_TYPE: u8 = 97;
+/*the rtp clock rate of h264 is always 90kHz(RFC 6184)*/
+const H264_CLOCK_RATE: u32 = 90000;
+
+#[derive(Clone)]
+struct AacConfig {
+    sampling_frequency: u32,
+    channels: u8,
+    audio_specific_config: BytesMut,
+}
+
+//the rtmp timestamp(milliseconds) to the 32 bits rtp timestamp, it wraps
+//around deliberately, e.g. a negative pts is mapped to the end of the range.
+fn rtp_timestamp(timestamp: i64, clock_rate: u32) -> u32 {
+    (timestamp.wrapping_mul(clock_rate as i64) / 1000) as u32
+}
+
+//Generate the sdp from the sequence headers of the rtmp stream,
+//the sps/pps and the aac config are carried in the fmtp.
+fn gen_sdp(
+    parameter_sets: &Option<(BytesMut, BytesMut)>,
+    aac_config: &Option<AacConfig>,
+) -> Result<String, RtspRemuxerError> {
+    let mut sdp =
+        String::from("v=0\r\no=- 0 0 IN IP4 127.0.0.1\r\ns=xiu\r\nc=IN IP4 0.0.0.0\r\nt=0 0\r\n");
+
+    if let Some((sps, pps)) = parameter_sets {
+        /*nalu header, profile_idc, constraint flags and level_idc*/
+        if sps.len() < 4 {
+            return Err(RtspRemuxerError {
+                value: RtspRemuxerErrorValue::InvalidSps,
+            });
+        }
+        let profile_level_id = hex::encode_upper(&sps[1..4]);
+        sdp += &format!(
+            "m=video 0 RTP/AVP {pt}\r\n\
+            a=rtpmap:{pt} H264/{H264_CLOCK_RATE}\r\n\
+            a=fmtp:{pt} packetization-mode=1; sprop-parameter-sets={},{}; profile-level-id={profile_level_id}\r\n\
+            a=control:streamid=0\r\n",
+            general_purpose::STANDARD.encode(sps),
+            general_purpose::STANDARD.encode(pps),
+            pt = VIDEO_PAYLOAD_TYPE,
+        );
+    }
+
+    if let Some(config) = aac_config {
+        sdp += &format!(
+            "m=audio 0 RTP/AVP {pt}\r\n\
+            a=rtpmap:{pt} MPEG4-GENERIC/{}/{}\r\n\
+            a=fmtp:{pt} profile-level-id=1;mode=AAC-hbr;sizelength=13;indexlength=3;indexdeltalength=3; config={}\r\n\
+            a=control:streamid=1\r\n",
+            config.sampling_frequency,
+            config.channels,
+            hex::encode(&config.audio_specific_config),
+            pt = AUDIO_PAYLOAD_TYPE,
+        );
+    }
+
+    Ok(sdp)
+}
+
+pub struct Rtmp2RtspRemuxerSession {
+    event_producer: StreamHubEventSender,
+    app_name: String,
+    stream_name: String,
+    //RTMP
+    data_receiver: FrameDataReceiver,
+    subscribe_id: Uuid,
+    video_demuxer: FlvVideoTagDemuxer,
+    audio_demuxer: FlvAudioTagDemuxer,
+    //RTSP
+    stream_path: String,
+    publish_id: Uuid,
+    stream_handler: Arc<RtspStreamHandler>,
+    /*the rtsp stream is published when the first a/v frame is received,
+    then the sdp can be generated from the sequence headers*/
+    frame_sender: Option<FrameDataSender>,
+    parameter_sets: Option<(BytesMut, BytesMut)>,
+    aac_config: Option<AacConfig>,
+    /*only log once if the video codec is not supported*/
+    unsupported_codec_logged: bool,
+}
+
+impl Rtmp2RtspRemuxerSession {
+    pub fn new(
+        app_name: String,
+        stream_name: String,
+        event_producer: StreamHubEventSender,
+    ) -> Self {
+        let (_, data_consumer) = mpsc::unbounded_channel();
+
+        Self {
+            event_producer,
+            stream_path: format!("{app_name}/{stream_name}"),
+            app_name,
+            stream_name,
+            data_receiver: data_consumer,
+            subscribe_id: Uuid::new(RandomDigitCount::Four),
+            video_demuxer: FlvVideoTagDemuxer::new(),
+            audio_demuxer: FlvAudioTagDemuxer::new(),
+            publish_id: Uuid::new(RandomDigitCount::Four),
+            stream_handler: Arc::new(RtspStreamHandler::new()),
+            frame_sender: None,
+            parameter_sets: None,
+            aac_config: None,
+            unsupported_codec_logged: false,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), RtspRemuxerError> {
+        self.subscribe_rtmp().await?;
+        let rv = self.receive_rtmp_data().await;
+
+        self.unsubscribe_rtmp();
+        self.unpublish_rtsp();
+        rv
+    }
+
+    fn rtsp_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtsp {
+            stream_path: self.stream_path.clone(),
+        }
+    }
+
+    fn rtmp_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtmp {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        }
+    }
+
+    fn get_publisher_info(&self) -> PublisherInfo {
+        PublisherInfo {
+            id: self.publish_id,
+            pub_type: PublishType::RemuxRtsp,
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
+        }
+    }
+
+    fn get_subscriber_info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            id: self.subscribe_id,
+            sub_type: SubscribeType::RemuxRtsp,
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
+        }
+    }
+
+    async fn publish_rtsp(&mut self) -> Result<(), RtspRemuxerError> {
+        self.update_sdp().await?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
+        let publish_event = StreamHubEvent::Publish {
+            identifier: self.rtsp_identifier(),
+            receiver: DataReceiver {
+                frame_receiver: Some(receiver),
+                packet_receiver: None,
+            },
+            info: self.get_publisher_info(),
+            stream_handler: self.stream_handler.clone(),
+            result_sender,
+        };
+
+        if self.event_producer.send(publish_event).is_err() {
+            return Err(RtspRemuxerError {
+                value: RtspRemuxerErrorValue::StreamHubEventSendErr,
+            });
+        }
+        wait_pub_sub_result(result_receiver).await?;
+
+        self.frame_sender = Some(sender);
+        Ok(())
+    }
+
+    fn unpublish_rtsp(&mut self) {
+        if self.frame_sender.take().is_none() {
+            return;
+        }
+        let unpublish_event = StreamHubEvent::UnPublish {
+            identifier: self.rtsp_identifier(),
+            info: self.get_publisher_info(),
+        };
+        if let Err(err) = self.event_producer.send(unpublish_event) {
+            log::error!("unpublish_rtsp err {}\n", err);
+        }
+    }
+
+    async fn subscribe_rtmp(&mut self) -> Result<(), RtspRemuxerError> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: self.rtmp_identifier(),
+            info: self.get_subscriber_info(),
+            sender: DataSender::Frame { sender },
+            result_sender,
+        };
+
+        if self.event_producer.send(subscribe_event).is_err() {
+            return Err(RtspRemuxerError {
+                value: RtspRemuxerErrorValue::StreamHubEventSendErr,
+            });
+        }
+        wait_pub_sub_result(result_receiver).await?;
+
+        self.data_receiver = receiver;
+        Ok(())
+    }
+
+    fn unsubscribe_rtmp(&mut self) {
+        let unsubscribe_event = StreamHubEvent::UnSubscribe {
+            identifier: self.rtmp_identifier(),
+            info: self.get_subscriber_info(),
+        };
+        if let Err(err) = self.event_producer.send(unsubscribe_event) {
+            log::error!("unsubscribe_rtmp err {}\n", err);
+        }
+    }
+
+    async fn receive_rtmp_data(&mut self) -> Result<(), RtspRemuxerError> {
+        let mut retry_count = 0;
+
+        loop {
+            if let Some(data) = self.data_receiver.recv().await {
+                match data {
+                    FrameData::Video { timestamp, data } => {
+                        self.on_rtmp_video(timestamp, data).await?
+                    }
+                    FrameData::Audio { timestamp, data } => {
+                        self.on_rtmp_audio(timestamp, data).await?
+                    }
+                    _ => continue,
+                }
+                retry_count = 0;
+            } else {
+                sleep(Duration::from_millis(100)).await;
+                retry_count += 1;
+            }
+
+            if retry_count > 10 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn on_rtmp_video(
+        &mut self,
+        timestamp: u32,
+        data: BytesMut,
+    ) -> Result<(), RtspRemuxerError> {
+        let mut reader = BytesReader::new(data.clone());
+        let tag_header = VideoTagHeader::unmarshal(&mut reader)?;
+
+        if tag_header.codec() != AvcCodecId::H264 {
+            if !self.unsupported_codec_logged {
+                log::warn!(
+                    "rtmp2rtsp: only h264 is supported, the video of {} is dropped",
+                    self.stream_path
+                );
+                self.unsupported_codec_logged = true;
+            }
+            return Ok(());
+        }
+
+        if tag_header.is_seq_header() {
+            let mut processor = Mpeg4AvcProcessor::new();
+            processor.decoder_configuration_record_load(&mut reader)?;
+            let avc = &processor.mpeg4_avc;
+            if let (Some(sps), Some(pps)) = (avc.sps.first(), avc.pps.first()) {
+                self.parameter_sets = Some((sps.data.clone(), pps.data.clone()));
+                self.update_sdp().await?;
+            }
+        }
+
+        let video_data = match self.video_demuxer.demux(timestamp, data)? {
+            Some(video_data) => video_data,
+            None => return Ok(()),
+        };
+
+        self.send_frame(FrameData::Video {
+            timestamp: rtp_timestamp(video_data.pts, H264_CLOCK_RATE),
+            data: video_data.data,
+        })
+        .await
+    }
+
+    async fn on_rtmp_audio(
+        &mut self,
+        timestamp: u32,
+        data: BytesMut,
+    ) -> Result<(), RtspRemuxerError> {
+        let mut reader = BytesReader::new(data.clone());
+        let tag_header = AudioTagHeader::unmarshal(&mut reader)?;
+        if tag_header.sound_format != SoundFormat::AAC as u8 {
+            return Ok(());
+        }
+        let payload = reader.extract_remaining_bytes();
+
+        match tag_header.aac_packet_type {
+            aac_packet_type::AAC_SEQHDR => {
+                self.audio_demuxer.demux(timestamp, data)?;
+                let (sampling_frequency, channels) = self.audio_demuxer.audio_config();
+                self.aac_config = Some(AacConfig {
+                    sampling_frequency,
+                    channels,
+                    audio_specific_config: payload,
+                });
+                self.update_sdp().await?;
+            }
+            aac_packet_type::AAC_RAW => {
+                let sampling_frequency = match &self.aac_config {
+                    Some(config) => config.sampling_frequency,
+                    None => return Ok(()),
+                };
+                /*the rtp packer of rtsp takes the raw aac frame*/
+                self.send_frame(FrameData::Audio {
+                    timestamp: rtp_timestamp(timestamp as i64, sampling_frequency),
+                    data: payload,
+                })
+                .await?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    async fn update_sdp(&mut self) -> Result<(), RtspRemuxerError> {
+        let sdp_str = gen_sdp(&self.parameter_sets, &self.aac_config)?;
+        if let Some(sdp) = Sdp::unmarshal(&sdp_str) {
+            self.stream_handler.set_sdp(sdp).await;
+        }
+        Ok(())
+    }
+
+    //fails if the rtsp stream is unpublished(e.g.: kicked off or rejected by the stream hub)
+    async fn send_frame(&mut self, frame: FrameData) -> Result<(), RtspRemuxerError> {
+        if self.frame_sender.is_none() {
+            self.publish_rtsp().await?;
+        }
+
+        if let Some(sender) = &self.frame_sender {
+            if sender.send(frame).is_err() {
+                return Err(RtspRemuxerError {
+                    value: RtspRemuxerErrorValue::FrameDataSendErr,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gen_sdp, rtp_timestamp, AacConfig, H264_CLOCK_RATE};
+    use crate::{global_trait::Unmarshal, sdp::fmtp::Fmtp, sdp::Sdp};
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_gen_sdp() {
+        let sps = BytesMut::from(&[0x67, 0x64, 0x00, 0x1F, 0xAC, 0xD9][..]);
+        let pps = BytesMut::from(&[0x68, 0xEB, 0xE3, 0xCB][..]);
+        let asc = BytesMut::from(&[0x11, 0x90][..]);
+
+        let sdp_str = gen_sdp(
+            &Some((sps.clone(), pps.clone())),
+            &Some(AacConfig {
+                sampling_frequency: 48000,
+                channels: 2,
+                audio_specific_config: asc.clone(),
+            }),
+        )
+        .unwrap();
+        assert!(sdp_str.contains("profile-level-id=64001F"));
+
+        let sdp = Sdp::unmarshal(&sdp_str).unwrap();
+        assert_eq!(sdp.medias.len(), 2);
+
+        let video = &sdp.medias[0];
+        assert_eq!(video.rtpmap.clock_rate, 90000);
+        match &video.fmtp {
+            Some(Fmtp::H264(fmtp)) => {
+                assert_eq!(fmtp.sps, sps);
+                assert_eq!(fmtp.pps, pps);
+            }
+            _ => panic!("no h264 fmtp"),
+        }
+
+        let audio = &sdp.medias[1];
+        assert_eq!(audio.rtpmap.clock_rate, 48000);
+        assert_eq!(audio.rtpmap.encoding_param, "2");
+        match &audio.fmtp {
+            Some(Fmtp::Mpeg4(fmtp)) => assert_eq!(fmtp.asc, asc),
+            _ => panic!("no mpeg4 fmtp"),
+        }
+        assert_eq!(audio.attributes.get("control").unwrap(), "streamid=1");
+
+        /*video only*/
+        let sdp = Sdp::unmarshal(&gen_sdp(&Some((sps, pps.clone())), &None).unwrap()).unwrap();
+        assert_eq!(sdp.medias.len(), 1);
+
+        /*the sps is too short to get the profile-level-id*/
+        let sps = BytesMut::from(&[0x67, 0x64][..]);
+        assert!(gen_sdp(&Some((sps, pps.clone())), &None).is_err());
+        assert!(gen_sdp(&Some((BytesMut::new(), pps)), &None).is_err());
+    }
+
+    #[test]
+    fn test_rtp_timestamp() {
+        assert_eq!(rtp_timestamp(1000, H264_CLOCK_RATE), 90000);
+        assert_eq!(rtp_timestamp(-1, H264_CLOCK_RATE), u32::MAX - 89);
+        assert_eq!(rtp_timestamp(1000, 44100), 44100);
+        /*wraps around after about 13 hours*/
+        assert_eq!(rtp_timestamp(47_721_859, H264_CLOCK_RATE), 14);
+    }
+}
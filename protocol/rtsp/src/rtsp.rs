@@ -1,20 +1,55 @@
-use streamhub::define::StreamHubEventSender;
+use streamhub::{auth::Auth, define::StreamHubEventSender, statistics::metrics::SessionCounters};
 
+use super::global_trait::Unmarshal;
+use super::rtsp_auth::RtspServerAuth;
+use super::rtsp_tunnel::{self, Base64Decoder, HttpTunnelRequest, TunnelIO, TunnelSenders};
+use super::session::errors::{SessionError, SessionErrorValue};
 use super::session::RtspServerSession;
+use bytes::Bytes;
+use bytesio::bytesio::{TNetIO, TcpIO, TlsIO};
+use bytesio::tls::ReloadableTlsAcceptor;
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
 use tokio::io::Error;
 use tokio::net::TcpListener;
+use tokio::sync::mpsc;
 
 pub struct RtspServer {
     address: String,
+    //rtsps if it is set
+    tls_acceptor: Option<ReloadableTlsAcceptor>,
+    handler: ConnectionHandler,
+}
+
+#[derive(Clone)]
+struct ConnectionHandler {
     event_producer: StreamHubEventSender,
+    auth: Option<Arc<Auth>>,
+    rtsp_auth: Option<Arc<RtspServerAuth>>,
+    tunnel_senders: TunnelSenders,
+    session_counters: SessionCounters,
 }
 
 impl RtspServer {
-    pub fn new(address: String, event_producer: StreamHubEventSender) -> Self {
+    pub fn new(
+        address: String,
+        event_producer: StreamHubEventSender,
+        auth: Option<Arc<Auth>>,
+        rtsp_auth: Option<RtspServerAuth>,
+        tls_acceptor: Option<ReloadableTlsAcceptor>,
+        session_counters: SessionCounters,
+    ) -> Self {
         Self {
             address,
-            event_producer,
+            tls_acceptor,
+            handler: ConnectionHandler {
+                event_producer,
+                auth,
+                rtsp_auth: rtsp_auth.map(Arc::new),
+                tunnel_senders: Arc::new(Mutex::new(HashMap::new())),
+                session_counters,
+            },
         }
     }
 
@@ -22,15 +57,269 @@ impl RtspServer {
         let socket_addr: &SocketAddr = &self.address.parse().unwrap();
         let listener = TcpListener::bind(socket_addr).await?;
 
-        log::info!("Rtsp server listening on tcp://{}", socket_addr);
+        if self.tls_acceptor.is_some() {
+            log::info!("Rtsps server listening on tcp://{}", socket_addr);
+        } else {
+            log::info!("Rtsp server listening on tcp://{}", socket_addr);
+        }
         loop {
-            let (tcp_stream, _) = listener.accept().await?;
-            let mut session = RtspServerSession::new(tcp_stream, self.event_producer.clone());
+            let (tcp_stream, remote_addr) = listener.accept().await?;
+            let tls_acceptor = self.tls_acceptor.clone();
+            let handler = self.handler.clone();
+
             tokio::spawn(async move {
-                if let Err(err) = session.run().await {
+                let net_io: Box<dyn TNetIO + Send + Sync> = match tls_acceptor {
+                    Some(acceptor) => match acceptor.accept(tcp_stream).await {
+                        Ok(tls_stream) => Box::new(TlsIO::new(tls_stream)),
+                        Err(err) => {
+                            log::error!("rtsps tls handshake error: {}", err);
+                            return;
+                        }
+                    },
+                    None => Box::new(TcpIO::new(tcp_stream)),
+                };
+
+                if let Err(err) = handler.handle(net_io, remote_addr).await {
                     log::error!("session run error, err: {}", err);
                 }
             });
         }
     }
 }
+
+impl ConnectionHandler {
+    //the rtsp session or the GET/POST connection of the rtsp over http tunnel
+    async fn handle(
+        &self,
+        mut net_io: Box<dyn TNetIO + Send + Sync>,
+        remote_addr: SocketAddr,
+    ) -> Result<(), SessionError> {
+        let mut data = net_io.read().await?;
+
+        if !rtsp_tunnel::is_http_request(&data) {
+            return self.run_session(net_io, remote_addr, &data).await;
+        }
+
+        let header_length = loop {
+            if let Some(header_length) = rtsp_tunnel::http_header_length(&data) {
+                break header_length;
+            }
+            if rtsp_tunnel::is_http_header_too_long(&data) {
+                return Err(SessionError {
+                    value: SessionErrorValue::InvalidTunnelRequest,
+                });
+            }
+            data.extend_from_slice(&net_io.read().await?[..]);
+        };
+
+        let request =
+            match HttpTunnelRequest::unmarshal(std::str::from_utf8(&data[..header_length])?) {
+                Some(request) => request,
+                None => {
+                    return Err(SessionError {
+                        value: SessionErrorValue::InvalidTunnelRequest,
+                    });
+                }
+            };
+        let content = data.split_off(header_length);
+        log::info!(
+            "rtsp over http tunnel {} request, path: {}, session cookie: {}",
+            request.method,
+            request.path,
+            request.session_cookie
+        );
+
+        if request.method == "GET" {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            {
+                let mut tunnel_senders = self.tunnel_senders.lock().unwrap();
+                //do not take over the tunnel of another client with the same cookie
+                if tunnel_senders
+                    .get(&request.session_cookie)
+                    .is_some_and(|tunnel_sender| !tunnel_sender.is_closed())
+                {
+                    return Err(SessionError {
+                        value: SessionErrorValue::DuplicateTunnel(request.session_cookie),
+                    });
+                }
+                tunnel_senders.insert(request.session_cookie.clone(), sender.clone());
+            }
+
+            let rv = match net_io
+                .write(Bytes::from(rtsp_tunnel::gen_get_response()))
+                .await
+            {
+                Ok(()) => {
+                    let tunnel_io = TunnelIO::new(net_io, receiver);
+                    self.run_session(Box::new(tunnel_io), remote_addr, &[])
+                        .await
+                }
+                Err(err) => Err(err.into()),
+            };
+
+            //only remove the tunnel of this session
+            let mut tunnel_senders = self.tunnel_senders.lock().unwrap();
+            if tunnel_senders
+                .get(&request.session_cookie)
+                .is_some_and(|tunnel_sender| tunnel_sender.same_channel(&sender))
+            {
+                tunnel_senders.remove(&request.session_cookie);
+            }
+            rv
+        } else {
+            let sender = match self
+                .tunnel_senders
+                .lock()
+                .unwrap()
+                .get(&request.session_cookie)
+            {
+                Some(sender) => sender.clone(),
+                None => {
+                    return Err(SessionError {
+                        value: SessionErrorValue::TunnelNotFound(request.session_cookie),
+                    });
+                }
+            };
+
+            // The client may close the POST connection after sending the requests
+            // and open a new one with the same session cookie.
+            let mut decoder = Base64Decoder::default();
+            let mut content = content;
+            loop {
+                let decoded = match decoder.decode(&content) {
+                    Some(decoded) => decoded,
+                    None => {
+                        return Err(SessionError {
+                            value: SessionErrorValue::InvalidTunnelRequest,
+                        });
+                    }
+                };
+                //the session is closed
+                if !decoded.is_empty() && sender.send(decoded).is_err() {
+                    return Ok(());
+                }
+
+                content = match net_io.read().await {
+                    Ok(content) => content,
+                    Err(_) => return Ok(()),
+                };
+            }
+        }
+    }
+
+    async fn run_session(
+        &self,
+        net_io: Box<dyn TNetIO + Send + Sync>,
+        remote_addr: SocketAddr,
+        received_data: &[u8],
+    ) -> Result<(), SessionError> {
+        let mut session = RtspServerSession::new(
+            net_io,
+            Some(remote_addr),
+            self.event_producer.clone(),
+            self.auth.clone(),
+            self.rtsp_auth.clone(),
+        );
+        session.extend_received_data(received_data);
+
+        self.session_counters.session_connected("rtsp");
+        let rv = session.run().await;
+        if rv.is_err() {
+            self.session_counters.session_error("rtsp");
+        }
+        self.session_counters.session_disconnected("rtsp");
+
+        rv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConnectionHandler;
+    use base64::{engine::general_purpose, Engine as _};
+    use bytesio::bytesio::TcpIO;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use streamhub::statistics::metrics::SessionCounters;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+
+    async fn read_until(stream: &mut TcpStream, pattern: &str) -> String {
+        let mut data = Vec::new();
+        while !String::from_utf8_lossy(&data).contains(pattern) {
+            let mut buf = [0_u8; 1024];
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0, "the connection is closed");
+            data.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8(data).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_with_duplicate_cookie() {
+        let (event_producer, _event_consumer) = mpsc::unbounded_channel();
+        let handler = ConnectionHandler {
+            event_producer,
+            auth: None,
+            rtsp_auth: None,
+            tunnel_senders: Arc::new(Mutex::new(HashMap::new())),
+            session_counters: SessionCounters::new(),
+        };
+        let tunnel_senders = handler.tunnel_senders.clone();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (stream, remote_addr) = listener.accept().await.unwrap();
+                let handler = handler.clone();
+                tokio::spawn(async move {
+                    let _ = handler
+                        .handle(Box::new(TcpIO::new(stream)), remote_addr)
+                        .await;
+                });
+            }
+        });
+
+        let get_request = "GET /live/test HTTP/1.0\r\nx-sessioncookie: 7a1b2c3d\r\n\r\n";
+        let mut first_get = TcpStream::connect(address).await.unwrap();
+        first_get.write_all(get_request.as_bytes()).await.unwrap();
+        assert!(read_until(&mut first_get, "\r\n\r\n")
+            .await
+            .starts_with("HTTP/1.0 200 OK"));
+
+        /*the second tunnel with the same cookie is rejected*/
+        let mut second_get = TcpStream::connect(address).await.unwrap();
+        second_get.write_all(get_request.as_bytes()).await.unwrap();
+        let mut buf = [0_u8; 1024];
+        assert_eq!(second_get.read(&mut buf).await.unwrap(), 0);
+
+        /*the requests of the POST connection still go to the first tunnel*/
+        let rtsp_request = "OPTIONS rtsp://127.0.0.1/live/test RTSP/1.0\r\nCSeq: 1\r\n\r\n";
+        let mut post = TcpStream::connect(address).await.unwrap();
+        post.write_all(
+            format!(
+                "POST /live/test HTTP/1.0\r\nx-sessioncookie: 7a1b2c3d\r\n\r\n{}",
+                general_purpose::STANDARD.encode(rtsp_request)
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+        assert!(read_until(&mut first_get, "RTSP/1.0 200 OK")
+            .await
+            .contains("CSeq: 1"));
+
+        /*the tunnel is removed after the first client leaves*/
+        drop(first_get);
+        for _ in 0..100 {
+            if tunnel_senders.lock().unwrap().is_empty() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("the tunnel is not removed");
+    }
+}
@@ -0,0 +1,458 @@
+use super::global_trait::Unmarshal;
+use base64::{engine::general_purpose, Engine as _};
+use indexmap::IndexMap;
+use rand::Rng;
+use streamhub::auth::{constant_time_eq, AuthAction};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AuthScheme {
+    #[default]
+    Basic,
+    Digest,
+}
+
+// The challenge from the server side (RFC 2617), e.g.:
+// WWW-Authenticate: Digest realm="IP Camera", nonce="a2dd1f3e", qop="auth"
+// WWW-Authenticate: Basic realm="IP Camera"
+#[derive(Debug, Clone, Default)]
+pub struct WwwAuthenticate {
+    pub scheme: AuthScheme,
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    pub algorithm: Option<String>,
+    pub qop: Option<String>,
+}
+
+//split the auth parameters by ',' except the ones inside the quotes
+fn parse_auth_params(raw_data: &str) -> IndexMap<String, String> {
+    let mut params = IndexMap::new();
+
+    let mut in_quotes = false;
+    let mut begin = 0;
+    let mut parts = Vec::new();
+    for (idx, c) in raw_data.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                parts.push(&raw_data[begin..idx]);
+                begin = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&raw_data[begin..]);
+
+    for part in parts {
+        if let Some((k, v)) = part.split_once('=') {
+            params.insert(
+                k.trim().to_lowercase(),
+                v.trim().trim_matches('"').to_string(),
+            );
+        }
+    }
+    params
+}
+
+impl Unmarshal for WwwAuthenticate {
+    fn unmarshal(raw_data: &str) -> Option<Self> {
+        let mut www_authenticate = WwwAuthenticate::default();
+
+        let (scheme, params) = raw_data.trim().split_once(' ')?;
+        www_authenticate.scheme = match scheme.to_lowercase().as_str() {
+            "basic" => AuthScheme::Basic,
+            "digest" => AuthScheme::Digest,
+            _ => return None,
+        };
+
+        let mut params = parse_auth_params(params);
+        www_authenticate.realm = params.remove("realm").unwrap_or_default();
+        www_authenticate.nonce = params.remove("nonce").unwrap_or_default();
+        www_authenticate.opaque = params.remove("opaque");
+        www_authenticate.algorithm = params.remove("algorithm");
+        www_authenticate.qop = params.remove("qop");
+
+        if www_authenticate.scheme == AuthScheme::Digest && www_authenticate.nonce.is_empty() {
+            return None;
+        }
+
+        Some(www_authenticate)
+    }
+}
+
+fn md5_hex(data: String) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
+//generate the Authorization header for the requests of the rtsp client
+pub struct RtspAuthenticator {
+    username: String,
+    password: String,
+    challenge: WwwAuthenticate,
+    nonce_count: u32,
+    cnonce: String,
+}
+
+impl RtspAuthenticator {
+    pub fn new(username: String, password: String, challenge: WwwAuthenticate) -> Self {
+        let cnonce: u64 = rand::thread_rng().gen();
+        Self {
+            username,
+            password,
+            challenge,
+            nonce_count: 0,
+            cnonce: format!("{cnonce:016x}"),
+        }
+    }
+
+    pub fn authorization(&mut self, method: &str, uri: &str) -> String {
+        match self.challenge.scheme {
+            AuthScheme::Basic => {
+                let credentials = format!("{}:{}", self.username, self.password);
+                format!("Basic {}", general_purpose::STANDARD.encode(credentials))
+            }
+            AuthScheme::Digest => {
+                let ha1 = md5_hex(format!(
+                    "{}:{}:{}",
+                    self.username, self.challenge.realm, self.password
+                ));
+                let ha2 = md5_hex(format!("{method}:{uri}"));
+
+                //only the "auth" quality of protection is supported
+                let qop_auth = matches!(&self.challenge.qop, Some(qop) if qop.split(',').any(|q| q.trim() == "auth"));
+
+                let mut authorization = format!(
+                    "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\"",
+                    self.username, self.challenge.realm, self.challenge.nonce, uri
+                );
+
+                let response = if qop_auth {
+                    self.nonce_count += 1;
+                    let nc = format!("{:08x}", self.nonce_count);
+                    authorization += &format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, self.cnonce);
+                    md5_hex(format!(
+                        "{}:{}:{}:{}:auth:{}",
+                        ha1, self.challenge.nonce, nc, self.cnonce, ha2
+                    ))
+                } else {
+                    md5_hex(format!("{}:{}:{}", ha1, self.challenge.nonce, ha2))
+                };
+                authorization += &format!(", response=\"{response}\"");
+
+                if let Some(opaque) = &self.challenge.opaque {
+                    authorization += &format!(", opaque=\"{opaque}\"");
+                }
+                if let Some(algorithm) = &self.challenge.algorithm {
+                    authorization += &format!(", algorithm={algorithm}");
+                }
+
+                authorization
+            }
+        }
+    }
+}
+
+// The credentials from the client side, e.g.:
+// Authorization: Digest username="admin", realm="xiu", nonce="a2dd1f3e", uri="rtsp://...", response="..."
+// Authorization: Basic YWRtaW46MTIzNDU=
+#[derive(Debug, Clone, Default)]
+pub struct Authorization {
+    pub scheme: AuthScheme,
+    pub username: String,
+    //only for basic
+    pub password: String,
+    pub realm: String,
+    pub nonce: String,
+    pub uri: String,
+    pub response: String,
+    pub qop: Option<String>,
+    pub nc: Option<String>,
+    pub cnonce: Option<String>,
+}
+
+impl Unmarshal for Authorization {
+    fn unmarshal(raw_data: &str) -> Option<Self> {
+        let mut authorization = Authorization::default();
+
+        let (scheme, params) = raw_data.trim().split_once(' ')?;
+        match scheme.to_lowercase().as_str() {
+            "basic" => {
+                let credentials = general_purpose::STANDARD.decode(params.trim()).ok()?;
+                let credentials = String::from_utf8(credentials).ok()?;
+                let (username, password) = credentials.split_once(':')?;
+                authorization.scheme = AuthScheme::Basic;
+                authorization.username = username.to_string();
+                authorization.password = password.to_string();
+            }
+            "digest" => {
+                let mut params = parse_auth_params(params);
+                authorization.scheme = AuthScheme::Digest;
+                authorization.username = params.remove("username")?;
+                authorization.realm = params.remove("realm").unwrap_or_default();
+                authorization.nonce = params.remove("nonce")?;
+                authorization.uri = params.remove("uri")?;
+                authorization.response = params.remove("response")?;
+                authorization.qop = params.remove("qop");
+                authorization.nc = params.remove("nc");
+                authorization.cnonce = params.remove("cnonce");
+            }
+            _ => return None,
+        }
+
+        Some(authorization)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RtspCredential {
+    //the stream path, "camera/door" matches itself and its tracks(camera/door/streamid=0),
+    //"camera/*" matches all the paths begin with "camera/", "*" matches all the paths.
+    pub path: String,
+    pub username: String,
+    pub password: String,
+    //allow DESCRIBE/SETUP/PLAY
+    pub read: bool,
+    //allow ANNOUNCE/SETUP/RECORD
+    pub publish: bool,
+}
+
+impl RtspCredential {
+    fn match_path(&self, path: &str) -> bool {
+        if let Some(prefix) = self.path.strip_suffix('*') {
+            return path.starts_with(prefix);
+        }
+        path == self.path || path.starts_with(&format!("{}/", self.path))
+    }
+
+    fn is_allowed(&self, action: &AuthAction) -> bool {
+        match action {
+            AuthAction::Publish => self.publish,
+            AuthAction::Play => self.read,
+        }
+    }
+}
+
+//check the Authorization header for the requests of the rtsp server, the paths
+//without any matched credentials are not protected.
+#[derive(Clone)]
+pub struct RtspServerAuth {
+    realm: String,
+    scheme: AuthScheme,
+    credentials: Vec<RtspCredential>,
+}
+
+impl RtspServerAuth {
+    pub fn new(realm: String, scheme: AuthScheme, credentials: Vec<RtspCredential>) -> Self {
+        Self {
+            realm,
+            scheme,
+            credentials,
+        }
+    }
+
+    pub fn gen_nonce() -> String {
+        let nonce: u128 = rand::thread_rng().gen();
+        format!("{nonce:032x}")
+    }
+
+    pub fn is_protected(&self, path: &str) -> bool {
+        self.credentials
+            .iter()
+            .any(|credential| credential.match_path(path))
+    }
+
+    //the value of the WWW-Authenticate header in the 401 response
+    pub fn challenge(&self, nonce: &str) -> String {
+        match self.scheme {
+            AuthScheme::Basic => format!("Basic realm=\"{}\"", self.realm),
+            AuthScheme::Digest => format!(
+                "Digest realm=\"{}\", nonce=\"{}\", algorithm=MD5",
+                self.realm, nonce
+            ),
+        }
+    }
+
+    //uri is the request uri, which should be the same with the one in the digest
+    pub fn authenticate(
+        &self,
+        method: &str,
+        uri: &str,
+        path: &str,
+        action: AuthAction,
+        authorization: Option<&String>,
+        nonce: &str,
+    ) -> bool {
+        if !self.is_protected(path) {
+            return true;
+        }
+
+        let authorization = match authorization.and_then(|value| Authorization::unmarshal(value)) {
+            Some(authorization) => authorization,
+            None => return false,
+        };
+        //do not accept the weaker scheme than the configured one
+        if authorization.scheme != self.scheme {
+            return false;
+        }
+
+        self.credentials.iter().any(|credential| {
+            credential.match_path(path)
+                && credential.is_allowed(&action)
+                && credential.username == authorization.username
+                && self.verify(method, uri, credential, &authorization, nonce)
+        })
+    }
+
+    fn verify(
+        &self,
+        method: &str,
+        uri: &str,
+        credential: &RtspCredential,
+        authorization: &Authorization,
+        nonce: &str,
+    ) -> bool {
+        match authorization.scheme {
+            AuthScheme::Basic => constant_time_eq(&credential.password, &authorization.password),
+            AuthScheme::Digest => {
+                if authorization.nonce != nonce
+                    || authorization.realm != self.realm
+                    || authorization.uri != uri
+                {
+                    return false;
+                }
+
+                let ha1 = md5_hex(format!(
+                    "{}:{}:{}",
+                    credential.username, self.realm, credential.password
+                ));
+                let ha2 = md5_hex(format!("{}:{}", method, authorization.uri));
+
+                let response = match (&authorization.qop, &authorization.nc, &authorization.cnonce)
+                {
+                    (Some(qop), Some(nc), Some(cnonce)) => {
+                        md5_hex(format!("{ha1}:{nonce}:{nc}:{cnonce}:{qop}:{ha2}"))
+                    }
+                    _ => md5_hex(format!("{ha1}:{nonce}:{ha2}")),
+                };
+                constant_time_eq(&response, &authorization.response)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{AuthScheme, RtspAuthenticator, RtspCredential, RtspServerAuth, WwwAuthenticate};
+    use crate::global_trait::Unmarshal;
+    use streamhub::auth::AuthAction;
+
+    #[test]
+    fn test_digest_authorization() {
+        //the example in RFC 2617 3.5
+        let challenge = WwwAuthenticate::unmarshal(
+            "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+             nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"",
+        )
+        .unwrap();
+        assert_eq!(challenge.scheme, AuthScheme::Digest);
+        assert_eq!(challenge.realm, "testrealm@host.com");
+
+        let mut authenticator = RtspAuthenticator::new(
+            String::from("Mufasa"),
+            String::from("Circle Of Life"),
+            challenge,
+        );
+        authenticator.cnonce = String::from("0a4f113b");
+
+        let authorization = authenticator.authorization("GET", "/dir/index.html");
+        assert!(authorization.contains("nc=00000001"));
+        assert!(authorization.contains("response=\"6629fae49393a05397450978507c4ef1\""));
+
+        let challenge = WwwAuthenticate::unmarshal("Basic realm=\"camera\"").unwrap();
+        let mut authenticator =
+            RtspAuthenticator::new(String::from("admin"), String::from("12345"), challenge);
+        assert_eq!(
+            authenticator.authorization("DESCRIBE", "rtsp://127.0.0.1/live"),
+            "Basic YWRtaW46MTIzNDU="
+        );
+    }
+
+    #[test]
+    fn test_server_authenticate() {
+        let server_auth = RtspServerAuth::new(
+            String::from("xiu"),
+            AuthScheme::Digest,
+            vec![RtspCredential {
+                path: String::from("camera/*"),
+                username: String::from("camera"),
+                password: String::from("123456"),
+                read: false,
+                publish: true,
+            }],
+        );
+        let nonce = RtspServerAuth::gen_nonce();
+        let uri = "rtsp://127.0.0.1/camera/door";
+
+        assert!(!server_auth.is_protected("live/test"));
+        assert!(server_auth.authenticate(
+            "DESCRIBE",
+            "rtsp://127.0.0.1/live/test",
+            "live/test",
+            AuthAction::Play,
+            None,
+            &nonce
+        ));
+        assert!(!server_auth.authenticate(
+            "ANNOUNCE",
+            uri,
+            "camera/door",
+            AuthAction::Publish,
+            None,
+            &nonce
+        ));
+
+        let challenge = WwwAuthenticate::unmarshal(&server_auth.challenge(&nonce)).unwrap();
+        let mut authenticator =
+            RtspAuthenticator::new(String::from("camera"), String::from("123456"), challenge);
+        let authorization = authenticator.authorization("ANNOUNCE", uri);
+        assert!(server_auth.authenticate(
+            "ANNOUNCE",
+            uri,
+            "camera/door",
+            AuthAction::Publish,
+            Some(&authorization),
+            &nonce
+        ));
+        //the read permission is not granted
+        let authorization = authenticator.authorization("DESCRIBE", uri);
+        assert!(!server_auth.authenticate(
+            "DESCRIBE",
+            uri,
+            "camera/door",
+            AuthAction::Play,
+            Some(&authorization),
+            &nonce
+        ));
+        //the stale nonce
+        let authorization = authenticator.authorization("SETUP", uri);
+        assert!(!server_auth.authenticate(
+            "SETUP",
+            uri,
+            "camera/door/streamid=0",
+            AuthAction::Publish,
+            Some(&authorization),
+            &RtspServerAuth::gen_nonce()
+        ));
+        //the digest is computed with another uri
+        let authorization = authenticator.authorization("ANNOUNCE", "rtsp://127.0.0.1/live/test");
+        assert!(!server_auth.authenticate(
+            "ANNOUNCE",
+            uri,
+            "camera/door",
+            AuthAction::Publish,
+            Some(&authorization),
+            &nonce
+        ));
+    }
+}
@@ -1,7 +1,6 @@
 use crate::global_trait::Marshal;
 
 use super::global_trait::Unmarshal;
-use super::rtsp_utils;
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub enum RtspRangeType {
@@ -10,17 +9,70 @@ pub enum RtspRangeType {
     CLOCK,
 }
 
+// Range: npt=now- / npt=0.000- / npt=10.5-20 / npt=0:01:10.5-
+// Range: clock=20220520T064812Z-20230520T064816Z
+// The npt times are in milliseconds and the clock times are unix timestamps in seconds.
 #[derive(Debug, Clone, Default)]
 pub struct RtspRange {
-    range_type: RtspRangeType,
-    begin: i64,
-    end: Option<i64>,
+    pub range_type: RtspRangeType,
+    //npt=now-, the begin is 0
+    pub now: bool,
+    pub begin: i64,
+    pub end: Option<i64>,
+}
+
+impl RtspRange {
+    //if it can be served by a live stream, it begins from now(or the beginning)
+    pub fn is_live(&self) -> bool {
+        self.range_type == RtspRangeType::NPT && (self.now || self.begin == 0)
+    }
+}
+
+//npt-sec: 10.5, npt-hhmmss: 0:01:10.5
+fn parse_npt_time(npt_time: &str) -> Option<i64> {
+    let (integer, fraction) = match npt_time.split_once('.') {
+        Some((integer, fraction)) => (integer, fraction),
+        None => (npt_time, ""),
+    };
+
+    let mut seconds: i64 = 0;
+    for value in integer.split(':') {
+        seconds = seconds
+            .checked_mul(60)?
+            .checked_add(value.parse::<i64>().ok()?)?;
+    }
+
+    let mut milliseconds: i64 = 0;
+    if !fraction.is_empty() {
+        //the fraction is sliced by bytes below
+        if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let fraction = format!("{:0<3}", &fraction[..std::cmp::min(fraction.len(), 3)]);
+        milliseconds = fraction.parse::<i64>().ok()?;
+    }
+
+    seconds.checked_mul(1000)?.checked_add(milliseconds)
+}
+
+fn marshal_npt_time(npt_time: i64) -> String {
+    format!("{}.{:03}", npt_time / 1000, npt_time % 1000)
+}
+
+fn marshal_clock_time(clock_time: i64) -> String {
+    if let Some(datetime) = chrono::NaiveDateTime::from_timestamp_opt(clock_time, 0) {
+        datetime.format("%Y%m%dT%H%M%SZ").to_string()
+    } else {
+        String::default()
+    }
 }
 
 impl Unmarshal for RtspRange {
     fn unmarshal(raw_data: &str) -> Option<Self> {
         let mut rtsp_range = RtspRange::default();
 
+        //ignore the parameters, e.g. npt=0-;time=19970123T143720Z
+        let raw_data = raw_data.split(';').next()?.trim();
         let kv: Vec<&str> = raw_data.splitn(2, '=').collect();
         if kv.len() < 2 {
             return None;
@@ -52,31 +104,21 @@ impl Unmarshal for RtspRange {
                 rtsp_range.range_type = RtspRangeType::NPT;
                 let ranges: Vec<&str> = kv[1].split('-').collect();
 
-                let get_npt_time = |range_time: &str| -> i64 {
-                    if let (Some(hour), Some(minute), Some(second), mill) =
-                        rtsp_utils::scanf!(range_time, |c| c == ':' || c == '.', i64, i64, i64, i64)
-                    {
-                        let mut result = (hour * 3600 + minute * 60 + second) * 1000;
-                        if let Some(m) = mill {
-                            result += m;
-                        }
-                        result
-                    } else {
-                        0
-                    }
-                };
-
-                match ranges[0] {
+                match ranges[0].trim() {
                     "now" => {
+                        rtsp_range.now = true;
                         rtsp_range.begin = 0;
                     }
-                    _ => {
-                        rtsp_range.begin = get_npt_time(ranges[0]);
+                    "" => {
+                        rtsp_range.begin = 0;
+                    }
+                    begin => {
+                        rtsp_range.begin = parse_npt_time(begin)?;
                     }
                 }
 
-                if ranges.len() == 2 && !ranges[1].is_empty() {
-                    rtsp_range.end = Some(get_npt_time(ranges[1]));
+                if ranges.len() == 2 && !ranges[1].trim().is_empty() {
+                    rtsp_range.end = Some(parse_npt_time(ranges[1].trim())?);
                 }
             }
             _ => {
@@ -90,7 +132,27 @@ impl Unmarshal for RtspRange {
 
 impl Marshal for RtspRange {
     fn marshal(&self) -> String {
-        String::default()
+        let (begin, end) = match self.range_type {
+            RtspRangeType::NPT => {
+                let begin = if self.now {
+                    String::from("now")
+                } else {
+                    marshal_npt_time(self.begin)
+                };
+                (begin, self.end.map(marshal_npt_time))
+            }
+            RtspRangeType::CLOCK => (
+                marshal_clock_time(self.begin),
+                self.end.map(marshal_clock_time),
+            ),
+        };
+
+        let range_type = match self.range_type {
+            RtspRangeType::NPT => "npt",
+            RtspRangeType::CLOCK => "clock",
+        };
+
+        format!("{}={}-{}", range_type, begin, end.unwrap_or_default())
     }
 }
 
@@ -98,6 +160,7 @@ impl Marshal for RtspRange {
 mod tests {
 
     use super::RtspRange;
+    use crate::global_trait::Marshal;
     use crate::global_trait::Unmarshal;
 
     #[test]
@@ -115,5 +178,22 @@ mod tests {
 
         let parser2 = RtspRange::unmarshal("npt=0-").unwrap();
         println!(" parser2: {:?}, {}", parser2, parser2.end.is_none());
+
+        assert!(parser1.is_live());
+        assert_eq!(parser1.marshal(), "npt=now-");
+        assert!(parser2.is_live());
+        assert_eq!(parser2.marshal(), "npt=0.000-");
+        assert_eq!(parser.marshal(), "clock=20220520T064812Z-20230520T064816Z");
+
+        let parser3 = RtspRange::unmarshal("npt=0:01:10.5-120").unwrap();
+        assert!(!parser3.is_live());
+        assert_eq!(parser3.begin, 70500);
+        assert_eq!(parser3.end, Some(120000));
+        assert_eq!(parser3.marshal(), "npt=70.500-120.000");
+
+        assert!(RtspRange::unmarshal("npt=1.éé-").is_none());
+        assert!(RtspRange::unmarshal("npt=1.5x-").is_none());
+        assert!(RtspRange::unmarshal("npt=9223372036854775807-").is_none());
+        assert!(RtspRange::unmarshal("npt=99999999999:0:0:0-").is_none());
     }
 }
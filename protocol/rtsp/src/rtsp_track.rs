@@ -1,14 +1,21 @@
 use super::rtsp_channel::RtcpChannel;
 use super::rtsp_channel::RtpChannel;
 use super::rtsp_codec::RtspCodecInfo;
+use super::rtsp_transport::ProtocolType;
 use super::rtsp_transport::RtspTransport;
 use crate::rtp::errors::UnPackerError;
+use crate::rtp::utils::Marshal;
+use crate::rtp::RtpPacket;
 use crate::rtsp_channel::TRtpFunc;
+use byteorder::BigEndian;
 use bytes::BytesMut;
 use bytesio::bytes_reader::BytesReader;
+use bytesio::bytes_writer::AsyncBytesWriter;
 use bytesio::bytesio::TNetIO;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
 pub trait Track {
     fn create_packer(&mut self, writer: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>);
@@ -42,6 +49,8 @@ pub struct RtspTrack {
 
     pub rtp_channel: Arc<Mutex<RtpChannel>>,
     pub rtcp_channel: Arc<Mutex<RtcpChannel>>,
+    //the count of the received udp rtp/rtcp packets, for checking the session activity
+    pub udp_packet_count: Arc<AtomicUsize>,
 }
 
 impl RtspTrack {
@@ -55,17 +64,23 @@ impl RtspTrack {
             uri: String::default(),
             rtp_channel: Arc::new(Mutex::new(rtp_channel)),
             rtcp_channel: Arc::new(Mutex::default()),
+            udp_packet_count: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    pub async fn rtp_receive_loop(&mut self, mut rtp_io: Box<dyn TNetIO + Send + Sync>) {
+    pub async fn rtp_receive_loop(
+        &mut self,
+        mut rtp_io: Box<dyn TNetIO + Send + Sync>,
+    ) -> JoinHandle<()> {
         let rtp_channel_out = self.rtp_channel.clone();
+        let udp_packet_count = self.udp_packet_count.clone();
         tokio::spawn(async move {
             let mut reader = BytesReader::new(BytesMut::new());
             let mut rtp_channel_in = rtp_channel_out.lock().await;
             loop {
                 match rtp_io.read().await {
                     Ok(data) => {
+                        udp_packet_count.fetch_add(1, Ordering::Relaxed);
                         reader.extend_from_slice(&data[..]);
                         if let Err(err) = rtp_channel_in.on_packet(&mut reader) {
                             log::error!("rtp_receive_loop on_packet error: {}", err);
@@ -77,11 +92,15 @@ impl RtspTrack {
                     }
                 }
             }
-        });
+        })
     }
     //send and receive rtcp data in a UDP channel
-    pub async fn rtcp_receive_loop(&mut self, rtcp_io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>) {
+    pub async fn rtcp_receive_loop(
+        &mut self,
+        rtcp_io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>,
+    ) -> JoinHandle<()> {
         let rtcp_channel_out = self.rtcp_channel.clone();
+        let udp_packet_count = self.udp_packet_count.clone();
 
         tokio::spawn(async move {
             let mut reader = BytesReader::new(BytesMut::new());
@@ -95,10 +114,11 @@ impl RtspTrack {
                         break;
                     }
                 };
+                udp_packet_count.fetch_add(1, Ordering::Relaxed);
                 reader.extend_from_slice(&data[..]);
                 rtcp_channel_in.on_rtcp(&mut reader, rtcp_io.clone()).await;
             }
-        });
+        })
     }
 
     pub async fn set_transport(&mut self, transport: RtspTransport) {
@@ -129,4 +149,47 @@ impl RtspTrack {
     pub async fn create_packer(&mut self, io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>) {
         self.rtp_channel.lock().await.create_packer(io);
     }
+
+    //send the packed rtp packets over the interleaved tcp channel or the udp channel
+    pub async fn set_packet_handler(&mut self) {
+        match self.transport.protocol_type {
+            ProtocolType::TCP => {
+                let channel_identifer = if let Some(interleaveds) = self.transport.interleaved {
+                    interleaveds[0]
+                } else {
+                    log::error!("set_packet_handler:should not be here!!!");
+                    0
+                };
+
+                self.rtp_channel.lock().await.on_packet_handler(Box::new(
+                    move |io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>, packet: RtpPacket| {
+                        Box::pin(async move {
+                            let msg = packet.marshal()?;
+                            let mut bytes_writer = AsyncBytesWriter::new(io);
+                            bytes_writer.write_u8(0x24)?;
+                            bytes_writer.write_u8(channel_identifer)?;
+                            bytes_writer.write_u16::<BigEndian>(msg.len() as u16)?;
+                            bytes_writer.write(&msg)?;
+                            bytes_writer.flush().await?;
+                            Ok(())
+                        })
+                    },
+                ));
+            }
+            ProtocolType::UDP => {
+                self.rtp_channel.lock().await.on_packet_handler(Box::new(
+                    move |io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>, packet: RtpPacket| {
+                        Box::pin(async move {
+                            let mut bytes_writer = AsyncBytesWriter::new(io);
+
+                            let msg = packet.marshal()?;
+                            bytes_writer.write(&msg)?;
+                            bytes_writer.flush().await?;
+                            Ok(())
+                        })
+                    },
+                ));
+            }
+        }
+    }
 }
@@ -0,0 +1,187 @@
+use super::global_trait::Unmarshal;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use bytes::{Bytes, BytesMut};
+use bytesio::bytesio::{NetType, TNetIO};
+use bytesio::bytesio_errors::{BytesIOError, BytesIOErrorValue};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+// RTSP over HTTP tunneling(Apple QuickTime style), for the networks which only allow http:
+// 1. the client sends a GET request, the server replies 200 and sends the rtsp
+//    responses and the interleaved rtp/rtcp data over this connection.
+// 2. the client sends a POST request with the same x-sessioncookie, the following
+//    rtsp requests are base64 encoded and sent over this connection, no reply.
+pub const TUNNEL_CONTENT_TYPE: &str = "application/x-rtsp-tunnelled";
+const MAX_HTTP_HEADER_LENGTH: usize = 8192;
+
+//the rtsp data decoded from the POST connections, keyed by the x-sessioncookie
+pub type TunnelSenders = Arc<Mutex<HashMap<String, UnboundedSender<BytesMut>>>>;
+
+pub fn is_http_request(data: &[u8]) -> bool {
+    data.starts_with(b"GET ") || data.starts_with(b"POST ")
+}
+
+//return the length of the http header if it is complete
+pub fn http_header_length(data: &[u8]) -> Option<usize> {
+    data.windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|idx| idx + 4)
+}
+
+pub fn is_http_header_too_long(data: &[u8]) -> bool {
+    data.len() > MAX_HTTP_HEADER_LENGTH
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HttpTunnelRequest {
+    pub method: String,
+    pub path: String,
+    pub session_cookie: String,
+}
+
+impl Unmarshal for HttpTunnelRequest {
+    fn unmarshal(raw_data: &str) -> Option<Self> {
+        let mut lines = raw_data.lines();
+
+        let mut fields = lines.next()?.split_ascii_whitespace();
+        let method = fields.next()?.to_string();
+        let path = fields.next()?.to_string();
+        if method != "GET" && method != "POST" {
+            return None;
+        }
+
+        let session_cookie = lines.find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if name.trim().eq_ignore_ascii_case("x-sessioncookie") {
+                Some(value.trim().to_string())
+            } else {
+                None
+            }
+        })?;
+        if session_cookie.is_empty() {
+            return None;
+        }
+
+        Some(HttpTunnelRequest {
+            method,
+            path,
+            session_cookie,
+        })
+    }
+}
+
+pub fn gen_get_response() -> String {
+    format!(
+        "HTTP/1.0 200 OK\r\nServer: xiu\r\nConnection: close\r\nCache-Control: no-store\r\n\
+         Pragma: no-cache\r\nContent-Type: {TUNNEL_CONTENT_TYPE}\r\n\r\n"
+    )
+}
+
+// The base64 data of the POST connection may be split at any position,
+// the incomplete quantum is kept until the next data arrives.
+#[derive(Default)]
+pub struct Base64Decoder {
+    remaining: Vec<u8>,
+}
+
+impl Base64Decoder {
+    pub fn decode(&mut self, data: &[u8]) -> Option<BytesMut> {
+        self.remaining
+            .extend(data.iter().filter(|c| !c.is_ascii_whitespace()));
+
+        let length = self.remaining.len() / 4 * 4;
+        if length == 0 {
+            return Some(BytesMut::new());
+        }
+
+        let decoded = general_purpose::STANDARD
+            .decode(&self.remaining[..length])
+            .ok()?;
+        self.remaining.drain(..length);
+
+        Some(BytesMut::from(&decoded[..]))
+    }
+}
+
+// The net io of the tunneled rtsp session: write to the GET connection and read
+// the decoded rtsp requests from the POST connections.
+pub struct TunnelIO {
+    io: Box<dyn TNetIO + Send + Sync>,
+    receiver: UnboundedReceiver<BytesMut>,
+}
+
+impl TunnelIO {
+    pub fn new(io: Box<dyn TNetIO + Send + Sync>, receiver: UnboundedReceiver<BytesMut>) -> Self {
+        Self { io, receiver }
+    }
+}
+
+#[async_trait]
+impl TNetIO for TunnelIO {
+    fn get_net_type(&self) -> NetType {
+        NetType::TCP
+    }
+
+    async fn write(&mut self, bytes: Bytes) -> Result<(), BytesIOError> {
+        self.io.write(bytes).await
+    }
+
+    async fn read_timeout(&mut self, duration: Duration) -> Result<BytesMut, BytesIOError> {
+        match tokio::time::timeout(duration, self.read()).await {
+            Ok(rv) => rv,
+            Err(_) => Err(BytesIOError {
+                value: BytesIOErrorValue::TimeoutError,
+            }),
+        }
+    }
+
+    async fn read(&mut self) -> Result<BytesMut, BytesIOError> {
+        loop {
+            tokio::select! {
+                data = self.receiver.recv() => {
+                    return data.ok_or(BytesIOError {
+                        value: BytesIOErrorValue::NoneReturn,
+                    });
+                }
+                //nothing is expected from the GET connection except the close
+                rv = self.io.read() => {
+                    rv?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{Base64Decoder, HttpTunnelRequest};
+    use crate::global_trait::Unmarshal;
+
+    #[test]
+    fn test_http_tunnel() {
+        let request = HttpTunnelRequest::unmarshal(
+            "POST /live/test HTTP/1.0\r\nx-sessioncookie: 7a1b2c3d\r\n\
+             Content-Type: application/x-rtsp-tunnelled\r\nContent-Length: 32767\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/live/test");
+        assert_eq!(request.session_cookie, "7a1b2c3d");
+
+        assert!(HttpTunnelRequest::unmarshal("GET /live/test HTTP/1.0\r\n\r\n").is_none());
+
+        //"OPTIONS rtsp://127.0.0.1/live/test RTSP/1.0\r\n" in base64, split randomly
+        let mut decoder = Base64Decoder::default();
+        let mut decoded = decoder.decode(b"T1BUSU9OUyBydHNwOi8vMTI3Lj").unwrap();
+        decoded.extend_from_slice(&decoder.decode(b"AuMC4xL2xpdmUvdGVzdCBSVFN\r\n").unwrap());
+        decoded.extend_from_slice(&decoder.decode(b"QLzEuMA0K").unwrap());
+        assert_eq!(
+            &decoded[..],
+            b"OPTIONS rtsp://127.0.0.1/live/test RTSP/1.0\r\n"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use super::global_trait::Marshal;
+use super::global_trait::Unmarshal;
+
+pub const DEFAULT_RTSP_PORT: u16 = 554;
+
+//rtsp://[username[:password]@]host[:port]/path[?query]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RtspUrl {
+    pub host: String,
+    pub port: u16,
+    //the path including the query string, e.g. Streaming/Channels/101?transportmode=unicast
+    pub path: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl RtspUrl {
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+impl Unmarshal for RtspUrl {
+    fn unmarshal(raw_data: &str) -> Option<Self> {
+        let mut rtsp_url = RtspUrl::default();
+
+        let data = raw_data.trim().strip_prefix("rtsp://")?;
+        let (authority, path) = match data.find('/') {
+            Some(idx) => (&data[..idx], &data[idx + 1..]),
+            None => (data, ""),
+        };
+        rtsp_url.path = path.to_string();
+
+        //the password may contain '@', so split from the last one
+        let host_with_port = if let Some((user_info, host_with_port)) = authority.rsplit_once('@') {
+            if let Some((username, password)) = user_info.split_once(':') {
+                rtsp_url.username = Some(username.to_string());
+                rtsp_url.password = Some(password.to_string());
+            } else {
+                rtsp_url.username = Some(user_info.to_string());
+                rtsp_url.password = Some(String::from(""));
+            }
+            host_with_port
+        } else {
+            authority
+        };
+
+        if let Some((host, port)) = host_with_port.rsplit_once(':') {
+            rtsp_url.host = host.to_string();
+            rtsp_url.port = port.parse::<u16>().ok()?;
+        } else {
+            rtsp_url.host = host_with_port.to_string();
+            rtsp_url.port = DEFAULT_RTSP_PORT;
+        }
+
+        if rtsp_url.host.is_empty() {
+            return None;
+        }
+
+        Some(rtsp_url)
+    }
+}
+
+//the url without the user information, it is used in the request line.
+impl Marshal for RtspUrl {
+    fn marshal(&self) -> String {
+        format!("rtsp://{}:{}/{}", self.host, self.port, self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::RtspUrl;
+    use crate::global_trait::Marshal;
+    use crate::global_trait::Unmarshal;
+
+    #[test]
+    fn test_parse_rtsp_url() {
+        let url = RtspUrl::unmarshal("rtsp://admin:p@ss@192.168.0.10/Streaming/Channels/101?a=b")
+            .unwrap();
+
+        assert_eq!(url.host, "192.168.0.10");
+        assert_eq!(url.port, 554);
+        assert_eq!(url.path, "Streaming/Channels/101?a=b");
+        assert_eq!(url.username.as_deref(), Some("admin"));
+        assert_eq!(url.password.as_deref(), Some("p@ss"));
+        assert_eq!(
+            url.marshal(),
+            "rtsp://192.168.0.10:554/Streaming/Channels/101?a=b"
+        );
+
+        let url = RtspUrl::unmarshal("rtsp://camera.local:8554").unwrap();
+        assert_eq!(url.address(), "camera.local:8554");
+        assert!(url.username.is_none());
+
+        assert!(RtspUrl::unmarshal("http://camera.local/live").is_none());
+    }
+}
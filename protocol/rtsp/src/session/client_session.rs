@@ -0,0 +1,1076 @@
+use super::define::rtsp_method_name;
+use super::errors::SessionError;
+use super::errors::SessionErrorValue;
+use super::{read_message, RtspMessage, RtspStreamHandler};
+use crate::global_trait::Marshal;
+use crate::global_trait::Unmarshal;
+use crate::http::RtspRequest;
+use crate::http::RtspResponse;
+use crate::rtp::errors::UnPackerError;
+use crate::rtp::RtpPacket;
+use crate::rtsp_auth::RtspAuthenticator;
+use crate::rtsp_auth::WwwAuthenticate;
+use crate::rtsp_codec;
+use crate::rtsp_codec::RtspCodecId;
+use crate::rtsp_codec::RtspCodecInfo;
+use crate::rtsp_track::RtspTrack;
+use crate::rtsp_track::TrackType;
+use crate::rtsp_transport::ProtocolType;
+use crate::rtsp_transport::RtspTransport;
+use crate::rtsp_url::RtspUrl;
+use crate::sdp::Sdp;
+
+use bytes::BytesMut;
+use bytesio::bytes_reader::BytesReader;
+use bytesio::bytes_writer::AsyncBytesWriter;
+use bytesio::bytesio::TNetIO;
+use bytesio::bytesio::TcpIO;
+use bytesio::bytesio::UdpIO;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use streamhub::{
+    define::{
+        wait_pub_sub_result, DataReceiver, DataSender, FrameData, FrameDataReceiver, Information,
+        NotifyInfo, PublishType, PublisherInfo, StreamHubEvent, StreamHubEventSender,
+        SubscribeType, SubscriberInfo,
+    },
+    stream::StreamIdentifier,
+    utils::{RandomDigitCount, Uuid},
+};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+const USER_AGENT: &str = "xiu";
+//the default session timeout in seconds(RFC 2326 12.37)
+const DEFAULT_SESSION_TIMEOUT: u64 = 60;
+//the session is treated as broken if no rtp data is received over tcp for a while
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientType {
+    //pull the stream from the remote server and publish it to the stream hub
+    Pull,
+    //subscribe the stream from the stream hub and push it to the remote server
+    Push,
+}
+
+enum ClientMessage {
+    Response(RtspResponse),
+    Interleaved {
+        channel_identifier: u8,
+        data: BytesMut,
+    },
+}
+
+// The rtsp client for relaying streams:
+// pull stream(e.g. from the IP cameras): OPTIONS->DESCRIBE->SETUP->PLAY
+// push stream(e.g. to the media archive servers): OPTIONS->ANNOUNCE->SETUP->RECORD->TEARDOWN
+pub struct RtspClientSession {
+    client_type: ClientType,
+    io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>,
+    reader: BytesReader,
+    writer: AsyncBytesWriter,
+
+    url: RtspUrl,
+    //the remote ip which the rtp/rtcp udp channels connect to
+    remote_ip: String,
+    //the local stream path which is pulled to or pushed from
+    stream_path: String,
+    protocol_type: ProtocolType,
+
+    tracks: HashMap<TrackType, RtspTrack>,
+    //the base url for the SETUP/PLAY requests
+    content_base: String,
+    cseq: u32,
+    session_id: Option<String>,
+    session_timeout: u64,
+    //use GET_PARAMETER as keepalive if the server supports it, otherwise OPTIONS
+    keepalive_method: &'static str,
+    authenticator: Option<RtspAuthenticator>,
+    //the rtp/rtcp receive loops of the udp transport
+    udp_tasks: Vec<JoinHandle<()>>,
+
+    stream_handler: Arc<RtspStreamHandler>,
+    frame_receiver: Option<FrameDataReceiver>,
+    event_producer: StreamHubEventSender,
+    id: Uuid,
+    is_published: bool,
+    is_subscribed: bool,
+}
+
+impl RtspClientSession {
+    pub fn new(
+        stream: TcpStream,
+        client_type: ClientType,
+        url: RtspUrl,
+        stream_path: String,
+        protocol_type: ProtocolType,
+        event_producer: StreamHubEventSender,
+    ) -> Self {
+        let remote_ip = if let Ok(addr) = stream.peer_addr() {
+            addr.ip().to_string()
+        } else {
+            url.host.clone()
+        };
+
+        let net_io: Box<dyn TNetIO + Send + Sync> = Box::new(TcpIO::new(stream));
+        let io = Arc::new(Mutex::new(net_io));
+
+        Self {
+            client_type,
+            io: io.clone(),
+            reader: BytesReader::new(BytesMut::default()),
+            writer: AsyncBytesWriter::new(io),
+            content_base: url.marshal(),
+            url,
+            remote_ip,
+            stream_path,
+            protocol_type,
+            tracks: HashMap::new(),
+            cseq: 0,
+            session_id: None,
+            session_timeout: DEFAULT_SESSION_TIMEOUT,
+            keepalive_method: rtsp_method_name::OPTIONS,
+            authenticator: None,
+            udp_tasks: Vec::new(),
+            stream_handler: Arc::new(RtspStreamHandler::new()),
+            frame_receiver: None,
+            event_producer,
+            id: Uuid::new(RandomDigitCount::Zero),
+            is_published: false,
+            is_subscribed: false,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), SessionError> {
+        let rv = self.run_session().await;
+        self.clean_up();
+        rv
+    }
+
+    //return None if the session is cancelled by the cancel sender
+    pub async fn run_until_cancelled(
+        &mut self,
+        cancel_receiver: &mut oneshot::Receiver<()>,
+    ) -> Option<Result<(), SessionError>> {
+        let rv = tokio::select! {
+            rv = self.run_session() => Some(rv),
+            _ = cancel_receiver => None,
+        };
+        self.clean_up();
+        rv
+    }
+
+    async fn run_session(&mut self) -> Result<(), SessionError> {
+        match self.client_type {
+            ClientType::Pull => self.pull().await,
+            ClientType::Push => self.push().await,
+        }
+    }
+
+    fn clean_up(&mut self) {
+        if self.is_published {
+            self.unpublish_from_stream_hub();
+        }
+        if self.is_subscribed {
+            self.unsubscribe_from_stream_hub();
+        }
+        for task in &self.udp_tasks {
+            task.abort();
+        }
+    }
+
+    async fn pull(&mut self) -> Result<(), SessionError> {
+        self.send_options().await?;
+        self.send_describe().await?;
+        self.set_frame_handlers().await;
+        self.send_setup().await?;
+        self.send_play().await?;
+        self.publish_to_stream_hub().await?;
+        self.receive_loop().await
+    }
+
+    async fn push(&mut self) -> Result<(), SessionError> {
+        self.send_options().await?;
+        self.send_announce().await?;
+        self.send_setup().await?;
+        self.send_record().await?;
+        self.send_loop().await
+    }
+
+    async fn send_options(&mut self) -> Result<(), SessionError> {
+        let response = self
+            .send_request(
+                rtsp_method_name::OPTIONS,
+                self.url.marshal(),
+                Vec::new(),
+                None,
+            )
+            .await?;
+
+        if let Some(public) = response.get_header("Public") {
+            if public
+                .split(',')
+                .any(|method| method.trim() == rtsp_method_name::GET_PARAMETER)
+            {
+                self.keepalive_method = rtsp_method_name::GET_PARAMETER;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_describe(&mut self) -> Result<(), SessionError> {
+        let url = self.url.marshal();
+        let headers = vec![(String::from("Accept"), String::from("application/sdp"))];
+        let response = self
+            .send_request(rtsp_method_name::DESCRIBE, url.clone(), headers, None)
+            .await?;
+
+        self.content_base = response
+            .get_header("Content-Base")
+            .or_else(|| response.get_header("Content-Location"))
+            .cloned()
+            .unwrap_or(url);
+
+        let sdp = match &response.body {
+            Some(body) => Sdp::unmarshal(body),
+            None => None,
+        };
+        let sdp = match sdp {
+            Some(sdp) => self.new_tracks(sdp)?,
+            None => {
+                return Err(SessionError {
+                    value: SessionErrorValue::ResponseParseError,
+                });
+            }
+        };
+        self.stream_handler.set_sdp(sdp).await;
+
+        Ok(())
+    }
+
+    async fn send_announce(&mut self) -> Result<(), SessionError> {
+        // The sender is used for sending sdp information from the stream hub to the client session
+        // receiver is used to receive the sdp information
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let request_event = StreamHubEvent::Request {
+            identifier: StreamIdentifier::Rtsp {
+                stream_path: self.stream_path.clone(),
+            },
+            sender,
+            pull: false,
+        };
+        if self.event_producer.send(request_event).is_err() {
+            return Err(SessionError {
+                value: SessionErrorValue::StreamHubEventSendErr,
+            });
+        }
+
+        let sdp = match receiver.recv().await {
+            Some(Information::Sdp { data }) => Sdp::unmarshal(&data),
+            _ => None,
+        };
+        let sdp = match sdp {
+            Some(sdp) => self.new_tracks(sdp)?,
+            None => {
+                return Err(SessionError {
+                    value: SessionErrorValue::SdpNotFound,
+                });
+            }
+        };
+
+        let headers = vec![(
+            String::from("Content-Type"),
+            String::from("application/sdp"),
+        )];
+        self.send_request(
+            rtsp_method_name::ANNOUNCE,
+            self.url.marshal(),
+            headers,
+            Some(sdp.marshal()),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn send_setup(&mut self) -> Result<(), SessionError> {
+        let mut track_types: Vec<TrackType> = self.tracks.keys().cloned().collect();
+        //setup the video track first
+        track_types.sort_by_key(|track_type| *track_type != TrackType::Video);
+
+        for (idx, track_type) in track_types.iter().enumerate() {
+            let control_url = self.tracks[track_type].media_control.clone();
+
+            let mut udp_ios: Option<(UdpIO, UdpIO)> = None;
+            let mut transport_data = match self.protocol_type {
+                ProtocolType::TCP => {
+                    format!(
+                        "RTP/AVP/TCP;unicast;interleaved={}-{}",
+                        2 * idx,
+                        2 * idx + 1
+                    )
+                }
+                ProtocolType::UDP => {
+                    let (rtp_io, rtcp_io, rtp_port) = Self::bind_udp_ports().await?;
+                    udp_ios = Some((rtp_io, rtcp_io));
+                    format!("RTP/AVP;unicast;client_port={}-{}", rtp_port, rtp_port + 1)
+                }
+            };
+            if self.client_type == ClientType::Push {
+                transport_data += ";mode=record";
+            }
+
+            let headers = vec![(String::from("Transport"), transport_data)];
+            let response = self
+                .send_request(rtsp_method_name::SETUP, control_url, headers, None)
+                .await?;
+
+            if let Some(session) = response.get_header("Session") {
+                //Session: 12345678;timeout=60
+                let mut parts = session.split(';');
+                if let Some(session_id) = parts.next() {
+                    self.session_id = Some(session_id.trim().to_string());
+                }
+                for part in parts {
+                    if let Some(timeout) = part.trim().strip_prefix("timeout=") {
+                        if let Ok(timeout) = timeout.parse::<u64>() {
+                            self.session_timeout = timeout;
+                        }
+                    }
+                }
+            }
+
+            let mut transport = match response
+                .get_header("Transport")
+                .and_then(|data| RtspTransport::unmarshal(data))
+            {
+                Some(transport) => transport,
+                None => {
+                    return Err(SessionError {
+                        value: SessionErrorValue::ResponseParseError,
+                    });
+                }
+            };
+
+            let track = self.tracks.get_mut(track_type).unwrap();
+            match udp_ios {
+                Some((mut rtp_io, mut rtcp_io)) => {
+                    let server_ports = match transport.server_port {
+                        Some(ports) => ports,
+                        None => {
+                            return Err(SessionError {
+                                value: SessionErrorValue::ResponseParseError,
+                            });
+                        }
+                    };
+                    rtp_io
+                        .connect(self.remote_ip.clone(), server_ports[0])
+                        .await?;
+                    rtcp_io
+                        .connect(self.remote_ip.clone(), server_ports[1])
+                        .await?;
+
+                    match self.client_type {
+                        ClientType::Pull => {
+                            self.udp_tasks
+                                .push(track.rtp_receive_loop(Box::new(rtp_io)).await);
+                        }
+                        ClientType::Push => {
+                            track
+                                .create_packer(Arc::new(Mutex::new(Box::new(rtp_io))))
+                                .await;
+                        }
+                    }
+                    self.udp_tasks.push(
+                        track
+                            .rtcp_receive_loop(Arc::new(Mutex::new(Box::new(rtcp_io))))
+                            .await,
+                    );
+                }
+                None => {
+                    if transport.interleaved.is_none() {
+                        transport.interleaved = Some([2 * idx as u8, 2 * idx as u8 + 1]);
+                    }
+                    if self.client_type == ClientType::Push {
+                        track.create_packer(self.io.clone()).await;
+                    }
+                }
+            }
+            track.set_transport(transport).await;
+            if self.client_type == ClientType::Push {
+                track.set_packet_handler().await;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn send_play(&mut self) -> Result<(), SessionError> {
+        let headers = vec![(String::from("Range"), String::from("npt=0.000-"))];
+        self.send_request(
+            rtsp_method_name::PLAY,
+            self.content_base.clone(),
+            headers,
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn send_record(&mut self) -> Result<(), SessionError> {
+        let headers = vec![(String::from("Range"), String::from("npt=0.000-"))];
+        self.send_request(
+            rtsp_method_name::RECORD,
+            self.content_base.clone(),
+            headers,
+            None,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn receive_loop(&mut self) -> Result<(), SessionError> {
+        let keepalive_interval = Duration::from_secs(std::cmp::max(self.session_timeout / 2, 5));
+        let mut last_keepalive_time = Instant::now();
+        let mut last_data_time = Instant::now();
+
+        loop {
+            if last_keepalive_time.elapsed() >= keepalive_interval {
+                self.write_request(
+                    self.keepalive_method,
+                    self.content_base.clone(),
+                    Vec::new(),
+                    None,
+                )
+                .await?;
+                last_keepalive_time = Instant::now();
+            }
+
+            match self.parse_message()? {
+                Some(ClientMessage::Interleaved {
+                    channel_identifier,
+                    data,
+                }) => {
+                    last_data_time = Instant::now();
+                    self.on_interleaved_data(channel_identifier, data).await;
+                    continue;
+                }
+                Some(ClientMessage::Response(response)) => {
+                    if response.status_code != http::StatusCode::OK.as_u16() {
+                        log::warn!(
+                            "keepalive response error, status code: {}",
+                            response.status_code
+                        );
+                    }
+                    continue;
+                }
+                None => {}
+            }
+
+            let wait_duration = keepalive_interval
+                .saturating_sub(last_keepalive_time.elapsed())
+                .min(RECEIVE_TIMEOUT);
+            if let Ok(data) = tokio::time::timeout(wait_duration, self.io.lock().await.read()).await
+            {
+                self.reader.extend_from_slice(&data?[..]);
+            }
+
+            //over udp, only the keepalive responses are received from the tcp connection
+            if self.protocol_type == ProtocolType::TCP && last_data_time.elapsed() > RECEIVE_TIMEOUT
+            {
+                return Err(SessionError {
+                    value: SessionErrorValue::ReceiveTimeout,
+                });
+            }
+        }
+    }
+
+    async fn send_loop(&mut self) -> Result<(), SessionError> {
+        // The sender is passsed to the stream hub, and using which send the a/v data from stream hub to the push session.
+        // The receiver is used for receiving and send to the remote server side.
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: StreamIdentifier::Rtsp {
+                stream_path: self.stream_path.clone(),
+            },
+            sender: DataSender::Frame { sender },
+            info: self.get_subscriber_info(),
+            result_sender,
+        };
+        if self.event_producer.send(subscribe_event).is_err() {
+            return Err(SessionError {
+                value: SessionErrorValue::StreamHubEventSendErr,
+            });
+        }
+        wait_pub_sub_result(result_receiver).await?;
+        self.is_subscribed = true;
+
+        let keepalive_interval = Duration::from_secs(std::cmp::max(self.session_timeout / 2, 5));
+        let mut keepalive_timer = tokio::time::interval_at(
+            tokio::time::Instant::now() + keepalive_interval,
+            keepalive_interval,
+        );
+        let io = self.io.clone();
+
+        loop {
+            tokio::select! {
+                frame_data = receiver.recv() => {
+                    let (track_type, timestamp, mut data) = match frame_data {
+                        Some(FrameData::Video { timestamp, data }) => {
+                            (TrackType::Video, timestamp, data)
+                        }
+                        Some(FrameData::Audio { timestamp, data }) => {
+                            (TrackType::Audio, timestamp, data)
+                        }
+                        Some(_) => continue,
+                        None => {
+                            log::info!(
+                                "rtsp push client: the stream {} is unpublished",
+                                self.stream_path
+                            );
+                            self.is_subscribed = false;
+                            self.write_request(
+                                rtsp_method_name::TEARDOWN,
+                                self.content_base.clone(),
+                                Vec::new(),
+                                None,
+                            )
+                            .await?;
+                            return Ok(());
+                        }
+                    };
+                    if let Some(track) = self.tracks.get_mut(&track_type) {
+                        track
+                            .rtp_channel
+                            .lock()
+                            .await
+                            .on_frame(&mut data, timestamp)
+                            .await?;
+                    }
+                }
+                //the keepalive responses and the rtcp packets from the server
+                data = async { io.lock().await.read().await } => {
+                    self.reader.extend_from_slice(&data?[..]);
+                    while let Some(message) = self.parse_message()? {
+                        if let ClientMessage::Interleaved { channel_identifier, data } = message {
+                            self.on_interleaved_data(channel_identifier, data).await;
+                        }
+                    }
+                }
+                _ = keepalive_timer.tick() => {
+                    self.write_request(
+                        self.keepalive_method,
+                        self.content_base.clone(),
+                        Vec::new(),
+                        None,
+                    )
+                    .await?;
+                }
+            }
+        }
+    }
+
+    async fn on_interleaved_data(&mut self, channel_identifier: u8, data: BytesMut) {
+        let mut reader = BytesReader::new(data);
+
+        for track in self.tracks.values_mut() {
+            if let Some(interleaveds) = track.transport.interleaved {
+                if channel_identifier == interleaveds[0] {
+                    if let Err(err) = track.on_rtp(&mut reader).await {
+                        log::warn!("rtsp client on rtp error: {}", err);
+                    }
+                } else if channel_identifier == interleaveds[1] {
+                    track.on_rtcp(&mut reader, self.io.clone()).await;
+                }
+            }
+        }
+    }
+
+    //only the supported audio/video tracks are relayed, and the media controls
+    //are replaced by streamid={index} which is used by our rtsp server.
+    fn new_tracks(&mut self, mut sdp: Sdp) -> Result<Sdp, SessionError> {
+        let mut medias = Vec::new();
+        for mut media in sdp.medias {
+            let track_type = match media.media_type.as_str() {
+                "audio" => TrackType::Audio,
+                "video" => TrackType::Video,
+                _ => continue,
+            };
+            if self.tracks.contains_key(&track_type) {
+                continue;
+            }
+
+            let codec_id = match rtsp_codec::RTSP_CODEC_NAME_2_ID
+                .get(&media.rtpmap.encoding_name.to_lowercase().as_str())
+            {
+                Some(RtspCodecId::G711A) | None => {
+                    log::warn!(
+                        "rtsp client: unsupported codec: {}",
+                        media.rtpmap.encoding_name
+                    );
+                    continue;
+                }
+                Some(codec_id) => codec_id.clone(),
+            };
+
+            let mut codec_info = RtspCodecInfo {
+                codec_id,
+                payload_type: media.rtpmap.payload_type as u8,
+                sample_rate: media.rtpmap.clock_rate,
+                ..Default::default()
+            };
+            if track_type == TrackType::Audio {
+                codec_info.channel_count = media.rtpmap.encoding_param.parse().unwrap_or(1);
+                media.rtpmap.encoding_param = codec_info.channel_count.to_string();
+            }
+
+            let control = format!("streamid={}", medias.len());
+            let control_url = match self.client_type {
+                ClientType::Pull => {
+                    let remote_control = media.attributes.get("control").cloned();
+                    Self::resolve_control_url(
+                        &self.content_base,
+                        &remote_control.unwrap_or_default(),
+                    )
+                }
+                ClientType::Push => Self::resolve_control_url(&self.content_base, &control),
+            };
+            media.attributes.insert(String::from("control"), control);
+
+            let track = RtspTrack::new(track_type.clone(), codec_info, control_url);
+            self.tracks.insert(track_type, track);
+            medias.push(media);
+        }
+
+        if medias.is_empty() {
+            return Err(SessionError {
+                value: SessionErrorValue::NoSupportedTrack,
+            });
+        }
+        sdp.medias = medias;
+
+        Ok(sdp)
+    }
+
+    // The handler must be set before the udp receive loops start, which
+    // hold the lock of the rtp channel.
+    async fn set_frame_handlers(&mut self) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        for track in self.tracks.values_mut() {
+            let sender_out = sender.clone();
+            track.rtp_channel.lock().await.on_frame_handler(Box::new(
+                move |msg: FrameData| -> Result<(), UnPackerError> {
+                    if let Err(err) = sender_out.send(msg) {
+                        log::error!("send frame error: {}", err);
+                    }
+                    Ok(())
+                },
+            ));
+            let rtcp_channel = Arc::clone(&track.rtcp_channel);
+            track
+                .rtp_channel
+                .lock()
+                .await
+                .on_packet_for_rtcp_handler(Box::new(move |packet: RtpPacket| {
+                    let rtcp_channel_in = Arc::clone(&rtcp_channel);
+                    Box::pin(async move {
+                        rtcp_channel_in.lock().await.on_packet(packet);
+                    })
+                }));
+        }
+
+        self.frame_receiver = Some(receiver);
+    }
+
+    fn resolve_control_url(base: &str, control: &str) -> String {
+        if control.starts_with("rtsp://") {
+            control.to_string()
+        } else if control.is_empty() || control == "*" {
+            base.to_string()
+        } else if base.ends_with('/') {
+            format!("{base}{control}")
+        } else {
+            format!("{base}/{control}")
+        }
+    }
+
+    // Bind a pair of udp ports for rtp and rtcp, the rtp port should be an even
+    // number and the rtcp one is the next port.
+    async fn bind_udp_ports() -> Result<(UdpIO, UdpIO, u16), SessionError> {
+        for _ in 0..10 {
+            if let Some(rtp_io) = UdpIO::bind(0).await {
+                if let Some(rtp_port) = rtp_io.get_local_port() {
+                    if rtp_port % 2 != 0 {
+                        continue;
+                    }
+                    if let Some(rtcp_io) = UdpIO::bind(rtp_port + 1).await {
+                        return Ok((rtp_io, rtcp_io, rtp_port));
+                    }
+                }
+            }
+        }
+
+        Err(SessionError {
+            value: SessionErrorValue::UdpBindError,
+        })
+    }
+
+    //send the request and retry once with the credentials if the server replies 401
+    async fn send_request(
+        &mut self,
+        method: &'static str,
+        url: String,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+    ) -> Result<RtspResponse, SessionError> {
+        let mut has_retried = false;
+
+        loop {
+            self.write_request(method, url.clone(), headers.clone(), body.clone())
+                .await?;
+            let response = self.read_response().await?;
+
+            if response.status_code == http::StatusCode::UNAUTHORIZED.as_u16() && !has_retried {
+                let challenge = response
+                    .get_header("WWW-Authenticate")
+                    .and_then(|data| WwwAuthenticate::unmarshal(data));
+
+                if let (Some(challenge), Some(username), Some(password)) = (
+                    challenge,
+                    self.url.username.clone(),
+                    self.url.password.clone(),
+                ) {
+                    self.authenticator =
+                        Some(RtspAuthenticator::new(username, password, challenge));
+                    has_retried = true;
+                    continue;
+                }
+            }
+
+            if response.status_code != http::StatusCode::OK.as_u16() {
+                log::error!(
+                    "rtsp client {} {} error: {} {}",
+                    method,
+                    url,
+                    response.status_code,
+                    response.reason_phrase
+                );
+                return Err(SessionError {
+                    value: SessionErrorValue::ResponseStatusError(response.status_code),
+                });
+            }
+
+            return Ok(response);
+        }
+    }
+
+    async fn write_request(
+        &mut self,
+        method: &'static str,
+        url: String,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+    ) -> Result<(), SessionError> {
+        self.cseq += 1;
+
+        let mut request = RtspRequest {
+            method: method.to_string(),
+            version: String::from("RTSP/1.0"),
+            body,
+            ..Default::default()
+        };
+        request
+            .headers
+            .insert(String::from("CSeq"), self.cseq.to_string());
+        request
+            .headers
+            .insert(String::from("User-Agent"), USER_AGENT.to_string());
+        if let Some(authenticator) = &mut self.authenticator {
+            request.headers.insert(
+                String::from("Authorization"),
+                authenticator.authorization(method, &url),
+            );
+        }
+        if let Some(session_id) = &self.session_id {
+            request
+                .headers
+                .insert(String::from("Session"), session_id.clone());
+        }
+        for (name, value) in headers {
+            request.headers.insert(name, value);
+        }
+        request.url = url;
+
+        self.writer.write(request.marshal().as_bytes())?;
+        self.writer.flush().await?;
+
+        Ok(())
+    }
+
+    async fn read_response(&mut self) -> Result<RtspResponse, SessionError> {
+        loop {
+            match self.parse_message()? {
+                Some(ClientMessage::Response(response)) => {
+                    return Ok(response);
+                }
+                //some servers send the rtp data before the PLAY response
+                Some(ClientMessage::Interleaved {
+                    channel_identifier,
+                    data,
+                }) => {
+                    self.on_interleaved_data(channel_identifier, data).await;
+                }
+                None => {
+                    let data = self.io.lock().await.read().await?;
+                    self.reader.extend_from_slice(&data[..]);
+                }
+            }
+        }
+    }
+
+    //parse a whole interleaved rtp/rtcp packet or rtsp response from the received data
+    fn parse_message(&mut self) -> Result<Option<ClientMessage>, SessionError> {
+        match read_message(&mut self.reader)? {
+            Some(RtspMessage::Interleaved {
+                channel_identifier,
+                data,
+            }) => Ok(Some(ClientMessage::Interleaved {
+                channel_identifier,
+                data,
+            })),
+            Some(RtspMessage::Text(message)) => {
+                match RtspResponse::unmarshal(std::str::from_utf8(&message)?) {
+                    Some(response) => Ok(Some(ClientMessage::Response(response))),
+                    None => Err(SessionError {
+                        value: SessionErrorValue::ResponseParseError,
+                    }),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn publish_to_stream_hub(&mut self) -> Result<(), SessionError> {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let publish_event = StreamHubEvent::Publish {
+            identifier: StreamIdentifier::Rtsp {
+                stream_path: self.stream_path.clone(),
+            },
+            receiver: DataReceiver {
+                frame_receiver: self.frame_receiver.take(),
+                packet_receiver: None,
+            },
+            info: self.get_publisher_info(),
+            stream_handler: self.stream_handler.clone(),
+            result_sender,
+        };
+
+        if self.event_producer.send(publish_event).is_err() {
+            return Err(SessionError {
+                value: SessionErrorValue::StreamHubEventSendErr,
+            });
+        }
+        wait_pub_sub_result(result_receiver).await?;
+        self.is_published = true;
+
+        Ok(())
+    }
+
+    fn unpublish_from_stream_hub(&mut self) {
+        let unpublish_event = StreamHubEvent::UnPublish {
+            identifier: StreamIdentifier::Rtsp {
+                stream_path: self.stream_path.clone(),
+            },
+            info: self.get_publisher_info(),
+        };
+
+        if let Err(err) = self.event_producer.send(unpublish_event) {
+            log::error!("unpublish_from_stream_hub err {}", err);
+        }
+        self.is_published = false;
+    }
+
+    fn unsubscribe_from_stream_hub(&mut self) {
+        let unsubscribe_event = StreamHubEvent::UnSubscribe {
+            identifier: StreamIdentifier::Rtsp {
+                stream_path: self.stream_path.clone(),
+            },
+            info: self.get_subscriber_info(),
+        };
+
+        if let Err(err) = self.event_producer.send(unsubscribe_event) {
+            log::error!("unsubscribe_from_stream_hub err {}", err);
+        }
+        self.is_subscribed = false;
+    }
+
+    fn get_subscriber_info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            id: self.id,
+            sub_type: SubscribeType::PublisherRtsp,
+            notify_info: NotifyInfo {
+                request_url: self.url.marshal(),
+                remote_addr: self.url.address(),
+            },
+        }
+    }
+
+    fn get_publisher_info(&self) -> PublisherInfo {
+        PublisherInfo {
+            id: self.id,
+            pub_type: PublishType::RelayRtsp,
+            notify_info: NotifyInfo {
+                request_url: self.url.marshal(),
+                remote_addr: self.url.address(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClientType, RtspClientSession};
+    use crate::global_trait::Unmarshal;
+    use crate::http::RtspRequest;
+    use crate::rtsp_transport::ProtocolType;
+    use crate::rtsp_url::RtspUrl;
+
+    use streamhub::define::{Information, StreamHubEvent};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+
+    const SDP: &str = "v=0\r\n\
+        o=- 0 0 IN IP4 127.0.0.1\r\n\
+        s=No Name\r\n\
+        c=IN IP4 127.0.0.1\r\n\
+        t=0 0\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        a=rtpmap:96 H264/90000\r\n\
+        a=fmtp:96 packetization-mode=1; sprop-parameter-sets=Z2QAHqzZQKAv+XARAAADAAEAAAMAMg8WLZY=,aOvjyyLA; profile-level-id=64001E\r\n\
+        m=audio 0 RTP/AVP 97\r\n\
+        a=rtpmap:97 MPEG4-GENERIC/48000/2\r\n\
+        a=fmtp:97 profile-level-id=1;mode=AAC-hbr;sizelength=13;indexlength=3;indexdeltalength=3; config=119056E500\r\n";
+
+    async fn read_request(stream: &mut TcpStream, data: &mut Vec<u8>) -> Option<RtspRequest> {
+        loop {
+            let text = String::from_utf8_lossy(data).to_string();
+            if let Some(idx) = text.find("\r\n\r\n") {
+                let content_length = text[..idx]
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .map(|len| len.parse::<usize>().unwrap())
+                    .unwrap_or(0);
+                let request_len = idx + 4 + content_length;
+                if data.len() >= request_len {
+                    let request = String::from_utf8(data.drain(..request_len).collect()).unwrap();
+                    return RtspRequest::unmarshal(&request);
+                }
+            }
+
+            let mut buf = [0_u8; 4096];
+            match stream.read(&mut buf).await {
+                Ok(0) | Err(_) => return None,
+                Ok(n) => data.extend_from_slice(&buf[..n]),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_push_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        /*the remote server which records the requests of the push session*/
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut data = Vec::new();
+            let mut requests = Vec::new();
+            while let Some(request) = read_request(&mut stream, &mut data).await {
+                let mut response =
+                    format!("RTSP/1.0 200 OK\r\nCSeq: {}\r\n", request.headers["CSeq"]);
+                if request.method == "SETUP" {
+                    let transport = &request.headers["Transport"];
+                    response +=
+                        &format!("Session: 12345678;timeout=60\r\nTransport: {transport}\r\n");
+                }
+                response += "\r\n";
+                stream.write_all(response.as_bytes()).await.unwrap();
+
+                let is_record = request.method == "RECORD";
+                requests.push(request);
+                if is_record {
+                    break;
+                }
+            }
+            requests
+        });
+
+        /*the stream hub which provides the sdp and refuses the subscription*/
+        let (event_producer, mut event_consumer) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(event) = event_consumer.recv().await {
+                if let StreamHubEvent::Request { sender, pull, .. } = event {
+                    assert!(!pull);
+                    let _ = sender.send(Information::Sdp {
+                        data: SDP.to_string(),
+                    });
+                }
+            }
+        });
+
+        let url = RtspUrl {
+            host: String::from("127.0.0.1"),
+            port,
+            path: String::from("live/test"),
+            username: None,
+            password: None,
+        };
+        let stream = TcpStream::connect(url.address()).await.unwrap();
+        let mut session = RtspClientSession::new(
+            stream,
+            ClientType::Push,
+            url,
+            String::from("live/test"),
+            ProtocolType::TCP,
+            event_producer,
+        );
+        assert!(session.run().await.is_err());
+
+        let requests = server.await.unwrap();
+        let methods: Vec<&str> = requests.iter().map(|r| r.method.as_str()).collect();
+        assert_eq!(methods, ["OPTIONS", "ANNOUNCE", "SETUP", "SETUP", "RECORD"]);
+
+        let announce = &requests[1];
+        let base_url = format!("rtsp://127.0.0.1:{port}/live/test");
+        assert_eq!(announce.url, base_url);
+        assert_eq!(announce.headers["Content-Type"], "application/sdp");
+        let body = announce.body.as_ref().unwrap();
+        assert!(body.contains("a=control:streamid=0"));
+        assert!(body.contains("a=control:streamid=1"));
+
+        /*the video track is setup first*/
+        assert_eq!(requests[2].url, format!("{base_url}/streamid=0"));
+        assert_eq!(
+            requests[2].headers["Transport"],
+            "RTP/AVP/TCP;unicast;interleaved=0-1;mode=record"
+        );
+        assert_eq!(requests[3].url, format!("{base_url}/streamid=1"));
+        assert_eq!(
+            requests[3].headers["Transport"],
+            "RTP/AVP/TCP;unicast;interleaved=2-3;mode=record"
+        );
+        assert_eq!(requests[3].headers["Session"], "12345678");
+        assert_eq!(requests[4].url, base_url);
+    }
+}
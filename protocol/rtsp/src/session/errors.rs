@@ -4,7 +4,9 @@ use {
     bytesio::{bytes_errors::BytesWriteError, bytesio_errors::BytesIOError},
     failure::{Backtrace, Fail},
     std::fmt,
+    std::io::Error,
     std::str::Utf8Error,
+    streamhub::errors::{AuthError, ChannelError},
 };
 
 #[derive(Debug)]
@@ -30,6 +32,59 @@ pub enum SessionErrorValue {
     CannotReceiveFrameData,
     #[fail(display = "pack error: {}\n", _0)]
     PackerError(#[cause] PackerError),
+    #[fail(display = "auth error: {}\n", _0)]
+    AuthError(#[cause] AuthError),
+    #[fail(display = "stream hub error: {}\n", _0)]
+    ChannelError(#[cause] ChannelError),
+    #[fail(display = "io error: {}\n", _0)]
+    IOError(#[cause] Error),
+    #[fail(display = "rtsp response error, status code: {}\n", _0)]
+    ResponseStatusError(u16),
+    #[fail(display = "cannot parse the rtsp response\n")]
+    ResponseParseError,
+    #[fail(display = "no supported audio/video track in the sdp\n")]
+    NoSupportedTrack,
+    #[fail(display = "cannot bind the local udp ports\n")]
+    UdpBindError,
+    #[fail(display = "no rtp data is received for a while\n")]
+    ReceiveTimeout,
+    #[fail(display = "cannot get the sdp of the stream from the stream hub\n")]
+    SdpNotFound,
+    #[fail(display = "no request or packet is received before the session timeout\n")]
+    SessionTimeout,
+    #[fail(display = "invalid rtsp over http tunnel request\n")]
+    InvalidTunnelRequest,
+    #[fail(display = "no rtsp over http tunnel for the session cookie: {}\n", _0)]
+    TunnelNotFound(String),
+    #[fail(
+        display = "the rtsp over http tunnel already exists for the session cookie: {}\n",
+        _0
+    )]
+    DuplicateTunnel(String),
+}
+
+impl From<Error> for SessionError {
+    fn from(error: Error) -> Self {
+        SessionError {
+            value: SessionErrorValue::IOError(error),
+        }
+    }
+}
+
+impl From<ChannelError> for SessionError {
+    fn from(error: ChannelError) -> Self {
+        SessionError {
+            value: SessionErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<AuthError> for SessionError {
+    fn from(error: AuthError) -> Self {
+        SessionError {
+            value: SessionErrorValue::AuthError(error),
+        }
+    }
 }
 
 impl From<BytesIOError> for SessionError {
@@ -1,3 +1,4 @@
+pub mod client_session;
 pub mod define;
 pub mod errors;
 use super::rtsp_codec;
@@ -5,9 +6,9 @@ use crate::global_trait::Marshal;
 use crate::global_trait::Unmarshal;
 use crate::http::RtspResponse;
 use crate::rtp::define::ANNEXB_NALU_START_CODE;
-use crate::rtp::utils::Marshal as RtpMarshal;
 
 use crate::rtp::RtpPacket;
+use crate::rtsp_auth::RtspServerAuth;
 use crate::rtsp_range::RtspRange;
 
 use crate::sdp::fmtp::Fmtp;
@@ -30,6 +31,7 @@ use errors::SessionErrorValue;
 use http::StatusCode;
 use streamhub::define::DataReceiver;
 use streamhub::define::DataSender;
+use streamhub::define::FrameDataReceiver;
 use streamhub::define::MediaInfo;
 use streamhub::define::VideoCodecType;
 
@@ -39,26 +41,38 @@ use super::sdp::Sdp;
 
 use async_trait::async_trait;
 use bytesio::bytesio::TNetIO;
-use bytesio::bytesio::TcpIO;
 use define::rtsp_method_name;
 
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
 
 use streamhub::{
+    auth::{Auth, AuthAction},
     define::{
-        FrameData, Information, InformationSender, NotifyInfo, PublishType, PublisherInfo,
-        StreamHubEvent, StreamHubEventSender, SubscribeType, SubscriberInfo, TStreamHandler,
+        wait_pub_sub_result, FrameData, Information, InformationSender, NotifyInfo,
+        PubSubResultReceiver, PublishType, PublisherInfo, StreamHubEvent, StreamHubEventSender,
+        SubscribeType, SubscriberInfo, TStreamHandler,
     },
     errors::{ChannelError, ChannelErrorValue},
     statistics::StreamStatistics,
     stream::StreamIdentifier,
     utils::{RandomDigitCount, Uuid},
 };
-use tokio::net::TcpStream;
 use tokio::sync::Mutex;
 
+const DESCRIBE_RETRY_TIMES: usize = 6;
+const DESCRIBE_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+//the session over udp is closed if no request or rtp/rtcp packet is received
+//within the timeout, the clients should send keepalives(e.g. GET_PARAMETER).
+const SESSION_TIMEOUT: Duration = Duration::from_secs(60);
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 pub struct RtspServerSession {
     io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>,
     reader: BytesReader,
@@ -70,47 +84,101 @@ pub struct RtspServerSession {
 
     stream_handler: Arc<RtspStreamHandler>,
     event_producer: StreamHubEventSender,
+    remote_addr: Option<SocketAddr>,
+    auth: Option<Arc<Auth>>,
+    //the rtsp username/password authentication(Basic/Digest)
+    rtsp_auth: Option<Arc<RtspServerAuth>>,
+    //the nonce of the digest challenge, it is unique for each session
+    auth_nonce: String,
+
+    //the player session receives the a/v frames from the stream hub after PLAY
+    frame_receiver: Option<FrameDataReceiver>,
+    publish_path: Option<String>,
+    subscribe_path: Option<String>,
+    is_paused: bool,
+    last_active_time: Instant,
+    udp_packet_count: usize,
+    udp_tasks: Vec<JoinHandle<()>>,
 }
 
-pub struct InterleavedBinaryData {
-    channel_identifier: u8,
-    length: u16,
+pub(crate) enum RtspMessage {
+    //the rtsp request or response
+    Text(BytesMut),
+    Interleaved {
+        channel_identifier: u8,
+        data: BytesMut,
+    },
 }
 
-impl InterleavedBinaryData {
-    // 10.12 Embedded (Interleaved) Binary Data
-    // Stream data such as RTP packets is encapsulated by an ASCII dollar
-    // sign (24 hexadecimal), followed by a one-byte channel identifier,
-    // followed by the length of the encapsulated binary data as a binary,
-    // two-byte integer in network byte order
-    fn new(reader: &mut BytesReader) -> Result<Option<Self>, SessionError> {
-        let is_dollar_sign = reader.advance_u8()? == 0x24;
-        log::debug!("dollar sign: {}", is_dollar_sign);
-        if is_dollar_sign {
-            reader.read_u8()?;
-            let channel_identifier = reader.read_u8()?;
-            log::debug!("channel_identifier: {}", channel_identifier);
-            let length = reader.read_u16::<BigEndian>()?;
-            log::debug!("length: {}", length);
-            return Ok(Some(InterleavedBinaryData {
-                channel_identifier,
-                length,
-            }));
+// Read a complete message from the reader, return None if more data is needed.
+// 10.12 Embedded (Interleaved) Binary Data
+// Stream data such as RTP packets is encapsulated by an ASCII dollar
+// sign (24 hexadecimal), followed by a one-byte channel identifier,
+// followed by the length of the encapsulated binary data as a binary,
+// two-byte integer in network byte order
+pub(crate) fn read_message(reader: &mut BytesReader) -> Result<Option<RtspMessage>, SessionError> {
+    if reader.is_empty() {
+        return Ok(None);
+    }
+
+    if reader.get(0)? == 0x24 {
+        if reader.len() < 4 {
+            return Ok(None);
+        }
+        let length = ((reader.get(2)? as usize) << 8) | reader.get(3)? as usize;
+        if reader.len() < 4 + length {
+            return Ok(None);
         }
-        Ok(None)
+
+        reader.read_u8()?;
+        let channel_identifier = reader.read_u8()?;
+        reader.read_u16::<BigEndian>()?;
+        let data = reader.read_bytes(length)?;
+
+        return Ok(Some(RtspMessage::Interleaved {
+            channel_identifier,
+            data,
+        }));
     }
+
+    let data = reader.get_remaining_bytes();
+    let header_length = match data.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(idx) => idx + 4,
+        None => return Ok(None),
+    };
+    let content_length = std::str::from_utf8(&data[..header_length])?
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                value.trim().parse::<usize>().ok()
+            } else {
+                None
+            }
+        })
+        .unwrap_or(0);
+    if data.len() < header_length + content_length {
+        return Ok(None);
+    }
+
+    Ok(Some(RtspMessage::Text(
+        reader.read_bytes(header_length + content_length)?,
+    )))
 }
 
 impl RtspServerSession {
-    pub fn new(stream: TcpStream, event_producer: StreamHubEventSender) -> Self {
-        // let remote_addr = if let Ok(addr) = stream.peer_addr() {
-        //     log::info!("server session: {}", addr.to_string());
-        //     Some(addr)
-        // } else {
-        //     None
-        // };
-
-        let net_io: Box<dyn TNetIO + Send + Sync> = Box::new(TcpIO::new(stream));
+    //the net io can be the plain tcp, the tls or the http tunnel.
+    pub fn new(
+        net_io: Box<dyn TNetIO + Send + Sync>,
+        remote_addr: Option<SocketAddr>,
+        event_producer: StreamHubEventSender,
+        auth: Option<Arc<Auth>>,
+        rtsp_auth: Option<Arc<RtspServerAuth>>,
+    ) -> Self {
+        if let Some(addr) = remote_addr {
+            log::info!("server session: {}", addr.to_string());
+        }
+
         let io = Arc::new(Mutex::new(net_io));
 
         Self {
@@ -122,40 +190,129 @@ impl RtspServerSession {
             session_id: None,
             event_producer,
             stream_handler: Arc::new(RtspStreamHandler::new()),
+            remote_addr,
+            auth,
+            rtsp_auth,
+            auth_nonce: RtspServerAuth::gen_nonce(),
+            frame_receiver: None,
+            publish_path: None,
+            subscribe_path: None,
+            is_paused: false,
+            last_active_time: Instant::now(),
+            udp_packet_count: 0,
+            udp_tasks: Vec::new(),
         }
     }
 
+    //the data which has been read from the net io before the session is created
+    pub fn extend_received_data(&mut self, data: &[u8]) {
+        self.reader.extend_from_slice(data);
+    }
+
     pub async fn run(&mut self) -> Result<(), SessionError> {
+        let rv = self.process().await;
+        self.release();
+        rv
+    }
+
+    async fn process(&mut self) -> Result<(), SessionError> {
+        let io = self.io.clone();
+        let mut frame_receiver: Option<FrameDataReceiver> = None;
+        let mut check_timer = tokio::time::interval(SESSION_CHECK_INTERVAL);
+
         loop {
-            while self.reader.len() < 4 {
-                let data = self.io.lock().await.read().await?;
-                self.reader.extend_from_slice(&data[..]);
+            while let Some(message) = read_message(&mut self.reader)? {
+                match message {
+                    RtspMessage::Interleaved {
+                        channel_identifier,
+                        data,
+                    } => {
+                        self.on_rtp_over_rtsp_message(channel_identifier, data)
+                            .await?;
+                    }
+                    RtspMessage::Text(data) => {
+                        self.on_rtsp_message(data).await?;
+                    }
+                }
+            }
+
+            //the receiver is set after PLAY and dropped after TEARDOWN
+            if let Some(receiver) = self.frame_receiver.take() {
+                frame_receiver = Some(receiver);
+            } else if self.subscribe_path.is_none() {
+                frame_receiver = None;
             }
 
-            if let Ok(data) = InterleavedBinaryData::new(&mut self.reader) {
-                match data {
-                    Some(a) => {
-                        if self.reader.len() < a.length as usize {
-                            let data = self.io.lock().await.read().await?;
-                            self.reader.extend_from_slice(&data[..]);
+            tokio::select! {
+                data = async { io.lock().await.read().await } => {
+                    self.reader.extend_from_slice(&data?[..]);
+                    self.last_active_time = Instant::now();
+                }
+                frame_data = async { frame_receiver.as_mut().unwrap().recv().await },
+                    if frame_receiver.is_some() => {
+                    match frame_data {
+                        Some(frame_data) => self.on_frame_data(frame_data).await?,
+                        None => {
+                            return Err(SessionError {
+                                value: SessionErrorValue::CannotReceiveFrameData,
+                            });
                         }
-                        self.on_rtp_over_rtsp_message(a.channel_identifier, a.length as usize)
-                            .await?;
-                    }
-                    None => {
-                        self.on_rtsp_message().await?;
                     }
                 }
+                _ = check_timer.tick() => {
+                    self.check_timeout()?;
+                }
             }
         }
     }
 
+    //the tcp connection is closed when the client is gone, but the sessions
+    //over udp need to be reaped if no data is received for a while.
+    fn check_timeout(&mut self) -> Result<(), SessionError> {
+        if self.udp_tasks.is_empty() {
+            return Ok(());
+        }
+
+        let udp_packet_count = self
+            .tracks
+            .values()
+            .map(|track| track.udp_packet_count.load(Ordering::Relaxed))
+            .sum();
+        if udp_packet_count != self.udp_packet_count {
+            self.udp_packet_count = udp_packet_count;
+            self.last_active_time = Instant::now();
+        }
+
+        if self.last_active_time.elapsed() > SESSION_TIMEOUT {
+            log::warn!("rtsp session {:?} is timed out", self.session_id);
+            return Err(SessionError {
+                value: SessionErrorValue::SessionTimeout,
+            });
+        }
+        Ok(())
+    }
+
+    //it is called when the session is torn down, disconnected or timed out
+    fn release(&mut self) {
+        for task in self.udp_tasks.drain(..) {
+            task.abort();
+        }
+        if let Some(stream_path) = self.subscribe_path.take() {
+            self.unsubscribe_from_stream_hub(stream_path);
+        }
+        if let Some(stream_path) = self.publish_path.take() {
+            self.unpublish_to_stream_hub(stream_path);
+        }
+        self.frame_receiver = None;
+        self.is_paused = false;
+    }
+
     async fn on_rtp_over_rtsp_message(
         &mut self,
         channel_identifier: u8,
-        length: usize,
+        data: BytesMut,
     ) -> Result<(), SessionError> {
-        let mut cur_reader = BytesReader::new(self.reader.read_bytes(length)?);
+        let mut cur_reader = BytesReader::new(data);
 
         for track in self.tracks.values_mut() {
             if let Some(interleaveds) = track.transport.interleaved {
@@ -174,9 +331,7 @@ impl RtspServerSession {
 
     //publish stream: OPTIONS->ANNOUNCE->SETUP->RECORD->TEARDOWN
     //subscribe stream: OPTIONS->DESCRIBE->SETUP->PLAY->TEARDOWN
-    async fn on_rtsp_message(&mut self) -> Result<(), SessionError> {
-        let data = self.reader.extract_remaining_bytes();
-
+    async fn on_rtsp_message(&mut self, data: BytesMut) -> Result<(), SessionError> {
         if let Some(rtsp_request) = RtspRequest::unmarshal(std::str::from_utf8(&data)?) {
             match rtsp_request.method.as_str() {
                 rtsp_method_name::OPTIONS => {
@@ -192,19 +347,23 @@ impl RtspServerSession {
                     self.handle_setup(&rtsp_request).await?;
                 }
                 rtsp_method_name::PLAY => {
-                    if self.handle_play(&rtsp_request).await.is_err() {
-                        self.unsubscribe_from_stream_hub(rtsp_request.path)?;
-                    }
+                    self.handle_play(&rtsp_request).await?;
                 }
                 rtsp_method_name::RECORD => {
                     self.handle_record(&rtsp_request).await?;
                 }
                 rtsp_method_name::TEARDOWN => {
-                    self.handle_teardown(&rtsp_request)?;
+                    self.handle_teardown(&rtsp_request).await?;
+                }
+                rtsp_method_name::PAUSE => {
+                    self.handle_pause(&rtsp_request).await?;
+                }
+                rtsp_method_name::GET_PARAMETER => {
+                    self.handle_get_parameter(&rtsp_request).await?;
+                }
+                rtsp_method_name::SET_PARAMETER => {
+                    self.handle_set_parameter(&rtsp_request).await?;
                 }
-                rtsp_method_name::PAUSE => {}
-                rtsp_method_name::GET_PARAMETER => {}
-                rtsp_method_name::SET_PARAMETER => {}
                 rtsp_method_name::REDIRECT => {}
 
                 _ => {}
@@ -225,30 +384,48 @@ impl RtspServerSession {
     }
 
     async fn handle_describe(&mut self, rtsp_request: &RtspRequest) -> Result<(), SessionError> {
+        if !self
+            .check_credentials(rtsp_request, AuthAction::Play)
+            .await?
+        {
+            return Ok(());
+        }
+        self.authenticate(rtsp_request, AuthAction::Play).await?;
+
         let status_code = http::StatusCode::OK;
 
-        // The sender is used for sending sdp information from the server session to client session
-        // receiver is used to receive the sdp information
-        let (sender, mut receiver) = mpsc::unbounded_channel();
+        // The stream may be pulled from the remote server on demand(rtsp relay),
+        // so request the pull only once and then wait for a while until it is published.
+        for retry_times in 0..DESCRIBE_RETRY_TIMES {
+            if retry_times > 0 {
+                tokio::time::sleep(DESCRIBE_RETRY_INTERVAL).await;
+            }
 
-        let request_event = StreamHubEvent::Request {
-            identifier: StreamIdentifier::Rtsp {
-                stream_path: rtsp_request.path.clone(),
-            },
-            sender,
-        };
+            // The sender is used for sending sdp information from the server session to client session
+            // receiver is used to receive the sdp information
+            let (sender, mut receiver) = mpsc::unbounded_channel();
 
-        if self.event_producer.send(request_event).is_err() {
-            return Err(SessionError {
-                value: SessionErrorValue::StreamHubEventSendErr,
-            });
-        }
+            let request_event = StreamHubEvent::Request {
+                identifier: StreamIdentifier::Rtsp {
+                    stream_path: rtsp_request.path.clone(),
+                },
+                sender,
+                pull: retry_times == 0,
+            };
 
-        if let Some(Information::Sdp { data }) = receiver.recv().await {
-            if let Some(sdp) = Sdp::unmarshal(&data) {
-                self.sdp = sdp;
-                //it can new tracks when get the sdp information;
-                self.new_tracks()?;
+            if self.event_producer.send(request_event).is_err() {
+                return Err(SessionError {
+                    value: SessionErrorValue::StreamHubEventSendErr,
+                });
+            }
+
+            if let Some(Information::Sdp { data }) = receiver.recv().await {
+                if let Some(sdp) = Sdp::unmarshal(&data) {
+                    self.sdp = sdp;
+                    //it can new tracks when get the sdp information;
+                    self.new_tracks()?;
+                }
+                break;
             }
         }
 
@@ -265,6 +442,14 @@ impl RtspServerSession {
     }
 
     async fn handle_announce(&mut self, rtsp_request: &RtspRequest) -> Result<(), SessionError> {
+        if !self
+            .check_credentials(rtsp_request, AuthAction::Publish)
+            .await?
+        {
+            return Ok(());
+        }
+        self.authenticate(rtsp_request, AuthAction::Publish).await?;
+
         if let Some(request_body) = &rtsp_request.body {
             if let Some(sdp) = Sdp::unmarshal(request_body) {
                 self.sdp = sdp.clone();
@@ -300,6 +485,7 @@ impl RtspServerSession {
             }));
         }
 
+        let (result_sender, result_receiver) = oneshot::channel();
         let publish_event = StreamHubEvent::Publish {
             identifier: StreamIdentifier::Rtsp {
                 stream_path: rtsp_request.path.clone(),
@@ -310,6 +496,7 @@ impl RtspServerSession {
             },
             info: self.get_publisher_info(),
             stream_handler: self.stream_handler.clone(),
+            result_sender,
         };
 
         if self.event_producer.send(publish_event).is_err() {
@@ -317,6 +504,9 @@ impl RtspServerSession {
                 value: SessionErrorValue::StreamHubEventSendErr,
             });
         }
+        self.wait_stream_hub_result(result_receiver, rtsp_request)
+            .await?;
+        self.publish_path = Some(rtsp_request.path.clone());
 
         let status_code = http::StatusCode::OK;
         let response = Self::gen_response(status_code, rtsp_request);
@@ -326,6 +516,18 @@ impl RtspServerSession {
     }
 
     async fn handle_setup(&mut self, rtsp_request: &RtspRequest) -> Result<(), SessionError> {
+        //if mode is empty then it is a player session.
+        let action = match rtsp_request
+            .get_header(&"Transport".to_string())
+            .and_then(|transport_data| RtspTransport::unmarshal(transport_data))
+        {
+            Some(trans) if trans.transport_mod.is_some() => AuthAction::Publish,
+            _ => AuthAction::Play,
+        };
+        if !self.check_credentials(rtsp_request, action).await? {
+            return Ok(());
+        }
+
         let status_code = http::StatusCode::OK;
         let mut response = Self::gen_response(status_code, rtsp_request);
 
@@ -367,7 +569,8 @@ impl RtspServerSession {
                                 if trans.transport_mod.is_none() {
                                     track.create_packer(Arc::new(Mutex::new(box_udp_io))).await;
                                 } else {
-                                    track.rtp_receive_loop(box_udp_io).await;
+                                    self.udp_tasks
+                                        .push(track.rtp_receive_loop(box_udp_io).await);
                                 }
                             }
 
@@ -378,7 +581,8 @@ impl RtspServerSession {
                                 rtcp_server_port = rtcp_io.get_local_port();
                                 let box_rtcp_io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>> =
                                     Arc::new(Mutex::new(Box::new(rtcp_io)));
-                                track.rtcp_receive_loop(box_rtcp_io).await;
+                                self.udp_tasks
+                                    .push(track.rtcp_receive_loop(box_rtcp_io).await);
                             }
                         }
                     }
@@ -397,9 +601,14 @@ impl RtspServerSession {
                     response
                         .headers
                         .insert("Transport".to_string(), new_transport_data);
-                    response
-                        .headers
-                        .insert("Session".to_string(), self.session_id.unwrap().to_string());
+                    response.headers.insert(
+                        "Session".to_string(),
+                        format!(
+                            "{};timeout={}",
+                            self.session_id.unwrap(),
+                            SESSION_TIMEOUT.as_secs()
+                        ),
+                    );
 
                     track.set_transport(trans).await;
                 }
@@ -413,65 +622,54 @@ impl RtspServerSession {
     }
 
     async fn handle_play(&mut self, rtsp_request: &RtspRequest) -> Result<(), SessionError> {
-        for track in self.tracks.values_mut() {
-            let protocol_type = track.transport.protocol_type.clone();
-
-            match protocol_type {
-                ProtocolType::TCP => {
-                    let channel_identifer = if let Some(interleaveds) = track.transport.interleaved
-                    {
-                        interleaveds[0]
-                    } else {
-                        log::error!("handle_play:should not be here!!!");
-                        0
-                    };
+        if !self
+            .check_credentials(rtsp_request, AuthAction::Play)
+            .await?
+        {
+            return Ok(());
+        }
 
-                    track.rtp_channel.lock().await.on_packet_handler(Box::new(
-                        move |io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>, packet: RtpPacket| {
-                            Box::pin(async move {
-                                let msg = packet.marshal()?;
-                                let mut bytes_writer = AsyncBytesWriter::new(io);
-                                bytes_writer.write_u8(0x24)?;
-                                bytes_writer.write_u8(channel_identifer)?;
-                                bytes_writer.write_u16::<BigEndian>(msg.len() as u16)?;
-                                bytes_writer.write(&msg)?;
-                                bytes_writer.flush().await?;
-                                Ok(())
-                            })
-                        },
-                    ));
-                }
-                ProtocolType::UDP => {
-                    track.rtp_channel.lock().await.on_packet_handler(Box::new(
-                        move |io: Arc<Mutex<Box<dyn TNetIO + Send + Sync>>>, packet: RtpPacket| {
-                            Box::pin(async move {
-                                let mut bytes_writer = AsyncBytesWriter::new(io);
-
-                                let msg = packet.marshal()?;
-                                bytes_writer.write(&msg)?;
-                                bytes_writer.flush().await?;
-                                Ok(())
-                            })
-                        },
-                    ));
-                }
+        let range = match rtsp_request.get_header(&"Range".to_string()) {
+            Some(range_str) => RtspRange::unmarshal(range_str),
+            None => Some(RtspRange::default()),
+        };
+        //all the streams are live, seeking to a position is not supported
+        let range = match range {
+            Some(range) if range.is_live() => range,
+            _ => {
+                let mut response =
+                    self.gen_session_response(StatusCode::from_u16(457).unwrap(), rtsp_request);
+                response.reason_phrase = String::from("Invalid Range");
+                return self.send_response(&response).await;
             }
-        }
+        };
 
-        let status_code = http::StatusCode::OK;
-        let response = Self::gen_response(status_code, rtsp_request);
+        let mut response = self.gen_session_response(http::StatusCode::OK, rtsp_request);
+        response
+            .headers
+            .insert(String::from("Range"), range.marshal());
 
-        self.send_response(&response).await?;
+        //resume the paused session
+        if self.subscribe_path.is_some() {
+            self.is_paused = false;
+            return self.send_response(&response).await;
+        }
+
+        for track in self.tracks.values_mut() {
+            track.set_packet_handler().await;
+        }
 
         // The sender is passsed to the stream hub, and using which send the a/v data from stream hub to the play session.
         // The receiver is used for receiving and send to the remote cient side.
-        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
         let publish_event = StreamHubEvent::Subscribe {
             identifier: StreamIdentifier::Rtsp {
                 stream_path: rtsp_request.path.clone(),
             },
             sender: DataSender::Frame { sender },
             info: self.get_subscriber_info(),
+            result_sender,
         };
 
         if self.event_producer.send(publish_event).is_err() {
@@ -479,56 +677,93 @@ impl RtspServerSession {
                 value: SessionErrorValue::StreamHubEventSendErr,
             });
         }
+        self.wait_stream_hub_result(result_receiver, rtsp_request)
+            .await?;
+        self.frame_receiver = Some(receiver);
+        self.subscribe_path = Some(rtsp_request.path.clone());
 
-        let mut retry_times = 0;
-        loop {
-            if let Some(frame_data) = receiver.recv().await {
-                match frame_data {
-                    FrameData::Audio {
-                        timestamp,
-                        mut data,
-                    } => {
-                        if let Some(audio_track) = self.tracks.get_mut(&TrackType::Audio) {
-                            audio_track
-                                .rtp_channel
-                                .lock()
-                                .await
-                                .on_frame(&mut data, timestamp)
-                                .await?;
-                        }
-                    }
-                    FrameData::Video {
-                        timestamp,
-                        mut data,
-                    } => {
-                        if let Some(video_track) = self.tracks.get_mut(&TrackType::Video) {
-                            video_track
-                                .rtp_channel
-                                .lock()
-                                .await
-                                .on_frame(&mut data, timestamp)
-                                .await?;
-                        }
-                    }
-                    _ => {}
+        self.send_response(&response).await
+    }
+
+    async fn on_frame_data(&mut self, frame_data: FrameData) -> Result<(), SessionError> {
+        //the live frames are dropped while pausing
+        if self.is_paused {
+            return Ok(());
+        }
+
+        match frame_data {
+            FrameData::Audio {
+                timestamp,
+                mut data,
+            } => {
+                if let Some(audio_track) = self.tracks.get_mut(&TrackType::Audio) {
+                    audio_track
+                        .rtp_channel
+                        .lock()
+                        .await
+                        .on_frame(&mut data, timestamp)
+                        .await?;
                 }
-            } else {
-                retry_times += 1;
-                log::info!(
-                    "send_channel_data: no data receives ,retry {} times!",
-                    retry_times
-                );
-
-                if retry_times > 10 {
-                    return Err(SessionError {
-                        value: SessionErrorValue::CannotReceiveFrameData,
-                    });
+            }
+            FrameData::Video {
+                timestamp,
+                mut data,
+            } => {
+                if let Some(video_track) = self.tracks.get_mut(&TrackType::Video) {
+                    video_track
+                        .rtp_channel
+                        .lock()
+                        .await
+                        .on_frame(&mut data, timestamp)
+                        .await?;
                 }
             }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    async fn handle_pause(&mut self, rtsp_request: &RtspRequest) -> Result<(), SessionError> {
+        if self.subscribe_path.is_none() {
+            let mut response =
+                self.gen_session_response(StatusCode::from_u16(455).unwrap(), rtsp_request);
+            response.reason_phrase = String::from("Method Not Valid in This State");
+            return self.send_response(&response).await;
         }
+
+        self.is_paused = true;
+        let response = self.gen_session_response(http::StatusCode::OK, rtsp_request);
+        self.send_response(&response).await
     }
 
-    pub fn unsubscribe_from_stream_hub(&mut self, stream_path: String) -> Result<(), SessionError> {
+    //it is used as the keepalive by most of the clients
+    async fn handle_get_parameter(
+        &mut self,
+        rtsp_request: &RtspRequest,
+    ) -> Result<(), SessionError> {
+        let response = self.gen_session_response(http::StatusCode::OK, rtsp_request);
+        self.send_response(&response).await
+    }
+
+    //no parameter can be set, the empty one is used as the keepalive
+    async fn handle_set_parameter(
+        &mut self,
+        rtsp_request: &RtspRequest,
+    ) -> Result<(), SessionError> {
+        let response = match &rtsp_request.body {
+            Some(body) if !body.trim().is_empty() => {
+                let mut response =
+                    self.gen_session_response(StatusCode::from_u16(451).unwrap(), rtsp_request);
+                response.reason_phrase = String::from("Parameter Not Understood");
+                response
+            }
+            _ => self.gen_session_response(http::StatusCode::OK, rtsp_request),
+        };
+        self.send_response(&response).await
+    }
+
+    pub fn unsubscribe_from_stream_hub(&mut self, stream_path: String) {
         let identifier = StreamIdentifier::Rtsp { stream_path };
 
         let subscribe_event = StreamHubEvent::UnSubscribe {
@@ -538,54 +773,52 @@ impl RtspServerSession {
         if let Err(err) = self.event_producer.send(subscribe_event) {
             log::error!("unsubscribe_from_stream_hub err {}\n", err);
         }
+    }
 
-        Ok(())
+    fn unpublish_to_stream_hub(&mut self, stream_path: String) {
+        let unpublish_event = StreamHubEvent::UnPublish {
+            identifier: StreamIdentifier::Rtsp {
+                stream_path: stream_path.clone(),
+            },
+            info: self.get_publisher_info(),
+        };
+
+        if self.event_producer.send(unpublish_event).is_err() {
+            log::error!("unpublish_to_channels error.stream_name: {}", stream_path);
+        } else {
+            log::info!(
+                "unpublish_to_channels successfully.stream name: {}",
+                stream_path
+            );
+        }
     }
 
     async fn handle_record(&mut self, rtsp_request: &RtspRequest) -> Result<(), SessionError> {
+        if !self
+            .check_credentials(rtsp_request, AuthAction::Publish)
+            .await?
+        {
+            return Ok(());
+        }
+
+        let mut response = self.gen_session_response(http::StatusCode::OK, rtsp_request);
         if let Some(range_str) = rtsp_request.headers.get(&String::from("Range")) {
             if let Some(range) = RtspRange::unmarshal(range_str) {
-                let status_code = http::StatusCode::OK;
-                let mut response = Self::gen_response(status_code, rtsp_request);
                 response
                     .headers
                     .insert(String::from("Range"), range.marshal());
-                response
-                    .headers
-                    .insert("Session".to_string(), self.session_id.unwrap().to_string());
-
-                self.send_response(&response).await?;
             }
         }
+        self.send_response(&response).await?;
 
         Ok(())
     }
 
-    fn handle_teardown(&mut self, rtsp_request: &RtspRequest) -> Result<(), SessionError> {
-        let stream_path = &rtsp_request.path;
-        let unpublish_event = StreamHubEvent::UnPublish {
-            identifier: StreamIdentifier::Rtsp {
-                stream_path: stream_path.clone(),
-            },
-            info: self.get_publisher_info(),
-        };
+    async fn handle_teardown(&mut self, rtsp_request: &RtspRequest) -> Result<(), SessionError> {
+        self.release();
 
-        let rv = self.event_producer.send(unpublish_event);
-        match rv {
-            Err(_) => {
-                log::error!("unpublish_to_channels error.stream_name: {}", stream_path);
-                Err(SessionError {
-                    value: SessionErrorValue::StreamHubEventSendErr,
-                })
-            }
-            Ok(()) => {
-                log::info!(
-                    "unpublish_to_channels successfully.stream name: {}",
-                    stream_path
-                );
-                Ok(())
-            }
-        }
+        let response = self.gen_session_response(http::StatusCode::OK, rtsp_request);
+        self.send_response(&response).await
     }
 
     fn new_tracks(&mut self) -> Result<(), SessionError> {
@@ -636,6 +869,88 @@ impl RtspServerSession {
         Ok(())
     }
 
+    //check the username/password, reply 401 with the challenge and return false if
+    //the request is rejected, the connection is kept for the client to retry.
+    async fn check_credentials(
+        &mut self,
+        rtsp_request: &RtspRequest,
+        action: AuthAction,
+    ) -> Result<bool, SessionError> {
+        let rtsp_auth = if let Some(rtsp_auth) = &self.rtsp_auth {
+            rtsp_auth.clone()
+        } else {
+            return Ok(true);
+        };
+
+        if rtsp_auth.authenticate(
+            &rtsp_request.method,
+            &rtsp_request.url,
+            &rtsp_request.path,
+            action,
+            rtsp_request.get_header(&"Authorization".to_string()),
+            &self.auth_nonce,
+        ) {
+            return Ok(true);
+        }
+
+        log::warn!(
+            "rtsp {} request is unauthorized, path: {}",
+            rtsp_request.method,
+            rtsp_request.path
+        );
+        let mut response = Self::gen_response(http::StatusCode::UNAUTHORIZED, rtsp_request);
+        response.headers.insert(
+            "WWW-Authenticate".to_string(),
+            rtsp_auth.challenge(&self.auth_nonce),
+        );
+        self.send_response(&response).await?;
+
+        Ok(false)
+    }
+
+    //reply 401 and return error if the request is rejected.
+    async fn authenticate(
+        &mut self,
+        rtsp_request: &RtspRequest,
+        action: AuthAction,
+    ) -> Result<(), SessionError> {
+        if let Some(auth) = &self.auth {
+            let identifier = StreamIdentifier::Rtsp {
+                stream_path: rtsp_request.path.clone(),
+            };
+            let url_parameters = rtsp_request.path_parameters.clone().unwrap_or_default();
+            let remote_addr = if let Some(addr) = self.remote_addr {
+                addr.to_string()
+            } else {
+                String::from("unknown")
+            };
+
+            if let Err(err) = auth
+                .authenticate(&identifier, action, &url_parameters, &remote_addr)
+                .await
+            {
+                let response = Self::gen_response(http::StatusCode::UNAUTHORIZED, rtsp_request);
+                self.send_response(&response).await?;
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    //the publish/subscribe may be rejected by the on_publish/on_play notify
+    async fn wait_stream_hub_result(
+        &mut self,
+        result_receiver: PubSubResultReceiver,
+        rtsp_request: &RtspRequest,
+    ) -> Result<(), SessionError> {
+        if let Err(err) = wait_pub_sub_result(result_receiver).await {
+            let response = Self::gen_response(http::StatusCode::FORBIDDEN, rtsp_request);
+            self.send_response(&response).await?;
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
     fn gen_response(status_code: StatusCode, rtsp_request: &RtspRequest) -> RtspResponse {
         let reason_phrase = if let Some(reason) = status_code.canonical_reason() {
             reason.to_string()
@@ -659,6 +974,20 @@ impl RtspServerSession {
         response
     }
 
+    fn gen_session_response(
+        &self,
+        status_code: StatusCode,
+        rtsp_request: &RtspRequest,
+    ) -> RtspResponse {
+        let mut response = Self::gen_response(status_code, rtsp_request);
+        if let Some(session_id) = &self.session_id {
+            response
+                .headers
+                .insert("Session".to_string(), session_id.to_string());
+        }
+        response
+    }
+
     fn get_subscriber_info(&mut self) -> SubscriberInfo {
         let id = if let Some(session_id) = &self.session_id {
             *session_id
@@ -733,7 +1062,7 @@ impl TStreamHandler for RtspStreamHandler {
             }
         };
         match sub_type {
-            SubscribeType::PlayerRtmp => {
+            SubscribeType::RemuxRtmp => {
                 let sdp_info = self.sdp.lock().await;
                 let mut video_clock_rate: u32 = 0;
                 let mut audio_clock_rate: u32 = 0;
@@ -822,3 +1151,249 @@ impl TStreamHandler for RtspStreamHandler {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{read_message, RtspMessage, RtspServerSession};
+    use crate::global_trait::Unmarshal;
+    use crate::http::RtspResponse;
+    use crate::rtsp_auth::{
+        AuthScheme, RtspAuthenticator, RtspCredential, RtspServerAuth, WwwAuthenticate,
+    };
+
+    use bytes::BytesMut;
+    use bytesio::bytes_reader::BytesReader;
+    use bytesio::bytesio::TcpIO;
+    use std::sync::Arc;
+    use streamhub::define::{Information, StreamHubEvent};
+    use streamhub::stream::StreamIdentifier;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+
+    const SDP: &str = "v=0\r\n\
+        o=- 0 0 IN IP4 127.0.0.1\r\n\
+        s=No Name\r\n\
+        c=IN IP4 127.0.0.1\r\n\
+        t=0 0\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        a=rtpmap:96 H264/90000\r\n\
+        a=fmtp:96 packetization-mode=1; sprop-parameter-sets=Z2QAHqzZQKAv+XARAAADAAEAAAMAMg8WLZY=,aOvjyyLA; profile-level-id=64001E\r\n\
+        a=control:streamid=0\r\n";
+
+    //start a server session and return the connected client stream
+    async fn start_session(
+        rtsp_auth: Option<Arc<RtspServerAuth>>,
+    ) -> (TcpStream, mpsc::UnboundedReceiver<StreamHubEvent>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, remote_addr) = listener.accept().await.unwrap();
+
+        let (event_producer, event_consumer) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut session = RtspServerSession::new(
+                Box::new(TcpIO::new(stream)),
+                Some(remote_addr),
+                event_producer,
+                None,
+                rtsp_auth,
+            );
+            let _ = session.run().await;
+        });
+
+        (client, event_consumer)
+    }
+
+    async fn request(client: &mut TcpStream, request: String) -> RtspResponse {
+        client.write_all(request.as_bytes()).await.unwrap();
+
+        let mut reader = BytesReader::new(BytesMut::new());
+        loop {
+            if let Some(RtspMessage::Text(data)) = read_message(&mut reader).unwrap() {
+                return RtspResponse::unmarshal(std::str::from_utf8(&data).unwrap()).unwrap();
+            }
+            let mut buf = [0_u8; 4096];
+            let n = client.read(&mut buf).await.unwrap();
+            assert!(n > 0);
+            reader.extend_from_slice(&buf[..n]);
+        }
+    }
+
+    #[test]
+    fn test_read_message() {
+        let mut reader = BytesReader::new(BytesMut::new());
+        reader.extend_from_slice(&[0x24, 0x01, 0x00, 0x03, 0x0a, 0x0b]);
+        assert!(read_message(&mut reader).unwrap().is_none());
+
+        reader.extend_from_slice(b"\x0cOPTIONS rtsp://127.0.0.1/live/test RTSP/1.0\r\n");
+        match read_message(&mut reader).unwrap() {
+            Some(RtspMessage::Interleaved {
+                channel_identifier,
+                data,
+            }) => {
+                assert_eq!(channel_identifier, 1);
+                assert_eq!(&data[..], &[0x0a, 0x0b, 0x0c]);
+            }
+            _ => panic!("the interleaved data is not parsed"),
+        }
+
+        /*wait for the whole body*/
+        reader.extend_from_slice(b"CSeq: 1\r\nContent-Length: 4\r\n\r\nab");
+        assert!(read_message(&mut reader).unwrap().is_none());
+        reader.extend_from_slice(b"cd");
+        match read_message(&mut reader).unwrap() {
+            Some(RtspMessage::Text(data)) => assert!(data.ends_with(b"\r\n\r\nabcd")),
+            _ => panic!("the request is not parsed"),
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_publish_session() {
+        let (mut client, mut event_consumer) = start_session(None).await;
+        let url = "rtsp://127.0.0.1:554/live/test";
+
+        let response = request(
+            &mut client,
+            format!("OPTIONS {url} RTSP/1.0\r\nCSeq: 1\r\n\r\n"),
+        )
+        .await;
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.get_header("CSeq").unwrap(), "1");
+        assert!(response.get_header("Public").unwrap().contains("ANNOUNCE"));
+
+        /*the stream hub accepts the publish*/
+        let hub = tokio::spawn(async move {
+            let mut identifiers = Vec::new();
+            while let Some(event) = event_consumer.recv().await {
+                match event {
+                    StreamHubEvent::Publish {
+                        identifier,
+                        result_sender,
+                        ..
+                    } => {
+                        identifiers.push(identifier);
+                        let _ = result_sender.send(Ok(()));
+                    }
+                    StreamHubEvent::UnPublish { identifier, .. } => {
+                        identifiers.push(identifier);
+                        return identifiers;
+                    }
+                    _ => {}
+                }
+            }
+            identifiers
+        });
+
+        let response = request(
+            &mut client,
+            format!(
+                "ANNOUNCE {url} RTSP/1.0\r\nCSeq: 2\r\nContent-Type: application/sdp\r\nContent-Length: {}\r\n\r\n{SDP}",
+                SDP.len()
+            ),
+        )
+        .await;
+        assert_eq!(response.status_code, 200);
+
+        let response = request(
+            &mut client,
+            format!(
+                "SETUP {url}/streamid=0 RTSP/1.0\r\nCSeq: 3\r\nTransport: RTP/AVP/TCP;unicast;interleaved=0-1;mode=record\r\n\r\n"
+            ),
+        )
+        .await;
+        assert_eq!(response.status_code, 200);
+        let session_id = response
+            .get_header("Session")
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        assert!(response
+            .get_header("Transport")
+            .unwrap()
+            .contains("interleaved=0-1"));
+
+        let response = request(
+            &mut client,
+            format!("RECORD {url} RTSP/1.0\r\nCSeq: 4\r\nSession: {session_id}\r\n\r\n"),
+        )
+        .await;
+        assert_eq!(response.status_code, 200);
+
+        let response = request(
+            &mut client,
+            format!("TEARDOWN {url} RTSP/1.0\r\nCSeq: 5\r\nSession: {session_id}\r\n\r\n"),
+        )
+        .await;
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.get_header("Session").unwrap(), &session_id);
+
+        let identifier = StreamIdentifier::Rtsp {
+            stream_path: String::from("live/test"),
+        };
+        assert_eq!(hub.await.unwrap(), [identifier.clone(), identifier]);
+    }
+
+    #[tokio::test]
+    async fn test_describe_with_digest_auth() {
+        let rtsp_auth = RtspServerAuth::new(
+            String::from("xiu"),
+            AuthScheme::Digest,
+            vec![RtspCredential {
+                path: String::from("live/*"),
+                username: String::from("admin"),
+                password: String::from("123456"),
+                read: true,
+                publish: false,
+            }],
+        );
+        let (mut client, mut event_consumer) = start_session(Some(Arc::new(rtsp_auth))).await;
+        let url = "rtsp://127.0.0.1:554/live/test";
+
+        /*the stream hub provides the sdp*/
+        tokio::spawn(async move {
+            while let Some(event) = event_consumer.recv().await {
+                if let StreamHubEvent::Request { sender, pull, .. } = event {
+                    assert!(pull);
+                    let _ = sender.send(Information::Sdp {
+                        data: SDP.to_string(),
+                    });
+                }
+            }
+        });
+
+        let response = request(
+            &mut client,
+            format!("DESCRIBE {url} RTSP/1.0\r\nCSeq: 1\r\n\r\n"),
+        )
+        .await;
+        assert_eq!(response.status_code, 401);
+        let challenge =
+            WwwAuthenticate::unmarshal(response.get_header("WWW-Authenticate").unwrap()).unwrap();
+
+        let mut authenticator =
+            RtspAuthenticator::new(String::from("admin"), String::from("123456"), challenge);
+        /*the digest of another uri is rejected and the connection is kept*/
+        let authorization = authenticator.authorization("DESCRIBE", "rtsp://127.0.0.1:554/live");
+        let response = request(
+            &mut client,
+            format!("DESCRIBE {url} RTSP/1.0\r\nCSeq: 2\r\nAuthorization: {authorization}\r\n\r\n"),
+        )
+        .await;
+        assert_eq!(response.status_code, 401);
+
+        let authorization = authenticator.authorization("DESCRIBE", url);
+        let response = request(
+            &mut client,
+            format!("DESCRIBE {url} RTSP/1.0\r\nCSeq: 3\r\nAuthorization: {authorization}\r\n\r\n"),
+        )
+        .await;
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.get_header("CSeq").unwrap(), "3");
+        assert!(response.body.unwrap().contains("H264/90000"));
+    }
+}
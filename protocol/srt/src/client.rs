@@ -0,0 +1,168 @@
+use {
+    super::{
+        connection::SrtConnection,
+        define::MAX_SEQ_NUMBER,
+        errors::{SrtError, SrtErrorValue},
+        handshake,
+        packet::{ControlType, Handshake, SrtPacket},
+    },
+    bytes::BytesMut,
+    std::{collections::VecDeque, io::ErrorKind, time::Duration},
+    tokio::{net::UdpSocket, time},
+};
+
+const MAX_PACKET_SIZE: usize = 1500;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(500);
+const HANDSHAKE_RETRY_TIMES: usize = 5;
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+
+//The srt caller, it can be used to push/pull the mpegts stream to/from a srt listener.
+pub struct SrtClient {
+    socket: UdpSocket,
+    connection: SrtConnection,
+    /*the received payloads which are not read*/
+    payloads: VecDeque<BytesMut>,
+}
+
+impl SrtClient {
+    pub async fn connect(
+        address: &str,
+        stream_id: &str,
+        latency: Duration,
+    ) -> Result<Self, SrtError> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.connect(address).await?;
+
+        let socket_id = (rand::random::<u32>() & 0x3FFF_FFFF).max(1);
+        let initial_seq_number = rand::random::<u32>() & MAX_SEQ_NUMBER;
+
+        let induction = Self::handshake(
+            &socket,
+            handshake::induction_request(socket_id, initial_seq_number),
+        )
+        .await?;
+        let conclusion = Self::handshake(
+            &socket,
+            handshake::conclusion_request(&induction, socket_id, stream_id, latency),
+        )
+        .await?;
+
+        if let Some(reason) = handshake::reject_reason(&conclusion) {
+            return Err(SrtError {
+                value: SrtErrorValue::HandshakeRejected(reason),
+            });
+        }
+
+        let connection = SrtConnection::new(
+            socket_id,
+            conclusion.socket_id,
+            conclusion.initial_seq_number,
+            handshake::negotiate_latency(&conclusion, latency),
+        );
+
+        Ok(Self {
+            socket,
+            connection,
+            payloads: VecDeque::new(),
+        })
+    }
+
+    //sends the handshake request and waits for the response with the same type or a rejection.
+    async fn handshake(socket: &UdpSocket, request: Handshake) -> Result<Handshake, SrtError> {
+        let handshake_type = request.handshake_type;
+        let data = handshake::handshake_packet(0, request).marshal()?;
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+
+        for _ in 0..HANDSHAKE_RETRY_TIMES {
+            socket.send(&data[..]).await?;
+
+            while let Ok(rv) = time::timeout(HANDSHAKE_TIMEOUT, socket.recv(&mut buf)).await {
+                let len = rv?;
+                if let Ok(SrtPacket::Control(control)) =
+                    SrtPacket::unmarshal(BytesMut::from(&buf[..len]))
+                {
+                    if let ControlType::Handshake(response) = control.control {
+                        if response.handshake_type == handshake_type
+                            || handshake::reject_reason(&response).is_some()
+                        {
+                            return Ok(response);
+                        }
+                    }
+                }
+            }
+        }
+
+        Err(SrtError {
+            value: SrtErrorValue::ConnectionTimeout,
+        })
+    }
+
+    pub async fn send(&mut self, payload: BytesMut) -> Result<(), SrtError> {
+        if self.connection.is_closed() {
+            return Err(SrtError {
+                value: SrtErrorValue::ConnectionClosed,
+            });
+        }
+        self.connection.send(payload);
+
+        //process the acks and naks without blocking
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        loop {
+            match self.socket.try_recv(&mut buf) {
+                Ok(len) => self.on_data(&buf[..len]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        let payloads = self.connection.on_tick();
+        self.payloads.extend(payloads);
+
+        self.flush().await
+    }
+
+    //returns none if the connection is closed.
+    pub async fn recv(&mut self) -> Result<Option<BytesMut>, SrtError> {
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        loop {
+            if let Some(payload) = self.payloads.pop_front() {
+                return Ok(Some(payload));
+            }
+            if self.connection.is_closed() {
+                return Ok(None);
+            }
+
+            if let Ok(rv) = time::timeout(TICK_INTERVAL, self.socket.recv(&mut buf)).await {
+                let len = rv?;
+                self.on_data(&buf[..len]);
+            }
+            let payloads = self.connection.on_tick();
+            self.payloads.extend(payloads);
+
+            self.flush().await?;
+        }
+    }
+
+    pub async fn close(&mut self) -> Result<(), SrtError> {
+        self.connection.close();
+        self.flush().await
+    }
+
+    fn on_data(&mut self, data: &[u8]) {
+        match SrtPacket::unmarshal(BytesMut::from(data)) {
+            Ok(packet) => {
+                let payloads = self.connection.on_packet(packet);
+                self.payloads.extend(payloads);
+            }
+            Err(err) => {
+                log::warn!("srt client unmarshal packet error: {}", err);
+            }
+        }
+    }
+
+    async fn flush(&mut self) -> Result<(), SrtError> {
+        while let Some(packet) = self.connection.poll_transmit() {
+            self.socket.send(&packet.marshal()?[..]).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,413 @@
+use {
+    super::{
+        define::{
+            packet_position, seq_add, seq_offset, seq_sub, DEFAULT_FLOW_WINDOW, FULL_ACK_INTERVAL,
+            KEEPALIVE_INTERVAL, NAK_INTERVAL, PEER_IDLE_TIMEOUT,
+        },
+        packet::{ControlPacket, ControlType, DataPacket, SrtPacket},
+    },
+    bytes::BytesMut,
+    std::{
+        collections::VecDeque,
+        time::{Duration, Instant},
+    },
+};
+
+const INIT_RTT_US: u32 = 100_000;
+const MAX_WINDOW_SIZE: usize = DEFAULT_FLOW_WINDOW as usize;
+
+//The live mode srt connection(no message api and no encryption), it only
+//handles the packets and the timers, the socket io is done by the session.
+pub struct SrtConnection {
+    local_socket_id: u32,
+    peer_socket_id: u32,
+    start_time: Instant,
+    latency: Duration,
+
+    /*sender side*/
+    next_send_seq: u32,
+    next_message_number: u32,
+    /*the sent packets which are not acknowledged, ordered by the sequence number*/
+    send_buffer: VecDeque<DataPacket>,
+
+    /*receiver side*/
+    /*the sequence number of the first packet in the receive window*/
+    next_recv_seq: u32,
+    /*the received packets after next_recv_seq, none for the lost packets*/
+    recv_window: VecDeque<Option<BytesMut>>,
+    /*the time when the first packet of the window is found lost*/
+    loss_since: Option<Instant>,
+    last_ack_seq: u32,
+    ack_number: u32,
+    /*(ack number, send time) of the full acks waiting for ackack*/
+    pending_acks: VecDeque<(u32, Instant)>,
+    rtt: u32,
+    rtt_variance: u32,
+
+    last_full_ack_time: Instant,
+    last_nak_time: Instant,
+    last_send_time: Instant,
+    last_recv_time: Instant,
+
+    outgoing: VecDeque<SrtPacket>,
+    closed: bool,
+}
+
+impl SrtConnection {
+    pub fn new(
+        local_socket_id: u32,
+        peer_socket_id: u32,
+        initial_seq_number: u32,
+        latency: Duration,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            local_socket_id,
+            peer_socket_id,
+            start_time: now,
+            latency,
+
+            next_send_seq: initial_seq_number,
+            next_message_number: 1,
+            send_buffer: VecDeque::new(),
+
+            next_recv_seq: initial_seq_number,
+            recv_window: VecDeque::new(),
+            loss_since: None,
+            last_ack_seq: initial_seq_number,
+            ack_number: 1,
+            pending_acks: VecDeque::new(),
+            rtt: INIT_RTT_US,
+            rtt_variance: INIT_RTT_US / 2,
+
+            last_full_ack_time: now,
+            last_nak_time: now,
+            last_send_time: now,
+            last_recv_time: now,
+
+            outgoing: VecDeque::new(),
+            closed: false,
+        }
+    }
+
+    pub fn local_socket_id(&self) -> u32 {
+        self.local_socket_id
+    }
+
+    pub fn peer_socket_id(&self) -> u32 {
+        self.peer_socket_id
+    }
+
+    //the connection is closed by the peer or timed out.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn poll_transmit(&mut self) -> Option<SrtPacket> {
+        let packet = self.outgoing.pop_front();
+        if packet.is_some() {
+            self.last_send_time = Instant::now();
+        }
+        packet
+    }
+
+    fn timestamp(&self) -> u32 {
+        self.start_time.elapsed().as_micros() as u32
+    }
+
+    fn send_control(&mut self, control: ControlType) {
+        self.outgoing.push_back(SrtPacket::Control(ControlPacket {
+            timestamp: self.timestamp(),
+            dest_socket_id: self.peer_socket_id,
+            control,
+        }));
+    }
+
+    pub fn send(&mut self, payload: BytesMut) {
+        let packet = DataPacket {
+            seq_number: self.next_send_seq,
+            position: packet_position::SOLO,
+            in_order: false,
+            retransmitted: false,
+            message_number: self.next_message_number,
+            timestamp: self.timestamp(),
+            dest_socket_id: self.peer_socket_id,
+            payload,
+        };
+        self.next_send_seq = seq_add(self.next_send_seq, 1);
+        self.next_message_number = (self.next_message_number % 0x03FF_FFFF) + 1;
+
+        //the oldest packets will not be retransmitted if the peer is too slow
+        if self.send_buffer.len() >= MAX_WINDOW_SIZE {
+            self.send_buffer.pop_front();
+        }
+        self.send_buffer.push_back(packet.clone());
+        self.outgoing.push_back(SrtPacket::Data(packet));
+    }
+
+    pub fn close(&mut self) {
+        if !self.closed {
+            self.send_control(ControlType::Shutdown);
+            self.closed = true;
+        }
+    }
+
+    //returns the payloads delivered in order.
+    pub fn on_packet(&mut self, packet: SrtPacket) -> Vec<BytesMut> {
+        self.last_recv_time = Instant::now();
+
+        match packet {
+            SrtPacket::Data(data) => return self.on_data(data),
+            SrtPacket::Control(control) => match control.control {
+                ControlType::Ack {
+                    ack_number,
+                    last_ack_seq,
+                    ..
+                } => {
+                    while let Some(packet) = self.send_buffer.front() {
+                        if seq_offset(packet.seq_number, last_ack_seq) <= 0 {
+                            break;
+                        }
+                        self.send_buffer.pop_front();
+                    }
+                    self.send_control(ControlType::AckAck { ack_number });
+                }
+                ControlType::Nak { loss_list } => self.on_nak(loss_list),
+                ControlType::AckAck { ack_number } => self.on_ackack(ack_number),
+                ControlType::Shutdown => self.closed = true,
+                _ => {}
+            },
+        }
+
+        Vec::new()
+    }
+
+    fn on_data(&mut self, data: DataPacket) -> Vec<BytesMut> {
+        let offset = seq_offset(self.next_recv_seq, data.seq_number);
+        //the packet is already delivered or dropped
+        if offset < 0 {
+            return Vec::new();
+        }
+        let offset = offset as usize;
+        if offset >= MAX_WINDOW_SIZE {
+            log::warn!(
+                "srt data packet seq: {} is out of the receive window",
+                data.seq_number
+            );
+            return Vec::new();
+        }
+
+        let window_len = self.recv_window.len();
+        if offset >= window_len {
+            if offset > window_len {
+                //report the new lost packets immediately
+                let first = seq_add(self.next_recv_seq, window_len as u32);
+                let last = seq_sub(data.seq_number, 1);
+                self.send_control(ControlType::Nak {
+                    loss_list: vec![(first, last)],
+                });
+            }
+            self.recv_window.resize(offset + 1, None);
+        }
+        self.recv_window[offset] = Some(data.payload);
+
+        self.deliver()
+    }
+
+    fn deliver(&mut self) -> Vec<BytesMut> {
+        let mut payloads = Vec::new();
+        while let Some(Some(_)) = self.recv_window.front() {
+            if let Some(Some(payload)) = self.recv_window.pop_front() {
+                payloads.push(payload);
+            }
+            self.next_recv_seq = seq_add(self.next_recv_seq, 1);
+        }
+
+        //the first packet of the window is lost if the window is not empty
+        self.loss_since = if self.recv_window.is_empty() {
+            None
+        } else if payloads.is_empty() {
+            self.loss_since.or_else(|| Some(Instant::now()))
+        } else {
+            Some(Instant::now())
+        };
+        payloads
+    }
+
+    fn on_nak(&mut self, loss_list: Vec<(u32, u32)>) {
+        let first_seq = match self.send_buffer.front() {
+            Some(packet) => packet.seq_number,
+            None => return,
+        };
+
+        for (first, last) in loss_list {
+            let count = seq_offset(first, last);
+            if count < 0 {
+                continue;
+            }
+            for i in 0..=count as u32 {
+                let index = seq_offset(first_seq, seq_add(first, i));
+                if index < 0 {
+                    continue;
+                }
+                match self.send_buffer.get(index as usize) {
+                    Some(packet) => {
+                        let mut packet = packet.clone();
+                        packet.retransmitted = true;
+                        self.outgoing.push_back(SrtPacket::Data(packet));
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    fn on_ackack(&mut self, ack_number: u32) {
+        while let Some((number, send_time)) = self.pending_acks.pop_front() {
+            if number == ack_number {
+                let sample = send_time.elapsed().as_micros() as u32;
+                let diff = self.rtt.abs_diff(sample);
+                self.rtt_variance = (self.rtt_variance * 3 + diff) / 4;
+                self.rtt = (self.rtt * 7 + sample) / 8;
+                break;
+            }
+        }
+    }
+
+    //called every few milliseconds to send the acks, naks and keepalives,
+    //returns the payloads delivered after dropping the too late lost packets.
+    pub fn on_tick(&mut self) -> Vec<BytesMut> {
+        let now = Instant::now();
+        let mut payloads = Vec::new();
+
+        if now.duration_since(self.last_recv_time) > PEER_IDLE_TIMEOUT {
+            log::warn!("srt connection: {} is timeout", self.local_socket_id);
+            self.closed = true;
+            return payloads;
+        }
+
+        //too late packet drop: give up the lost packets after the latency
+        if let Some(loss_since) = self.loss_since {
+            if now.duration_since(loss_since) > self.latency {
+                let mut dropped = 0;
+                while let Some(None) = self.recv_window.front() {
+                    self.recv_window.pop_front();
+                    self.next_recv_seq = seq_add(self.next_recv_seq, 1);
+                    dropped += 1;
+                }
+                log::warn!(
+                    "srt connection: {} drops {} too late packets",
+                    self.local_socket_id,
+                    dropped
+                );
+                self.loss_since = None;
+                payloads = self.deliver();
+            }
+        }
+
+        if now.duration_since(self.last_full_ack_time) >= FULL_ACK_INTERVAL {
+            self.last_full_ack_time = now;
+            if self.next_recv_seq != self.last_ack_seq {
+                self.last_ack_seq = self.next_recv_seq;
+                let ack_number = self.ack_number;
+                self.ack_number = self.ack_number.wrapping_add(1);
+                self.pending_acks.push_back((ack_number, now));
+                if self.pending_acks.len() > 64 {
+                    self.pending_acks.pop_front();
+                }
+                self.send_control(ControlType::Ack {
+                    ack_number,
+                    last_ack_seq: self.next_recv_seq,
+                    rtt: self.rtt,
+                    rtt_variance: self.rtt_variance,
+                    available_buffer: (MAX_WINDOW_SIZE - self.recv_window.len()) as u32,
+                });
+            }
+        }
+
+        //periodic nak reports all the lost packets in the receive window
+        if now.duration_since(self.last_nak_time) >= NAK_INTERVAL {
+            self.last_nak_time = now;
+            let loss_list = self.loss_list();
+            if !loss_list.is_empty() {
+                self.send_control(ControlType::Nak { loss_list });
+            }
+        }
+
+        if self.outgoing.is_empty() && now.duration_since(self.last_send_time) >= KEEPALIVE_INTERVAL
+        {
+            self.send_control(ControlType::KeepAlive);
+        }
+
+        payloads
+    }
+
+    fn loss_list(&self) -> Vec<(u32, u32)> {
+        let mut loss_list: Vec<(u32, u32)> = Vec::new();
+        for (index, _) in self
+            .recv_window
+            .iter()
+            .enumerate()
+            .filter(|(_, payload)| payload.is_none())
+        {
+            let seq = seq_add(self.next_recv_seq, index as u32);
+            match loss_list.last_mut() {
+                Some((_, last)) if seq_add(*last, 1) == seq => *last = seq,
+                _ => loss_list.push((seq, seq)),
+            }
+        }
+        loss_list
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SrtConnection;
+    use crate::packet::{ControlType, SrtPacket};
+    use bytes::BytesMut;
+    use std::time::Duration;
+
+    fn transmit(
+        from: &mut SrtConnection,
+        to: &mut SrtConnection,
+        drop_seq: Option<u32>,
+    ) -> Vec<BytesMut> {
+        let mut payloads = Vec::new();
+        while let Some(packet) = from.poll_transmit() {
+            if let SrtPacket::Data(data) = &packet {
+                if Some(data.seq_number) == drop_seq && !data.retransmitted {
+                    continue;
+                }
+            }
+            payloads.extend(to.on_packet(packet));
+        }
+        payloads
+    }
+
+    #[test]
+    fn test_retransmit_lost_packet() {
+        let mut sender = SrtConnection::new(1, 2, 0x7FFF_FFFE, Duration::from_millis(120));
+        let mut receiver = SrtConnection::new(2, 1, 0x7FFF_FFFE, Duration::from_millis(120));
+
+        for i in 0..4u8 {
+            sender.send(BytesMut::from(&[i][..]));
+        }
+        //the second packet is lost, the sequence number wraps around
+        let payloads = transmit(&mut sender, &mut receiver, Some(0x7FFF_FFFF));
+        assert_eq!(payloads.len(), 1);
+
+        //the receiver reports the lost packet
+        let nak = receiver.poll_transmit().unwrap();
+        assert!(matches!(
+            &nak,
+            SrtPacket::Control(control) if control.control == ControlType::Nak {
+                loss_list: vec![(0x7FFF_FFFF, 0x7FFF_FFFF)]
+            }
+        ));
+        sender.on_packet(nak);
+
+        let payloads = transmit(&mut sender, &mut receiver, None);
+        let payloads: Vec<u8> = payloads.iter().map(|payload| payload[0]).collect();
+        assert_eq!(payloads, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+pub const SRT_VERSION: u32 = 0x0001_0500;
+pub const UDT_VERSION: u32 = 4;
+pub const HS_VERSION_5: u32 = 5;
+/*the extension field of the induction response from the listener*/
+pub const SRT_MAGIC_CODE: u16 = 0x4A17;
+pub const UDT_DGRAM: u16 = 2;
+
+pub const DEFAULT_MTU: u32 = 1500;
+pub const DEFAULT_FLOW_WINDOW: u32 = 8192;
+/*7 ts packets per srt data packet*/
+pub const TS_PAYLOAD_SIZE: usize = 1316;
+pub const MAX_SEQ_NUMBER: u32 = 0x7FFF_FFFF;
+
+pub const HEADER_SIZE: usize = 16;
+pub const HANDSHAKE_CIF_SIZE: usize = 48;
+
+pub const FULL_ACK_INTERVAL: Duration = Duration::from_millis(10);
+pub const NAK_INTERVAL: Duration = Duration::from_millis(20);
+pub const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(1);
+pub const PEER_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+pub const DEFAULT_LATENCY: Duration = Duration::from_millis(120);
+
+pub mod control_type {
+    pub const HANDSHAKE: u16 = 0x0000;
+    pub const KEEPALIVE: u16 = 0x0001;
+    pub const ACK: u16 = 0x0002;
+    pub const NAK: u16 = 0x0003;
+    pub const SHUTDOWN: u16 = 0x0005;
+    pub const ACKACK: u16 = 0x0006;
+}
+
+pub mod handshake_type {
+    pub const WAVEAHAND: u32 = 0x0000_0000;
+    pub const INDUCTION: u32 = 0x0000_0001;
+    pub const CONCLUSION: u32 = 0xFFFF_FFFF;
+    pub const AGREEMENT: u32 = 0xFFFF_FFFE;
+    /*the handshake type of a rejection is 1000 + reject reason*/
+    pub const FAILURE_TYPES: u32 = 1000;
+}
+
+pub mod extension_type {
+    pub const HSREQ: u16 = 1;
+    pub const HSRSP: u16 = 2;
+    pub const SID: u16 = 5;
+}
+
+/*the flags in the extension field of the conclusion handshake*/
+pub mod extension_flag {
+    pub const HSREQ: u16 = 0x0001;
+    pub const KMREQ: u16 = 0x0002;
+    pub const CONFIG: u16 = 0x0004;
+}
+
+/*the flags of the HSREQ/HSRSP extension*/
+pub mod srt_flag {
+    pub const TSBPDSND: u32 = 0x0000_0001;
+    pub const TSBPDRCV: u32 = 0x0000_0002;
+    pub const TLPKTDROP: u32 = 0x0000_0008;
+    pub const PERIODICNAK: u32 = 0x0000_0010;
+    pub const REXMITFLG: u32 = 0x0000_0020;
+}
+
+pub mod reject_reason {
+    pub const ROGUE: u32 = 4;
+    pub const VERSION: u32 = 8;
+    pub const UNSECURE: u32 = 11;
+    /*access control reasons which can be set by the application*/
+    pub const BAD_REQUEST: u32 = 1400;
+    pub const UNAUTHORIZED: u32 = 1401;
+    pub const FORBIDDEN: u32 = 1403;
+    pub const NOT_FOUND: u32 = 1404;
+    pub const CONFLICT: u32 = 1409;
+}
+
+/*the packet position flags of the data packet*/
+pub mod packet_position {
+    pub const MIDDLE: u8 = 0b00;
+    pub const LAST: u8 = 0b01;
+    pub const FIRST: u8 = 0b10;
+    pub const SOLO: u8 = 0b11;
+}
+
+//returns the sequence number after adding the offset.
+pub fn seq_add(seq: u32, offset: u32) -> u32 {
+    seq.wrapping_add(offset) & MAX_SEQ_NUMBER
+}
+
+//returns the sequence number after subtracting the offset.
+pub fn seq_sub(seq: u32, offset: u32) -> u32 {
+    seq.wrapping_sub(offset) & MAX_SEQ_NUMBER
+}
+
+//returns the offset from seq1 to seq2 in the 31 bits sequence space.
+pub fn seq_offset(seq1: u32, seq2: u32) -> i32 {
+    let diff = seq2.wrapping_sub(seq1) & MAX_SEQ_NUMBER;
+    if diff > MAX_SEQ_NUMBER / 2 {
+        diff as i32 - (MAX_SEQ_NUMBER as i32) - 1
+    } else {
+        diff as i32
+    }
+}
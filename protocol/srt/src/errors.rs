@@ -0,0 +1,158 @@
+use {
+    bytesio::bytes_errors::{BytesReadError, BytesWriteError},
+    failure::{Backtrace, Fail},
+    rtmp::{amf0::errors::Amf0WriteError, cache::errors::CacheError},
+    std::fmt,
+    streamhub::errors::{AuthError, ChannelError},
+    xflv::errors::{FlvDemuxerError, FlvMuxerError, Mpeg4AvcHevcError},
+    xmpegts::errors::MpegTsError,
+};
+
+#[derive(Debug)]
+pub struct SrtError {
+    pub value: SrtErrorValue,
+}
+
+#[derive(Debug, Fail)]
+pub enum SrtErrorValue {
+    #[fail(display = "bytes read error:{}\n", _0)]
+    BytesReadError(#[cause] BytesReadError),
+    #[fail(display = "bytes write error:{}\n", _0)]
+    BytesWriteError(#[cause] BytesWriteError),
+    #[fail(display = "io error:{}\n", _0)]
+    IOError(#[cause] std::io::Error),
+    #[fail(display = "mpegts error:{}\n", _0)]
+    MpegTsError(#[cause] MpegTsError),
+    #[fail(display = "mpeg avc error:{}\n", _0)]
+    MpegAvcError(#[cause] Mpeg4AvcHevcError),
+    #[fail(display = "flv muxer error:{}\n", _0)]
+    FlvMuxerError(#[cause] FlvMuxerError),
+    #[fail(display = "flv demuxer error:{}\n", _0)]
+    FlvDemuxerError(#[cause] FlvDemuxerError),
+    #[fail(display = "amf write error:{}\n", _0)]
+    Amf0WriteError(#[cause] Amf0WriteError),
+    #[fail(display = "cache error:{}\n", _0)]
+    CacheError(#[cause] CacheError),
+    #[fail(display = "auth error:{}\n", _0)]
+    AuthError(#[cause] AuthError),
+    #[fail(display = "stream hub event send error\n")]
+    StreamHubEventSendErr,
+    #[fail(display = "stream hub error: {}\n", _0)]
+    ChannelError(#[cause] ChannelError),
+    #[fail(display = "send frame data error\n")]
+    SendFrameDataErr,
+    #[fail(display = "invalid srt packet\n")]
+    InvalidPacket,
+    #[fail(display = "invalid stream id: {}\n", _0)]
+    InvalidStreamId(String),
+    #[fail(display = "handshake is rejected, reason: {}\n", _0)]
+    HandshakeRejected(u32),
+    #[fail(display = "connection timeout\n")]
+    ConnectionTimeout,
+    #[fail(display = "connection is closed\n")]
+    ConnectionClosed,
+}
+
+impl From<ChannelError> for SrtError {
+    fn from(error: ChannelError) -> Self {
+        SrtError {
+            value: SrtErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<BytesReadError> for SrtError {
+    fn from(error: BytesReadError) -> Self {
+        SrtError {
+            value: SrtErrorValue::BytesReadError(error),
+        }
+    }
+}
+
+impl From<BytesWriteError> for SrtError {
+    fn from(error: BytesWriteError) -> Self {
+        SrtError {
+            value: SrtErrorValue::BytesWriteError(error),
+        }
+    }
+}
+
+impl From<std::io::Error> for SrtError {
+    fn from(error: std::io::Error) -> Self {
+        SrtError {
+            value: SrtErrorValue::IOError(error),
+        }
+    }
+}
+
+impl From<MpegTsError> for SrtError {
+    fn from(error: MpegTsError) -> Self {
+        SrtError {
+            value: SrtErrorValue::MpegTsError(error),
+        }
+    }
+}
+
+impl From<Mpeg4AvcHevcError> for SrtError {
+    fn from(error: Mpeg4AvcHevcError) -> Self {
+        SrtError {
+            value: SrtErrorValue::MpegAvcError(error),
+        }
+    }
+}
+
+impl From<FlvMuxerError> for SrtError {
+    fn from(error: FlvMuxerError) -> Self {
+        SrtError {
+            value: SrtErrorValue::FlvMuxerError(error),
+        }
+    }
+}
+
+impl From<FlvDemuxerError> for SrtError {
+    fn from(error: FlvDemuxerError) -> Self {
+        SrtError {
+            value: SrtErrorValue::FlvDemuxerError(error),
+        }
+    }
+}
+
+impl From<Amf0WriteError> for SrtError {
+    fn from(error: Amf0WriteError) -> Self {
+        SrtError {
+            value: SrtErrorValue::Amf0WriteError(error),
+        }
+    }
+}
+
+impl From<CacheError> for SrtError {
+    fn from(error: CacheError) -> Self {
+        SrtError {
+            value: SrtErrorValue::CacheError(error),
+        }
+    }
+}
+
+impl From<AuthError> for SrtError {
+    fn from(error: AuthError) -> Self {
+        SrtError {
+            value: SrtErrorValue::AuthError(error),
+        }
+    }
+}
+
+impl fmt::Display for SrtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for SrtError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
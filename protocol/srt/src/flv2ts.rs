@@ -0,0 +1,91 @@
+use {
+    super::{define::TS_PAYLOAD_SIZE, errors::SrtError},
+    bytes::BytesMut,
+    streamhub::define::FrameData,
+    xflv::{
+        define::frame_type,
+        demuxer::{FlvAudioTagDemuxer, FlvVideoTagDemuxer},
+    },
+    xmpegts::{
+        define::{epsi_stream_type, MPEG_FLAG_IDR_FRAME},
+        ts::TsMuxer,
+    },
+};
+
+//Remuxes the rtmp frame data(flv tag bodies) to the mpegts stream.
+pub struct Flv2TsRemuxer {
+    video_demuxer: FlvVideoTagDemuxer,
+    audio_demuxer: FlvAudioTagDemuxer,
+    ts_muxer: TsMuxer,
+    video_pid: u16,
+    audio_pid: u16,
+}
+
+impl Default for Flv2TsRemuxer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Flv2TsRemuxer {
+    pub fn new() -> Self {
+        let mut ts_muxer = TsMuxer::new();
+        let audio_pid = ts_muxer
+            .add_stream(epsi_stream_type::PSI_STREAM_AAC, BytesMut::new())
+            .unwrap();
+        let video_pid = ts_muxer
+            .add_stream(epsi_stream_type::PSI_STREAM_H264, BytesMut::new())
+            .unwrap();
+
+        Self {
+            video_demuxer: FlvVideoTagDemuxer::new(),
+            audio_demuxer: FlvAudioTagDemuxer::new(),
+            ts_muxer,
+            video_pid,
+            audio_pid,
+        }
+    }
+
+    //returns the ts data split into the srt payloads(7 ts packets at most).
+    pub fn process(&mut self, frame_data: FrameData) -> Result<Vec<BytesMut>, SrtError> {
+        match frame_data {
+            FrameData::Video { timestamp, data } => {
+                if let Some(video_data) = self.video_demuxer.demux(timestamp, data)? {
+                    let flags = if video_data.frame_type == frame_type::KEY_FRAME {
+                        MPEG_FLAG_IDR_FRAME
+                    } else {
+                        0
+                    };
+                    self.ts_muxer.write(
+                        self.video_pid,
+                        video_data.pts * 90,
+                        video_data.dts * 90,
+                        flags,
+                        video_data.data,
+                    )?;
+                }
+            }
+            FrameData::Audio { timestamp, data } => {
+                let audio_data = self.audio_demuxer.demux(timestamp, data)?;
+                if audio_data.has_data {
+                    self.ts_muxer.write(
+                        self.audio_pid,
+                        audio_data.pts * 90,
+                        audio_data.dts * 90,
+                        0,
+                        audio_data.data,
+                    )?;
+                }
+            }
+            _ => {}
+        }
+
+        let mut ts_data = self.ts_muxer.get_data();
+        let mut payloads = Vec::new();
+        while !ts_data.is_empty() {
+            let size = ts_data.len().min(TS_PAYLOAD_SIZE);
+            payloads.push(ts_data.split_to(size));
+        }
+        Ok(payloads)
+    }
+}
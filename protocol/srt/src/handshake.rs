@@ -0,0 +1,153 @@
+use {
+    super::{
+        define::{
+            extension_flag, handshake_type, srt_flag, DEFAULT_FLOW_WINDOW, DEFAULT_MTU,
+            HS_VERSION_5, SRT_MAGIC_CODE, SRT_VERSION, UDT_DGRAM, UDT_VERSION,
+        },
+        packet::{
+            ControlPacket, ControlType, Handshake, HandshakeExtension, SrtOptions, SrtPacket,
+        },
+    },
+    std::time::Duration,
+};
+
+/*
+ The caller-listener handshake(HSv5):
+   caller                              listener
+     |---- induction(version 4) -------->|
+     |<--- induction(version 5, cookie) -|
+     |---- conclusion(cookie, HSREQ, SID)->|
+     |<--- conclusion(HSRSP) or rejection -|
+*/
+
+const SRT_FLAGS: u32 = srt_flag::TSBPDSND
+    | srt_flag::TSBPDRCV
+    | srt_flag::TLPKTDROP
+    | srt_flag::PERIODICNAK
+    | srt_flag::REXMITFLG;
+
+pub fn handshake_packet(dest_socket_id: u32, handshake: Handshake) -> SrtPacket {
+    SrtPacket::Control(ControlPacket {
+        timestamp: 0,
+        dest_socket_id,
+        control: ControlType::Handshake(handshake),
+    })
+}
+
+pub fn induction_request(socket_id: u32, initial_seq_number: u32) -> Handshake {
+    Handshake {
+        version: UDT_VERSION,
+        extension_field: UDT_DGRAM,
+        initial_seq_number,
+        mtu: DEFAULT_MTU,
+        flow_window: DEFAULT_FLOW_WINDOW,
+        handshake_type: handshake_type::INDUCTION,
+        socket_id,
+        ..Default::default()
+    }
+}
+
+pub fn induction_response(request: &Handshake, socket_id: u32, syn_cookie: u32) -> Handshake {
+    Handshake {
+        version: HS_VERSION_5,
+        extension_field: SRT_MAGIC_CODE,
+        initial_seq_number: request.initial_seq_number,
+        mtu: request.mtu,
+        flow_window: request.flow_window,
+        handshake_type: handshake_type::INDUCTION,
+        socket_id,
+        syn_cookie,
+        peer_ip: request.peer_ip,
+        ..Default::default()
+    }
+}
+
+pub fn conclusion_request(
+    induction_response: &Handshake,
+    socket_id: u32,
+    stream_id: &str,
+    latency: Duration,
+) -> Handshake {
+    let latency = latency.as_millis() as u16;
+    Handshake {
+        version: HS_VERSION_5,
+        extension_field: extension_flag::HSREQ | extension_flag::CONFIG,
+        initial_seq_number: induction_response.initial_seq_number,
+        mtu: induction_response.mtu,
+        flow_window: induction_response.flow_window,
+        handshake_type: handshake_type::CONCLUSION,
+        socket_id,
+        syn_cookie: induction_response.syn_cookie,
+        peer_ip: induction_response.peer_ip,
+        extensions: vec![
+            HandshakeExtension::HsReq(SrtOptions {
+                srt_version: SRT_VERSION,
+                flags: SRT_FLAGS,
+                recv_tsbpd_delay: latency,
+                send_tsbpd_delay: latency,
+            }),
+            HandshakeExtension::StreamId(stream_id.to_string()),
+        ],
+        ..Default::default()
+    }
+}
+
+pub fn conclusion_response(request: &Handshake, socket_id: u32, latency: Duration) -> Handshake {
+    let latency = negotiate_latency(request, latency).as_millis() as u16;
+    Handshake {
+        version: HS_VERSION_5,
+        extension_field: extension_flag::HSREQ,
+        initial_seq_number: request.initial_seq_number,
+        mtu: request.mtu,
+        flow_window: request.flow_window,
+        handshake_type: handshake_type::CONCLUSION,
+        socket_id,
+        syn_cookie: request.syn_cookie,
+        peer_ip: request.peer_ip,
+        extensions: vec![HandshakeExtension::HsRsp(SrtOptions {
+            srt_version: SRT_VERSION,
+            flags: SRT_FLAGS,
+            recv_tsbpd_delay: latency,
+            send_tsbpd_delay: latency,
+        })],
+        ..Default::default()
+    }
+}
+
+pub fn rejection(request: &Handshake, reason: u32) -> Handshake {
+    Handshake {
+        version: HS_VERSION_5,
+        initial_seq_number: request.initial_seq_number,
+        mtu: request.mtu,
+        flow_window: request.flow_window,
+        handshake_type: handshake_type::FAILURE_TYPES + reason,
+        syn_cookie: request.syn_cookie,
+        peer_ip: request.peer_ip,
+        ..Default::default()
+    }
+}
+
+//returns the reject reason if the handshake is a rejection.
+pub fn reject_reason(handshake: &Handshake) -> Option<u32> {
+    match handshake.handshake_type {
+        handshake_type::WAVEAHAND
+        | handshake_type::INDUCTION
+        | handshake_type::CONCLUSION
+        | handshake_type::AGREEMENT => None,
+        value if value >= handshake_type::FAILURE_TYPES => {
+            Some(value - handshake_type::FAILURE_TYPES)
+        }
+        _ => None,
+    }
+}
+
+//the larger latency of the two peers is used.
+pub fn negotiate_latency(peer_handshake: &Handshake, latency: Duration) -> Duration {
+    match peer_handshake.srt_options() {
+        Some(options) => {
+            let peer_latency = options.recv_tsbpd_delay.max(options.send_tsbpd_delay);
+            latency.max(Duration::from_millis(peer_latency as u64))
+        }
+        None => latency,
+    }
+}
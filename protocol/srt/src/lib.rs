@@ -0,0 +1,11 @@
+pub mod client;
+pub mod connection;
+pub mod define;
+pub mod errors;
+pub mod flv2ts;
+pub mod handshake;
+pub mod packet;
+pub mod session;
+pub mod srt;
+pub mod stream_id;
+pub mod ts2flv;
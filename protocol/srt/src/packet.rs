@@ -0,0 +1,488 @@
+use {
+    super::{
+        define::{control_type, extension_type, HANDSHAKE_CIF_SIZE, HEADER_SIZE, MAX_SEQ_NUMBER},
+        errors::{SrtError, SrtErrorValue},
+    },
+    byteorder::BigEndian,
+    bytes::BytesMut,
+    bytesio::{bytes_reader::BytesReader, bytes_writer::BytesWriter},
+};
+
+/*
+ ** Data Packet **
+ 0                   1                   2                   3
+ 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|0|                    Packet Sequence Number                   |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|P P|O|K K|R|                   Message Number                  |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|                           Timestamp                           |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|                     Destination Socket ID                     |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+ ** Control Packet **
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|1|         Control Type        |            Subtype            |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|                   Type-specific Information                   |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|                           Timestamp                           |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|                     Destination Socket ID                     |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+|                   Control Information Field                   |
++-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+ reference: https://datatracker.ietf.org/doc/html/draft-sharabayko-srt
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataPacket {
+    pub seq_number: u32,
+    pub position: u8,
+    pub in_order: bool,
+    pub retransmitted: bool,
+    pub message_number: u32,
+    pub timestamp: u32,
+    pub dest_socket_id: u32,
+    pub payload: BytesMut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SrtOptions {
+    pub srt_version: u32,
+    pub flags: u32,
+    pub recv_tsbpd_delay: u16,
+    pub send_tsbpd_delay: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeExtension {
+    HsReq(SrtOptions),
+    HsRsp(SrtOptions),
+    StreamId(String),
+    Unknown { ext_type: u16, data: BytesMut },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Handshake {
+    pub version: u32,
+    pub encryption: u16,
+    pub extension_field: u16,
+    pub initial_seq_number: u32,
+    pub mtu: u32,
+    pub flow_window: u32,
+    pub handshake_type: u32,
+    pub socket_id: u32,
+    pub syn_cookie: u32,
+    pub peer_ip: [u8; 16],
+    pub extensions: Vec<HandshakeExtension>,
+}
+
+impl Handshake {
+    pub fn stream_id(&self) -> Option<&str> {
+        self.extensions.iter().find_map(|ext| match ext {
+            HandshakeExtension::StreamId(stream_id) => Some(stream_id.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn srt_options(&self) -> Option<SrtOptions> {
+        self.extensions.iter().find_map(|ext| match ext {
+            HandshakeExtension::HsReq(options) | HandshakeExtension::HsRsp(options) => {
+                Some(*options)
+            }
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlType {
+    Handshake(Handshake),
+    KeepAlive,
+    Ack {
+        ack_number: u32,
+        /*the sequence number of the next expected packet*/
+        last_ack_seq: u32,
+        /*in microseconds*/
+        rtt: u32,
+        rtt_variance: u32,
+        available_buffer: u32,
+    },
+    /*the lost sequence numbers, (first, last) for each range*/
+    Nak {
+        loss_list: Vec<(u32, u32)>,
+    },
+    Shutdown,
+    AckAck {
+        ack_number: u32,
+    },
+    Unknown {
+        control_type: u16,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlPacket {
+    pub timestamp: u32,
+    pub dest_socket_id: u32,
+    pub control: ControlType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SrtPacket {
+    Data(DataPacket),
+    Control(ControlPacket),
+}
+
+impl SrtPacket {
+    pub fn dest_socket_id(&self) -> u32 {
+        match self {
+            SrtPacket::Data(data) => data.dest_socket_id,
+            SrtPacket::Control(control) => control.dest_socket_id,
+        }
+    }
+
+    pub fn unmarshal(data: BytesMut) -> Result<Self, SrtError> {
+        if data.len() < HEADER_SIZE {
+            return Err(SrtError {
+                value: SrtErrorValue::InvalidPacket,
+            });
+        }
+
+        let mut reader = BytesReader::new(data);
+        let first_word = reader.read_u32::<BigEndian>()?;
+        let second_word = reader.read_u32::<BigEndian>()?;
+        let timestamp = reader.read_u32::<BigEndian>()?;
+        let dest_socket_id = reader.read_u32::<BigEndian>()?;
+
+        if first_word & 0x8000_0000 == 0 {
+            return Ok(SrtPacket::Data(DataPacket {
+                seq_number: first_word,
+                position: (second_word >> 30) as u8,
+                in_order: (second_word >> 29) & 0x01 == 1,
+                retransmitted: (second_word >> 26) & 0x01 == 1,
+                message_number: second_word & 0x03FF_FFFF,
+                timestamp,
+                dest_socket_id,
+                payload: reader.extract_remaining_bytes(),
+            }));
+        }
+
+        let control = match ((first_word >> 16) & 0x7FFF) as u16 {
+            control_type::HANDSHAKE => ControlType::Handshake(Self::read_handshake(&mut reader)?),
+            control_type::KEEPALIVE => ControlType::KeepAlive,
+            control_type::ACK => {
+                let last_ack_seq = reader.read_u32::<BigEndian>()?;
+                //a light ack only contains the last acknowledged sequence number
+                let mut fields = [0u32; 3];
+                for field in &mut fields {
+                    if reader.len() < 4 {
+                        break;
+                    }
+                    *field = reader.read_u32::<BigEndian>()?;
+                }
+                ControlType::Ack {
+                    ack_number: second_word,
+                    last_ack_seq,
+                    rtt: fields[0],
+                    rtt_variance: fields[1],
+                    available_buffer: fields[2],
+                }
+            }
+            control_type::NAK => {
+                let mut loss_list = Vec::new();
+                while reader.len() >= 4 {
+                    let seq = reader.read_u32::<BigEndian>()?;
+                    if seq & 0x8000_0000 != 0 {
+                        let last = reader.read_u32::<BigEndian>()?;
+                        loss_list.push((seq & MAX_SEQ_NUMBER, last & MAX_SEQ_NUMBER));
+                    } else {
+                        loss_list.push((seq, seq));
+                    }
+                }
+                ControlType::Nak { loss_list }
+            }
+            control_type::SHUTDOWN => ControlType::Shutdown,
+            control_type::ACKACK => ControlType::AckAck {
+                ack_number: second_word,
+            },
+            control_type => ControlType::Unknown { control_type },
+        };
+
+        Ok(SrtPacket::Control(ControlPacket {
+            timestamp,
+            dest_socket_id,
+            control,
+        }))
+    }
+
+    fn read_handshake(reader: &mut BytesReader) -> Result<Handshake, SrtError> {
+        if reader.len() < HANDSHAKE_CIF_SIZE {
+            return Err(SrtError {
+                value: SrtErrorValue::InvalidPacket,
+            });
+        }
+
+        let mut handshake = Handshake {
+            version: reader.read_u32::<BigEndian>()?,
+            encryption: reader.read_u16::<BigEndian>()?,
+            extension_field: reader.read_u16::<BigEndian>()?,
+            initial_seq_number: reader.read_u32::<BigEndian>()?,
+            mtu: reader.read_u32::<BigEndian>()?,
+            flow_window: reader.read_u32::<BigEndian>()?,
+            handshake_type: reader.read_u32::<BigEndian>()?,
+            socket_id: reader.read_u32::<BigEndian>()?,
+            syn_cookie: reader.read_u32::<BigEndian>()?,
+            ..Default::default()
+        };
+        handshake
+            .peer_ip
+            .copy_from_slice(&reader.read_bytes(16)?[..]);
+
+        while reader.len() >= 4 {
+            let ext_type = reader.read_u16::<BigEndian>()?;
+            let ext_length = reader.read_u16::<BigEndian>()? as usize * 4;
+            let data = reader.read_bytes(ext_length)?;
+
+            let extension = match ext_type {
+                extension_type::HSREQ | extension_type::HSRSP if ext_length >= 12 => {
+                    let mut ext_reader = BytesReader::new(data);
+                    let options = SrtOptions {
+                        srt_version: ext_reader.read_u32::<BigEndian>()?,
+                        flags: ext_reader.read_u32::<BigEndian>()?,
+                        recv_tsbpd_delay: ext_reader.read_u16::<BigEndian>()?,
+                        send_tsbpd_delay: ext_reader.read_u16::<BigEndian>()?,
+                    };
+                    if ext_type == extension_type::HSREQ {
+                        HandshakeExtension::HsReq(options)
+                    } else {
+                        HandshakeExtension::HsRsp(options)
+                    }
+                }
+                extension_type::SID => {
+                    //the stream id is saved in 32 bits little endian words
+                    let mut stream_id: Vec<u8> = Vec::with_capacity(data.len());
+                    for word in data.chunks(4) {
+                        stream_id.extend(word.iter().rev());
+                    }
+                    while stream_id.last() == Some(&0) {
+                        stream_id.pop();
+                    }
+                    HandshakeExtension::StreamId(String::from_utf8_lossy(&stream_id).into_owned())
+                }
+                _ => HandshakeExtension::Unknown { ext_type, data },
+            };
+            handshake.extensions.push(extension);
+        }
+
+        Ok(handshake)
+    }
+
+    pub fn marshal(&self) -> Result<BytesMut, SrtError> {
+        let mut writer = BytesWriter::new();
+
+        match self {
+            SrtPacket::Data(data) => {
+                writer.write_u32::<BigEndian>(data.seq_number & MAX_SEQ_NUMBER)?;
+                writer.write_u32::<BigEndian>(
+                    ((data.position as u32 & 0x03) << 30)
+                        | ((data.in_order as u32) << 29)
+                        | ((data.retransmitted as u32) << 26)
+                        | (data.message_number & 0x03FF_FFFF),
+                )?;
+                writer.write_u32::<BigEndian>(data.timestamp)?;
+                writer.write_u32::<BigEndian>(data.dest_socket_id)?;
+                writer.write(&data.payload[..])?;
+            }
+            SrtPacket::Control(control) => {
+                let (control_type, type_specific) = match &control.control {
+                    ControlType::Handshake(_) => (control_type::HANDSHAKE, 0),
+                    ControlType::KeepAlive => (control_type::KEEPALIVE, 0),
+                    ControlType::Ack { ack_number, .. } => (control_type::ACK, *ack_number),
+                    ControlType::Nak { .. } => (control_type::NAK, 0),
+                    ControlType::Shutdown => (control_type::SHUTDOWN, 0),
+                    ControlType::AckAck { ack_number } => (control_type::ACKACK, *ack_number),
+                    ControlType::Unknown { control_type } => (*control_type, 0),
+                };
+                writer.write_u32::<BigEndian>(0x8000_0000 | ((control_type as u32) << 16))?;
+                writer.write_u32::<BigEndian>(type_specific)?;
+                writer.write_u32::<BigEndian>(control.timestamp)?;
+                writer.write_u32::<BigEndian>(control.dest_socket_id)?;
+
+                match &control.control {
+                    ControlType::Handshake(handshake) => {
+                        Self::write_handshake(&mut writer, handshake)?;
+                    }
+                    ControlType::Ack {
+                        last_ack_seq,
+                        rtt,
+                        rtt_variance,
+                        available_buffer,
+                        ..
+                    } => {
+                        writer.write_u32::<BigEndian>(*last_ack_seq)?;
+                        writer.write_u32::<BigEndian>(*rtt)?;
+                        writer.write_u32::<BigEndian>(*rtt_variance)?;
+                        writer.write_u32::<BigEndian>(*available_buffer)?;
+                        //packets receiving rate, estimated link capacity and receiving rate
+                        for _ in 0..3 {
+                            writer.write_u32::<BigEndian>(0)?;
+                        }
+                    }
+                    ControlType::Nak { loss_list } => {
+                        for (first, last) in loss_list {
+                            if first == last {
+                                writer.write_u32::<BigEndian>(*first)?;
+                            } else {
+                                writer.write_u32::<BigEndian>(*first | 0x8000_0000)?;
+                                writer.write_u32::<BigEndian>(*last)?;
+                            }
+                        }
+                    }
+                    //the keepalive/shutdown/ackack control information field is padded
+                    _ => writer.write_u32::<BigEndian>(0)?,
+                }
+            }
+        }
+
+        Ok(writer.extract_current_bytes())
+    }
+
+    fn write_handshake(writer: &mut BytesWriter, handshake: &Handshake) -> Result<(), SrtError> {
+        writer.write_u32::<BigEndian>(handshake.version)?;
+        writer.write_u16::<BigEndian>(handshake.encryption)?;
+        writer.write_u16::<BigEndian>(handshake.extension_field)?;
+        writer.write_u32::<BigEndian>(handshake.initial_seq_number)?;
+        writer.write_u32::<BigEndian>(handshake.mtu)?;
+        writer.write_u32::<BigEndian>(handshake.flow_window)?;
+        writer.write_u32::<BigEndian>(handshake.handshake_type)?;
+        writer.write_u32::<BigEndian>(handshake.socket_id)?;
+        writer.write_u32::<BigEndian>(handshake.syn_cookie)?;
+        writer.write(&handshake.peer_ip)?;
+
+        for extension in &handshake.extensions {
+            match extension {
+                HandshakeExtension::HsReq(options) | HandshakeExtension::HsRsp(options) => {
+                    let ext_type = if matches!(extension, HandshakeExtension::HsReq(_)) {
+                        extension_type::HSREQ
+                    } else {
+                        extension_type::HSRSP
+                    };
+                    writer.write_u16::<BigEndian>(ext_type)?;
+                    writer.write_u16::<BigEndian>(3)?;
+                    writer.write_u32::<BigEndian>(options.srt_version)?;
+                    writer.write_u32::<BigEndian>(options.flags)?;
+                    writer.write_u16::<BigEndian>(options.recv_tsbpd_delay)?;
+                    writer.write_u16::<BigEndian>(options.send_tsbpd_delay)?;
+                }
+                HandshakeExtension::StreamId(stream_id) => {
+                    let mut data = stream_id.as_bytes().to_vec();
+                    data.resize(data.len().div_ceil(4) * 4, 0);
+                    writer.write_u16::<BigEndian>(extension_type::SID)?;
+                    writer.write_u16::<BigEndian>((data.len() / 4) as u16)?;
+                    for word in data.chunks(4) {
+                        let reversed: Vec<u8> = word.iter().rev().copied().collect();
+                        writer.write(&reversed)?;
+                    }
+                }
+                HandshakeExtension::Unknown { ext_type, data } => {
+                    writer.write_u16::<BigEndian>(*ext_type)?;
+                    writer.write_u16::<BigEndian>((data.len() / 4) as u16)?;
+                    writer.write(&data[..])?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ControlPacket, ControlType, DataPacket, Handshake, HandshakeExtension, SrtOptions,
+        SrtPacket,
+    };
+    use crate::define::{handshake_type, packet_position, srt_flag};
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_packet_marshal_unmarshal() {
+        let packets = vec![
+            SrtPacket::Data(DataPacket {
+                seq_number: 0x7FFF_FFFF,
+                position: packet_position::SOLO,
+                in_order: false,
+                retransmitted: true,
+                message_number: 12,
+                timestamp: 3000,
+                dest_socket_id: 0x1234_5678,
+                payload: BytesMut::from(&[0x47u8; 188][..]),
+            }),
+            SrtPacket::Control(ControlPacket {
+                timestamp: 1,
+                dest_socket_id: 2,
+                control: ControlType::Handshake(Handshake {
+                    version: 5,
+                    extension_field: 5,
+                    initial_seq_number: 100,
+                    mtu: 1500,
+                    flow_window: 8192,
+                    handshake_type: handshake_type::CONCLUSION,
+                    socket_id: 3,
+                    syn_cookie: 4,
+                    extensions: vec![
+                        HandshakeExtension::HsReq(SrtOptions {
+                            srt_version: 0x0001_0500,
+                            flags: srt_flag::TSBPDSND | srt_flag::TSBPDRCV,
+                            recv_tsbpd_delay: 120,
+                            send_tsbpd_delay: 120,
+                        }),
+                        HandshakeExtension::StreamId(String::from("#!::r=live/test,m=publish")),
+                    ],
+                    ..Default::default()
+                }),
+            }),
+            SrtPacket::Control(ControlPacket {
+                timestamp: 5,
+                dest_socket_id: 6,
+                control: ControlType::Nak {
+                    loss_list: vec![(10, 10), (12, 20)],
+                },
+            }),
+            SrtPacket::Control(ControlPacket {
+                timestamp: 7,
+                dest_socket_id: 8,
+                control: ControlType::Ack {
+                    ack_number: 1,
+                    last_ack_seq: 21,
+                    rtt: 100_000,
+                    rtt_variance: 50_000,
+                    available_buffer: 8192,
+                },
+            }),
+        ];
+
+        for packet in packets {
+            let data = packet.marshal().unwrap();
+            assert_eq!(SrtPacket::unmarshal(data).unwrap(), packet);
+        }
+
+        //the stream id is saved in 32 bits little endian words
+        let packet = SrtPacket::Control(ControlPacket {
+            timestamp: 0,
+            dest_socket_id: 0,
+            control: ControlType::Handshake(Handshake {
+                extensions: vec![HandshakeExtension::StreamId(String::from("abcdef"))],
+                ..Default::default()
+            }),
+        });
+        let data = packet.marshal().unwrap();
+        //header(16) + cif(48) + extension type and length(4)
+        assert_eq!(&data[68..76], b"dcba\0\0fe");
+    }
+}
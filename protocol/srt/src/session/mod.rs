@@ -0,0 +1,395 @@
+use {
+    super::{
+        connection::SrtConnection,
+        define::reject_reason,
+        errors::{SrtError, SrtErrorValue},
+        flv2ts::Flv2TsRemuxer,
+        handshake,
+        packet::{ControlType, Handshake, SrtPacket},
+        stream_id::{SrtStreamId, StreamMode},
+        ts2flv::Ts2FlvRemuxer,
+    },
+    bytes::BytesMut,
+    rtmp::{cache::Cache, session::common::RtmpStreamHandler},
+    std::{net::SocketAddr, sync::Arc, time::Duration},
+    streamhub::{
+        auth::{Auth, AuthAction},
+        define::{
+            wait_pub_sub_result, DataReceiver, DataSender, FrameData, FrameDataSender, NotifyInfo,
+            PubSubResultReceiver, PublishType, PublisherInfo, StreamHubEvent, StreamHubEventSender,
+            SubscribeType, SubscriberInfo,
+        },
+        errors::ChannelErrorValue,
+        utils::{RandomDigitCount, Uuid},
+    },
+    tokio::{
+        net::UdpSocket,
+        sync::{
+            mpsc::{self, UnboundedReceiver, UnboundedSender},
+            oneshot,
+        },
+        time,
+    },
+};
+
+pub type PacketSender = UnboundedSender<SrtPacket>;
+pub type PacketReceiver = UnboundedReceiver<SrtPacket>;
+
+const TICK_INTERVAL: Duration = Duration::from_millis(10);
+const GOP_NUM: usize = 1;
+
+pub struct SrtServerSession {
+    socket: Arc<UdpSocket>,
+    remote_addr: SocketAddr,
+    connection: SrtConnection,
+    latency: Duration,
+    /*the conclusion request from the caller*/
+    handshake: Handshake,
+    /*resent if the caller retransmits the conclusion request*/
+    handshake_response: Option<SrtPacket>,
+    stream_id: SrtStreamId,
+    /*the packets dispatched by the server according to the destination socket id*/
+    packet_receiver: PacketReceiver,
+    event_producer: StreamHubEventSender,
+    auth: Option<Arc<Auth>>,
+    session_id: Uuid,
+}
+
+impl SrtServerSession {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        socket: Arc<UdpSocket>,
+        remote_addr: SocketAddr,
+        socket_id: u32,
+        handshake: Handshake,
+        stream_id: SrtStreamId,
+        latency: Duration,
+        packet_receiver: PacketReceiver,
+        event_producer: StreamHubEventSender,
+        auth: Option<Arc<Auth>>,
+    ) -> Self {
+        let connection = SrtConnection::new(
+            socket_id,
+            handshake.socket_id,
+            handshake.initial_seq_number,
+            handshake::negotiate_latency(&handshake, latency),
+        );
+
+        Self {
+            socket,
+            remote_addr,
+            connection,
+            latency,
+            handshake,
+            handshake_response: None,
+            stream_id,
+            packet_receiver,
+            event_producer,
+            auth,
+            session_id: Uuid::new(RandomDigitCount::Four),
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), SrtError> {
+        if let Err(err) = self.authenticate().await {
+            self.reject(reject_reason::UNAUTHORIZED).await?;
+            return Err(err);
+        }
+
+        let rv = match self.stream_id.mode {
+            StreamMode::Publish => self.publish().await,
+            StreamMode::Request => self.play().await,
+        };
+
+        //the session is rejected before connected if the stream hub rejects it
+        if self.handshake_response.is_some() {
+            self.connection.close();
+            self.flush().await?;
+        }
+        rv
+    }
+
+    //accept the connection after the stream hub accepts the publish/subscribe,
+    //otherwise reject it
+    async fn accept(&mut self, result_receiver: PubSubResultReceiver) -> Result<(), SrtError> {
+        if let Err(err) = wait_pub_sub_result(result_receiver).await {
+            let reason = match err.value {
+                ChannelErrorValue::NotifyRejected(_) => reject_reason::FORBIDDEN,
+                ChannelErrorValue::NoAppOrStreamName => reject_reason::NOT_FOUND,
+                ChannelErrorValue::Exists => reject_reason::CONFLICT,
+                _ => reject_reason::BAD_REQUEST,
+            };
+            self.reject(reason).await?;
+            return Err(err.into());
+        }
+
+        let response = handshake::handshake_packet(
+            self.connection.peer_socket_id(),
+            handshake::conclusion_response(
+                &self.handshake,
+                self.connection.local_socket_id(),
+                self.latency,
+            ),
+        );
+        self.send_packet(&response).await?;
+        self.handshake_response = Some(response);
+
+        log::info!(
+            "srt session: {} is connected from {}, stream id: {:?}",
+            self.session_id,
+            self.remote_addr,
+            self.stream_id
+        );
+        Ok(())
+    }
+
+    async fn authenticate(&self) -> Result<(), SrtError> {
+        if let Some(auth) = &self.auth {
+            let action = match self.stream_id.mode {
+                StreamMode::Publish => AuthAction::Publish,
+                StreamMode::Request => AuthAction::Play,
+            };
+            auth.authenticate(
+                &self.stream_id.identifier(),
+                action,
+                &self.stream_id.url_parameters,
+                &self.remote_addr.to_string(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn reject(&mut self, reason: u32) -> Result<(), SrtError> {
+        let packet = handshake::handshake_packet(
+            self.connection.peer_socket_id(),
+            handshake::rejection(&self.handshake, reason),
+        );
+        self.send_packet(&packet).await
+    }
+
+    fn get_notify_info(&self) -> NotifyInfo {
+        NotifyInfo {
+            request_url: format!(
+                "srt://{}/{}/{}",
+                self.socket
+                    .local_addr()
+                    .map(|addr| addr.to_string())
+                    .unwrap_or_default(),
+                self.stream_id.app_name,
+                self.stream_id.stream_name
+            ),
+            remote_addr: self.remote_addr.to_string(),
+        }
+    }
+
+    async fn publish(&mut self) -> Result<(), SrtError> {
+        let stream_handler = Arc::new(RtmpStreamHandler::new());
+        stream_handler
+            .set_cache(Cache::new(
+                self.stream_id.app_name.clone(),
+                self.stream_id.stream_name.clone(),
+                GOP_NUM,
+            ))
+            .await;
+
+        let (frame_sender, frame_receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
+        let publish_event = StreamHubEvent::Publish {
+            identifier: self.stream_id.identifier(),
+            info: self.get_publisher_info(),
+            receiver: DataReceiver {
+                frame_receiver: Some(frame_receiver),
+                packet_receiver: None,
+            },
+            stream_handler: stream_handler.clone(),
+            result_sender,
+        };
+        if self.event_producer.send(publish_event).is_err() {
+            return Err(SrtError {
+                value: SrtErrorValue::StreamHubEventSendErr,
+            });
+        }
+        self.accept(result_receiver).await?;
+
+        let rv = self.receive_ts_data(&frame_sender, &stream_handler).await;
+
+        let unpublish_event = StreamHubEvent::UnPublish {
+            identifier: self.stream_id.identifier(),
+            info: self.get_publisher_info(),
+        };
+        if let Err(err) = self.event_producer.send(unpublish_event) {
+            log::error!("srt session unpublish error: {}", err);
+        }
+
+        rv
+    }
+
+    async fn receive_ts_data(
+        &mut self,
+        frame_sender: &FrameDataSender,
+        stream_handler: &Arc<RtmpStreamHandler>,
+    ) -> Result<(), SrtError> {
+        let mut remuxer = Ts2FlvRemuxer::new();
+        let mut interval = time::interval(TICK_INTERVAL);
+
+        while !self.connection.is_closed() {
+            let payloads = tokio::select! {
+                packet = self.packet_receiver.recv() => match packet {
+                    Some(packet) => self.on_packet(packet).await?,
+                    None => break,
+                },
+                _ = interval.tick() => self.connection.on_tick(),
+            };
+
+            for payload in payloads {
+                //a corrupt payload does not stop the session
+                let frame_datas = match remuxer.process(&payload[..]) {
+                    Ok(frame_datas) => frame_datas,
+                    Err(err) => {
+                        log::error!("srt remux ts data error, drop the payload: {}", err);
+                        continue;
+                    }
+                };
+                for frame_data in frame_datas {
+                    Self::on_frame_data(frame_data, frame_sender, stream_handler).await?;
+                }
+            }
+            self.flush().await?;
+        }
+
+        for frame_data in remuxer.flush().unwrap_or_default() {
+            Self::on_frame_data(frame_data, frame_sender, stream_handler).await?;
+        }
+        Ok(())
+    }
+
+    async fn on_frame_data(
+        frame_data: FrameData,
+        frame_sender: &FrameDataSender,
+        stream_handler: &Arc<RtmpStreamHandler>,
+    ) -> Result<(), SrtError> {
+        match &frame_data {
+            FrameData::Video { timestamp, data } => {
+                stream_handler.save_video_data(data, *timestamp).await?;
+            }
+            FrameData::Audio { timestamp, data } => {
+                stream_handler.save_audio_data(data, *timestamp).await?;
+            }
+            FrameData::MetaData { timestamp, data } => {
+                stream_handler.save_metadata(data, *timestamp).await;
+            }
+            _ => {}
+        }
+
+        frame_sender.send(frame_data).map_err(|_| SrtError {
+            value: SrtErrorValue::SendFrameDataErr,
+        })
+    }
+
+    async fn play(&mut self) -> Result<(), SrtError> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: self.stream_id.identifier(),
+            info: self.get_subscriber_info(),
+            sender: DataSender::Frame { sender },
+            result_sender,
+        };
+        if self.event_producer.send(subscribe_event).is_err() {
+            return Err(SrtError {
+                value: SrtErrorValue::StreamHubEventSendErr,
+            });
+        }
+        self.accept(result_receiver).await?;
+
+        let mut remuxer = Flv2TsRemuxer::new();
+        let mut interval = time::interval(TICK_INTERVAL);
+
+        let rv: Result<(), SrtError> = async {
+            while !self.connection.is_closed() {
+                tokio::select! {
+                    frame_data = receiver.recv() => match frame_data {
+                        Some(frame_data) => match remuxer.process(frame_data) {
+                            Ok(payloads) => {
+                                for payload in payloads {
+                                    self.connection.send(payload);
+                                }
+                            }
+                            Err(err) => {
+                                log::error!("srt remux frame data error, drop the frame: {}", err);
+                            }
+                        },
+                        //the stream is unpublished or the subscriber is kicked off
+                        None => break,
+                    },
+                    packet = self.packet_receiver.recv() => match packet {
+                        Some(packet) => {
+                            self.on_packet(packet).await?;
+                        }
+                        None => break,
+                    },
+                    _ = interval.tick() => {
+                        self.connection.on_tick();
+                    }
+                }
+                self.flush().await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        let unsubscribe_event = StreamHubEvent::UnSubscribe {
+            identifier: self.stream_id.identifier(),
+            info: self.get_subscriber_info(),
+        };
+        if let Err(err) = self.event_producer.send(unsubscribe_event) {
+            log::error!("srt session unsubscribe error: {}", err);
+        }
+
+        rv
+    }
+
+    async fn on_packet(&mut self, packet: SrtPacket) -> Result<Vec<BytesMut>, SrtError> {
+        if let SrtPacket::Control(control) = &packet {
+            if let ControlType::Handshake(_) = control.control {
+                //the caller does not receive the conclusion response
+                if let Some(response) = self.handshake_response.clone() {
+                    self.send_packet(&response).await?;
+                }
+                return Ok(Vec::new());
+            }
+        }
+        Ok(self.connection.on_packet(packet))
+    }
+
+    async fn send_packet(&self, packet: &SrtPacket) -> Result<(), SrtError> {
+        self.socket
+            .send_to(&packet.marshal()?[..], self.remote_addr)
+            .await?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), SrtError> {
+        while let Some(packet) = self.connection.poll_transmit() {
+            self.send_packet(&packet).await?;
+        }
+        Ok(())
+    }
+
+    fn get_publisher_info(&self) -> PublisherInfo {
+        PublisherInfo {
+            id: self.session_id,
+            pub_type: PublishType::PushSrt,
+            notify_info: self.get_notify_info(),
+        }
+    }
+
+    fn get_subscriber_info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            id: self.session_id,
+            sub_type: SubscribeType::PlayerSrt,
+            notify_info: self.get_notify_info(),
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use {
+    super::{
+        define::{handshake_type, reject_reason, HS_VERSION_5},
+        errors::SrtError,
+        handshake,
+        packet::{ControlType, Handshake, SrtPacket},
+        session::{PacketSender, SrtServerSession},
+        stream_id::SrtStreamId,
+    },
+    bytes::BytesMut,
+    std::{
+        collections::{hash_map::DefaultHasher, HashMap},
+        hash::{Hash, Hasher},
+        net::SocketAddr,
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+    streamhub::{auth::Auth, define::StreamHubEventSender, statistics::metrics::SessionCounters},
+    tokio::{
+        net::UdpSocket,
+        sync::mpsc::{self, UnboundedSender},
+    },
+};
+
+const MAX_PACKET_SIZE: usize = 1500;
+
+//The srt listener, all the sessions share one udp socket and the
+//received packets are dispatched by the destination socket id.
+pub struct SrtServer {
+    address: String,
+    event_producer: StreamHubEventSender,
+    latency: Duration,
+    auth: Option<Arc<Auth>>,
+    /*used to generate the syn cookie of the induction response*/
+    cookie_secret: u64,
+    /*socket id to the packet sender of the session*/
+    sessions: HashMap<u32, PacketSender>,
+    /*(peer address, peer socket id) to the socket id*/
+    peers: HashMap<(SocketAddr, u32), u32>,
+    session_counters: SessionCounters,
+}
+
+impl SrtServer {
+    pub fn new(
+        address: String,
+        event_producer: StreamHubEventSender,
+        latency: Duration,
+        auth: Option<Arc<Auth>>,
+        session_counters: SessionCounters,
+    ) -> Self {
+        Self {
+            address,
+            event_producer,
+            latency,
+            auth,
+            cookie_secret: rand::random(),
+            sessions: HashMap::new(),
+            peers: HashMap::new(),
+            session_counters,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), SrtError> {
+        let socket_addr: &SocketAddr = &self.address.parse().unwrap();
+        let socket = Arc::new(UdpSocket::bind(socket_addr).await?);
+
+        log::info!("Srt server listening on udp://{}", socket_addr);
+
+        let (exit_sender, mut exit_receiver) = mpsc::unbounded_channel();
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        loop {
+            tokio::select! {
+                rv = socket.recv_from(&mut buf) => {
+                    //e.g. windows reports the icmp port unreachable of a sent packet as
+                    //connection reset, the other sessions on the socket are not affected
+                    let (len, remote_addr) = match rv {
+                        Ok(rv) => rv,
+                        Err(err) => {
+                            log::warn!("srt server receive packet error: {}", err);
+                            continue;
+                        }
+                    };
+                    match SrtPacket::unmarshal(BytesMut::from(&buf[..len])) {
+                        Ok(packet) => {
+                            if let Err(err) = self
+                                .on_packet(&socket, packet, remote_addr, &exit_sender)
+                                .await
+                            {
+                                log::error!("srt server process packet from {} error: {}", remote_addr, err);
+                            }
+                        }
+                        Err(err) => {
+                            log::warn!("srt server unmarshal packet from {} error: {}", remote_addr, err);
+                        }
+                    }
+                }
+                Some(socket_id) = exit_receiver.recv() => {
+                    self.sessions.remove(&socket_id);
+                    self.peers.retain(|_, id| *id != socket_id);
+                }
+            }
+        }
+    }
+
+    async fn on_packet(
+        &mut self,
+        socket: &Arc<UdpSocket>,
+        packet: SrtPacket,
+        remote_addr: SocketAddr,
+        exit_sender: &UnboundedSender<u32>,
+    ) -> Result<(), SrtError> {
+        let dest_socket_id = packet.dest_socket_id();
+        if dest_socket_id != 0 {
+            if let Some(sender) = self.sessions.get(&dest_socket_id) {
+                if sender.send(packet).is_err() {
+                    log::warn!("srt session: {} is closed", dest_socket_id);
+                }
+            }
+            return Ok(());
+        }
+
+        let handshake = match packet {
+            SrtPacket::Control(control) => match control.control {
+                ControlType::Handshake(handshake) => handshake,
+                _ => return Ok(()),
+            },
+            SrtPacket::Data(_) => return Ok(()),
+        };
+
+        match handshake.handshake_type {
+            handshake_type::INDUCTION => {
+                let response =
+                    handshake::induction_response(&handshake, 0, self.gen_cookie(&remote_addr, 0));
+                Self::send_handshake(socket, handshake.socket_id, response, remote_addr).await
+            }
+            handshake_type::CONCLUSION => {
+                self.on_conclusion(socket, handshake, remote_addr, exit_sender)
+                    .await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn on_conclusion(
+        &mut self,
+        socket: &Arc<UdpSocket>,
+        handshake: Handshake,
+        remote_addr: SocketAddr,
+        exit_sender: &UnboundedSender<u32>,
+    ) -> Result<(), SrtError> {
+        //the conclusion request is retransmitted
+        if let Some(socket_id) = self.peers.get(&(remote_addr, handshake.socket_id)) {
+            if let Some(sender) = self.sessions.get(socket_id) {
+                let _ = sender.send(handshake::handshake_packet(0, handshake));
+            }
+            return Ok(());
+        }
+
+        //the cookie of the last minute is also accepted
+        if handshake.syn_cookie != self.gen_cookie(&remote_addr, 0)
+            && handshake.syn_cookie != self.gen_cookie(&remote_addr, 1)
+        {
+            log::warn!("srt server: invalid syn cookie from {}", remote_addr);
+            let response = handshake::rejection(&handshake, reject_reason::ROGUE);
+            return Self::send_handshake(socket, handshake.socket_id, response, remote_addr).await;
+        }
+
+        if handshake.version < HS_VERSION_5 {
+            let response = handshake::rejection(&handshake, reject_reason::VERSION);
+            return Self::send_handshake(socket, handshake.socket_id, response, remote_addr).await;
+        }
+
+        if handshake.encryption != 0 {
+            log::warn!("srt server: the encryption is not supported now");
+            let response = handshake::rejection(&handshake, reject_reason::UNSECURE);
+            return Self::send_handshake(socket, handshake.socket_id, response, remote_addr).await;
+        }
+
+        let stream_id = match SrtStreamId::parse(handshake.stream_id().unwrap_or_default()) {
+            Ok(stream_id) => stream_id,
+            Err(err) => {
+                log::warn!("srt server: {} from {}", err, remote_addr);
+                let response = handshake::rejection(&handshake, reject_reason::BAD_REQUEST);
+                return Self::send_handshake(socket, handshake.socket_id, response, remote_addr)
+                    .await;
+            }
+        };
+
+        let socket_id = self.gen_socket_id();
+        let (packet_sender, packet_receiver) = mpsc::unbounded_channel();
+        self.sessions.insert(socket_id, packet_sender);
+        self.peers
+            .insert((remote_addr, handshake.socket_id), socket_id);
+
+        let mut session = SrtServerSession::new(
+            socket.clone(),
+            remote_addr,
+            socket_id,
+            handshake,
+            stream_id,
+            self.latency,
+            packet_receiver,
+            self.event_producer.clone(),
+            self.auth.clone(),
+        );
+        let exit_sender = exit_sender.clone();
+        let session_counters = self.session_counters.clone();
+        session_counters.session_connected("srt");
+        tokio::spawn(async move {
+            if let Err(err) = session.run().await {
+                session_counters.session_error("srt");
+                log::error!("session run error, err: {}", err);
+            }
+            session_counters.session_disconnected("srt");
+            let _ = exit_sender.send(socket_id);
+        });
+
+        Ok(())
+    }
+
+    async fn send_handshake(
+        socket: &UdpSocket,
+        dest_socket_id: u32,
+        handshake: Handshake,
+        remote_addr: SocketAddr,
+    ) -> Result<(), SrtError> {
+        let packet = handshake::handshake_packet(dest_socket_id, handshake);
+        socket.send_to(&packet.marshal()?[..], remote_addr).await?;
+        Ok(())
+    }
+
+    //the cookie is bound to the peer address and changes every minute.
+    fn gen_cookie(&self, remote_addr: &SocketAddr, minutes_ago: u64) -> u32 {
+        let minutes = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() / 60)
+            .unwrap_or_default()
+            - minutes_ago;
+
+        let mut hasher = DefaultHasher::new();
+        self.cookie_secret.hash(&mut hasher);
+        remote_addr.hash(&mut hasher);
+        minutes.hash(&mut hasher);
+        hasher.finish() as u32
+    }
+
+    fn gen_socket_id(&self) -> u32 {
+        loop {
+            let socket_id = rand::random::<u32>() & 0x3FFF_FFFF;
+            if socket_id != 0 && !self.sessions.contains_key(&socket_id) {
+                return socket_id;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SrtServer;
+    use crate::{client::SrtClient, define::reject_reason, errors::SrtErrorValue};
+    use bytes::BytesMut;
+    use std::time::Duration;
+    use streamhub::StreamsHub;
+    use tokio::{net::UdpSocket, time};
+    use xmpegts::{
+        define::{epsi_stream_type, MPEG_FLAG_IDR_FRAME},
+        ts::{TsDemuxer, TsMuxer},
+    };
+
+    const SPS: [u8; 10] = [0x67, 0x42, 0xc0, 0x1e, 0xd9, 0x00, 0xa0, 0x47, 0xfe, 0xc8];
+    const PPS: [u8; 4] = [0x68, 0xce, 0x3c, 0x80];
+
+    fn gen_ts_data() -> BytesMut {
+        let mut ts_muxer = TsMuxer::new();
+        let audio_pid = ts_muxer
+            .add_stream(epsi_stream_type::PSI_STREAM_AAC, BytesMut::new())
+            .unwrap();
+        let video_pid = ts_muxer
+            .add_stream(epsi_stream_type::PSI_STREAM_H264, BytesMut::new())
+            .unwrap();
+
+        for i in 0..10i64 {
+            let mut video = BytesMut::new();
+            if i == 0 {
+                for nalu in [&SPS[..], &PPS[..], &[0x65, 0x88, 0x84, 0x00][..]] {
+                    video.extend_from_slice(&[0x00, 0x00, 0x00, 0x01]);
+                    video.extend_from_slice(nalu);
+                }
+            } else {
+                video.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x41, 0x9a, 0x02]);
+            }
+            let flags = if i == 0 { MPEG_FLAG_IDR_FRAME } else { 0 };
+            let timestamp = 90 * 40 * i;
+            ts_muxer
+                .write(video_pid, timestamp, timestamp, flags, video)
+                .unwrap();
+
+            //aac lc, 44100Hz, 2 channels, 4 bytes raw data
+            let adts = [
+                0xFF, 0xF1, 0x50, 0x80, 0x01, 0x7F, 0xFC, 0x21, 0x10, 0x04, 0x60,
+            ];
+            ts_muxer
+                .write(
+                    audio_pid,
+                    timestamp,
+                    timestamp,
+                    0,
+                    BytesMut::from(&adts[..]),
+                )
+                .unwrap();
+        }
+
+        ts_muxer.get_data()
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_play() {
+        let mut stream_hub = StreamsHub::new(None);
+        let producer = stream_hub.get_hub_event_sender();
+        let session_counters = stream_hub.get_session_counters();
+        tokio::spawn(async move { stream_hub.run().await });
+
+        let address = UdpSocket::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let mut server = SrtServer::new(
+            address.clone(),
+            producer,
+            Duration::from_millis(120),
+            None,
+            session_counters,
+        );
+        tokio::spawn(async move { server.run().await });
+        time::sleep(Duration::from_millis(100)).await;
+
+        let latency = Duration::from_millis(50);
+        let err = SrtClient::connect(&address, "#!::r=live", latency)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.value,
+            SrtErrorValue::HandshakeRejected(reject_reason::BAD_REQUEST)
+        ));
+
+        let mut publisher = SrtClient::connect(&address, "#!::r=live/test,m=publish", latency)
+            .await
+            .unwrap();
+        let mut ts_data = gen_ts_data();
+        while !ts_data.is_empty() {
+            let size = ts_data.len().min(1316);
+            publisher.send(ts_data.split_to(size)).await.unwrap();
+        }
+        time::sleep(Duration::from_millis(200)).await;
+
+        //the player receives the cached gop
+        let mut player = SrtClient::connect(&address, "#!::r=live/test,m=request", latency)
+            .await
+            .unwrap();
+        let mut ts_demuxer = TsDemuxer::new();
+        let mut video_frames = Vec::new();
+        time::timeout(Duration::from_secs(5), async {
+            while video_frames.len() < 2 {
+                let payload = player.recv().await.unwrap().unwrap();
+                for frame in ts_demuxer.demux(&payload[..]).unwrap() {
+                    if frame.stream_type == epsi_stream_type::PSI_STREAM_H264 {
+                        video_frames.push(frame);
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert!(video_frames[0].random_access);
+        assert_eq!(video_frames[1].dts - video_frames[0].dts, 90 * 40);
+
+        player.close().await.unwrap();
+        publisher.close().await.unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+use {
+    super::errors::{SrtError, SrtErrorValue},
+    streamhub::stream::StreamIdentifier,
+};
+
+const STREAM_ID_PREFIX: &str = "#!::";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamMode {
+    Publish,
+    Request,
+}
+
+/*
+ The stream id uses the access control syntax:
+   #!::r=live/test,m=publish,token=xxx
+ - r: the resource name "app_name/stream_name".
+ - m: "publish" or "request"(default) for playing.
+ - the other keys are passed to the auth as url parameters.
+ A plain stream id "live/test" is the same as "#!::r=live/test".
+ reference: https://github.com/Haivision/srt/blob/master/docs/features/access-control.md
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrtStreamId {
+    pub app_name: String,
+    pub stream_name: String,
+    pub mode: StreamMode,
+    pub url_parameters: String,
+}
+
+impl SrtStreamId {
+    pub fn parse(stream_id: &str) -> Result<Self, SrtError> {
+        let invalid = || SrtError {
+            value: SrtErrorValue::InvalidStreamId(stream_id.to_string()),
+        };
+
+        let mut resource = None;
+        let mut mode = StreamMode::Request;
+        let mut parameters = Vec::new();
+
+        if let Some(key_values) = stream_id.strip_prefix(STREAM_ID_PREFIX) {
+            for key_value in key_values.split(',').filter(|kv| !kv.is_empty()) {
+                let (key, value) = key_value.split_once('=').ok_or_else(invalid)?;
+                match key {
+                    "r" => resource = Some(value),
+                    "m" => {
+                        mode = match value {
+                            "publish" => StreamMode::Publish,
+                            "request" => StreamMode::Request,
+                            _ => return Err(invalid()),
+                        }
+                    }
+                    _ => parameters.push(key_value),
+                }
+            }
+        } else {
+            resource = Some(stream_id);
+        }
+
+        let (app_name, stream_name) = resource
+            .and_then(|r| r.trim_matches('/').split_once('/'))
+            .ok_or_else(invalid)?;
+        if app_name.is_empty() || stream_name.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            app_name: app_name.to_string(),
+            stream_name: stream_name.to_string(),
+            mode,
+            url_parameters: parameters.join("&"),
+        })
+    }
+
+    pub fn identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtmp {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SrtStreamId, StreamMode};
+
+    #[test]
+    fn test_parse_stream_id() {
+        let stream_id = SrtStreamId::parse("#!::r=live/test,m=publish,token=123").unwrap();
+        assert_eq!(stream_id.app_name, "live");
+        assert_eq!(stream_id.stream_name, "test");
+        assert_eq!(stream_id.mode, StreamMode::Publish);
+        assert_eq!(stream_id.url_parameters, "token=123");
+
+        let stream_id = SrtStreamId::parse("#!::m=request,r=live/a/b").unwrap();
+        assert_eq!(stream_id.stream_name, "a/b");
+        assert_eq!(stream_id.mode, StreamMode::Request);
+
+        let stream_id = SrtStreamId::parse("live/test").unwrap();
+        assert_eq!(stream_id.mode, StreamMode::Request);
+
+        assert!(SrtStreamId::parse("#!::r=test,m=publish").is_err());
+        assert!(SrtStreamId::parse("#!::r=live/test,m=unknown").is_err());
+        assert!(SrtStreamId::parse("").is_err());
+    }
+}
@@ -0,0 +1,413 @@
+use {
+    super::errors::SrtError,
+    bytes::BytesMut,
+    bytesio::{bytes_reader::BytesReader, bytes_writer::BytesWriter},
+    h264_decoder::sps::SpsParser,
+    indexmap::IndexMap,
+    rtmp::{
+        amf0::{amf0_writer::Amf0Writer, Amf0ValueType},
+        remuxer::rtsp2rtmp::find_start_code,
+    },
+    streamhub::define::FrameData,
+    xflv::{
+        define::{
+            aac_packet_type, avc_packet_type, frame_type,
+            h264_nal_type::{H264_NAL_AUD, H264_NAL_IDR, H264_NAL_PPS, H264_NAL_SPS},
+        },
+        flv_tag_header::{AudioTagHeader, VideoTagHeader},
+        mpeg4_avc::{Mpeg4Avc, Mpeg4AvcProcessor, Pps, Sps},
+        Marshal,
+    },
+    xmpegts::{
+        define::{epsi_stream_type, PTS_NO_VALUE},
+        ts::{TsDemuxer, TsFrame},
+    },
+};
+
+const AAC_SAMPLING_FREQUENCIES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+/*the pts/dts of the pes header is 33 bits*/
+const TIMESTAMP_WRAP: i64 = 1 << 33;
+
+//Remuxes the h264/aac mpegts stream to the rtmp frame data(flv tag bodies).
+pub struct Ts2FlvRemuxer {
+    ts_demuxer: TsDemuxer,
+    /*the dts of the first frame in 90kHz*/
+    base_timestamp: Option<i64>,
+    sps: Option<BytesMut>,
+    pps: Option<BytesMut>,
+    /*the video frames are dropped before the sequence header is sent*/
+    video_seq_header_sent: bool,
+    audio_specific_config: Option<[u8; 2]>,
+    unsupported_stream_warned: bool,
+}
+
+impl Default for Ts2FlvRemuxer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ts2FlvRemuxer {
+    pub fn new() -> Self {
+        Self {
+            ts_demuxer: TsDemuxer::new(),
+            base_timestamp: None,
+            sps: None,
+            pps: None,
+            video_seq_header_sent: false,
+            audio_specific_config: None,
+            unsupported_stream_warned: false,
+        }
+    }
+
+    pub fn process(&mut self, data: &[u8]) -> Result<Vec<FrameData>, SrtError> {
+        let mut frame_datas = Vec::new();
+        for ts_frame in self.ts_demuxer.demux(data)? {
+            self.process_ts_frame(ts_frame, &mut frame_datas)?;
+        }
+        Ok(frame_datas)
+    }
+
+    pub fn flush(&mut self) -> Result<Vec<FrameData>, SrtError> {
+        let mut frame_datas = Vec::new();
+        for ts_frame in self.ts_demuxer.flush()? {
+            self.process_ts_frame(ts_frame, &mut frame_datas)?;
+        }
+        Ok(frame_datas)
+    }
+
+    fn process_ts_frame(
+        &mut self,
+        ts_frame: TsFrame,
+        frame_datas: &mut Vec<FrameData>,
+    ) -> Result<(), SrtError> {
+        //the frame can not be placed on the timeline
+        if ts_frame.pts == PTS_NO_VALUE || ts_frame.dts == PTS_NO_VALUE {
+            log::debug!(
+                "srt: drop the frame without timestamp, pid: {}",
+                ts_frame.pid
+            );
+            return Ok(());
+        }
+
+        match ts_frame.stream_type {
+            epsi_stream_type::PSI_STREAM_H264 => self.on_h264(ts_frame, frame_datas),
+            epsi_stream_type::PSI_STREAM_AAC | epsi_stream_type::PSI_STREAM_MPEG4_AAC => {
+                self.on_aac(ts_frame, frame_datas)
+            }
+            stream_type => {
+                if !self.unsupported_stream_warned {
+                    log::warn!(
+                        "srt: the stream type: {:#x} is not supported now, only h264 and aac are supported",
+                        stream_type
+                    );
+                    self.unsupported_stream_warned = true;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    //the timestamp in milliseconds relative to the first frame
+    fn timestamp(&mut self, timestamp: i64) -> u32 {
+        let base_timestamp = *self.base_timestamp.get_or_insert(timestamp);
+        let mut diff = timestamp.wrapping_sub(base_timestamp);
+        if diff < -TIMESTAMP_WRAP / 2 {
+            diff += TIMESTAMP_WRAP;
+        }
+        (diff.max(0) / 90) as u32
+    }
+
+    fn on_h264(
+        &mut self,
+        ts_frame: TsFrame,
+        frame_datas: &mut Vec<FrameData>,
+    ) -> Result<(), SrtError> {
+        let dts = self.timestamp(ts_frame.dts);
+        let composition_time = ((ts_frame.pts - ts_frame.dts).max(0) / 90) as i32;
+
+        let mut nalus = ts_frame.data;
+        let mut frame_nalus = Vec::new();
+        let mut sps_pps_changed = false;
+        let mut contains_idr = false;
+
+        while let Some(first_pos) = find_start_code(&nalus[..]) {
+            let mut nalu_with_start_code =
+                if let Some(distance_to_first_pos) = find_start_code(&nalus[first_pos + 3..]) {
+                    let mut second_pos = first_pos + 3 + distance_to_first_pos;
+                    while second_pos > 0 && nalus[second_pos - 1] == 0 {
+                        second_pos -= 1;
+                    }
+                    nalus.split_to(second_pos)
+                } else {
+                    nalus.split_to(nalus.len())
+                };
+
+            let nalu = nalu_with_start_code.split_off(first_pos + 3);
+            if nalu.is_empty() {
+                continue;
+            }
+            match nalu[0] & 0x1F {
+                H264_NAL_SPS => {
+                    sps_pps_changed |= self.sps.as_ref() != Some(&nalu);
+                    self.sps = Some(nalu);
+                }
+                H264_NAL_PPS => {
+                    sps_pps_changed |= self.pps.as_ref() != Some(&nalu);
+                    self.pps = Some(nalu);
+                }
+                H264_NAL_AUD => {}
+                nalu_type => {
+                    contains_idr |= nalu_type == H264_NAL_IDR;
+                    frame_nalus.push(nalu);
+                }
+            }
+        }
+
+        if sps_pps_changed || !self.video_seq_header_sent {
+            if let (Some(sps), Some(pps)) = (self.sps.clone(), self.pps.clone()) {
+                frame_datas.push(FrameData::MetaData {
+                    timestamp: 0,
+                    data: Self::gen_meta_data(&sps)?,
+                });
+                frame_datas.push(FrameData::Video {
+                    timestamp: dts,
+                    data: Self::gen_video_seq_header(sps, pps)?,
+                });
+                self.video_seq_header_sent = true;
+            }
+        }
+
+        if !self.video_seq_header_sent || frame_nalus.is_empty() {
+            return Ok(());
+        }
+
+        let video_tag_header = VideoTagHeader {
+            frame_type: if contains_idr || ts_frame.random_access {
+                frame_type::KEY_FRAME
+            } else {
+                frame_type::INTER_FRAME
+            },
+            codec_id: 7,
+            avc_packet_type: avc_packet_type::AVC_NALU,
+            composition_time,
+            ..VideoTagHeader::defalut()
+        };
+
+        let mut processor = Mpeg4AvcProcessor {
+            mpeg4_avc: Mpeg4Avc {
+                nalu_length: 4,
+                ..Default::default()
+            },
+        };
+
+        let mut writer = BytesWriter::new();
+        writer.write(&video_tag_header.marshal()?)?;
+        writer.write(&processor.nalus_to_mpeg4avc(frame_nalus)?)?;
+
+        frame_datas.push(FrameData::Video {
+            timestamp: dts,
+            data: writer.extract_current_bytes(),
+        });
+
+        Ok(())
+    }
+
+    fn gen_meta_data(sps: &BytesMut) -> Result<BytesMut, SrtError> {
+        let mut reader = BytesReader::new(sps.clone());
+        reader.read_u8()?;
+        let (width, height) = SpsParser::new(reader).parse().unwrap_or((0, 0));
+
+        let mut amf_writer = Amf0Writer::new();
+        amf_writer.write_string(&String::from("@setDataFrame"))?;
+        amf_writer.write_string(&String::from("onMetaData"))?;
+
+        let mut properties = IndexMap::new();
+        properties.insert(String::from("width"), Amf0ValueType::Number(width as f64));
+        properties.insert(String::from("height"), Amf0ValueType::Number(height as f64));
+        properties.insert(String::from("videocodecid"), Amf0ValueType::Number(7.));
+        properties.insert(String::from("audiocodecid"), Amf0ValueType::Number(10.));
+        amf_writer.write_eacm_array(&properties)?;
+
+        Ok(amf_writer.extract_current_bytes())
+    }
+
+    fn gen_video_seq_header(sps: BytesMut, pps: BytesMut) -> Result<BytesMut, SrtError> {
+        let video_tag_header = VideoTagHeader {
+            frame_type: frame_type::KEY_FRAME,
+            codec_id: 7,
+            avc_packet_type: avc_packet_type::AVC_SEQHDR,
+            composition_time: 0,
+            ..VideoTagHeader::defalut()
+        };
+
+        //sps: nalu header(1 byte) | profile_idc | constraint flags | level_idc
+        let (profile, compatibility, level) = if sps.len() >= 4 {
+            (sps[1], sps[2], sps[3])
+        } else {
+            (0, 0, 0)
+        };
+        let mut processor = Mpeg4AvcProcessor {
+            mpeg4_avc: Mpeg4Avc {
+                profile,
+                compatibility,
+                level,
+                nalu_length: 4,
+                nb_sps: 1,
+                sps: vec![Sps { data: sps }],
+                nb_pps: 1,
+                pps: vec![Pps { data: pps }],
+                ..Default::default()
+            },
+        };
+
+        let mut writer = BytesWriter::new();
+        writer.write(&video_tag_header.marshal()?)?;
+        writer.write(&processor.decoder_configuration_record_save()?)?;
+
+        Ok(writer.extract_current_bytes())
+    }
+
+    //a pes packet may contain several adts frames
+    fn on_aac(
+        &mut self,
+        ts_frame: TsFrame,
+        frame_datas: &mut Vec<FrameData>,
+    ) -> Result<(), SrtError> {
+        let mut data = ts_frame.data;
+        let mut frame_index: u32 = 0;
+
+        while data.len() >= 7 {
+            if data[0] != 0xFF || data[1] & 0xF0 != 0xF0 {
+                log::warn!("srt: invalid adts sync word, drop the aac data");
+                break;
+            }
+            let protection_absent = data[1] & 0x01;
+            let profile = data[2] >> 6;
+            let sampling_frequency_index = (data[2] >> 2) & 0x0F;
+            let channel_configuration = ((data[2] & 0x01) << 2) | (data[3] >> 6);
+            let frame_length = (((data[3] & 0x03) as usize) << 11)
+                | ((data[4] as usize) << 3)
+                | ((data[5] as usize) >> 5);
+            let header_length = if protection_absent == 1 { 7 } else { 9 };
+
+            if frame_length <= header_length || frame_length > data.len() {
+                break;
+            }
+            let mut adts_frame = data.split_to(frame_length);
+            let raw_frame = adts_frame.split_off(header_length);
+
+            //audio specific config: object type(5 bits) | sampling index(4 bits) | channels(4 bits)
+            let audio_object_type = profile + 1;
+            let audio_specific_config = [
+                (audio_object_type << 3) | (sampling_frequency_index >> 1),
+                ((sampling_frequency_index & 0x01) << 7) | (channel_configuration << 3),
+            ];
+
+            let sampling_frequency = AAC_SAMPLING_FREQUENCIES
+                .get(sampling_frequency_index as usize)
+                .copied()
+                .unwrap_or(44100);
+            let timestamp =
+                self.timestamp(ts_frame.pts) + frame_index * 1024 * 1000 / sampling_frequency;
+            frame_index += 1;
+
+            if self.audio_specific_config != Some(audio_specific_config) {
+                self.audio_specific_config = Some(audio_specific_config);
+                frame_datas.push(FrameData::Audio {
+                    timestamp,
+                    data: Self::gen_audio_tag(aac_packet_type::AAC_SEQHDR, &audio_specific_config)?,
+                });
+            }
+
+            frame_datas.push(FrameData::Audio {
+                timestamp,
+                data: Self::gen_audio_tag(aac_packet_type::AAC_RAW, &raw_frame[..])?,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn gen_audio_tag(aac_packet_type: u8, payload: &[u8]) -> Result<BytesMut, SrtError> {
+        let audio_tag_header = AudioTagHeader {
+            sound_format: 10,
+            sound_rate: 3,
+            sound_size: 1,
+            sound_type: 1,
+            aac_packet_type,
+        };
+
+        let mut writer = BytesWriter::new();
+        writer.write(&audio_tag_header.marshal()?)?;
+        writer.write(payload)?;
+
+        Ok(writer.extract_current_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ts2FlvRemuxer;
+    use bytes::BytesMut;
+    use streamhub::define::FrameData;
+    use xmpegts::{define::epsi_stream_type, ts::TsMuxer};
+
+    //an aac lc, 44100Hz, stereo adts frame
+    fn gen_adts_frame(payload_length: usize) -> BytesMut {
+        let frame_length = payload_length + 7;
+        let mut frame = BytesMut::from(
+            &[
+                0xFF,
+                0xF1,
+                0x50,
+                0x80 | (frame_length >> 11) as u8,
+                (frame_length >> 3) as u8,
+                ((frame_length & 0x07) << 5) as u8 | 0x1F,
+                0xFC,
+            ][..],
+        );
+        frame.extend_from_slice(&vec![0x21; payload_length]);
+        frame
+    }
+
+    #[test]
+    fn test_frame_without_timestamp() {
+        let mut ts_muxer = TsMuxer::new();
+        let audio_pid = ts_muxer
+            .add_stream(epsi_stream_type::PSI_STREAM_AAC, BytesMut::new())
+            .unwrap();
+        for pts in [90000, 90000 + 960, 90000 + 1920] {
+            ts_muxer
+                .write(audio_pid, pts, pts, 0, gen_adts_frame(100))
+                .unwrap();
+        }
+
+        //clear the pts flag of the second pes packet
+        let mut data = ts_muxer.get_data();
+        let pes_positions: Vec<usize> = data
+            .windows(4)
+            .enumerate()
+            .filter(|(_, bytes)| *bytes == [0x00, 0x00, 0x01, 0xC0])
+            .map(|(pos, _)| pos)
+            .collect();
+        assert_eq!(pes_positions.len(), 3);
+        data[pes_positions[1] + 7] &= 0x3F;
+
+        let mut remuxer = Ts2FlvRemuxer::new();
+        let mut frame_datas = remuxer.process(&data[..]).unwrap();
+        frame_datas.extend(remuxer.flush().unwrap());
+
+        //the sequence header and the two frames with timestamps
+        let timestamps: Vec<u32> = frame_datas
+            .iter()
+            .filter_map(|frame_data| match frame_data {
+                FrameData::Audio { timestamp, .. } => Some(*timestamp),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(timestamps, vec![0, 0, 21]);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod errors;
 pub mod http;
+pub mod remuxer;
 pub mod session;
 pub mod webrtc;
 pub mod whep;
@@ -0,0 +1,44 @@
+use {super::errors::WebRTCRemuxerError, bytes::BytesMut, std::sync::Arc};
+
+/*the rtp clock rate of opus is always 48kHz(RFC 7587)*/
+pub const OPUS_CLOCK_RATE: u32 = 48000;
+
+pub struct OpusFrame {
+    //the presentation timestamp in milliseconds
+    pub timestamp: u32,
+    pub data: BytesMut,
+}
+
+//Transcode the AAC audio of the RTMP stream into the Opus audio of the WebRTC track.
+pub trait TAudioTranscoder: Send {
+    //the aac frame is with the adts header, the output may contain zero or more opus frames.
+    fn transcode(
+        &mut self,
+        timestamp: u32,
+        aac_frame: BytesMut,
+    ) -> Result<Vec<OpusFrame>, WebRTCRemuxerError>;
+}
+
+//a transcoder is created for each remuxed stream
+pub type AudioTranscoderCreator = Arc<dyn Fn() -> Box<dyn TAudioTranscoder> + Send + Sync>;
+
+//The fallback transcoder which drops all the audio frames,
+//the WHEP players only receive the video.
+#[derive(Default)]
+pub struct DropAudioTranscoder {}
+
+impl DropAudioTranscoder {
+    pub fn creator() -> AudioTranscoderCreator {
+        Arc::new(|| Box::<DropAudioTranscoder>::default())
+    }
+}
+
+impl TAudioTranscoder for DropAudioTranscoder {
+    fn transcode(
+        &mut self,
+        _timestamp: u32,
+        _aac_frame: BytesMut,
+    ) -> Result<Vec<OpusFrame>, WebRTCRemuxerError> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,90 @@
+use {
+    failure::{Backtrace, Fail},
+    std::fmt,
+    streamhub::errors::ChannelError,
+    tokio::sync::broadcast::error::RecvError,
+    webrtc::rtp::Error as RtpError,
+    webrtc::util::Error as RTCUtilError,
+    xflv::errors::FlvDemuxerError,
+};
+
+#[derive(Debug)]
+pub struct WebRTCRemuxerError {
+    pub value: WebRTCRemuxerErrorValue,
+}
+
+#[derive(Debug, Fail)]
+pub enum WebRTCRemuxerErrorValue {
+    #[fail(display = "receive error: {}\n", _0)]
+    RecvError(#[cause] RecvError),
+    #[fail(display = "flv demuxer error: {}\n", _0)]
+    FlvDemuxerError(#[cause] FlvDemuxerError),
+    #[fail(display = "rtp error: {}\n", _0)]
+    RtpError(#[cause] RtpError),
+    #[fail(display = "webrtc util error: {}\n", _0)]
+    RTCUtilError(#[cause] RTCUtilError),
+    #[fail(display = "audio transcode error: {}\n", _0)]
+    AudioTranscodeError(String),
+    #[fail(display = "stream hub event send error\n")]
+    StreamHubEventSendErr,
+    #[fail(display = "stream hub error: {}\n", _0)]
+    ChannelError(#[cause] ChannelError),
+    #[fail(display = "packet data send error\n")]
+    PacketDataSendErr,
+}
+
+impl From<ChannelError> for WebRTCRemuxerError {
+    fn from(error: ChannelError) -> Self {
+        WebRTCRemuxerError {
+            value: WebRTCRemuxerErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<RecvError> for WebRTCRemuxerError {
+    fn from(error: RecvError) -> Self {
+        WebRTCRemuxerError {
+            value: WebRTCRemuxerErrorValue::RecvError(error),
+        }
+    }
+}
+
+impl From<FlvDemuxerError> for WebRTCRemuxerError {
+    fn from(error: FlvDemuxerError) -> Self {
+        WebRTCRemuxerError {
+            value: WebRTCRemuxerErrorValue::FlvDemuxerError(error),
+        }
+    }
+}
+
+impl From<RtpError> for WebRTCRemuxerError {
+    fn from(error: RtpError) -> Self {
+        WebRTCRemuxerError {
+            value: WebRTCRemuxerErrorValue::RtpError(error),
+        }
+    }
+}
+
+impl From<RTCUtilError> for WebRTCRemuxerError {
+    fn from(error: RTCUtilError) -> Self {
+        WebRTCRemuxerError {
+            value: WebRTCRemuxerErrorValue::RTCUtilError(error),
+        }
+    }
+}
+
+impl fmt::Display for WebRTCRemuxerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)
+    }
+}
+
+impl Fail for WebRTCRemuxerError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.value.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.value.backtrace()
+    }
+}
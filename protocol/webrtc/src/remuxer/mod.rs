@@ -0,0 +1,70 @@
+pub mod audio_transcoder;
+pub mod errors;
+pub mod rtmp2webrtc;
+
+use streamhub::{
+    define::{BroadcastEvent, BroadcastEventReceiver, PublishType, StreamHubEventSender},
+    stream::StreamIdentifier,
+};
+
+use self::{
+    audio_transcoder::AudioTranscoderCreator, errors::WebRTCRemuxerError,
+    rtmp2webrtc::Rtmp2WebRTCRemuxerSession,
+};
+
+//Receive publish event from stream hub and
+//remux from rtmp to webrtc for the whep players
+pub struct WebRTCRemuxer {
+    receiver: BroadcastEventReceiver,
+    event_producer: StreamHubEventSender,
+    audio_transcoder_creator: AudioTranscoderCreator,
+}
+
+impl WebRTCRemuxer {
+    pub fn new(
+        receiver: BroadcastEventReceiver,
+        event_producer: StreamHubEventSender,
+        audio_transcoder_creator: AudioTranscoderCreator,
+    ) -> Self {
+        Self {
+            receiver,
+            event_producer,
+            audio_transcoder_creator,
+        }
+    }
+    pub async fn run(&mut self) -> Result<(), WebRTCRemuxerError> {
+        log::info!("webrtc remuxer start...");
+
+        loop {
+            let val = self.receiver.recv().await?;
+            match val {
+                BroadcastEvent::Publish { identifier, info } => {
+                    /*the rtmp stream remuxed from rtsp/whip is not remuxed again*/
+                    if info.pub_type == PublishType::RemuxRtmp {
+                        continue;
+                    }
+                    if let StreamIdentifier::Rtmp {
+                        app_name,
+                        stream_name,
+                    } = identifier
+                    {
+                        let mut session = Rtmp2WebRTCRemuxerSession::new(
+                            app_name,
+                            stream_name,
+                            self.event_producer.clone(),
+                            (self.audio_transcoder_creator)(),
+                        );
+                        tokio::spawn(async move {
+                            if let Err(err) = session.run().await {
+                                log::error!("rtmp2webrtc session error: {}\n", err);
+                            }
+                        });
+                    }
+                }
+                _ => {
+                    log::trace!("other infos...");
+                }
+            }
+        }
+    }
+}
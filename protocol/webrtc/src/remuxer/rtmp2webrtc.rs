@@ -0,0 +1,394 @@
+use {
+    super::{
+        audio_transcoder::{TAudioTranscoder, OPUS_CLOCK_RATE},
+        errors::{WebRTCRemuxerError, WebRTCRemuxerErrorValue},
+    },
+    crate::session::WebRTCStreamHandler,
+    bytes::{Bytes, BytesMut},
+    std::{sync::Arc, time::Duration},
+    streamhub::{
+        define::{
+            wait_pub_sub_result, DataReceiver, DataSender, FrameData, FrameDataReceiver,
+            NotifyInfo, PacketData, PacketDataSender, PublishType, PublisherInfo, StreamHubEvent,
+            StreamHubEventSender, SubscribeType, SubscriberInfo,
+        },
+        stream::StreamIdentifier,
+        utils::{RandomDigitCount, Uuid},
+    },
+    tokio::{
+        sync::{mpsc, oneshot},
+        time::sleep,
+    },
+    webrtc::{
+        rtp::{
+            codecs::{h264::H264Payloader, opus::OpusPayloader},
+            header::Header,
+            packet::Packet,
+            packetizer::Payloader,
+        },
+        util::Marshal,
+    },
+    xflv::{
+        define::{AvcCodecId, SoundFormat},
+        demuxer::{FlvAudioTagDemuxer, FlvVideoTagDemuxer},
+    },
+};
+
+const RTP_MTU: usize = 1200;
+const H264_CLOCK_RATE: u32 = 90000;
+/*the payload types and ssrcs are rewritten by the whep tracks*/
+const VIDEO_PAYLOAD_TYPE: u8 = 96;
+const AUDIO_PAYLOAD_TYPE: u8 = 111;
+
+//split the frames into the marshaled rtp packets
+struct RtpPacker {
+    payloader: Box<dyn Payloader + Send + Sync>,
+    payload_type: u8,
+    clock_rate: u32,
+    sequence_number: u16,
+    /*the marker bit is set for the last packet of a video frame*/
+    set_marker: bool,
+}
+
+impl RtpPacker {
+    fn new(
+        payloader: Box<dyn Payloader + Send + Sync>,
+        payload_type: u8,
+        clock_rate: u32,
+        set_marker: bool,
+    ) -> Self {
+        Self {
+            payloader,
+            payload_type,
+            clock_rate,
+            sequence_number: 0,
+            set_marker,
+        }
+    }
+
+    //the timestamp is in milliseconds
+    fn pack(&mut self, timestamp: i64, frame: Bytes) -> Result<Vec<BytesMut>, WebRTCRemuxerError> {
+        let payloads = self.payloader.payload(RTP_MTU, &frame)?;
+        let rtp_timestamp = (timestamp * self.clock_rate as i64 / 1000) as u32;
+        let payloads_count = payloads.len();
+
+        let mut packets = Vec::with_capacity(payloads_count);
+        for (index, payload) in payloads.into_iter().enumerate() {
+            let packet = Packet {
+                header: Header {
+                    version: 2,
+                    marker: self.set_marker && index + 1 == payloads_count,
+                    payload_type: self.payload_type,
+                    sequence_number: self.sequence_number,
+                    timestamp: rtp_timestamp,
+                    ..Default::default()
+                },
+                payload,
+            };
+            self.sequence_number = self.sequence_number.wrapping_add(1);
+            packets.push(BytesMut::from(&packet.marshal()?[..]));
+        }
+
+        Ok(packets)
+    }
+}
+
+pub struct Rtmp2WebRTCRemuxerSession {
+    event_producer: StreamHubEventSender,
+    app_name: String,
+    stream_name: String,
+    //RTMP
+    data_receiver: FrameDataReceiver,
+    subscribe_id: Uuid,
+    video_demuxer: FlvVideoTagDemuxer,
+    audio_demuxer: FlvAudioTagDemuxer,
+    audio_transcoder: Box<dyn TAudioTranscoder>,
+    //WebRTC
+    publish_id: Uuid,
+    packet_sender: Option<PacketDataSender>,
+    video_packer: RtpPacker,
+    audio_packer: RtpPacker,
+    /*only log once if the video codec is not supported*/
+    unsupported_codec_logged: bool,
+}
+
+impl Rtmp2WebRTCRemuxerSession {
+    pub fn new(
+        app_name: String,
+        stream_name: String,
+        event_producer: StreamHubEventSender,
+        audio_transcoder: Box<dyn TAudioTranscoder>,
+    ) -> Self {
+        let (_, data_consumer) = mpsc::unbounded_channel();
+
+        Self {
+            event_producer,
+            app_name,
+            stream_name,
+            data_receiver: data_consumer,
+            subscribe_id: Uuid::new(RandomDigitCount::Four),
+            video_demuxer: FlvVideoTagDemuxer::new(),
+            audio_demuxer: FlvAudioTagDemuxer::new(),
+            audio_transcoder,
+            publish_id: Uuid::new(RandomDigitCount::Four),
+            packet_sender: None,
+            video_packer: RtpPacker::new(
+                Box::<H264Payloader>::default(),
+                VIDEO_PAYLOAD_TYPE,
+                H264_CLOCK_RATE,
+                true,
+            ),
+            audio_packer: RtpPacker::new(
+                Box::new(OpusPayloader),
+                AUDIO_PAYLOAD_TYPE,
+                OPUS_CLOCK_RATE,
+                false,
+            ),
+            unsupported_codec_logged: false,
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), WebRTCRemuxerError> {
+        self.publish_webrtc().await?;
+        let rv = match self.subscribe_rtmp().await {
+            Ok(()) => {
+                let rv = self.receive_rtmp_data().await;
+                self.unsubscribe_rtmp();
+                rv
+            }
+            Err(err) => Err(err),
+        };
+
+        self.unpublish_webrtc();
+        rv
+    }
+
+    fn webrtc_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::WebRTC {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        }
+    }
+
+    fn rtmp_identifier(&self) -> StreamIdentifier {
+        StreamIdentifier::Rtmp {
+            app_name: self.app_name.clone(),
+            stream_name: self.stream_name.clone(),
+        }
+    }
+
+    fn get_publisher_info(&self) -> PublisherInfo {
+        PublisherInfo {
+            id: self.publish_id,
+            pub_type: PublishType::PushRtp,
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
+        }
+    }
+
+    fn get_subscriber_info(&self) -> SubscriberInfo {
+        SubscriberInfo {
+            id: self.subscribe_id,
+            sub_type: SubscribeType::RemuxWebrtc,
+            notify_info: NotifyInfo {
+                request_url: String::from(""),
+                remote_addr: String::from(""),
+            },
+        }
+    }
+
+    async fn publish_webrtc(&mut self) -> Result<(), WebRTCRemuxerError> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        let publish_event = StreamHubEvent::Publish {
+            identifier: self.webrtc_identifier(),
+            receiver: DataReceiver {
+                packet_receiver: Some(receiver),
+                frame_receiver: None,
+            },
+            info: self.get_publisher_info(),
+            stream_handler: Arc::new(WebRTCStreamHandler::new()),
+            result_sender,
+        };
+
+        if self.event_producer.send(publish_event).is_err() {
+            return Err(WebRTCRemuxerError {
+                value: WebRTCRemuxerErrorValue::StreamHubEventSendErr,
+            });
+        }
+        wait_pub_sub_result(result_receiver).await?;
+
+        self.packet_sender = Some(sender);
+        Ok(())
+    }
+
+    fn unpublish_webrtc(&mut self) {
+        let unpublish_event = StreamHubEvent::UnPublish {
+            identifier: self.webrtc_identifier(),
+            info: self.get_publisher_info(),
+        };
+        if let Err(err) = self.event_producer.send(unpublish_event) {
+            log::error!("unpublish_webrtc err {}\n", err);
+        }
+    }
+
+    async fn subscribe_rtmp(&mut self) -> Result<(), WebRTCRemuxerError> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        let subscribe_event = StreamHubEvent::Subscribe {
+            identifier: self.rtmp_identifier(),
+            info: self.get_subscriber_info(),
+            sender: DataSender::Frame { sender },
+            result_sender,
+        };
+
+        if self.event_producer.send(subscribe_event).is_err() {
+            return Err(WebRTCRemuxerError {
+                value: WebRTCRemuxerErrorValue::StreamHubEventSendErr,
+            });
+        }
+        wait_pub_sub_result(result_receiver).await?;
+
+        self.data_receiver = receiver;
+        Ok(())
+    }
+
+    fn unsubscribe_rtmp(&mut self) {
+        let unsubscribe_event = StreamHubEvent::UnSubscribe {
+            identifier: self.rtmp_identifier(),
+            info: self.get_subscriber_info(),
+        };
+        if let Err(err) = self.event_producer.send(unsubscribe_event) {
+            log::error!("unsubscribe_rtmp err {}\n", err);
+        }
+    }
+
+    async fn receive_rtmp_data(&mut self) -> Result<(), WebRTCRemuxerError> {
+        let mut retry_count = 0;
+
+        loop {
+            if let Some(data) = self.data_receiver.recv().await {
+                match data {
+                    FrameData::Video { timestamp, data } => self.on_rtmp_video(timestamp, data)?,
+                    FrameData::Audio { timestamp, data } => self.on_rtmp_audio(timestamp, data)?,
+                    _ => continue,
+                }
+                retry_count = 0;
+            } else {
+                sleep(Duration::from_millis(100)).await;
+                retry_count += 1;
+            }
+
+            //the rtmp stream may be interrupted, retry 10 times like the other remuxers.
+            if retry_count > 10 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_rtmp_video(&mut self, timestamp: u32, data: BytesMut) -> Result<(), WebRTCRemuxerError> {
+        let video_data = match self.video_demuxer.demux(timestamp, data)? {
+            Some(video_data) => video_data,
+            None => return Ok(()),
+        };
+
+        if video_data.codec_id != AvcCodecId::H264 as u8 {
+            if !self.unsupported_codec_logged {
+                log::warn!(
+                    "rtmp2webrtc: only h264 is supported, the video of {}/{} is dropped",
+                    self.app_name,
+                    self.stream_name
+                );
+                self.unsupported_codec_logged = true;
+            }
+            return Ok(());
+        }
+
+        /*the annexb data with the sps and pps before the idr frames*/
+        let packets = self
+            .video_packer
+            .pack(video_data.pts, video_data.data.freeze())?;
+        for packet in packets {
+            self.send_packet(PacketData::Video {
+                timestamp,
+                data: packet,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn on_rtmp_audio(&mut self, timestamp: u32, data: BytesMut) -> Result<(), WebRTCRemuxerError> {
+        let audio_data = self.audio_demuxer.demux(timestamp, data)?;
+        if !audio_data.has_data || audio_data.sound_format != SoundFormat::AAC as u8 {
+            return Ok(());
+        }
+
+        for opus_frame in self
+            .audio_transcoder
+            .transcode(audio_data.pts as u32, audio_data.data)?
+        {
+            let packets = self
+                .audio_packer
+                .pack(opus_frame.timestamp as i64, opus_frame.data.freeze())?;
+            for packet in packets {
+                self.send_packet(PacketData::Audio {
+                    timestamp: opus_frame.timestamp,
+                    data: packet,
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    //fails if the webrtc stream is unpublished(e.g.: kicked off or rejected by the stream hub)
+    fn send_packet(&mut self, packet: PacketData) -> Result<(), WebRTCRemuxerError> {
+        if let Some(sender) = &self.packet_sender {
+            if sender.send(packet).is_err() {
+                return Err(WebRTCRemuxerError {
+                    value: WebRTCRemuxerErrorValue::PacketDataSendErr,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RtpPacker, H264_CLOCK_RATE, RTP_MTU, VIDEO_PAYLOAD_TYPE};
+    use bytes::{BufMut, BytesMut};
+    use webrtc::{rtp::codecs::h264::H264Payloader, rtp::packet::Packet, util::Unmarshal};
+
+    #[test]
+    fn test_pack_h264_frame() {
+        let mut packer = RtpPacker::new(
+            Box::<H264Payloader>::default(),
+            VIDEO_PAYLOAD_TYPE,
+            H264_CLOCK_RATE,
+            true,
+        );
+
+        /*an idr frame which needs to be fragmented(FU-A)*/
+        let mut frame = BytesMut::new();
+        frame.put_slice(&[0x00, 0x00, 0x00, 0x01, 0x65]);
+        frame.put_bytes(0xAB, RTP_MTU * 2);
+
+        let packets = packer.pack(40, frame.freeze()).unwrap();
+        assert_eq!(packets.len(), 3);
+        for (index, data) in packets.into_iter().enumerate() {
+            let packet = Packet::unmarshal(&mut data.freeze()).unwrap();
+            assert_eq!(packet.header.sequence_number, index as u16);
+            assert_eq!(packet.header.timestamp, 40 * 90);
+            assert_eq!(packet.header.marker, index == 2);
+            assert!(packet.payload.len() <= RTP_MTU);
+        }
+    }
+}
@@ -4,6 +4,7 @@ use {
     failure::{Backtrace, Fail},
     std::fmt,
     std::str::Utf8Error,
+    streamhub::errors::{AuthError, ChannelError},
     webrtc::error::Error as RTCError,
 };
 
@@ -26,6 +27,8 @@ pub enum SessionErrorValue {
     RTCError(#[cause] RTCError),
     #[fail(display = "stream hub event send error\n")]
     StreamHubEventSendErr,
+    #[fail(display = "stream hub error: {}\n", _0)]
+    ChannelError(#[cause] ChannelError),
     #[fail(display = "cannot receive frame data from stream hub\n")]
     CannotReceiveFrameData,
     #[fail(display = "Http Request path error\n")]
@@ -36,6 +39,24 @@ pub enum SessionErrorValue {
     HttpRequestEmptySdp,
     #[fail(display = "Cannot find Content-Length\n")]
     HttpRequestNoContentLength,
+    #[fail(display = "auth error: {}\n", _0)]
+    AuthError(#[cause] AuthError),
+}
+
+impl From<ChannelError> for SessionError {
+    fn from(error: ChannelError) -> Self {
+        SessionError {
+            value: SessionErrorValue::ChannelError(error),
+        }
+    }
+}
+
+impl From<AuthError> for SessionError {
+    fn from(error: AuthError) -> Self {
+        SessionError {
+            value: SessionErrorValue::AuthError(error),
+        }
+    }
 }
 
 impl From<RTCError> for SessionError {
@@ -1,8 +1,10 @@
 pub mod errors;
 use streamhub::{
+    auth::{Auth, AuthAction},
     define::{
-        DataReceiver, DataSender, InformationSender, NotifyInfo, PublishType, PublisherInfo,
-        StreamHubEvent, StreamHubEventSender, SubscribeType, SubscriberInfo, TStreamHandler,
+        wait_pub_sub_result, DataReceiver, DataSender, InformationSender, NotifyInfo,
+        PubSubResultReceiver, PublishType, PublisherInfo, StreamHubEvent, StreamHubEventSender,
+        SubscribeType, SubscriberInfo, TStreamHandler,
     },
     errors::ChannelError,
     statistics::StreamStatistics,
@@ -10,13 +12,12 @@ use streamhub::{
     utils::{RandomDigitCount, Uuid},
 };
 use tokio::sync::broadcast;
+use tokio::sync::oneshot;
 use tokio::sync::Mutex;
 
 use bytesio::bytesio::TNetIO;
-use bytesio::bytesio::TcpIO;
 use std::io::Read;
-use std::{collections::HashMap, fs::File, sync::Arc};
-use tokio::net::TcpStream;
+use std::{collections::HashMap, fs::File, net::SocketAddr, sync::Arc};
 
 use super::http::define::http_method_name;
 use super::http::parse_content_length;
@@ -47,11 +48,18 @@ pub struct WebRTCServerSession {
     pub session_id: Option<Uuid>,
     pub http_request_data: Option<HttpRequest>,
     pub peer_connection: Option<Arc<RTCPeerConnection>>,
+    remote_addr: Option<SocketAddr>,
+    auth: Option<Arc<Auth>>,
 }
 
 impl WebRTCServerSession {
-    pub fn new(stream: TcpStream, event_producer: StreamHubEventSender) -> Self {
-        let net_io: Box<dyn TNetIO + Send + Sync> = Box::new(TcpIO::new(stream));
+    //the net io is a plain tcp connection or a tls one(https)
+    pub fn new(
+        net_io: Box<dyn TNetIO + Send + Sync>,
+        remote_addr: Option<SocketAddr>,
+        event_producer: StreamHubEventSender,
+        auth: Option<Arc<Auth>>,
+    ) -> Self {
         let io = Arc::new(Mutex::new(net_io));
 
         Self {
@@ -63,6 +71,8 @@ impl WebRTCServerSession {
             session_id: None,
             http_request_data: None,
             peer_connection: None,
+            remote_addr,
+            auth,
         }
     }
 
@@ -156,6 +166,18 @@ impl WebRTCServerSession {
                     );
                     let offer = RTCSessionDescription::offer(sdp_data.clone())?;
 
+                    let action = match t.to_lowercase().as_str() {
+                        "whip" => AuthAction::Publish,
+                        _ => AuthAction::Play,
+                    };
+                    self.authenticate(
+                        &app_name,
+                        &stream_name,
+                        http_request.path_parameters.as_ref().unwrap(),
+                        action,
+                    )
+                    .await?;
+
                     match t.to_lowercase().as_str() {
                         "whip" => {
                             self.publish_whip(app_name, stream_name, path, offer)
@@ -244,6 +266,50 @@ impl WebRTCServerSession {
         Ok(())
     }
 
+    //reply 401 and return error if the request is rejected.
+    async fn authenticate(
+        &mut self,
+        app_name: &str,
+        stream_name: &str,
+        url_parameters: &str,
+        action: AuthAction,
+    ) -> Result<(), SessionError> {
+        if let Some(auth) = &self.auth {
+            let identifier = StreamIdentifier::WebRTC {
+                app_name: app_name.to_string(),
+                stream_name: stream_name.to_string(),
+            };
+            let remote_addr = if let Some(addr) = self.remote_addr {
+                addr.to_string()
+            } else {
+                String::from("unknown")
+            };
+
+            if let Err(err) = auth
+                .authenticate(&identifier, action, url_parameters, &remote_addr)
+                .await
+            {
+                let response = Self::gen_response(http::StatusCode::UNAUTHORIZED);
+                self.send_response(&response).await?;
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
+    //the publish/subscribe may be rejected by the on_publish/on_play notify
+    async fn wait_stream_hub_result(
+        &mut self,
+        result_receiver: PubSubResultReceiver,
+    ) -> Result<(), SessionError> {
+        if let Err(err) = wait_pub_sub_result(result_receiver).await {
+            let response = Self::gen_response(http::StatusCode::FORBIDDEN);
+            self.send_response(&response).await?;
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
     async fn publish_whip(
         &mut self,
         app_name: String,
@@ -254,6 +320,7 @@ impl WebRTCServerSession {
         // The sender is used for sending audio/video frame data to the stream hub
         // receiver is passed to the stream hub for receiving the a/v packet data
         let (sender, receiver) = mpsc::unbounded_channel();
+        let (result_sender, result_receiver) = oneshot::channel();
 
         let publish_event = StreamHubEvent::Publish {
             identifier: StreamIdentifier::WebRTC {
@@ -266,6 +333,7 @@ impl WebRTCServerSession {
             },
             info: self.get_publisher_info(),
             stream_handler: self.stream_handler.clone(),
+            result_sender,
         };
 
         if self.event_sender.send(publish_event).is_err() {
@@ -273,6 +341,7 @@ impl WebRTCServerSession {
                 value: SessionErrorValue::StreamHubEventSendErr,
             });
         }
+        self.wait_stream_hub_result(result_receiver).await?;
 
         let response = match handle_whip(offer, sender).await {
             Ok((session_description, peer_connection)) => {
@@ -335,6 +404,7 @@ impl WebRTCServerSession {
         let (sender, receiver) = mpsc::unbounded_channel();
 
         let subscriber_info = self.get_subscriber_info();
+        let (result_sender, result_receiver) = oneshot::channel();
 
         let subscribe_event = StreamHubEvent::Subscribe {
             identifier: StreamIdentifier::WebRTC {
@@ -343,6 +413,7 @@ impl WebRTCServerSession {
             },
             sender: DataSender::Packet { sender },
             info: subscriber_info.clone(),
+            result_sender,
         };
 
         if self.event_sender.send(subscribe_event).is_err() {
@@ -350,6 +421,7 @@ impl WebRTCServerSession {
                 value: SessionErrorValue::StreamHubEventSendErr,
             });
         }
+        self.wait_stream_hub_result(result_receiver).await?;
 
         let (pc_state_sender, mut pc_state_receiver) = broadcast::channel(1);
 
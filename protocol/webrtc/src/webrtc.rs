@@ -1,8 +1,10 @@
-use streamhub::define::StreamHubEventSender;
+use streamhub::{auth::Auth, define::StreamHubEventSender, statistics::metrics::SessionCounters};
 
 use super::session::WebRTCServerSession;
 
 use super::http::define::http_method_name;
+use bytesio::bytesio::{TNetIO, TcpIO, TlsIO};
+use bytesio::tls::ReloadableTlsAcceptor;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -15,14 +17,27 @@ pub struct WebRTCServer {
     address: String,
     event_producer: StreamHubEventSender,
     uuid_2_sessions: Arc<Mutex<HashMap<Uuid, Arc<Mutex<WebRTCServerSession>>>>>,
+    auth: Option<Arc<Auth>>,
+    //whip/whep over https if it is set
+    tls_acceptor: Option<ReloadableTlsAcceptor>,
+    session_counters: SessionCounters,
 }
 
 impl WebRTCServer {
-    pub fn new(address: String, event_producer: StreamHubEventSender) -> Self {
+    pub fn new(
+        address: String,
+        event_producer: StreamHubEventSender,
+        auth: Option<Arc<Auth>>,
+        tls_acceptor: Option<ReloadableTlsAcceptor>,
+        session_counters: SessionCounters,
+    ) -> Self {
         Self {
             address,
             event_producer,
             uuid_2_sessions: Arc::new(Mutex::new(HashMap::new())),
+            auth,
+            tls_acceptor,
+            session_counters,
         }
     }
 
@@ -30,19 +45,44 @@ impl WebRTCServer {
         let socket_addr: &SocketAddr = &self.address.parse().unwrap();
         let listener = TcpListener::bind(socket_addr).await?;
 
-        log::info!("WebRTC server listening on tcp://{}", socket_addr);
+        if self.tls_acceptor.is_some() {
+            log::info!("WebRTC server listening on https://{}", socket_addr);
+        } else {
+            log::info!("WebRTC server listening on tcp://{}", socket_addr);
+        }
         loop {
-            let (tcp_stream, _) = listener.accept().await?;
-            let session = Arc::new(Mutex::new(WebRTCServerSession::new(
-                tcp_stream,
-                self.event_producer.clone(),
-            )));
+            let (tcp_stream, remote_addr) = listener.accept().await?;
+            let tls_acceptor = self.tls_acceptor.clone();
+            let event_producer = self.event_producer.clone();
+            let auth = self.auth.clone();
             let uuid_2_sessions = self.uuid_2_sessions.clone();
+            let session_counters = self.session_counters.clone();
+
             tokio::spawn(async move {
+                let net_io: Box<dyn TNetIO + Send + Sync> = match tls_acceptor {
+                    Some(acceptor) => match acceptor.accept(tcp_stream).await {
+                        Ok(tls_stream) => Box::new(TlsIO::new(tls_stream)),
+                        Err(err) => {
+                            log::error!("webrtc tls handshake error: {}", err);
+                            return;
+                        }
+                    },
+                    None => Box::new(TcpIO::new(tcp_stream)),
+                };
+
+                let session = Arc::new(Mutex::new(WebRTCServerSession::new(
+                    net_io,
+                    Some(remote_addr),
+                    event_producer,
+                    auth,
+                )));
+                session_counters.session_connected("webrtc");
                 let mut session_unlock = session.lock().await;
                 if let Err(err) = session_unlock.run(uuid_2_sessions.clone()).await {
+                    session_counters.session_error("webrtc");
                     log::error!("session run error, err: {}", err);
                 }
+                session_counters.session_disconnected("webrtc");
 
                 if let Some(http_request_data) = &session_unlock.http_request_data {
                     let mut uuid_2_session_unlock = uuid_2_sessions.lock().await;